@@ -16,11 +16,37 @@ try:
         calculate_pathfinding,
         collision_detection,
         calculate_field_of_view,
-        PhysicsEngine
+        PhysicsEngine,
+        Vec2
     )
     _has_rust_core = True
 except ImportError:
+    import math
+    from typing import NamedTuple
+
     _has_rust_core = False
+
+    class Vec2(NamedTuple):
+        """Python fallback for Vec2: an (x, y) pair with vector arithmetic"""
+        x: float = 0.0
+        y: float = 0.0
+
+        def __add__(self, other):
+            return Vec2(self.x + other[0], self.y + other[1])
+
+        def __sub__(self, other):
+            return Vec2(self.x - other[0], self.y - other[1])
+
+        def __mul__(self, scale):
+            return Vec2(self.x * scale, self.y * scale)
+
+        __rmul__ = __mul__
+
+        def __neg__(self):
+            return Vec2(-self.x, -self.y)
+
+        def length(self):
+            return math.hypot(self.x, self.y)
     
     # Provide Python fallbacks for core functionality
     def calculate_pathfinding(start_x, start_y, end_x, end_y, walkable_map, max_steps=None):
@@ -72,19 +98,18 @@ except ImportError:
         
         return []
 
-    def collision_detection(entity1_x, entity1_y, entity1_width, entity1_height,
-                          entity2_x, entity2_y, entity2_width, entity2_height):
-        """Python fallback for collision detection"""
+    def collision_detection(a, b):
+        """Python fallback for collision detection between two (x, y, width, height) rectangles"""
+        (ax, ay, a_width, a_height), (bx, by, b_width, b_height) = a, b
         return (
-            entity1_x < entity2_x + entity2_width and
-            entity1_x + entity1_width > entity2_x and
-            entity1_y < entity2_y + entity2_height and
-            entity1_y + entity1_height > entity2_y
+            ax < bx + b_width and
+            ax + a_width > bx and
+            ay < by + b_height and
+            ay + a_height > by
         )
 
     def calculate_field_of_view(origin_x, origin_y, radius, obstacle_map):
         """Python fallback for FOV calculation"""
-        import math
         
         height = len(obstacle_map)
         width = len(obstacle_map[0]) if height > 0 else 0
@@ -127,9 +152,9 @@ except ImportError:
             self.gravity = gravity
             self.friction = friction
             
-        def update_entity(self, position_x, position_y, velocity_x, velocity_y, 
-                          is_on_ground, delta_time):
-            """Update entity position and velocity"""
+        def update_entity(self, position, velocity, is_on_ground, delta_time):
+            """Update entity position and velocity, each a Vec2 or an (x, y) pair"""
+            (position_x, position_y), (velocity_x, velocity_y) = position, velocity
             # Apply gravity if not on ground
             new_velocity_y = velocity_y
             if not is_on_ground:
@@ -148,7 +173,7 @@ except ImportError:
             new_position_x = position_x + new_velocity_x * delta_time
             new_position_y = position_y + new_velocity_y * delta_time
             
-            return ((new_position_x, new_position_y), (new_velocity_x, new_velocity_y))
+            return (Vec2(new_position_x, new_position_y), Vec2(new_velocity_x, new_velocity_y))
 
 def has_rust_core():
     """Check if Rust core is available"""
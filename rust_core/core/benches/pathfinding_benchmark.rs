@@ -0,0 +1,41 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use llamaquest::dijkstra::DijkstraMap;
+use llamaquest::fov::field_of_view;
+use llamaquest::pathfinding::find_path;
+
+const SIZE: usize = 128;
+
+/// An open map with a lattice of pillars, so every algorithm has walls to work around
+fn pillar_map() -> Vec<Vec<bool>> {
+    (0..SIZE)
+        .map(|y| (0..SIZE).map(|x| x % 8 != 4 || y % 8 != 4).collect())
+        .collect()
+}
+
+fn benchmarks(c: &mut Criterion) {
+    let walkable = pillar_map();
+    let obstacles: Vec<Vec<bool>> = walkable
+        .iter()
+        .map(|row| row.iter().map(|&walkable| !walkable).collect())
+        .collect();
+
+    c.bench_function("find_path diagonal", |b| {
+        b.iter(|| {
+            find_path(
+                black_box((0, 0)),
+                black_box((SIZE - 1, SIZE - 1)),
+                &walkable,
+                None,
+            )
+        })
+    });
+    c.bench_function("dijkstra from_goals", |b| {
+        b.iter(|| DijkstraMap::from_goals(&walkable, black_box(&[(SIZE / 2, SIZE / 2)]), true))
+    });
+    c.bench_function("field_of_view radius 20", |b| {
+        b.iter(|| field_of_view(black_box((SIZE / 2, SIZE / 2)), 20, &obstacles))
+    });
+}
+
+criterion_group!(benches, benchmarks);
+criterion_main!(benches);
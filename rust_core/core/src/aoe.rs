@@ -0,0 +1,229 @@
+//! Area-of-effect templates: the cells a blast, cone, beam or ring covers.
+//!
+//! Shapes are built from [`crate::raster`], so a blast covers exactly the cells of
+//! a filled circle. On a wall map, cells the origin cannot see are clipped away.
+
+use crate::grid;
+use crate::raster::{self, Cell};
+use crate::vec2::Vec2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Template {
+    /// A filled circle around the origin, origin included
+    Blast { radius: u32 },
+    /// The part of a filled circle within `angle / 2` radians either side of the
+    /// direction from the origin to `toward`, origin excluded
+    Cone {
+        toward: Cell,
+        radius: u32,
+        angle: f32,
+    },
+    /// A line `width` cells wide from the origin through `toward`, keeping only cells
+    /// ahead of the origin.
+    /// It ends at `toward`, or `length` cells from the origin when that is given.
+    Beam {
+        toward: Cell,
+        width: f32,
+        length: Option<u32>,
+    },
+    /// The filled circle of `outer_radius` less that of `inner_radius`
+    Ring {
+        inner_radius: u32,
+        outer_radius: u32,
+    },
+}
+
+impl Template {
+    /// Cells covered on an open map, row by row except for beams, which run outwards
+    /// from the origin
+    pub fn cells(&self, origin: Cell) -> Vec<Cell> {
+        match *self {
+            Template::Blast { radius } => raster::filled_circle(origin, radius),
+            Template::Cone {
+                toward,
+                radius,
+                angle,
+            } => {
+                let direction = offset(origin, toward).normalize();
+                if direction == Vec2::ZERO {
+                    return Vec::new();
+                }
+                let min_cos = (angle.clamp(0.0, std::f32::consts::TAU) / 2.0).cos();
+                raster::filled_circle(origin, radius)
+                    .into_iter()
+                    .filter(|&cell| {
+                        cell != origin && offset(origin, cell).normalize().dot(direction) >= min_cos
+                    })
+                    .collect()
+            }
+            Template::Beam {
+                toward,
+                width,
+                length,
+            } => {
+                let end = match length {
+                    Some(length) => {
+                        let direction = offset(origin, toward).normalize() * length as f32;
+                        (
+                            origin.0 + direction.x.round() as i32,
+                            origin.1 + direction.y.round() as i32,
+                        )
+                    }
+                    None => toward,
+                };
+                if end == origin {
+                    return Vec::new();
+                }
+                let mut cells = raster::thick_line(origin, end, width);
+                // Drop the origin and the rounded cap behind it
+                let direction = offset(origin, end);
+                cells.retain(|&cell| offset(origin, cell).dot(direction) > 0.0);
+                if width > 1.0 {
+                    // Thick lines come row by row; put them in order along the beam
+                    cells.sort_by(|&a, &b| {
+                        offset(origin, a)
+                            .length_squared()
+                            .total_cmp(&offset(origin, b).length_squared())
+                    });
+                }
+                cells
+            }
+            Template::Ring {
+                inner_radius,
+                outer_radius,
+            } => {
+                if inner_radius >= outer_radius {
+                    return Vec::new();
+                }
+                let inner = raster::filled_circle(origin, inner_radius);
+                raster::filled_circle(origin, outer_radius)
+                    .into_iter()
+                    .filter(|cell| {
+                        inner
+                            .binary_search_by_key(&(cell.1, cell.0), |&(x, y)| (y, x))
+                            .is_err()
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+fn offset(from: Cell, to: Cell) -> Vec2 {
+    Vec2::new((to.0 - from.0) as f32, (to.1 - from.1) as f32)
+}
+
+/// Whether `wall_map` leaves a clear line of sight from `origin` to `cell`: no wall
+/// on the [`raster::line`] between them, though either end may be a wall.
+/// Cells outside the map count as open.
+pub fn in_line_of_sight(origin: Cell, cell: Cell, wall_map: &[Vec<bool>]) -> bool {
+    let path = raster::line(origin, cell);
+    let between = &path[1.min(path.len() - 1)..path.len() - 1];
+    between.iter().all(|&(x, y)| !is_wall(wall_map, x, y))
+}
+
+fn is_wall(wall_map: &[Vec<bool>], x: i32, y: i32) -> bool {
+    x >= 0 && y >= 0 && grid::flag(wall_map, x as usize, y as usize)
+}
+
+/// Cells of `template` around `origin`. With a `wall_map`, only cells on the map and
+/// in sight of the origin are kept, so walls themselves are hit but shield what is
+/// behind them.
+pub fn affected_cells(
+    origin: Cell,
+    template: &Template,
+    wall_map: Option<&[Vec<bool>]>,
+) -> Vec<Cell> {
+    let mut cells = template.cells(origin);
+    if let Some(wall_map) = wall_map {
+        let (width, height) = grid::dimensions(wall_map);
+        cells.retain(|&(x, y)| {
+            x >= 0
+                && y >= 0
+                && (x as usize) < width
+                && (y as usize) < height
+                && in_line_of_sight(origin, (x, y), wall_map)
+        });
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn blasts_and_rings_are_filled_circles() {
+        let blast = Template::Blast { radius: 2 };
+        assert_eq!(blast.cells((3, 3)), raster::filled_circle((3, 3), 2));
+        let ring = Template::Ring {
+            inner_radius: 1,
+            outer_radius: 2,
+        };
+        let cells = ring.cells((0, 0));
+        assert_eq!(cells.len(), raster::filled_circle((0, 0), 2).len() - 5);
+        assert!(!cells.contains(&(0, 0)) && !cells.contains(&(1, 0)));
+        assert!(cells.contains(&(2, 0)));
+        let empty = Template::Ring {
+            inner_radius: 2,
+            outer_radius: 2,
+        };
+        assert!(empty.cells((0, 0)).is_empty());
+    }
+
+    #[test]
+    fn cones_open_towards_their_target() {
+        let cone = Template::Cone {
+            toward: (5, 0),
+            radius: 3,
+            angle: FRAC_PI_2,
+        };
+        let cells = cone.cells((0, 0));
+        assert!(cells.contains(&(3, 0)) && cells.contains(&(2, 2)));
+        assert!(cells.iter().all(|&(x, y)| x > 0 && y.abs() <= x));
+        let pointless = Template::Cone {
+            toward: (0, 0),
+            radius: 3,
+            angle: FRAC_PI_2,
+        };
+        assert!(pointless.cells((0, 0)).is_empty());
+    }
+
+    #[test]
+    fn beams_run_outwards_from_the_origin() {
+        let beam = |width, length| Template::Beam {
+            toward: (4, 0),
+            width,
+            length,
+        };
+        assert_eq!(
+            beam(1.0, None).cells((0, 0)),
+            [(1, 0), (2, 0), (3, 0), (4, 0)]
+        );
+        assert_eq!(beam(1.0, Some(2)).cells((0, 0)), [(1, 0), (2, 0)]);
+        assert!(beam(1.0, Some(0)).cells((0, 0)).is_empty());
+        let wide = beam(3.0, None).cells((0, 0));
+        assert!(wide.iter().all(|&(x, y)| x > 0 && y.abs() <= 1));
+        assert!(wide.windows(2).all(|pair| {
+            offset((0, 0), pair[0]).length_squared() <= offset((0, 0), pair[1]).length_squared()
+        }));
+    }
+
+    #[test]
+    fn walls_are_hit_but_shield_what_is_behind_them() {
+        let walls = vec![vec![false, false, true, false, false]];
+        let cells = affected_cells((0, 0), &Template::Blast { radius: 3 }, Some(&walls));
+        assert_eq!(cells, [(0, 0), (1, 0), (2, 0)]);
+        assert!(in_line_of_sight((0, 0), (2, 0), &walls));
+        assert!(!in_line_of_sight((0, 0), (3, 0), &walls));
+        assert!(in_line_of_sight((1, 0), (1, 0), &walls));
+        // Cells off the map count as open
+        assert!(in_line_of_sight((-3, 0), (-1, 0), &walls));
+        assert_eq!(
+            affected_cells((0, 0), &Template::Blast { radius: 1 }, None).len(),
+            5
+        );
+    }
+}
@@ -0,0 +1,398 @@
+//! Behaviour trees shared by many agents, each keeping its own running state.
+//!
+//! Composite and decorator nodes are evaluated here; `action` and `condition`
+//! leaves are decided by a callback, so the embedder owns what agents actually do.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    Failure,
+    Running,
+}
+
+impl Status {
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Success => "success",
+            Status::Failure => "failure",
+            Status::Running => "running",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeKind {
+    /// Run children in order until one fails
+    Sequence,
+    /// Run children in order until one succeeds
+    Selector,
+    /// Tick every child; succeed once `success_threshold` have succeeded
+    Parallel {
+        success_threshold: usize,
+    },
+    Inverter,
+    /// Turn a finished child's result into success
+    Succeeder,
+    /// Run the child `count` times (forever when unset), stopping early on failure
+    Repeat {
+        count: Option<usize>,
+    },
+    /// Fail without running the child until `seconds` after it last finished
+    Cooldown {
+        seconds: f64,
+    },
+    /// Stay running for `seconds`, then succeed
+    Wait {
+        seconds: f64,
+    },
+    Action(String),
+    Condition(String),
+}
+
+impl NodeKind {
+    /// Whether the node wraps exactly one child
+    pub fn is_decorator(&self) -> bool {
+        matches!(
+            self,
+            NodeKind::Inverter
+                | NodeKind::Succeeder
+                | NodeKind::Repeat { .. }
+                | NodeKind::Cooldown { .. }
+        )
+    }
+}
+
+/// A node and the indices of its children, which always come after it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub children: Vec<usize>,
+}
+
+/// Per-agent, per-node runtime state
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NodeState {
+    /// Child a sequence or selector resumes from
+    pub cursor: usize,
+    /// Completed repetitions
+    pub counter: usize,
+    /// Agent time at which a cooldown allows its child to run again
+    pub ready_at: f64,
+    /// Agent time at which a wait started
+    pub started_at: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Agent {
+    pub time: f64,
+    /// One state per tree node
+    pub nodes: Vec<NodeState>,
+}
+
+/// Deepest tree accepted, root included; ticking recurses once per level
+pub const MAX_DEPTH: usize = 64;
+
+/// A tree definition rooted at node 0, and the agents running it. Removed agent
+/// ids are not reused.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BehaviorTree {
+    pub nodes: Vec<Node>,
+    pub agents: Vec<Option<Agent>>,
+}
+
+impl BehaviorTree {
+    /// A tree with no agents yet
+    pub fn new(nodes: Vec<Node>) -> Self {
+        BehaviorTree {
+            nodes,
+            agents: Vec::new(),
+        }
+    }
+
+    fn fresh_agent(&self) -> Agent {
+        Agent {
+            time: 0.0,
+            nodes: vec![NodeState::default(); self.nodes.len()],
+        }
+    }
+
+    pub fn agent(&self, id: usize) -> Option<&Agent> {
+        self.agents.get(id).and_then(Option::as_ref)
+    }
+
+    /// Add an agent running this tree and return its id
+    pub fn add_agent(&mut self) -> usize {
+        let agent = self.fresh_agent();
+        self.agents.push(Some(agent));
+        self.agents.len() - 1
+    }
+
+    /// Remove an agent, returning whether it existed
+    pub fn remove_agent(&mut self, id: usize) -> bool {
+        self.agents.get_mut(id).and_then(Option::take).is_some()
+    }
+
+    /// Forget an agent's running nodes, timers and cooldowns, returning whether it
+    /// existed
+    pub fn reset_agent(&mut self, id: usize) -> bool {
+        let fresh = self.fresh_agent();
+        match self.agents.get_mut(id).and_then(Option::as_mut) {
+            Some(agent) => {
+                *agent = fresh;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of agents that have not been removed
+    pub fn len(&self) -> usize {
+        self.agents.iter().filter(|agent| agent.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Tick one agent, or `None` if there is no agent with that id. `leaf` decides
+    /// the result of each action or condition from its name; an error aborts the
+    /// rest of the tick.
+    pub fn tick_agent<E>(
+        &mut self,
+        id: usize,
+        delta_time: f64,
+        leaf: &mut impl FnMut(&str) -> Result<Status, E>,
+    ) -> Option<Result<Status, E>> {
+        let mut agent = self.agent(id)?.clone();
+        agent.time += delta_time;
+        let status = self.tick_node(0, &mut agent, leaf);
+        self.agents[id] = Some(agent);
+        Some(status)
+    }
+
+    /// Tick every agent, returning `(agent_id, status)` pairs; `leaf` is called
+    /// with the agent id and the leaf name
+    pub fn tick<E>(
+        &mut self,
+        delta_time: f64,
+        mut leaf: impl FnMut(usize, &str) -> Result<Status, E>,
+    ) -> Result<Vec<(usize, Status)>, E> {
+        let mut results = Vec::with_capacity(self.agents.len());
+        for id in 0..self.agents.len() {
+            if let Some(status) = self.tick_agent(id, delta_time, &mut |name| leaf(id, name)) {
+                results.push((id, status?));
+            }
+        }
+        Ok(results)
+    }
+
+    fn tick_node<E>(
+        &self,
+        index: usize,
+        agent: &mut Agent,
+        leaf: &mut impl FnMut(&str) -> Result<Status, E>,
+    ) -> Result<Status, E> {
+        let node = &self.nodes[index];
+        let status = match &node.kind {
+            NodeKind::Sequence | NodeKind::Selector => {
+                // A sequence continues on success, a selector on failure
+                let proceed = if matches!(node.kind, NodeKind::Sequence) {
+                    Status::Success
+                } else {
+                    Status::Failure
+                };
+                let mut result = proceed;
+                let mut i = agent.nodes[index].cursor;
+                while i < node.children.len() {
+                    let status = self.tick_node(node.children[i], agent, leaf)?;
+                    if status != proceed {
+                        result = status;
+                        break;
+                    }
+                    i += 1;
+                }
+                agent.nodes[index].cursor = if result == Status::Running { i } else { 0 };
+                result
+            }
+            NodeKind::Parallel { success_threshold } => {
+                let threshold = (*success_threshold).min(node.children.len());
+                let (mut successes, mut failures) = (0, 0);
+                for &child in &node.children {
+                    match self.tick_node(child, agent, leaf)? {
+                        Status::Success => successes += 1,
+                        Status::Failure => failures += 1,
+                        Status::Running => {}
+                    }
+                }
+                if successes >= threshold {
+                    Status::Success
+                } else if failures > node.children.len() - threshold {
+                    Status::Failure
+                } else {
+                    Status::Running
+                }
+            }
+            NodeKind::Inverter => match self.tick_node(node.children[0], agent, leaf)? {
+                Status::Success => Status::Failure,
+                Status::Failure => Status::Success,
+                Status::Running => Status::Running,
+            },
+            NodeKind::Succeeder => match self.tick_node(node.children[0], agent, leaf)? {
+                Status::Running => Status::Running,
+                _ => Status::Success,
+            },
+            NodeKind::Repeat { count } => match self.tick_node(node.children[0], agent, leaf)? {
+                Status::Running => Status::Running,
+                Status::Failure => {
+                    agent.nodes[index].counter = 0;
+                    Status::Failure
+                }
+                Status::Success => {
+                    agent.nodes[index].counter += 1;
+                    if count.is_some_and(|count| agent.nodes[index].counter >= count) {
+                        agent.nodes[index].counter = 0;
+                        Status::Success
+                    } else {
+                        // Run the next repetition on the next tick rather than looping here
+                        Status::Running
+                    }
+                }
+            },
+            NodeKind::Cooldown { seconds } => {
+                if agent.time < agent.nodes[index].ready_at {
+                    Status::Failure
+                } else {
+                    let status = self.tick_node(node.children[0], agent, leaf)?;
+                    if status != Status::Running {
+                        agent.nodes[index].ready_at = agent.time + seconds;
+                    }
+                    status
+                }
+            }
+            NodeKind::Wait { seconds } => {
+                let started_at = *agent.nodes[index].started_at.get_or_insert(agent.time);
+                if agent.time - started_at >= *seconds {
+                    agent.nodes[index].started_at = None;
+                    Status::Success
+                } else {
+                    Status::Running
+                }
+            }
+            NodeKind::Action(name) => leaf(name)?,
+            NodeKind::Condition(name) => match leaf(name)? {
+                // Conditions answer immediately; "still thinking" counts as no
+                Status::Running => Status::Failure,
+                status => status,
+            },
+        };
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    fn node(kind: NodeKind, children: &[usize]) -> Node {
+        Node {
+            kind,
+            children: children.to_vec(),
+        }
+    }
+
+    /// Guard with a condition, then wait before acting
+    fn patrol() -> BehaviorTree {
+        BehaviorTree::new(vec![
+            node(NodeKind::Sequence, &[1, 2, 3]),
+            node(NodeKind::Condition("awake".to_string()), &[]),
+            node(NodeKind::Wait { seconds: 1.0 }, &[]),
+            node(NodeKind::Action("walk".to_string()), &[]),
+        ])
+    }
+
+    fn always(status: Status) -> impl FnMut(&str) -> Result<Status, Infallible> {
+        move |_| Ok(status)
+    }
+
+    #[test]
+    fn sequences_resume_from_the_running_child() {
+        let mut tree = patrol();
+        let id = tree.add_agent();
+        let mut calls = Vec::new();
+        let mut leaf = |name: &str| -> Result<Status, Infallible> {
+            calls.push(name.to_string());
+            Ok(Status::Success)
+        };
+        assert_eq!(
+            tree.tick_agent(id, 0.5, &mut leaf),
+            Some(Ok(Status::Running))
+        );
+        assert_eq!(
+            tree.tick_agent(id, 0.6, &mut leaf),
+            Some(Ok(Status::Running))
+        );
+        assert_eq!(
+            tree.tick_agent(id, 0.4, &mut leaf),
+            Some(Ok(Status::Success))
+        );
+        // The condition is not asked again while the wait is running
+        assert_eq!(calls, ["awake", "walk"]);
+    }
+
+    #[test]
+    fn running_conditions_count_as_failure() {
+        let mut tree = patrol();
+        let id = tree.add_agent();
+        let status = tree.tick_agent(id, 2.0, &mut always(Status::Running));
+        assert_eq!(status, Some(Ok(Status::Failure)));
+    }
+
+    #[test]
+    fn cooldowns_and_repeats_keep_per_agent_state() {
+        let mut tree = BehaviorTree::new(vec![
+            node(NodeKind::Cooldown { seconds: 1.0 }, &[1]),
+            node(NodeKind::Repeat { count: Some(2) }, &[2]),
+            node(NodeKind::Action("swing".to_string()), &[]),
+        ]);
+        let first = tree.add_agent();
+        let second = tree.add_agent();
+        let mut leaf = always(Status::Success);
+        assert_eq!(
+            tree.tick_agent(first, 0.1, &mut leaf),
+            Some(Ok(Status::Running))
+        );
+        assert_eq!(
+            tree.tick_agent(first, 0.1, &mut leaf),
+            Some(Ok(Status::Success))
+        );
+        assert_eq!(
+            tree.tick_agent(first, 0.1, &mut leaf),
+            Some(Ok(Status::Failure))
+        );
+        assert_eq!(
+            tree.tick_agent(second, 0.1, &mut leaf),
+            Some(Ok(Status::Running))
+        );
+        assert!(tree.reset_agent(first));
+        assert_eq!(tree.agent(first).unwrap().nodes[0], NodeState::default());
+    }
+
+    #[test]
+    fn errors_abort_the_tick_and_removed_agents_are_skipped() {
+        let mut tree = patrol();
+        let removed = tree.add_agent();
+        let id = tree.add_agent();
+        assert!(tree.remove_agent(removed));
+        assert!(!tree.remove_agent(removed));
+        assert_eq!(
+            tree.tick_agent(removed, 1.0, &mut always(Status::Success)),
+            None
+        );
+        let failed = tree.tick(1.0, |_, name| Err(name.to_string()));
+        assert_eq!(failed, Err("awake".to_string()));
+        assert_eq!(tree.agent(id).unwrap().time, 1.0);
+        assert_eq!(tree.len(), 1);
+    }
+}
@@ -0,0 +1,184 @@
+//! A 2D follow camera: smoothing, a deadzone, velocity look-ahead, world bounds and
+//! trauma-driven screen shake.
+//!
+//! Everything is frame-rate independent, so updating once per rendered frame with
+//! an interpolated target gives the same motion at any frame rate and does not
+//! jitter against a fixed physics step.
+
+use crate::rng::Rng;
+use crate::shapes::Rect;
+use crate::vec2::Vec2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera {
+    /// Centre of the view in world units, before shake
+    pub position: Vec2,
+    /// Width and height of the view in world units
+    pub viewport: Vec2,
+    /// How quickly the camera catches up, per second; 0 snaps straight to the goal
+    pub smoothing: f32,
+    /// Half-size of the box around the centre the target can move in without the
+    /// camera following
+    pub deadzone: Vec2,
+    /// Seconds of target velocity to lead by
+    pub look_ahead: f32,
+    /// Area the view must stay inside
+    pub bounds: Option<Rect>,
+    /// Shake strength from 0 to 1; the shake itself grows with its square
+    pub trauma: f32,
+    /// Trauma lost per second
+    pub trauma_decay: f32,
+    /// Offset at full trauma, in world units
+    pub max_shake_offset: Vec2,
+    /// Rotation at full trauma, in radians
+    pub max_shake_angle: f32,
+    /// Speed of the shake noise, roughly changes of direction per second
+    pub shake_frequency: f32,
+    pub seed: u64,
+    time: f32,
+    shake_offset: Vec2,
+    shake_angle: f32,
+}
+
+impl Camera {
+    pub fn new(position: Vec2, viewport: Vec2) -> Self {
+        Camera {
+            position,
+            viewport,
+            smoothing: 8.0,
+            deadzone: Vec2::ZERO,
+            look_ahead: 0.0,
+            bounds: None,
+            trauma: 0.0,
+            trauma_decay: 1.0,
+            max_shake_offset: Vec2::new(8.0, 8.0),
+            max_shake_angle: 0.05,
+            shake_frequency: 15.0,
+            seed: 0,
+            time: 0.0,
+            shake_offset: Vec2::ZERO,
+            shake_angle: 0.0,
+        }
+    }
+
+    /// Current shake offset, added to [`Camera::position`] when rendering
+    pub fn shake_offset(&self) -> Vec2 {
+        self.shake_offset
+    }
+
+    /// Current shake rotation in radians
+    pub fn shake_angle(&self) -> f32 {
+        self.shake_angle
+    }
+
+    /// Seconds the shake noise has run for
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Put back shake state read from [`Camera::time`], [`Camera::shake_offset`] and
+    /// [`Camera::shake_angle`], e.g. when loading a saved camera
+    pub fn restore_shake(&mut self, time: f32, offset: Vec2, angle: f32) {
+        self.time = time;
+        self.shake_offset = offset;
+        self.shake_angle = angle;
+    }
+
+    /// Centre of the view to render from, shake included
+    pub fn view_center(&self) -> Vec2 {
+        self.position + self.shake_offset
+    }
+
+    /// The area to render, shake included but not its rotation
+    pub fn view_rect(&self) -> Rect {
+        let center = self.view_center();
+        Rect::new(
+            center.x - self.viewport.x / 2.0,
+            center.y - self.viewport.y / 2.0,
+            self.viewport.x,
+            self.viewport.y,
+        )
+    }
+
+    /// Add trauma, e.g. 0.3 for a hit and 1.0 for an explosion; it is capped at 1
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// Jump to centre on `target` at once, within the bounds
+    pub fn snap_to(&mut self, target: Vec2) {
+        self.position = self.clamp_to_bounds(target);
+    }
+
+    /// Follow a target moving at `velocity` for `delta` seconds
+    pub fn update(&mut self, target: Vec2, velocity: Vec2, delta: f32) {
+        let delta = delta.max(0.0);
+        let focus = target + velocity * self.look_ahead;
+        let goal = Vec2::new(
+            deadzone_follow(self.position.x, focus.x, self.deadzone.x),
+            deadzone_follow(self.position.y, focus.y, self.deadzone.y),
+        );
+        self.position = if self.smoothing > 0.0 {
+            self.position
+                .lerp(goal, 1.0 - (-self.smoothing * delta).exp())
+        } else {
+            goal
+        };
+        self.position = self.clamp_to_bounds(self.position);
+
+        self.time += delta;
+        self.trauma = (self.trauma - self.trauma_decay * delta).clamp(0.0, 1.0);
+        let shake = self.trauma * self.trauma;
+        let t = self.time * self.shake_frequency;
+        self.shake_offset = Vec2::new(
+            self.max_shake_offset.x * shake * noise(self.seed, 0, t),
+            self.max_shake_offset.y * shake * noise(self.seed, 1, t),
+        );
+        self.shake_angle = self.max_shake_angle * shake * noise(self.seed, 2, t);
+    }
+
+    /// `center` moved so the view stays inside the bounds, or centred on them when
+    /// the view is larger
+    fn clamp_to_bounds(&self, center: Vec2) -> Vec2 {
+        let Some(bounds) = self.bounds else {
+            return center;
+        };
+        let clamp_axis = |value: f32, start: f32, size: f32, view: f32| {
+            if view >= size {
+                start + size / 2.0
+            } else {
+                value.clamp(start + view / 2.0, start + size - view / 2.0)
+            }
+        };
+        Vec2::new(
+            clamp_axis(center.x, bounds.x, bounds.width, self.viewport.x),
+            clamp_axis(center.y, bounds.y, bounds.height, self.viewport.y),
+        )
+    }
+}
+
+/// Where the camera must be on one axis to keep `focus` within `deadzone` of it
+fn deadzone_follow(camera: f32, focus: f32, deadzone: f32) -> f32 {
+    let deadzone = deadzone.max(0.0);
+    if focus > camera + deadzone {
+        focus - deadzone
+    } else if focus < camera - deadzone {
+        focus + deadzone
+    } else {
+        camera
+    }
+}
+
+/// Smooth 1D value noise in `[-1, 1]`, a separate stream for each `channel`
+fn noise(seed: u64, channel: u64, t: f32) -> f32 {
+    let lattice = |i: i64| {
+        let key = seed ^ channel.wrapping_mul(0xD6E8_FEB8_6659_FD93) ^ (i as u64);
+        Rng::new(key).next_f32() * 2.0 - 1.0
+    };
+    let i = t.floor();
+    let f = t - i;
+    // Smoothstep between lattice values so the shake has no corners
+    let f = f * f * (3.0 - 2.0 * f);
+    let (a, b) = (lattice(i as i64), lattice(i as i64 + 1));
+    a + (b - a) * f
+}
@@ -0,0 +1,48 @@
+//! Stable 64-bit hashing of simulation state, for spotting desyncs between peers.
+//!
+//! FNV-1a is used rather than `std::hash` because its output is fixed by
+//! specification: the same bytes hash the same on every platform, build and run.
+
+const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+/// An FNV-1a hash being built up from byte strings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checksum {
+    hash: u64,
+}
+
+impl Default for Checksum {
+    fn default() -> Self {
+        Checksum { hash: OFFSET_BASIS }
+    }
+}
+
+impl Checksum {
+    pub fn new() -> Self {
+        Checksum::default()
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash = (self.hash ^ u64::from(byte)).wrapping_mul(PRIME);
+        }
+    }
+
+    /// Hash a length prefix and then `bytes`, so consecutive strings cannot run together
+    pub fn update_framed(&mut self, bytes: &[u8]) {
+        self.update(&(bytes.len() as u64).to_le_bytes());
+        self.update(bytes);
+    }
+
+    pub fn value(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// The checksum of a single byte string
+pub fn checksum(bytes: &[u8]) -> u64 {
+    let mut hash = Checksum::new();
+    hash.update(bytes);
+    hash.value()
+}
@@ -0,0 +1,286 @@
+//! Game time: pausing, time scaling, fixed-step accumulation and named timers,
+//! all advanced by the same tick.
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Slack for rounding error, so 0.1 + 0.1 + 0.1 seconds counts as three 0.1 steps
+const EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Timer {
+    Countdown {
+        duration: f64,
+        remaining: f64,
+        repeat: bool,
+        /// How many times it has run out since the last tick
+        fired: u32,
+    },
+    Stopwatch {
+        elapsed: f64,
+        running: bool,
+    },
+}
+
+impl Timer {
+    /// A countdown of `duration` game seconds, which should be positive
+    pub fn countdown(duration: f64, repeat: bool) -> Self {
+        Timer::Countdown {
+            duration,
+            remaining: duration,
+            repeat,
+            fired: 0,
+        }
+    }
+
+    /// A running stopwatch starting from zero
+    pub fn stopwatch() -> Self {
+        Timer::Stopwatch {
+            elapsed: 0.0,
+            running: true,
+        }
+    }
+
+    /// Game seconds left on a countdown, or `None` for a stopwatch
+    pub fn remaining(&self) -> Option<f64> {
+        match self {
+            Timer::Countdown { remaining, .. } => Some(remaining.max(0.0)),
+            Timer::Stopwatch { .. } => None,
+        }
+    }
+
+    /// Game seconds counted by a stopwatch, or run so far by a countdown
+    pub fn elapsed(&self) -> f64 {
+        match self {
+            Timer::Countdown {
+                duration,
+                remaining,
+                ..
+            } => duration - remaining.max(0.0),
+            Timer::Stopwatch { elapsed, .. } => *elapsed,
+        }
+    }
+
+    /// Whether this is a one-shot countdown that has run out
+    pub fn finished(&self) -> bool {
+        matches!(self, Timer::Countdown { remaining, repeat: false, .. } if *remaining <= 0.0)
+    }
+
+    fn advance(&mut self, delta: f64) {
+        match self {
+            Timer::Countdown {
+                duration,
+                remaining,
+                repeat,
+                fired,
+            } => {
+                *fired = 0;
+                if *remaining <= 0.0 {
+                    return;
+                }
+                *remaining -= delta;
+                if *remaining > EPSILON {
+                    return;
+                }
+                if !*repeat {
+                    *fired = 1;
+                    *remaining = 0.0;
+                    return;
+                }
+                // Every whole `duration` past the deadline is one more firing;
+                // the cast saturates should a huge delta fire it over u32::MAX times
+                let overdue = EPSILON - *remaining;
+                *fired = (overdue.div_euclid(*duration) + 1.0) as u32;
+                *remaining = EPSILON + *duration - overdue.rem_euclid(*duration);
+            }
+            Timer::Stopwatch { elapsed, running } => {
+                if *running {
+                    *elapsed += delta;
+                }
+            }
+        }
+    }
+}
+
+/// Why a tick was refused
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TickError {
+    /// The real frame time is infinite or NaN
+    NotFinite,
+    /// The frame time scaled by `time_scale` overflows
+    Overflow,
+}
+
+impl fmt::Display for TickError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TickError::NotFinite => write!(f, "tick delta must be finite"),
+            TickError::Overflow => write!(f, "tick delta times time_scale is too large"),
+        }
+    }
+}
+
+impl std::error::Error for TickError {}
+
+/// `advance(real_delta)` returns how many fixed steps are due; run the simulation
+/// that many times at `fixed_step` and interpolate rendering with `alpha`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameClock {
+    /// Scaled game time elapsed
+    pub time: f64,
+    /// Unscaled time passed to `advance`, including while paused
+    pub real_time: f64,
+    /// Scaled time added by the last tick
+    pub delta: f64,
+    pub frame: u64,
+    pub paused: bool,
+    /// Finite and not negative; 0 stops time
+    pub time_scale: f64,
+    /// Positive
+    pub fixed_step: f64,
+    /// Fixed steps a single tick may produce; leftover time beyond it is dropped so a
+    /// long hitch cannot snowball into ever longer catch-up frames
+    pub max_steps: u32,
+    pub accumulator: f64,
+    /// Fixed steps produced by the last tick
+    pub steps: u32,
+    pub timers: HashMap<String, Timer>,
+}
+
+impl GameClock {
+    /// A clock at time 0; `fixed_step` must be positive and `time_scale` finite
+    /// and not negative
+    pub fn new(fixed_step: f64, time_scale: f64, max_steps: u32) -> Self {
+        GameClock {
+            time: 0.0,
+            real_time: 0.0,
+            delta: 0.0,
+            frame: 0,
+            paused: false,
+            time_scale,
+            fixed_step,
+            max_steps,
+            accumulator: 0.0,
+            steps: 0,
+            timers: HashMap::new(),
+        }
+    }
+
+    /// Advance by a real frame time and return the number of fixed steps due;
+    /// fails, changing nothing, if it or the scaled time it adds is not finite
+    pub fn advance(&mut self, real_delta: f64) -> Result<u32, TickError> {
+        if !real_delta.is_finite() {
+            return Err(TickError::NotFinite);
+        }
+        let real_delta = real_delta.max(0.0);
+        let delta = if self.paused {
+            0.0
+        } else {
+            real_delta * self.time_scale
+        };
+        if !delta.is_finite() {
+            return Err(TickError::Overflow);
+        }
+        self.real_time += real_delta;
+        self.frame += 1;
+        self.delta = delta;
+        self.time += self.delta;
+
+        self.accumulator += self.delta;
+        let due = (self.accumulator / self.fixed_step + EPSILON).floor();
+        self.steps = (due as u32).min(self.max_steps);
+        self.accumulator = if due > self.max_steps as f64 {
+            0.0
+        } else {
+            (self.accumulator - due * self.fixed_step).max(0.0)
+        };
+
+        for timer in self.timers.values_mut() {
+            timer.advance(self.delta);
+        }
+        Ok(self.steps)
+    }
+
+    /// Interpolation factor between the last two fixed steps, 0 to 1
+    pub fn alpha(&self) -> f64 {
+        (self.accumulator / self.fixed_step).clamp(0.0, 1.0)
+    }
+
+    /// Countdowns that ran out during the last tick, with how many times each did,
+    /// sorted by name
+    pub fn fired(&self) -> Vec<(String, u32)> {
+        let mut fired: Vec<(String, u32)> = self
+            .timers
+            .iter()
+            .filter_map(|(name, timer)| match timer {
+                Timer::Countdown { fired, .. } if *fired > 0 => Some((name.clone(), *fired)),
+                _ => None,
+            })
+            .collect();
+        fired.sort();
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_accumulate_fixed_steps() {
+        let mut clock = GameClock::new(0.1, 1.0, 8);
+        assert_eq!(clock.advance(0.05), Ok(0));
+        assert_eq!(clock.advance(0.05), Ok(1));
+        assert_eq!(clock.advance(0.1), Ok(1));
+        assert_eq!(clock.advance(0.1), Ok(1));
+        assert!(clock.alpha() < 1e-6);
+        // A hitch is capped and its leftover time dropped
+        assert_eq!(clock.advance(5.0), Ok(8));
+        assert_eq!(clock.alpha(), 0.0);
+        assert_eq!(clock.frame, 5);
+    }
+
+    #[test]
+    fn pausing_and_scaling_change_game_time_only() {
+        let mut clock = GameClock::new(1.0, 2.0, 8);
+        clock.advance(1.0).unwrap();
+        clock.paused = true;
+        clock.advance(1.0).unwrap();
+        assert_eq!((clock.time, clock.real_time, clock.delta), (2.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn countdowns_fire_and_repeat() {
+        let mut clock = GameClock::new(0.1, 1.0, 8);
+        clock
+            .timers
+            .insert("fuse".to_string(), Timer::countdown(0.3, false));
+        clock
+            .timers
+            .insert("pulse".to_string(), Timer::countdown(0.1, true));
+        for _ in 0..3 {
+            clock.advance(0.1).unwrap();
+        }
+        assert_eq!(
+            clock.fired(),
+            [("fuse".to_string(), 1), ("pulse".to_string(), 1)]
+        );
+        assert!(clock.timers["fuse"].finished());
+        clock.advance(0.25).unwrap();
+        assert_eq!(clock.fired(), [("pulse".to_string(), 2)]);
+        assert!((clock.timers["pulse"].remaining().unwrap() - 0.05).abs() < 1e-6);
+    }
+
+    #[test]
+    fn stopwatches_count_while_running_and_bad_ticks_change_nothing() {
+        let mut clock = GameClock::new(0.1, 1e308, 8);
+        clock.timers.insert("lap".to_string(), Timer::stopwatch());
+        assert_eq!(clock.advance(f64::NAN), Err(TickError::NotFinite));
+        assert_eq!(clock.advance(10.0), Err(TickError::Overflow));
+        assert_eq!(clock.frame, 0);
+        clock.time_scale = 1.0;
+        clock.advance(0.5).unwrap();
+        assert_eq!(clock.timers["lap"].elapsed(), 0.5);
+        assert_eq!(clock.timers["lap"].remaining(), None);
+    }
+}
@@ -0,0 +1,391 @@
+//! Little binary encoding shared by the replay and saved-state formats.
+//!
+//! Fixed-size numbers are little-endian, counts and lengths are LEB128 varints, and
+//! every top-level blob starts with four magic bytes and a format version so the
+//! reader can reject the wrong kind of file before decoding anything.
+
+use std::fmt;
+
+use crate::vec2::Vec2;
+
+/// Why a [`Reader`] rejected its input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The data does not start with the expected magic bytes; holds what was expected
+    WrongKind(&'static str),
+    UnsupportedVersion {
+        found: u8,
+        expected: u8,
+    },
+    /// The data ends in the middle of a value
+    Truncated,
+    /// A value was read but makes no sense, e.g. an out-of-range index
+    Invalid(String),
+    /// Bytes are left over after the last value
+    TrailingData(usize),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::WrongKind(kind) => write!(f, "data is not {}", kind),
+            DecodeError::UnsupportedVersion { found, expected } => {
+                write!(f, "unsupported version {}, expected {}", found, expected)
+            }
+            DecodeError::Truncated => write!(f, "data is truncated"),
+            DecodeError::Invalid(reason) => write!(f, "{}", reason),
+            DecodeError::TrailingData(count) => write!(f, "{} unexpected bytes at the end", count),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Clone, Debug, Default)]
+pub struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Writer::default()
+    }
+
+    /// A writer that starts with `magic` and `version`, for [`Reader::with_header`]
+    pub fn with_header(magic: &[u8; 4], version: u8) -> Self {
+        let mut writer = Writer::new();
+        writer.bytes.extend_from_slice(magic);
+        writer.u8(version);
+        writer
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    /// A count or index, as a varint
+    pub fn usize(&mut self, value: usize) {
+        self.varint(value as u64);
+    }
+
+    /// A signed varint, zigzag-encoded so small negative numbers stay short
+    pub fn i64(&mut self, value: i64) {
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    pub fn f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn f64(&mut self, value: f64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn vec2(&mut self, value: Vec2) {
+        self.f32(value.x);
+        self.f32(value.y);
+    }
+
+    /// A length-prefixed byte string
+    pub fn bytes(&mut self, value: &[u8]) {
+        self.usize(value.len());
+        self.bytes.extend_from_slice(value);
+    }
+
+    pub fn str(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    /// A presence flag followed by the value, if any
+    pub fn option<T>(&mut self, value: Option<T>, write: impl FnOnce(&mut Self, T)) {
+        self.bool(value.is_some());
+        if let Some(value) = value {
+            write(self, value);
+        }
+    }
+}
+
+pub struct Reader<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Reader { data, at: 0 }
+    }
+
+    /// Check the header written by [`Writer::with_header`]; `kind` names the format in
+    /// the error, e.g. `"a replay"`
+    pub fn with_header(
+        data: &'a [u8],
+        magic: &[u8; 4],
+        version: u8,
+        kind: &'static str,
+    ) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(data);
+        if reader.take(magic.len()).ok() != Some(magic.as_slice()) {
+            return Err(DecodeError::WrongKind(kind));
+        }
+        let found = reader.u8()?;
+        if found != version {
+            return Err(DecodeError::UnsupportedVersion {
+                found,
+                expected: version,
+            });
+        }
+        Ok(reader)
+    }
+
+    /// Fail unless every byte has been read
+    pub fn finish(self) -> Result<(), DecodeError> {
+        match self.data.len() - self.at {
+            0 => Ok(()),
+            left => Err(DecodeError::TrailingData(left)),
+        }
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self
+            .at
+            .checked_add(count)
+            .filter(|&end| end <= self.data.len())
+            .ok_or(DecodeError::Truncated)?;
+        let bytes = &self.data[self.at..end];
+        self.at = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        Ok(self.take(N)?.try_into().expect("took exactly N bytes"))
+    }
+
+    pub fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, DecodeError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(DecodeError::Invalid(format!("invalid flag byte {}", other))),
+        }
+    }
+
+    pub fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DecodeError::Invalid(
+            "varint is longer than ten bytes".to_string(),
+        ))
+    }
+
+    pub fn usize(&mut self) -> Result<usize, DecodeError> {
+        let value = self.varint()?;
+        usize::try_from(value)
+            .map_err(|_| DecodeError::Invalid(format!("count {} is too large", value)))
+    }
+
+    pub fn i64(&mut self) -> Result<i64, DecodeError> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    pub fn f32(&mut self) -> Result<f32, DecodeError> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, DecodeError> {
+        Ok(f64::from_le_bytes(self.array()?))
+    }
+
+    pub fn vec2(&mut self) -> Result<Vec2, DecodeError> {
+        Ok(Vec2::new(self.f32()?, self.f32()?))
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let length = self.usize()?;
+        self.take(length)
+    }
+
+    pub fn str(&mut self) -> Result<&'a str, DecodeError> {
+        std::str::from_utf8(self.bytes()?)
+            .map_err(|_| DecodeError::Invalid("string is not valid UTF-8".to_string()))
+    }
+
+    pub fn option<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, DecodeError>,
+    ) -> Result<Option<T>, DecodeError> {
+        if self.bool()? {
+            read(self).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varints_round_trip_and_stay_short() {
+        let values = [
+            0,
+            1,
+            127,
+            128,
+            300,
+            16_383,
+            16_384,
+            u64::from(u32::MAX),
+            u64::MAX,
+        ];
+        let mut writer = Writer::new();
+        for value in values {
+            writer.varint(value);
+        }
+        let bytes = writer.into_bytes();
+        // 1 + 1 + 1 + 2 + 2 + 2 + 3 + 5 + 10
+        assert_eq!(bytes.len(), 27);
+        let mut reader = Reader::new(&bytes);
+        for value in values {
+            assert_eq!(reader.varint(), Ok(value));
+        }
+        reader.finish().unwrap();
+    }
+
+    #[test]
+    fn zigzag_keeps_small_negative_numbers_short() {
+        let values = [0, -1, 1, -64, 63, -65, i64::MIN, i64::MAX];
+        let mut writer = Writer::new();
+        for value in values {
+            writer.i64(value);
+        }
+        let bytes = writer.into_bytes();
+        assert_eq!(&bytes[..5], [0, 1, 2, 127, 126]);
+        let mut reader = Reader::new(&bytes);
+        for value in values {
+            assert_eq!(reader.i64(), Ok(value));
+        }
+        reader.finish().unwrap();
+    }
+
+    #[test]
+    fn mixed_values_round_trip_behind_a_header() {
+        let mut writer = Writer::with_header(b"TEST", 3);
+        writer.bool(true);
+        writer.u64(0x0102_0304_0506_0708);
+        writer.f32(-1.5);
+        writer.f64(f64::INFINITY);
+        writer.vec2(Vec2::new(2.0, -3.0));
+        writer.str("héllo");
+        writer.option(Some(7usize), Writer::usize);
+        writer.option(None::<usize>, Writer::usize);
+        let bytes = writer.into_bytes();
+
+        let mut reader = Reader::with_header(&bytes, b"TEST", 3, "a test").unwrap();
+        assert_eq!(reader.bool(), Ok(true));
+        assert_eq!(reader.u64(), Ok(0x0102_0304_0506_0708));
+        assert_eq!(reader.f32(), Ok(-1.5));
+        assert_eq!(reader.f64(), Ok(f64::INFINITY));
+        assert_eq!(reader.vec2(), Ok(Vec2::new(2.0, -3.0)));
+        assert_eq!(reader.str(), Ok("héllo"));
+        assert_eq!(reader.option(Reader::usize), Ok(Some(7)));
+        assert_eq!(reader.option(Reader::usize), Ok(None));
+        reader.finish().unwrap();
+    }
+
+    #[test]
+    fn truncated_data_is_refused_wherever_it_stops() {
+        let mut writer = Writer::new();
+        writer.varint(300);
+        writer.f64(1.0);
+        writer.str("name");
+        let bytes = writer.into_bytes();
+        for end in 0..bytes.len() {
+            let mut reader = Reader::new(&bytes[..end]);
+            let read = reader
+                .varint()
+                .and_then(|_| reader.f64())
+                .and_then(|_| reader.str().map(str::len));
+            assert_eq!(read, Err(DecodeError::Truncated), "cut at {}", end);
+        }
+        // A length prefix claiming more bytes than there are
+        assert_eq!(Reader::new(&[5, b'a']).bytes(), Err(DecodeError::Truncated));
+        assert_eq!(
+            Reader::new(&[u8::MAX; 9]).varint(),
+            Err(DecodeError::Truncated)
+        );
+    }
+
+    #[test]
+    fn malformed_values_are_refused() {
+        assert!(matches!(
+            Reader::new(&[0x80; 11]).varint(),
+            Err(DecodeError::Invalid(_))
+        ));
+        assert!(matches!(
+            Reader::new(&[2]).bool(),
+            Err(DecodeError::Invalid(_))
+        ));
+        assert!(matches!(
+            Reader::new(&[2, 0xC3, 0x28]).str(),
+            Err(DecodeError::Invalid(_))
+        ));
+        let mut reader = Reader::new(&[1, 2, 3]);
+        reader.u8().unwrap();
+        assert_eq!(reader.finish(), Err(DecodeError::TrailingData(2)));
+    }
+
+    #[test]
+    fn headers_are_checked_before_anything_else() {
+        let bytes = Writer::with_header(b"LQRP", 2).into_bytes();
+        assert!(matches!(
+            Reader::with_header(&bytes, b"LQSV", 2, "a save"),
+            Err(DecodeError::WrongKind("a save"))
+        ));
+        assert!(matches!(
+            Reader::with_header(&bytes, b"LQRP", 3, "a replay"),
+            Err(DecodeError::UnsupportedVersion {
+                found: 2,
+                expected: 3
+            })
+        ));
+        assert!(matches!(
+            Reader::with_header(&bytes[..2], b"LQRP", 2, "a replay"),
+            Err(DecodeError::WrongKind(_))
+        ));
+    }
+}
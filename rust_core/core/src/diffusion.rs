@@ -0,0 +1,50 @@
+//! Conservative diffusion over flat row-major grids, shared by the scent, fluid and
+//! temperature layers
+
+use crate::grid;
+
+/// One explicit diffusion step between orthogonal neighbours.
+///
+/// The flow between two cells is `rate * min(conductance) * difference / 4`, so a
+/// conductance of 0 is a wall and nothing is created or destroyed. `rate` above 1
+/// can overshoot and oscillate.
+pub fn diffuse(values: &mut [f32], conductance: &[f32], width: usize, height: usize, rate: f32) {
+    let mut next = values.to_vec();
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * width + x;
+            if conductance[index] <= 0.0 {
+                continue;
+            }
+            // Looking right and down visits every pair once
+            for (dx, dy) in [(1, 0), (0, 1)] {
+                let Some((nx, ny)) = grid::offset(x, y, dx, dy, width, height) else {
+                    continue;
+                };
+                let neighbour = ny * width + nx;
+                let link = conductance[index].min(conductance[neighbour]);
+                if link <= 0.0 {
+                    continue;
+                }
+                let flow = rate * link * (values[neighbour] - values[index]) * 0.25;
+                next[index] += flow;
+                next[neighbour] -= flow;
+            }
+        }
+    }
+    values.copy_from_slice(&next);
+}
+
+/// Flatten a `[y][x]` walkable map into conductances of 1 (open) and 0 (wall)
+pub fn open_cells(walkable_map: &[Vec<bool>]) -> (usize, usize, Vec<f32>) {
+    let (width, height) = grid::dimensions(walkable_map);
+    let mut conductance = vec![0.0; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            if grid::flag(walkable_map, x, y) {
+                conductance[y * width + x] = 1.0;
+            }
+        }
+    }
+    (width, height, conductance)
+}
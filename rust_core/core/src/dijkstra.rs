@@ -0,0 +1,320 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::grid;
+use crate::hooks::{self, Level};
+
+/// A "Dijkstra map": the walking distance from every cell to the nearest goal.
+///
+/// Goals hold 0, unreachable and blocked cells hold infinity. Every step costs 1,
+/// including diagonal ones when they are enabled.
+pub struct DijkstraMap {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<f32>,
+    passable: Vec<bool>,
+    diagonal: bool,
+}
+
+/// How many cells a scan settles between checks for cancellation and progress
+const CHECK_INTERVAL: u64 = 4096;
+
+/// Heap entry ordered so that `BinaryHeap` pops the lowest value first
+#[derive(PartialEq)]
+struct Frontier(f32, usize);
+
+impl Eq for Frontier {}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .0
+            .partial_cmp(&self.0)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.1.cmp(&self.1))
+    }
+}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl DijkstraMap {
+    /// Build a map over the walkable cells with every value unset
+    pub fn new(walkable_map: &[Vec<bool>], diagonal: bool) -> Self {
+        let (width, height) = grid::dimensions(walkable_map);
+        let mut passable = vec![false; width * height];
+        for (y, row) in walkable_map.iter().enumerate() {
+            for (x, &walkable) in row.iter().enumerate().take(width) {
+                passable[y * width + x] = walkable;
+            }
+        }
+        DijkstraMap {
+            width,
+            height,
+            values: vec![f32::INFINITY; width * height],
+            passable,
+            diagonal,
+        }
+    }
+
+    /// Build a map and scan it outwards from the given goal cells
+    pub fn from_goals(
+        walkable_map: &[Vec<bool>],
+        goals: &[(usize, usize)],
+        diagonal: bool,
+    ) -> Self {
+        let mut map = DijkstraMap::new(walkable_map, diagonal);
+        let mut seeded = 0;
+        for &(x, y) in goals {
+            if x >= map.width || y >= map.height {
+                hooks::log(
+                    Level::Warning,
+                    format_args!(
+                        "ignoring Dijkstra goal ({}, {}): it is outside the {}x{} map",
+                        x, y, map.width, map.height
+                    ),
+                );
+            } else if map.passable[y * map.width + x] {
+                map.values[y * map.width + x] = 0.0;
+                seeded += 1;
+            } else {
+                hooks::log(
+                    Level::Warning,
+                    format_args!("ignoring Dijkstra goal ({}, {}): the cell is blocked", x, y),
+                );
+            }
+        }
+        if seeded == 0 {
+            hooks::log(
+                Level::Warning,
+                format_args!("Dijkstra map has no open goals, so every cell is unreachable"),
+            );
+        }
+        map.rescan();
+        map
+    }
+
+    fn neighbours(&self) -> &'static [(isize, isize)] {
+        if self.diagonal {
+            &grid::EIGHT_WAY
+        } else {
+            &grid::CARDINAL
+        }
+    }
+
+    /// Relax the map until no cell is more than one step above its lowest neighbour.
+    ///
+    /// Starting values may be arbitrary, which is what lets callers seed or rescale
+    /// a map and scan it again.
+    pub fn rescan(&mut self) {
+        self.scan("scan");
+    }
+
+    /// [`DijkstraMap::rescan`], reporting progress under `stage`
+    fn scan(&mut self, stage: &'static str) {
+        let total = self
+            .passable
+            .iter()
+            .filter(|&&passable| passable)
+            .count()
+            .max(1);
+        let mut heap = BinaryHeap::new();
+        for (index, value) in self.values.iter_mut().enumerate() {
+            if !self.passable[index] {
+                *value = f32::INFINITY;
+            } else if value.is_finite() {
+                heap.push(Frontier(*value, index));
+            }
+        }
+
+        let neighbours = self.neighbours();
+        let mut expanded = 0;
+        while let Some(Frontier(value, index)) = heap.pop() {
+            if value > self.values[index] {
+                continue;
+            }
+            expanded += 1;
+            if expanded % CHECK_INTERVAL == 0 {
+                if hooks::cancelled() {
+                    break;
+                }
+                hooks::report(stage, expanded as f32 / total as f32);
+            }
+            let (x, y) = (index % self.width, index / self.width);
+            for &(dx, dy) in neighbours {
+                if let Some((nx, ny)) = grid::offset(x, y, dx, dy, self.width, self.height) {
+                    let neighbour = ny * self.width + nx;
+                    if self.passable[neighbour] && self.values[neighbour] > value + 1.0 {
+                        self.values[neighbour] = value + 1.0;
+                        heap.push(Frontier(value + 1.0, neighbour));
+                    }
+                }
+            }
+        }
+        hooks::count("nodes_expanded", expanded);
+    }
+
+    /// Turn a map of distances from danger into one that leads away from it.
+    ///
+    /// Scaling by a coefficient below -1 and rescanning makes cells far from the
+    /// threats the lowest, while the extra weight beyond -1 lets fleeing creatures
+    /// double back past a threat instead of cornering themselves.
+    pub fn invert(&mut self, coefficient: f32) {
+        for value in self.values.iter_mut() {
+            if value.is_finite() {
+                *value *= coefficient;
+            }
+        }
+        self.scan("flee");
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> f32 {
+        self.values[y * self.width + x]
+    }
+
+    /// The neighbouring cell with the lowest value, if it is lower than the current one
+    pub fn downhill(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        let mut best = None;
+        let mut best_value = self.get(x, y);
+        for &(dx, dy) in self.neighbours() {
+            if let Some((nx, ny)) = grid::offset(x, y, dx, dy, self.width, self.height) {
+                let value = self.get(nx, ny);
+                if value < best_value {
+                    best_value = value;
+                    best = Some((nx, ny));
+                }
+            }
+        }
+        best
+    }
+
+    /// Follow the map downhill from a cell until a local minimum, including the start
+    pub fn roll_downhill(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let mut path = vec![(x, y)];
+        let mut current = (x, y);
+        while let Some(next) = self.downhill(current.0, current.1) {
+            path.push(next);
+            current = next;
+        }
+        path
+    }
+
+    pub fn to_rows(&self) -> Vec<Vec<f32>> {
+        if self.width == 0 {
+            return vec![Vec::new(); self.height];
+        }
+        self.values
+            .chunks(self.width)
+            .map(|row| row.to_vec())
+            .collect()
+    }
+}
+
+/// Octile walking distances from `start` over a flat passability mask.
+///
+/// Diagonal steps cost `sqrt(2)` and may not cut wall corners. Only cells within
+/// `max_distance` are returned, as `(index, distance)` pairs.
+pub fn weighted_distances(
+    passable: &[bool],
+    width: usize,
+    height: usize,
+    start: (usize, usize),
+    max_distance: f32,
+) -> Vec<(usize, f32)> {
+    let mut reached = Vec::new();
+    if start.0 >= width || start.1 >= height || !passable[start.1 * width + start.0] {
+        return reached;
+    }
+
+    let mut distances = vec![f32::INFINITY; width * height];
+    let mut heap = BinaryHeap::new();
+    let start_index = start.1 * width + start.0;
+    distances[start_index] = 0.0;
+    heap.push(Frontier(0.0, start_index));
+
+    while let Some(Frontier(distance, index)) = heap.pop() {
+        if distance > distances[index] {
+            continue;
+        }
+        reached.push((index, distance));
+        let (x, y) = (index % width, index / width);
+        for &(dx, dy) in &grid::EIGHT_WAY {
+            let Some((nx, ny)) = grid::offset(x, y, dx, dy, width, height) else {
+                continue;
+            };
+            let neighbour = ny * width + nx;
+            if !passable[neighbour] {
+                continue;
+            }
+            let step = if dx != 0 && dy != 0 {
+                // Squeezing diagonally between two walls is not allowed
+                if !passable[y * width + nx] || !passable[ny * width + x] {
+                    continue;
+                }
+                std::f32::consts::SQRT_2
+            } else {
+                1.0
+            };
+            let next = distance + step;
+            if next <= max_distance && next < distances[neighbour] {
+                distances[neighbour] = next;
+                heap.push(Frontier(next, neighbour));
+            }
+        }
+    }
+
+    hooks::count("nodes_expanded", reached.len() as u64);
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Arguments;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::hooks::Hooks;
+
+    static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    fn record(level: Level, message: Arguments) {
+        if level == Level::Warning {
+            WARNINGS.lock().unwrap().push(message.to_string());
+        }
+    }
+
+    /// Warnings about ignored goals at `(x, _)`, so other tests' logs do not interfere
+    fn goal_warnings(x: usize) -> Vec<String> {
+        let prefix = format!("ignoring Dijkstra goal ({}, ", x);
+        let warnings = WARNINGS.lock().unwrap();
+        warnings
+            .iter()
+            .filter(|warning| warning.starts_with(&prefix))
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn ignored_goals_say_why() {
+        hooks::install(Hooks {
+            cancelled: || false,
+            report: |_, _| {},
+            count: |_, _| {},
+            log: record,
+        });
+        let walkable = vec![vec![true, false, true]; 2];
+        let map = DijkstraMap::from_goals(&walkable, &[(0, 0), (1, 1), (17, 0)], false);
+        assert_eq!(map.values[0], 0.0);
+        assert_eq!(
+            goal_warnings(1),
+            ["ignoring Dijkstra goal (1, 1): the cell is blocked"]
+        );
+        assert_eq!(
+            goal_warnings(17),
+            ["ignoring Dijkstra goal (17, 0): it is outside the 3x2 map"]
+        );
+    }
+}
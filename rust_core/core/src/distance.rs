@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+
+use crate::grid;
+
+/// Distance metric used when measuring how far a cell is from the nearest wall
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Metric {
+    /// Eight-way steps; an NxN footprint fits wherever the distance is at least (N + 1) / 2
+    Chebyshev,
+    /// Four-way steps
+    Manhattan,
+    /// Straight-line distance between cell centres
+    Euclidean,
+}
+
+impl Metric {
+    /// The metric called `name`: `"chebyshev"`, `"manhattan"` or `"euclidean"`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "chebyshev" => Some(Metric::Chebyshev),
+            "manhattan" => Some(Metric::Manhattan),
+            "euclidean" => Some(Metric::Euclidean),
+            _ => None,
+        }
+    }
+}
+
+/// Distance from every cell to the nearest blocking cell.
+///
+/// Blocking cells have distance 0. When `edges_block` is set the area outside the
+/// map counts as wall, otherwise a map without any blocking cells is infinitely far
+/// from a wall everywhere.
+pub fn wall_distance(
+    walkable_map: &[Vec<bool>],
+    metric: Metric,
+    edges_block: bool,
+) -> Vec<Vec<f32>> {
+    let (width, height) = grid::dimensions(walkable_map);
+    if width == 0 || height == 0 {
+        return vec![Vec::new(); height];
+    }
+
+    // Pad the map with a one-cell ring so the edges can act as walls
+    let pad = usize::from(edges_block);
+    let padded_width = width + 2 * pad;
+    let padded_height = height + 2 * pad;
+    let mut blocked = vec![edges_block; padded_width * padded_height];
+    for (y, row) in walkable_map.iter().enumerate() {
+        for (x, &walkable) in row.iter().enumerate().take(width) {
+            blocked[(y + pad) * padded_width + x + pad] = !walkable;
+        }
+    }
+
+    let distances = match metric {
+        Metric::Chebyshev => step_distance(&blocked, padded_width, padded_height, &grid::EIGHT_WAY),
+        Metric::Manhattan => step_distance(&blocked, padded_width, padded_height, &grid::CARDINAL),
+        Metric::Euclidean => euclidean_distance(&blocked, padded_width, padded_height),
+    };
+
+    (0..height)
+        .map(|y| {
+            let start = (y + pad) * padded_width + pad;
+            distances[start..start + width].to_vec()
+        })
+        .collect()
+}
+
+/// Multi-source breadth-first search outwards from every blocking cell
+fn step_distance(
+    blocked: &[bool],
+    width: usize,
+    height: usize,
+    neighbours: &[(isize, isize)],
+) -> Vec<f32> {
+    let mut distances = vec![f32::INFINITY; width * height];
+    let mut queue = VecDeque::new();
+
+    for (index, &is_blocked) in blocked.iter().enumerate() {
+        if is_blocked {
+            distances[index] = 0.0;
+            queue.push_back(index);
+        }
+    }
+
+    while let Some(index) = queue.pop_front() {
+        let (x, y) = (index % width, index / width);
+        let next = distances[index] + 1.0;
+        for &(dx, dy) in neighbours {
+            if let Some((nx, ny)) = grid::offset(x, y, dx, dy, width, height) {
+                let neighbour = ny * width + nx;
+                if distances[neighbour] > next {
+                    distances[neighbour] = next;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+
+    distances
+}
+
+/// Exact Euclidean distance transform (Felzenszwalb & Huttenlocher), one pass per axis
+fn euclidean_distance(blocked: &[bool], width: usize, height: usize) -> Vec<f32> {
+    let mut squared: Vec<f64> = blocked
+        .iter()
+        .map(|&is_blocked| if is_blocked { 0.0 } else { f64::INFINITY })
+        .collect();
+
+    let mut column = vec![0.0; height];
+    for x in 0..width {
+        for y in 0..height {
+            column[y] = squared[y * width + x];
+        }
+        let transformed = squared_distance_1d(&column);
+        for y in 0..height {
+            squared[y * width + x] = transformed[y];
+        }
+    }
+
+    for y in 0..height {
+        let row = &mut squared[y * width..(y + 1) * width];
+        let transformed = squared_distance_1d(row);
+        row.copy_from_slice(&transformed);
+    }
+
+    squared.into_iter().map(|d| d.sqrt() as f32).collect()
+}
+
+/// Lower envelope of the parabolas rooted at each sample of `f`
+fn squared_distance_1d(f: &[f64]) -> Vec<f64> {
+    let n = f.len();
+    let mut result = vec![f64::INFINITY; n];
+    let mut vertices = vec![0usize; n];
+    let mut boundaries = vec![0.0f64; n + 1];
+    let mut k: isize = -1;
+
+    for q in 0..n {
+        if f[q].is_infinite() {
+            continue;
+        }
+        loop {
+            if k < 0 {
+                k = 0;
+                vertices[0] = q;
+                boundaries[0] = f64::NEG_INFINITY;
+                boundaries[1] = f64::INFINITY;
+                break;
+            }
+            let v = vertices[k as usize];
+            let s =
+                ((f[q] + (q * q) as f64) - (f[v] + (v * v) as f64)) / (2.0 * (q as f64 - v as f64));
+            if s <= boundaries[k as usize] {
+                k -= 1;
+                continue;
+            }
+            k += 1;
+            vertices[k as usize] = q;
+            boundaries[k as usize] = s;
+            boundaries[k as usize + 1] = f64::INFINITY;
+            break;
+        }
+    }
+
+    if k < 0 {
+        return result;
+    }
+
+    let mut j = 0usize;
+    for (q, value) in result.iter_mut().enumerate() {
+        while boundaries[j + 1] < q as f64 {
+            j += 1;
+        }
+        let v = vertices[j];
+        let d = q as f64 - v as f64;
+        *value = d * d + f[v];
+    }
+
+    result
+}
@@ -0,0 +1,342 @@
+//! Easing curves and tweens for animating values over time.
+//!
+//! Curves map progress `t` from 0 to 1 onto eased progress, 0 at the start and 1 at
+//! the end; `back` and `elastic` overshoot in between.
+
+use std::collections::BTreeMap;
+use std::f32::consts::PI;
+
+use crate::hooks;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+/// Every curve with its name, as accepted by [`Easing::from_name`]
+pub const EASINGS: [(&str, Easing); 16] = [
+    ("linear", Easing::Linear),
+    ("quad_in", Easing::QuadIn),
+    ("quad_out", Easing::QuadOut),
+    ("quad_in_out", Easing::QuadInOut),
+    ("cubic_in", Easing::CubicIn),
+    ("cubic_out", Easing::CubicOut),
+    ("cubic_in_out", Easing::CubicInOut),
+    ("back_in", Easing::BackIn),
+    ("back_out", Easing::BackOut),
+    ("back_in_out", Easing::BackInOut),
+    ("elastic_in", Easing::ElasticIn),
+    ("elastic_out", Easing::ElasticOut),
+    ("elastic_in_out", Easing::ElasticInOut),
+    ("bounce_in", Easing::BounceIn),
+    ("bounce_out", Easing::BounceOut),
+    ("bounce_in_out", Easing::BounceInOut),
+];
+
+/// How far `back` curves pull back before setting off, as in most engines
+const BACK: f32 = 1.701_58;
+
+impl Easing {
+    pub fn from_name(name: &str) -> Option<Self> {
+        EASINGS
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|&(_, easing)| easing)
+    }
+
+    /// Eased progress at `t`, clamped to `[0, 1]` first
+    pub fn apply(self, t: f32) -> f32 {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => in_out(t, |t| t * t),
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => in_out(t, |t| t * t * t),
+            Easing::BackIn => back_in(t, BACK),
+            Easing::BackOut => 1.0 - back_in(1.0 - t, BACK),
+            // The in-out curve pulls back further so each half looks as strong
+            Easing::BackInOut => in_out(t, |t| back_in(t, BACK * 1.525)),
+            Easing::ElasticIn => elastic_in(t),
+            Easing::ElasticOut => 1.0 - elastic_in(1.0 - t),
+            Easing::ElasticInOut => in_out(t, elastic_in),
+            Easing::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => in_out(t, |t| 1.0 - bounce_out(1.0 - t)),
+        }
+    }
+}
+
+/// An in-out curve: `ease_in` over the first half, mirrored over the second
+fn in_out(t: f32, ease_in: impl Fn(f32) -> f32) -> f32 {
+    if t < 0.5 {
+        ease_in(2.0 * t) / 2.0
+    } else {
+        1.0 - ease_in(2.0 - 2.0 * t) / 2.0
+    }
+}
+
+fn back_in(t: f32, overshoot: f32) -> f32 {
+    t * t * ((overshoot + 1.0) * t - overshoot)
+}
+
+fn elastic_in(t: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        return t;
+    }
+    -(2.0f32.powf(10.0 * t - 10.0)) * ((10.0 * t - 10.75) * (2.0 * PI / 3.0)).sin()
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984_375
+    }
+}
+
+/// A value animated from `start` to `end` over `duration` seconds, after `delay`.
+///
+/// Values are any number of components, e.g. one for an alpha or two for a
+/// position, eased together.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tween {
+    pub start: Vec<f32>,
+    pub end: Vec<f32>,
+    pub duration: f32,
+    pub delay: f32,
+    pub easing: Easing,
+    /// Seconds since the tween was started, delay included
+    pub elapsed: f32,
+}
+
+impl Tween {
+    /// Progress from 0 to 1, before easing
+    pub fn progress(&self) -> f32 {
+        let active = self.elapsed - self.delay;
+        if self.duration <= 0.0 {
+            return if active >= 0.0 { 1.0 } else { 0.0 };
+        }
+        (active / self.duration).clamp(0.0, 1.0)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.delay + self.duration.max(0.0)
+    }
+
+    /// Current value, one entry per component
+    pub fn value(&self) -> Vec<f32> {
+        let eased = self.easing.apply(self.progress());
+        self.start
+            .iter()
+            .zip(&self.end)
+            .map(|(start, end)| start + (end - start) * eased)
+            .collect()
+    }
+
+    /// Advance by `delta` seconds, returning whether the tween is now finished
+    pub fn advance(&mut self, delta: f32) -> bool {
+        self.elapsed += delta.max(0.0);
+        self.is_finished()
+    }
+}
+
+/// Many tweens, advanced together once per frame, each carrying a `T` for its
+/// embedder. Finished tweens can still be read until the next `update`, which
+/// removes them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tweens<T> {
+    pub tweens: BTreeMap<u64, (Tween, T)>,
+    /// Ids reported by the last `update`, removed on the next one
+    pub finished: Vec<u64>,
+    pub next_id: u64,
+}
+
+impl<T> Default for Tweens<T> {
+    fn default() -> Self {
+        Tweens {
+            tweens: BTreeMap::new(),
+            finished: Vec::new(),
+            next_id: 0,
+        }
+    }
+}
+
+impl<T> Tweens<T> {
+    pub fn new() -> Self {
+        Tweens::default()
+    }
+
+    /// Start a tween and return its id; a negative duration or delay counts as 0
+    pub fn add(&mut self, mut tween: Tween, payload: T) -> u64 {
+        tween.duration = tween.duration.max(0.0);
+        tween.delay = tween.delay.max(0.0);
+        let id = self.next_id;
+        self.next_id += 1;
+        self.tweens.insert(id, (tween, payload));
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&(Tween, T)> {
+        self.tweens.get(&id)
+    }
+
+    /// Advance every tween by `delta` seconds and return the ids of those that
+    /// finished, in the order they were added
+    pub fn update(&mut self, delta: f32) -> &[u64] {
+        for id in self.finished.drain(..) {
+            self.tweens.remove(&id);
+        }
+        for (&id, (tween, _)) in self.tweens.iter_mut() {
+            if tween.advance(delta) {
+                self.finished.push(id);
+            }
+        }
+        hooks::count("tweens_updated", self.tweens.len() as u64);
+        &self.finished
+    }
+
+    /// Stop a tween where it is; returns whether it existed
+    pub fn cancel(&mut self, id: u64) -> bool {
+        self.finished.retain(|&finished| finished != id);
+        self.tweens.remove(&id).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.tweens.clear();
+        self.finished.clear();
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.tweens.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tweens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tweens.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_curve_starts_at_0_and_ends_at_1() {
+        for (name, easing) in EASINGS {
+            assert!(easing.apply(0.0).abs() < 1e-6, "{} at 0", name);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6, "{} at 1", name);
+            // Progress outside [0, 1], or none at all, is clamped first
+            assert_eq!(easing.apply(-3.0), easing.apply(0.0), "{}", name);
+            assert_eq!(easing.apply(7.0), easing.apply(1.0), "{}", name);
+            assert_eq!(easing.apply(f32::NAN), easing.apply(0.0), "{}", name);
+        }
+    }
+
+    #[test]
+    fn in_out_curves_pass_through_the_middle() {
+        for easing in [
+            Easing::QuadInOut,
+            Easing::CubicInOut,
+            Easing::BackInOut,
+            Easing::ElasticInOut,
+            Easing::BounceInOut,
+        ] {
+            assert!((easing.apply(0.5) - 0.5).abs() < 1e-6, "{:?}", easing);
+        }
+        assert_eq!(Easing::QuadIn.apply(0.5), 0.25);
+        assert_eq!(Easing::QuadOut.apply(0.5), 0.75);
+        assert!(
+            Easing::BackIn.apply(0.2) < 0.0,
+            "back pulls back before setting off"
+        );
+        assert!(
+            Easing::BackOut.apply(0.8) > 1.0,
+            "and overshoots on the way out"
+        );
+    }
+
+    #[test]
+    fn every_name_maps_back_to_its_curve() {
+        for (name, easing) in EASINGS {
+            assert_eq!(Easing::from_name(name), Some(easing));
+        }
+        assert_eq!(Easing::from_name("quad"), None);
+    }
+
+    #[test]
+    fn tweens_wait_out_their_delay_then_ease_every_component() {
+        let mut tween = Tween {
+            start: vec![0.0, 10.0],
+            end: vec![4.0, 2.0],
+            duration: 2.0,
+            delay: 1.0,
+            easing: Easing::Linear,
+            elapsed: 0.0,
+        };
+        assert!(!tween.advance(1.0));
+        assert_eq!(tween.value(), [0.0, 10.0]);
+        assert!(!tween.advance(1.0));
+        assert_eq!(tween.value(), [2.0, 6.0]);
+        assert!(!tween.advance(-5.0), "time never runs backwards");
+        assert!(tween.advance(1.0));
+        assert_eq!(tween.value(), [4.0, 2.0]);
+        let instant = Tween {
+            duration: 0.0,
+            delay: 0.0,
+            ..tween
+        };
+        assert_eq!(instant.progress(), 1.0);
+    }
+
+    #[test]
+    fn finished_tweens_are_readable_until_the_next_update() {
+        let fade = |duration| Tween {
+            start: vec![1.0],
+            end: vec![0.0],
+            duration,
+            delay: -1.0,
+            easing: Easing::Linear,
+            elapsed: 0.0,
+        };
+        let mut tweens = Tweens::new();
+        let quick = tweens.add(fade(0.5), "quick");
+        let slow = tweens.add(fade(2.0), "slow");
+        assert_eq!(tweens.get(quick).unwrap().0.delay, 0.0);
+        assert_eq!(tweens.update(1.0), [quick]);
+        assert!(tweens.contains(quick));
+        assert!(tweens.update(0.5).is_empty());
+        assert!(!tweens.contains(quick));
+        assert!(tweens.cancel(slow));
+        assert!(tweens.is_empty());
+    }
+}
@@ -0,0 +1,229 @@
+//! Pending game-time events (buff expiries, fuses, respawns) with any payload.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Heap entry ordered so that `BinaryHeap` pops the earliest time, then the
+/// earliest scheduled, first
+#[derive(Clone, Debug, PartialEq)]
+struct Due(f64, u64);
+
+impl Eq for Due {}
+
+impl Ord for Due {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .0
+            .partial_cmp(&self.0)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.1.cmp(&self.1))
+    }
+}
+
+impl PartialOrd for Due {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Events scheduled at an absolute time or after a delay from `now`, handed back
+/// in time order once they fall due and cancellable by their handle. Events due
+/// at the same time come out in the order they were scheduled.
+#[derive(Clone, Debug)]
+pub struct EventScheduler<T> {
+    heap: BinaryHeap<Due>,
+    /// Live events by handle; cancelled ones are dropped here and skipped in the heap
+    pending: HashMap<u64, (f64, T)>,
+    next_handle: u64,
+    now: f64,
+}
+
+impl<T> EventScheduler<T> {
+    pub fn new(now: f64) -> Self {
+        EventScheduler::resume(now, 0)
+    }
+
+    /// An empty scheduler that hands out handles from `next_handle`, for putting
+    /// back saved events with [`EventScheduler::restore`]
+    pub fn resume(now: f64, next_handle: u64) -> Self {
+        EventScheduler {
+            heap: BinaryHeap::new(),
+            pending: HashMap::new(),
+            next_handle,
+            now,
+        }
+    }
+
+    pub fn now(&self) -> f64 {
+        self.now
+    }
+
+    /// The handle the next scheduled event will get
+    pub fn next_handle(&self) -> u64 {
+        self.next_handle
+    }
+
+    /// Schedule an event at an absolute game time and return its handle, or `None`
+    /// if the time is not finite
+    pub fn schedule_at(&mut self, time: f64, payload: T) -> Option<u64> {
+        if !time.is_finite() {
+            return None;
+        }
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.restore(handle, time, payload);
+        Some(handle)
+    }
+
+    /// Schedule an event `delay` after the current time, as `schedule_at` does
+    pub fn schedule_in(&mut self, delay: f64, payload: T) -> Option<u64> {
+        self.schedule_at(self.now + delay.max(0.0), payload)
+    }
+
+    /// Put back a saved event under its original handle
+    pub fn restore(&mut self, handle: u64, time: f64, payload: T) {
+        self.heap.push(Due(time, handle));
+        self.pending.insert(handle, (time, payload));
+    }
+
+    /// Cancel a pending event, returning whether it was still pending
+    pub fn cancel(&mut self, handle: u64) -> bool {
+        let cancelled = self.pending.remove(&handle).is_some();
+        self.prune();
+        cancelled
+    }
+
+    /// Game time of a pending event
+    pub fn time_of(&self, handle: u64) -> Option<f64> {
+        self.pending.get(&handle).map(|(time, _)| *time)
+    }
+
+    /// Time of the next pending event
+    pub fn peek_time(&mut self) -> Option<f64> {
+        self.prune();
+        self.heap.peek().map(|Due(time, _)| *time)
+    }
+
+    /// Pending events as `(handle, time, payload)` in scheduling order
+    pub fn events(&self) -> Vec<(u64, f64, &T)> {
+        let mut events: Vec<_> = self
+            .pending
+            .iter()
+            .map(|(&handle, (time, payload))| (handle, *time, payload))
+            .collect();
+        events.sort_by_key(|&(handle, _, _)| handle);
+        events
+    }
+
+    /// Move time forward by `delta_time` and return the events that fell due as
+    /// `(handle, time, payload)` in order
+    pub fn advance(&mut self, delta_time: f64) -> Vec<(u64, f64, T)> {
+        self.now += delta_time.max(0.0);
+        self.take_due(self.now)
+    }
+
+    /// Set the current time and return the events due by then, as `advance` does;
+    /// time never moves backwards
+    pub fn advance_to(&mut self, time: f64) -> Vec<(u64, f64, T)> {
+        self.now = self.now.max(time);
+        self.take_due(self.now)
+    }
+
+    /// Remove and return every live event due at or before `time`
+    pub fn take_due(&mut self, time: f64) -> Vec<(u64, f64, T)> {
+        let mut due = Vec::new();
+        while let Some(Due(at, handle)) = self.heap.peek() {
+            if *at > time {
+                break;
+            }
+            let handle = *handle;
+            self.heap.pop();
+            if let Some((at, payload)) = self.pending.remove(&handle) {
+                due.push((handle, at, payload));
+            }
+        }
+        due
+    }
+
+    /// Drop cancelled entries sitting at the top of the heap
+    fn prune(&mut self) {
+        while let Some(Due(_, handle)) = self.heap.peek() {
+            if self.pending.contains_key(handle) {
+                break;
+            }
+            self.heap.pop();
+        }
+    }
+
+    /// Drop every pending event
+    pub fn clear(&mut self) {
+        self.heap.clear();
+        self.pending.clear();
+    }
+
+    pub fn contains(&self, handle: u64) -> bool {
+        self.pending.contains_key(&handle)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_come_out_in_time_then_scheduling_order() {
+        let mut events = EventScheduler::new(0.0);
+        let fuse = events.schedule_in(2.0, "fuse").unwrap();
+        let first = events.schedule_at(1.0, "first").unwrap();
+        let second = events.schedule_at(1.0, "second").unwrap();
+        assert_eq!(events.peek_time(), Some(1.0));
+        assert_eq!(
+            events.advance(1.5),
+            [(first, 1.0, "first"), (second, 1.0, "second")]
+        );
+        assert_eq!(events.advance_to(0.0), []);
+        assert_eq!(events.now(), 1.5);
+        assert_eq!(events.advance_to(2.0), [(fuse, 2.0, "fuse")]);
+    }
+
+    #[test]
+    fn cancelled_events_never_fall_due() {
+        let mut events = EventScheduler::new(0.0);
+        let buff = events.schedule_in(1.0, ()).unwrap();
+        assert!(events.cancel(buff));
+        assert!(!events.cancel(buff));
+        assert_eq!(events.peek_time(), None);
+        assert!(events.advance(5.0).is_empty());
+        assert_eq!(events.schedule_at(f64::NAN, ()), None);
+    }
+
+    #[test]
+    fn restored_events_keep_their_handles() {
+        let mut events = EventScheduler::new(3.0);
+        events.schedule_in(1.0, 'a');
+        events.schedule_in(0.5, 'b');
+        let saved: Vec<_> = events
+            .events()
+            .into_iter()
+            .map(|(handle, time, &payload)| (handle, time, payload))
+            .collect();
+        let mut restored = EventScheduler::resume(events.now(), events.next_handle());
+        for &(handle, time, payload) in &saved {
+            restored.restore(handle, time, payload);
+        }
+        assert_eq!(restored.schedule_in(0.0, 'c'), Some(2));
+        assert_eq!(
+            restored.advance(1.0),
+            [(2, 3.0, 'c'), (1, 3.5, 'b'), (0, 4.0, 'a')]
+        );
+        assert!(restored.is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+//! Autoexplore: the walkable edge of the known map, and the nearest way there.
+
+use crate::dijkstra::DijkstraMap;
+use crate::grid;
+
+/// Frontier cell picked as the next target, and the path from the explorer to it
+pub type ExploreTarget = ((usize, usize), Vec<(usize, usize)>);
+
+/// Cells that are known and walkable but border at least one unknown cell.
+///
+/// A cell is known once it has been explored or is currently in view.
+pub fn frontier_cells(
+    explored_map: &[Vec<bool>],
+    visible_map: &[Vec<bool>],
+    walkable_map: &[Vec<bool>],
+    diagonal: bool,
+) -> Vec<(usize, usize)> {
+    let (width, height) = grid::dimensions(walkable_map);
+    let known =
+        |x: usize, y: usize| grid::flag(explored_map, x, y) || grid::flag(visible_map, x, y);
+    let neighbours: &[(isize, isize)] = if diagonal {
+        &grid::EIGHT_WAY
+    } else {
+        &grid::CARDINAL
+    };
+
+    let mut frontier = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            if !known(x, y) || !grid::flag(walkable_map, x, y) {
+                continue;
+            }
+            let borders_unknown = neighbours.iter().any(|&(dx, dy)| {
+                grid::offset(x, y, dx, dy, width, height).is_some_and(|(nx, ny)| !known(nx, ny))
+            });
+            if borders_unknown {
+                frontier.push((x, y));
+            }
+        }
+    }
+    frontier
+}
+
+/// Nearest reachable frontier cell and the path to it, or `None` once nothing is left
+pub fn autoexplore(
+    start: (usize, usize),
+    explored_map: &[Vec<bool>],
+    visible_map: &[Vec<bool>],
+    walkable_map: &[Vec<bool>],
+    diagonal: bool,
+) -> Option<ExploreTarget> {
+    let (width, height) = grid::dimensions(walkable_map);
+    if start.0 >= width || start.1 >= height {
+        return None;
+    }
+
+    // Only walk over cells the explorer already knows about
+    let passable: Vec<Vec<bool>> = (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    grid::flag(walkable_map, x, y)
+                        && (grid::flag(explored_map, x, y)
+                            || grid::flag(visible_map, x, y)
+                            || (x, y) == start)
+                })
+                .collect()
+        })
+        .collect();
+
+    // Standing on a frontier cell means the unknown neighbour cannot be seen from
+    // here, so it is not worth returning as a target
+    let goals: Vec<(usize, usize)> =
+        frontier_cells(explored_map, visible_map, walkable_map, diagonal)
+            .into_iter()
+            .filter(|&cell| cell != start)
+            .collect();
+    if goals.is_empty() {
+        return None;
+    }
+
+    let map = DijkstraMap::from_goals(&passable, &goals, diagonal);
+    if !map.get(start.0, start.1).is_finite() {
+        return None;
+    }
+
+    let path = map.roll_downhill(start.0, start.1);
+    let target = *path.last()?;
+    Some((target, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_frontier_is_known_floor_next_to_the_unknown() {
+        let walkable = vec![vec![true; 4]];
+        let explored = vec![vec![true, true, false, false]];
+        let visible = vec![vec![false; 4]];
+        assert_eq!(
+            frontier_cells(&explored, &visible, &walkable, false),
+            [(1, 0)]
+        );
+    }
+
+    #[test]
+    fn autoexplore_walks_known_cells_to_the_nearest_frontier() {
+        let walkable = vec![vec![true; 5], vec![true, false, false, false, true]];
+        let explored = vec![vec![true, true, true, false, false], vec![true; 5]];
+        let visible = vec![vec![false; 5]; 2];
+        let (target, path) = autoexplore((0, 1), &explored, &visible, &walkable, false).unwrap();
+        assert_eq!(target, (2, 0));
+        assert_eq!(path, [(0, 1), (0, 0), (1, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn nothing_is_left_once_everything_is_known() {
+        let known = vec![vec![true; 3]; 3];
+        assert_eq!(autoexplore((1, 1), &known, &known, &known, true), None);
+    }
+}
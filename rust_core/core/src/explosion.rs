@@ -0,0 +1,186 @@
+//! Explosion damage with distance falloff and occlusion by cover.
+//!
+//! A cover map holds, for each cell, the fraction of a blast it stops: 1 for a wall,
+//! 0.5 for a low wall, 0 for open ground. Damage reaching a cell is scaled by the
+//! falloff and by what gets past each cell of cover on the line from the centre.
+
+use crate::grid;
+use crate::raster::{self, Cell};
+
+/// How damage drops off with distance from the centre
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Falloff {
+    /// Full damage everywhere within the radius
+    None,
+    /// Down to zero at the radius in a straight line
+    Linear,
+    /// Like `Linear`, squared: damage stays concentrated near the centre
+    Quadratic,
+}
+
+impl Falloff {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Falloff::None),
+            "linear" => Some(Falloff::Linear),
+            "quadratic" => Some(Falloff::Quadratic),
+            _ => None,
+        }
+    }
+
+    /// Damage multiplier `distance` cells out of `radius`
+    pub fn scale(self, distance: f32, radius: f32) -> f32 {
+        if distance > radius {
+            return 0.0;
+        }
+        let remaining = if radius > 0.0 {
+            1.0 - distance / radius
+        } else {
+            1.0
+        };
+        match self {
+            Falloff::None => 1.0,
+            Falloff::Linear => remaining,
+            Falloff::Quadratic => remaining * remaining,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Explosion {
+    pub center: Cell,
+    /// Damage at the centre
+    pub power: f32,
+    /// Cells further than this take no damage
+    pub radius: u32,
+    pub falloff: Falloff,
+}
+
+impl Explosion {
+    /// Damage dealt to `cell`, or 0 if it is off the map.
+    ///
+    /// Cover between the centre and `cell` reduces the damage, but the cover of the
+    /// centre and of `cell` itself does not, so walls next to a blast are hit too.
+    pub fn damage_at(&self, cell: Cell, cover_map: &[Vec<f32>]) -> f32 {
+        if cover(cover_map, cell).is_none() {
+            return 0.0;
+        }
+        let (dx, dy) = (
+            (cell.0 - self.center.0) as f32,
+            (cell.1 - self.center.1) as f32,
+        );
+        let mut damage = self.power
+            * self
+                .falloff
+                .scale((dx * dx + dy * dy).sqrt(), self.radius as f32);
+        if damage == 0.0 {
+            return 0.0;
+        }
+        let path = raster::line(self.center, cell);
+        for &between in &path[1.min(path.len() - 1)..path.len() - 1] {
+            damage *= 1.0 - cover(cover_map, between).unwrap_or(0.0);
+            if damage <= 0.0 {
+                return 0.0;
+            }
+        }
+        damage
+    }
+
+    /// Damage dealt to every cell of a `[y][x]` cover map, as a map of the same size
+    pub fn damage_map(&self, cover_map: &[Vec<f32>]) -> Vec<Vec<f32>> {
+        let (width, height) = grid::dimensions(cover_map);
+        let mut damage = vec![vec![0.0; width]; height];
+        // Only cells within the radius can be hit
+        let radius = i64::from(self.radius);
+        let (x, y) = (i64::from(self.center.0), i64::from(self.center.1));
+        let columns = (x - radius).max(0)..=(x + radius).min(width as i64 - 1);
+        for y in (y - radius).max(0)..=(y + radius).min(height as i64 - 1) {
+            for x in columns.clone() {
+                damage[y as usize][x as usize] = self.damage_at((x as i32, y as i32), cover_map);
+            }
+        }
+        damage
+    }
+}
+
+/// Cover of a cell clamped to `[0, 1]`, or `None` off the map
+fn cover(cover_map: &[Vec<f32>], (x, y): Cell) -> Option<f32> {
+    if x < 0 || y < 0 {
+        return None;
+    }
+    let value = *cover_map.get(y as usize)?.get(x as usize)?;
+    // NaN counts as open ground
+    Some(if value.is_nan() {
+        0.0
+    } else {
+        value.clamp(0.0, 1.0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explosion(power: f32, radius: u32, falloff: Falloff) -> Explosion {
+        Explosion {
+            center: (0, 0),
+            power,
+            radius,
+            falloff,
+        }
+    }
+
+    #[test]
+    fn falloff_scales_with_distance() {
+        assert_eq!(Falloff::None.scale(1.5, 2.0), 1.0);
+        assert_eq!(Falloff::Linear.scale(1.0, 2.0), 0.5);
+        assert_eq!(Falloff::Quadratic.scale(1.0, 2.0), 0.25);
+        for falloff in [Falloff::None, Falloff::Linear, Falloff::Quadratic] {
+            assert_eq!(falloff.scale(2.5, 2.0), 0.0);
+            assert_eq!(falloff.scale(0.0, 0.0), 1.0);
+        }
+        assert_eq!(Falloff::from_name("quadratic"), Some(Falloff::Quadratic));
+        assert_eq!(Falloff::from_name("cubic"), None);
+    }
+
+    #[test]
+    fn damage_map_covers_the_radius_only() {
+        let open = vec![vec![0.0; 5]; 5];
+        let blast = Explosion {
+            center: (2, 2),
+            ..explosion(10.0, 2, Falloff::Linear)
+        };
+        let damage = blast.damage_map(&open);
+        assert_eq!((damage.len(), damage[0].len()), (5, 5));
+        assert_eq!(damage[2][2], 10.0);
+        assert_eq!(damage[2][3], 5.0);
+        assert_eq!(damage[2][4], 0.0);
+        assert_eq!(damage[4][4], 0.0);
+        // A centre off the map still reaches the cells in range
+        let corner = Explosion {
+            center: (-1, -1),
+            ..explosion(10.0, 2, Falloff::None)
+        };
+        assert_eq!(corner.damage_map(&open)[0][0], 10.0);
+        assert_eq!(corner.damage_at((-1, -1), &open), 0.0, "off the map");
+    }
+
+    #[test]
+    fn cover_between_reduces_damage_but_its_own_cover_does_not() {
+        let row = vec![vec![0.0, 0.5, 0.0, 1.0, 0.0, f32::NAN, 0.0]];
+        let blast = explosion(8.0, 10, Falloff::None);
+        let damage: Vec<f32> = (0..5).map(|x| blast.damage_at((x, 0), &row)).collect();
+        assert_eq!(damage, [8.0, 8.0, 4.0, 4.0, 0.0]);
+        let past_nan = vec![vec![0.0, f32::NAN, 2.0, 0.0]];
+        assert_eq!(
+            blast.damage_at((2, 0), &past_nan),
+            8.0,
+            "NaN cover is open ground"
+        );
+        assert_eq!(
+            blast.damage_at((3, 0), &past_nan),
+            0.0,
+            "cover above 1 is a wall"
+        );
+    }
+}
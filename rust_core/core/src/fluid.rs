@@ -0,0 +1,232 @@
+//! Gas clouds and liquid floods spreading over a grid, contained by walls.
+
+use crate::diffusion;
+use crate::grid;
+use crate::hooks;
+
+/// Amounts below this are treated as empty so clouds and puddles finish fading
+const TRACE: f32 = 1e-4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Medium {
+    /// Spreads freely towards lower concentrations and dissipates proportionally
+    Gas,
+    /// Only flows out of cells deeper than `min_depth` and dries by a fixed amount
+    Liquid,
+}
+
+impl Medium {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "gas" => Some(Medium::Gas),
+            "liquid" => Some(Medium::Liquid),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Medium::Gas => "gas",
+            Medium::Liquid => "liquid",
+        }
+    }
+}
+
+/// Fluid amounts over a row-major grid.
+///
+/// Gas flows from high to low concentration and thins out by `evaporation` (a
+/// fraction) each step. Liquid behaves like water finding its level: cells only
+/// push to lower neighbours while deeper than `min_depth`, so floods settle into
+/// puddles, and `evaporation` is removed as a fixed depth per step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FluidGrid {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<f32>,
+    /// 1 for open cells and 0 for walls
+    pub conductance: Vec<f32>,
+    pub medium: Medium,
+    /// Share of a pressure difference that flows per step, 0 to 1
+    pub flow_rate: f32,
+    pub evaporation: f32,
+    pub min_depth: f32,
+}
+
+impl FluidGrid {
+    /// An empty grid over the open cells of a `[y][x]` walkable map
+    pub fn new(
+        walkable_map: &[Vec<bool>],
+        medium: Medium,
+        flow_rate: f32,
+        evaporation: f32,
+        min_depth: f32,
+    ) -> Self {
+        let (width, height, conductance) = diffusion::open_cells(walkable_map);
+        FluidGrid {
+            width,
+            height,
+            values: vec![0.0; width * height],
+            conductance,
+            medium,
+            flow_rate,
+            evaporation,
+            min_depth,
+        }
+    }
+
+    /// The index of a cell in `values`, or `None` outside the grid
+    pub fn index(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| y * self.width + x)
+    }
+
+    /// Pour fluid into an open cell, or take it out with a negative amount; walls
+    /// never hold any
+    pub fn add(&mut self, index: usize, amount: f32) {
+        if self.conductance[index] > 0.0 {
+            self.values[index] = (self.values[index] + amount).max(0.0);
+        }
+    }
+
+    /// Open or close a cell, e.g. for doors; closing it removes the fluid inside
+    pub fn set_open(&mut self, index: usize, open: bool) {
+        self.conductance[index] = if open { 1.0 } else { 0.0 };
+        if !open {
+            self.values[index] = 0.0;
+        }
+    }
+
+    /// Total amount of fluid on the grid
+    pub fn total(&self) -> f32 {
+        self.values.iter().sum()
+    }
+
+    fn spread_liquid(&mut self, rate: f32) {
+        let mut next = self.values.clone();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y * self.width + x;
+                if self.conductance[index] <= 0.0 {
+                    continue;
+                }
+                for (dx, dy) in [(1, 0), (0, 1)] {
+                    let Some((nx, ny)) = grid::offset(x, y, dx, dy, self.width, self.height) else {
+                        continue;
+                    };
+                    let neighbour = ny * self.width + nx;
+                    if self.conductance[neighbour] <= 0.0 {
+                        continue;
+                    }
+                    let (high, low) = if self.values[index] >= self.values[neighbour] {
+                        (index, neighbour)
+                    } else {
+                        (neighbour, index)
+                    };
+                    if self.values[high] <= self.min_depth {
+                        continue;
+                    }
+                    // Never push more than the surplus above the puddle depth
+                    let surplus = self.values[high] - self.min_depth.max(self.values[low]);
+                    let flow = (rate * 0.25 * (self.values[high] - self.values[low])).min(surplus);
+                    if flow > 0.0 {
+                        next[high] -= flow;
+                        next[low] += flow;
+                    }
+                }
+            }
+        }
+        self.values = next;
+    }
+
+    /// Advance the simulation `steps` times, stopping early if cancelled
+    pub fn step(&mut self, steps: usize) {
+        for done in 0..steps {
+            if hooks::cancelled() {
+                break;
+            }
+            hooks::report("step", done as f32 / steps as f32);
+            self.step_once();
+        }
+    }
+
+    pub fn step_once(&mut self) {
+        let rate = self.flow_rate.clamp(0.0, 1.0);
+        match self.medium {
+            Medium::Gas => {
+                diffusion::diffuse(
+                    &mut self.values,
+                    &self.conductance,
+                    self.width,
+                    self.height,
+                    rate,
+                );
+                let keep = 1.0 - self.evaporation.clamp(0.0, 1.0);
+                for value in self.values.iter_mut() {
+                    *value *= keep;
+                }
+            }
+            Medium::Liquid => {
+                self.spread_liquid(rate);
+                for value in self.values.iter_mut() {
+                    *value -= self.evaporation.max(0.0);
+                }
+            }
+        }
+        for value in self.values.iter_mut() {
+            if *value < TRACE {
+                *value = 0.0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room(medium: Medium) -> FluidGrid {
+        FluidGrid::new(&[vec![true; 4], vec![true; 4]], medium, 0.8, 0.0, 0.05)
+    }
+
+    #[test]
+    fn gas_evens_out_and_is_conserved() {
+        let mut grid = room(Medium::Gas);
+        grid.add(0, 8.0);
+        grid.step(200);
+        assert!((grid.total() - 8.0).abs() < 1e-3);
+        assert!(grid.values.iter().all(|&value| (value - 1.0).abs() < 0.01));
+    }
+
+    #[test]
+    fn liquid_settles_into_a_puddle() {
+        let mut grid = room(Medium::Liquid);
+        grid.add(0, 0.2);
+        grid.step(200);
+        // Nothing flows once every wet cell is at or below the puddle depth
+        assert!(grid.values.iter().all(|&value| value <= 0.05 + 1e-6));
+        assert!((grid.total() - 0.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn evaporation_dries_the_grid() {
+        let mut grid = room(Medium::Liquid);
+        grid.evaporation = 0.1;
+        grid.add(3, 1.0);
+        grid.step(20);
+        assert_eq!(grid.total(), 0.0);
+    }
+
+    #[test]
+    fn closed_cells_empty_and_block_the_flow() {
+        let mut grid = FluidGrid::new(&[vec![true; 3]], Medium::Gas, 0.8, 0.0, 0.0);
+        grid.add(0, 1.0);
+        grid.set_open(1, false);
+        grid.add(1, 1.0);
+        grid.step(10);
+        assert_eq!(grid.values, [1.0, 0.0, 0.0]);
+        assert_eq!(grid.index(3, 0), None);
+        assert_eq!(
+            Medium::from_name("liquid").map(Medium::name),
+            Some("liquid")
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! Group movement: formation slot layouts, and planning a move that sends each
+//! unit to a slot by the shortest total walk.
+
+use std::collections::VecDeque;
+
+use crate::dijkstra::DijkstraMap;
+use crate::grid;
+use crate::hooks;
+use crate::vec2::Vec2;
+
+/// Arrangement of slots around a formation's anchor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    /// One rank side by side, across the facing direction
+    Line,
+    /// A V with its point at the anchor, ranks trailing back to both sides
+    Wedge,
+    /// Square-ish ranks and files behind the anchor
+    Box,
+}
+
+impl Shape {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "line" => Some(Shape::Line),
+            "wedge" => Some(Shape::Wedge),
+            "box" => Some(Shape::Box),
+            _ => None,
+        }
+    }
+
+    /// Slot offsets as `(right, back)` distances from the anchor, front slots first
+    fn local_slots(self, count: usize, spacing: f32) -> Vec<(f32, f32)> {
+        match self {
+            Shape::Line => {
+                let middle = (count as f32 - 1.0) / 2.0;
+                (0..count)
+                    .map(|i| ((i as f32 - middle) * spacing, 0.0))
+                    .collect()
+            }
+            Shape::Wedge => (0..count)
+                .map(|i| {
+                    let rank = i.div_ceil(2) as f32;
+                    let side = if i % 2 == 1 { -1.0 } else { 1.0 };
+                    (side * rank * spacing, rank * spacing)
+                })
+                .collect(),
+            Shape::Box => {
+                let files = (count as f32).sqrt().ceil().max(1.0) as usize;
+                (0..count)
+                    .map(|i| {
+                        let (rank, file) = (i / files, i % files);
+                        // The last rank may be short, so centre each rank on its own width
+                        let in_rank = files.min(count - rank * files);
+                        let middle = (in_rank as f32 - 1.0) / 2.0;
+                        ((file as f32 - middle) * spacing, rank as f32 * spacing)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// World positions of `count` formation slots anchored at `anchor` and facing `facing`
+pub fn formation_slots(
+    shape: Shape,
+    count: usize,
+    anchor: Vec2,
+    facing: Vec2,
+    spacing: f32,
+) -> Vec<Vec2> {
+    let mut forward = facing.normalize();
+    if forward == Vec2::ZERO {
+        forward = Vec2::new(1.0, 0.0);
+    }
+    let right = Vec2::new(-forward.y, forward.x);
+    shape
+        .local_slots(count, spacing)
+        .into_iter()
+        .map(|(r, back)| anchor + right * r - forward * back)
+        .collect()
+}
+
+/// Minimum-cost perfect assignment of rows to columns for a square cost matrix.
+///
+/// This is the O(n³) Hungarian algorithm; `result[row]` is the chosen column.
+pub fn assign(costs: &[Vec<f32>]) -> Vec<usize> {
+    let n = costs.len();
+    // 1-based potentials and matching, with column 0 as the virtual start
+    let mut u = vec![0.0f64; n + 1];
+    let mut v = vec![0.0f64; n + 1];
+    let mut matched_row = vec![0usize; n + 1];
+    let mut way = vec![0usize; n + 1];
+
+    for row in 1..=n {
+        matched_row[0] = row;
+        let mut column = 0;
+        let mut min_slack = vec![f64::INFINITY; n + 1];
+        let mut used = vec![false; n + 1];
+        loop {
+            used[column] = true;
+            let current_row = matched_row[column];
+            let mut delta = f64::INFINITY;
+            let mut next_column = 0;
+            for j in 1..=n {
+                if used[j] {
+                    continue;
+                }
+                let slack = costs[current_row - 1][j - 1] as f64 - u[current_row] - v[j];
+                if slack < min_slack[j] {
+                    min_slack[j] = slack;
+                    way[j] = column;
+                }
+                if min_slack[j] < delta {
+                    delta = min_slack[j];
+                    next_column = j;
+                }
+            }
+            for j in 0..=n {
+                if used[j] {
+                    u[matched_row[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_slack[j] -= delta;
+                }
+            }
+            column = next_column;
+            if matched_row[column] == 0 {
+                break;
+            }
+        }
+        // Flip the augmenting path back to the start
+        while column != 0 {
+            let previous = way[column];
+            matched_row[column] = matched_row[previous];
+            column = previous;
+        }
+    }
+
+    let mut result = vec![0; n];
+    for column in 1..=n {
+        if matched_row[column] > 0 {
+            result[matched_row[column] - 1] = column - 1;
+        }
+    }
+    result
+}
+
+/// Nearest walkable cell to `target` that is not already taken
+fn nearest_free_cell(
+    walkable_map: &[Vec<bool>],
+    taken: &[bool],
+    target: (usize, usize),
+) -> Option<(usize, usize)> {
+    let (width, height) = grid::dimensions(walkable_map);
+    let mut seen = vec![false; width * height];
+    let mut queue = VecDeque::from([target]);
+    seen[target.1 * width + target.0] = true;
+    while let Some((x, y)) = queue.pop_front() {
+        if grid::flag(walkable_map, x, y) && !taken[y * width + x] {
+            return Some((x, y));
+        }
+        for &(dx, dy) in &grid::EIGHT_WAY {
+            if let Some((nx, ny)) = grid::offset(x, y, dx, dy, width, height) {
+                if !seen[ny * width + nx] {
+                    seen[ny * width + nx] = true;
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// A planned group move: where each unit ends up, how it gets there and when it leaves
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormationMove {
+    /// Destination cell of each unit, in the order the units were given
+    pub slots: Vec<Option<(usize, usize)>>,
+    /// Path of each unit to its slot, including both ends, or `None` if unreachable
+    pub paths: Vec<Option<Vec<(usize, usize)>>>,
+    /// Unit indices from the front of the formation to the back; units earlier in
+    /// the list should be moved first so they are not blocked by those behind them
+    pub arrival_order: Vec<usize>,
+    /// Turns each unit should wait before setting off so the group arrives together
+    pub delays: Vec<usize>,
+}
+
+/// Move `units` into formation around `destination`, facing from the group's centre
+/// towards it. Units and the destination must lie on the map.
+pub fn plan_move(
+    units: &[(usize, usize)],
+    destination: (usize, usize),
+    walkable_map: &[Vec<bool>],
+    shape: Shape,
+    spacing: f32,
+    diagonal: bool,
+) -> FormationMove {
+    let (width, height) = grid::dimensions(walkable_map);
+    let count = units.len();
+    let centre = units.iter().fold(Vec2::ZERO, |sum, &(x, y)| {
+        sum + Vec2::new(x as f32, y as f32)
+    }) * (1.0 / count.max(1) as f32);
+    let anchor = Vec2::new(destination.0 as f32, destination.1 as f32);
+    let slots = formation_slots(shape, count, anchor, anchor - centre, spacing);
+
+    // Snap slots to distinct walkable cells, front slots getting first pick
+    let mut taken = vec![false; width * height];
+    let slot_cells: Vec<Option<(usize, usize)>> = slots
+        .iter()
+        .map(|slot| {
+            let x = slot.x.round().clamp(0.0, width.saturating_sub(1) as f32) as usize;
+            let y = slot.y.round().clamp(0.0, height.saturating_sub(1) as f32) as usize;
+            if width == 0 || height == 0 {
+                return None;
+            }
+            let cell = nearest_free_cell(walkable_map, &taken, (x, y))?;
+            taken[cell.1 * width + cell.0] = true;
+            Some(cell)
+        })
+        .collect();
+
+    // Walking distance from every slot decides both the assignment and the paths
+    let maps: Vec<Option<DijkstraMap>> = slot_cells
+        .iter()
+        .enumerate()
+        .map(|(done, cell)| {
+            hooks::report("distances", done as f32 / count as f32);
+            let cell = (*cell)?;
+            Some(hooks::quiet(|| {
+                DijkstraMap::from_goals(walkable_map, &[cell], diagonal)
+            }))
+        })
+        .collect();
+    let distance = |unit: (usize, usize), slot: usize| match &maps[slot] {
+        Some(map) if unit.0 < width && unit.1 < height => map.get(unit.0, unit.1),
+        _ => f32::INFINITY,
+    };
+    // Unreachable pairs get a large finite cost so the assignment stays well defined
+    let unreachable = (width * height + 1) as f32 * 4.0;
+    let costs: Vec<Vec<f32>> = units
+        .iter()
+        .map(|&unit| {
+            (0..count)
+                .map(|slot| {
+                    let d = distance(unit, slot);
+                    if d.is_finite() {
+                        d
+                    } else {
+                        unreachable
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    hooks::report("assignment", 0.0);
+    let assignment = assign(&costs);
+
+    let mut paths = Vec::with_capacity(count);
+    for (unit, &slot) in assignment.iter().enumerate() {
+        hooks::report("paths", unit as f32 / count as f32);
+        let (x, y) = units[unit];
+        paths.push(match &maps[slot] {
+            Some(map) if distance((x, y), slot).is_finite() => Some(map.roll_downhill(x, y)),
+            _ => None,
+        });
+    }
+
+    let lengths: Vec<usize> = paths
+        .iter()
+        .map(|path| path.as_ref().map_or(0, |path| path.len() - 1))
+        .collect();
+    let longest = lengths.iter().copied().max().unwrap_or(0);
+    let delays = lengths.iter().map(|&length| longest - length).collect();
+
+    // Slots are generated front to back, so slot order is arrival order
+    let mut arrival_order: Vec<usize> = (0..count).collect();
+    arrival_order.sort_by_key(|&unit| assignment[unit]);
+
+    FormationMove {
+        slots: assignment.iter().map(|&slot| slot_cells[slot]).collect(),
+        paths,
+        arrival_order,
+        delays,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_spread_across_the_facing_direction() {
+        let slots = formation_slots(Shape::Line, 3, Vec2::ZERO, Vec2::new(0.0, 1.0), 2.0);
+        assert_eq!(
+            slots,
+            [
+                Vec2::new(2.0, 0.0),
+                Vec2::new(0.0, 0.0),
+                Vec2::new(-2.0, 0.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn wedges_and_boxes_trail_behind_the_anchor() {
+        let facing = Vec2::new(1.0, 0.0);
+        let wedge = formation_slots(Shape::Wedge, 3, Vec2::ZERO, facing, 1.0);
+        assert_eq!(wedge[0], Vec2::ZERO);
+        assert!(wedge[1..].iter().all(|slot| slot.x == -1.0));
+        let square = formation_slots(Shape::Box, 4, Vec2::ZERO, facing, 1.0);
+        assert_eq!(square.iter().filter(|slot| slot.x == 0.0).count(), 2);
+        assert_eq!(Shape::from_name("box"), Some(Shape::Box));
+    }
+
+    #[test]
+    fn assignment_minimises_the_total_cost() {
+        let costs = vec![
+            vec![4.0, 1.0, 3.0],
+            vec![2.0, 0.0, 5.0],
+            vec![3.0, 2.0, 2.0],
+        ];
+        assert_eq!(assign(&costs), [1, 0, 2]);
+        assert!(assign(&[]).is_empty());
+    }
+
+    #[test]
+    fn moves_send_every_unit_to_its_own_slot() {
+        let open = vec![vec![true; 8]; 5];
+        let units = [(0, 1), (0, 2), (0, 3)];
+        let plan = plan_move(&units, (6, 2), &open, Shape::Line, 1.0, false);
+        let mut slots: Vec<_> = plan.slots.iter().map(|slot| slot.unwrap()).collect();
+        slots.sort();
+        assert_eq!(slots, [(6, 1), (6, 2), (6, 3)]);
+        for (path, slot) in plan.paths.iter().zip(&plan.slots) {
+            assert_eq!(path.as_ref().unwrap().last(), slot.as_ref());
+        }
+        assert_eq!(plan.delays, [0, 0, 0]);
+        assert_eq!(plan.arrival_order.len(), 3);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::grid;
+
+/// Cells visible within `radius` of `origin` on a `[y][x]` map of obstacles.
+///
+/// Obstacles block sight but are themselves visible.
+pub fn field_of_view(
+    (origin_x, origin_y): (usize, usize),
+    radius: usize,
+    obstacle_map: &[Vec<bool>],
+) -> Vec<Vec<bool>> {
+    // Create a visibility map initialized to false
+    let (width, height) = grid::dimensions(obstacle_map);
+
+    let mut visibility_map = vec![vec![false; width]; height];
+
+    // Mark the origin as visible
+    if origin_y < height && origin_x < width {
+        visibility_map[origin_y][origin_x] = true;
+    }
+
+    // Basic raycasting algorithm
+    // In a real implementation, this would use a more sophisticated algorithm
+    // such as recursive shadowcasting for better performance
+
+    // Cast rays in a circle
+    for angle in 0..360 {
+        let angle_rad = angle as f32 * std::f32::consts::PI / 180.0;
+        let mut ray_x = origin_x as f32;
+        let mut ray_y = origin_y as f32;
+
+        for _ in 1..=radius {
+            ray_x += angle_rad.cos();
+            ray_y += angle_rad.sin();
+
+            let tile_x = ray_x.round() as usize;
+            let tile_y = ray_y.round() as usize;
+
+            // Check boundaries
+            if tile_y >= height || tile_x >= width {
+                break;
+            }
+
+            // Mark as visible
+            visibility_map[tile_y][tile_x] = true;
+
+            // Stop if hit obstacle
+            if obstacle_map[tile_y][tile_x] {
+                break;
+            }
+        }
+    }
+
+    visibility_map
+}
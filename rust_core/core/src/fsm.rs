@@ -0,0 +1,292 @@
+//! Finite state machines shared by many entities, each with its own current
+//! state, blackboard and pending events.
+
+use std::collections::{HashMap, HashSet};
+
+/// A blackboard entry
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlackboardValue {
+    Bool(bool),
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    pub fn from_op(op: &str) -> Option<Self> {
+        match op {
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessOrEqual),
+            ">" => Some(Comparison::Greater),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            "==" => Some(Comparison::Equal),
+            "!=" => Some(Comparison::NotEqual),
+            _ => None,
+        }
+    }
+
+    /// Values of different kinds are never equal and never ordered
+    pub fn holds(self, left: &BlackboardValue, right: &BlackboardValue) -> bool {
+        use BlackboardValue::Number;
+        match (self, left, right) {
+            (Comparison::Equal, _, _) => left == right,
+            (Comparison::NotEqual, _, _) => left != right,
+            (Comparison::Less, Number(a), Number(b)) => a < b,
+            (Comparison::LessOrEqual, Number(a), Number(b)) => a <= b,
+            (Comparison::Greater, Number(a), Number(b)) => a > b,
+            (Comparison::GreaterOrEqual, Number(a), Number(b)) => a >= b,
+            _ => false,
+        }
+    }
+}
+
+/// A blackboard comparison, e.g. `health < 10`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Check {
+    pub key: String,
+    pub comparison: Comparison,
+    pub value: BlackboardValue,
+}
+
+/// A transition fires when every condition it lists holds
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transition {
+    /// Index of the target state
+    pub to: usize,
+    /// Minimum seconds spent in the current state
+    pub after: Option<f64>,
+    /// Event that must have been sent since the last tick
+    pub event: Option<String>,
+    pub checks: Vec<Check>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct State {
+    pub name: String,
+    /// Tried in order; the first that is ready is taken
+    pub transitions: Vec<Transition>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entity {
+    pub state: usize,
+    pub time_in_state: f64,
+    pub blackboard: HashMap<String, BlackboardValue>,
+    /// Events sent since the last tick
+    pub events: HashSet<String>,
+}
+
+/// Many entities running the same state machine definition: a list of states and
+/// the index of the one entities start in. Removed entity ids are not reused.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateMachines {
+    pub states: Vec<State>,
+    pub initial: usize,
+    pub entities: Vec<Option<Entity>>,
+}
+
+impl Transition {
+    pub fn ready(&self, entity: &Entity) -> bool {
+        self.after.is_none_or(|after| entity.time_in_state >= after)
+            && self
+                .event
+                .as_ref()
+                .is_none_or(|event| entity.events.contains(event))
+            && self.checks.iter().all(|check| {
+                entity
+                    .blackboard
+                    .get(&check.key)
+                    .is_some_and(|value| check.comparison.holds(value, &check.value))
+            })
+    }
+}
+
+impl StateMachines {
+    /// A machine with no entities yet
+    pub fn new(states: Vec<State>, initial: usize) -> Self {
+        StateMachines {
+            states,
+            initial,
+            entities: Vec::new(),
+        }
+    }
+
+    pub fn entity(&self, id: usize) -> Option<&Entity> {
+        self.entities.get(id).and_then(Option::as_ref)
+    }
+
+    pub fn entity_mut(&mut self, id: usize) -> Option<&mut Entity> {
+        self.entities.get_mut(id).and_then(Option::as_mut)
+    }
+
+    /// The index of the state with this name
+    pub fn state_index(&self, name: &str) -> Option<usize> {
+        self.states.iter().position(|state| state.name == name)
+    }
+
+    pub fn state_names(&self) -> Vec<String> {
+        self.states.iter().map(|state| state.name.clone()).collect()
+    }
+
+    /// Add an entity in the initial state and return its id
+    pub fn add_entity(&mut self, blackboard: HashMap<String, BlackboardValue>) -> usize {
+        self.entities.push(Some(Entity {
+            state: self.initial,
+            time_in_state: 0.0,
+            blackboard,
+            events: HashSet::new(),
+        }));
+        self.entities.len() - 1
+    }
+
+    /// Remove an entity, returning whether it existed
+    pub fn remove_entity(&mut self, id: usize) -> bool {
+        self.entities.get_mut(id).and_then(Option::take).is_some()
+    }
+
+    /// Queue an event for every entity's next tick
+    pub fn broadcast(&mut self, event: &str) {
+        for entity in self.entities.iter_mut().flatten() {
+            entity.events.insert(event.to_string());
+        }
+    }
+
+    /// Number of entities that have not been removed
+    pub fn len(&self) -> usize {
+        self.entities
+            .iter()
+            .filter(|entity| entity.is_some())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Advance every entity, returning `(id, from_state, to_state)` for each change
+    pub fn advance(&mut self, delta_time: f64) -> Vec<(usize, usize, usize)> {
+        let mut changes = Vec::new();
+        for (id, entity) in self.entities.iter_mut().enumerate() {
+            let Some(entity) = entity else {
+                continue;
+            };
+            entity.time_in_state += delta_time;
+            let next = self.states[entity.state]
+                .transitions
+                .iter()
+                .find(|transition| transition.ready(entity))
+                .map(|transition| transition.to);
+            if let Some(to) = next {
+                changes.push((id, entity.state, to));
+                entity.state = to;
+                entity.time_in_state = 0.0;
+            }
+            // Events only live for the tick after they were sent
+            entity.events.clear();
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transition(to: usize) -> Transition {
+        Transition {
+            to,
+            after: None,
+            event: None,
+            checks: Vec::new(),
+        }
+    }
+
+    /// idle -> alert on a noise, alert -> idle after 2 seconds, either -> flee when hurt
+    fn guard() -> StateMachines {
+        let flee = Transition {
+            checks: vec![Check {
+                key: "health".to_string(),
+                comparison: Comparison::Less,
+                value: BlackboardValue::Number(10.0),
+            }],
+            ..transition(2)
+        };
+        let idle = State {
+            name: "idle".to_string(),
+            transitions: vec![
+                flee.clone(),
+                Transition {
+                    event: Some("noise".to_string()),
+                    ..transition(1)
+                },
+            ],
+        };
+        let alert = State {
+            name: "alert".to_string(),
+            transitions: vec![
+                flee,
+                Transition {
+                    after: Some(2.0),
+                    ..transition(0)
+                },
+            ],
+        };
+        let states = vec![
+            idle,
+            alert,
+            State {
+                name: "flee".to_string(),
+                transitions: Vec::new(),
+            },
+        ];
+        StateMachines::new(states, 0)
+    }
+
+    #[test]
+    fn events_fire_once_and_timers_count_time_in_state() {
+        let mut machines = guard();
+        let id = machines.add_entity(HashMap::new());
+        machines.broadcast("noise");
+        assert_eq!(machines.advance(0.5), [(id, 0, 1)]);
+        assert!(machines.entity(id).unwrap().events.is_empty());
+        assert_eq!(machines.advance(1.5), []);
+        assert_eq!(machines.advance(0.5), [(id, 1, 0)]);
+        assert_eq!(machines.entity(id).unwrap().time_in_state, 0.0);
+    }
+
+    #[test]
+    fn blackboard_checks_compare_values_of_the_same_kind() {
+        let mut machines = guard();
+        let hurt = HashMap::from([("health".to_string(), BlackboardValue::Number(3.0))]);
+        let confused = HashMap::from([("health".to_string(), BlackboardValue::Text("3".into()))]);
+        let hurt = machines.add_entity(hurt);
+        machines.add_entity(confused);
+        assert_eq!(machines.advance(0.1), [(hurt, 0, 2)]);
+        assert!(
+            Comparison::NotEqual.holds(&BlackboardValue::Bool(true), &BlackboardValue::Number(1.0))
+        );
+        assert_eq!(Comparison::from_op(">="), Some(Comparison::GreaterOrEqual));
+    }
+
+    #[test]
+    fn removed_entities_are_skipped() {
+        let mut machines = guard();
+        let id = machines.add_entity(HashMap::new());
+        assert!(machines.remove_entity(id));
+        assert!(!machines.remove_entity(id));
+        machines.broadcast("noise");
+        assert_eq!(machines.advance(1.0), []);
+        assert_eq!(machines.len(), 0);
+        assert_eq!(machines.state_index("flee"), Some(2));
+        assert_eq!(machines.state_names(), ["idle", "alert", "flee"]);
+    }
+}
@@ -0,0 +1,296 @@
+//! Goal-oriented action planning: A* over symbolic world states, so agents chain
+//! actions such as "fetch axe" and "chop tree" to reach a goal.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::hooks::{self, Level};
+
+/// World state as one value per interned variable; variables never set are 0 (false)
+type State = Vec<i64>;
+
+#[derive(Clone, Debug)]
+struct Action {
+    name: String,
+    cost: f32,
+    preconditions: Vec<(usize, i64)>,
+    effects: Vec<(usize, i64)>,
+}
+
+struct Node {
+    state: State,
+    cost: f32,
+    parent: Option<usize>,
+    action: Option<usize>,
+}
+
+#[derive(PartialEq)]
+struct Open {
+    estimate: f32,
+    node: usize,
+}
+
+impl Eq for Open {}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Goal-oriented action planner over symbolic world states.
+///
+/// Variables are named integers (booleans work as 0/1). An action applies when all of
+/// its preconditions hold and then overwrites the variables in its effects. Plans are
+/// found with A* and are the cheapest sequence reaching every goal condition.
+#[derive(Clone, Debug, Default)]
+pub struct GoapPlanner {
+    variables: HashMap<String, usize>,
+    actions: Vec<Action>,
+}
+
+impl GoapPlanner {
+    pub fn new() -> Self {
+        GoapPlanner::default()
+    }
+
+    fn intern(variables: &mut HashMap<String, usize>, name: String) -> usize {
+        let next = variables.len();
+        *variables.entry(name).or_insert(next)
+    }
+
+    fn conditions(
+        variables: &mut HashMap<String, usize>,
+        values: HashMap<String, i64>,
+    ) -> Vec<(usize, i64)> {
+        let mut conditions: Vec<(usize, i64)> = values
+            .into_iter()
+            .map(|(name, value)| (Self::intern(variables, name), value))
+            .collect();
+        conditions.sort_unstable();
+        conditions
+    }
+
+    /// Register an action with its preconditions, effects and cost; the cost must be
+    /// positive for plans to be the cheapest
+    pub fn add_action(
+        &mut self,
+        name: String,
+        preconditions: HashMap<String, i64>,
+        effects: HashMap<String, i64>,
+        cost: f32,
+    ) {
+        let preconditions = Self::conditions(&mut self.variables, preconditions);
+        let effects = Self::conditions(&mut self.variables, effects);
+        self.actions.push(Action {
+            name,
+            cost,
+            preconditions,
+            effects,
+        });
+    }
+
+    /// Names of the registered actions, in registration order
+    pub fn action_names(&self) -> Vec<String> {
+        self.actions
+            .iter()
+            .map(|action| action.name.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Cheapest action sequence from `start` to `goal`, or `None` if there is none
+    /// within `max_nodes` expansions or the search was cancelled
+    pub fn plan(
+        &self,
+        start: HashMap<String, i64>,
+        goal: HashMap<String, i64>,
+        max_nodes: usize,
+    ) -> Option<Vec<String>> {
+        // Variables only mentioned in the query can never change, but still need a slot
+        let mut variables = self.variables.clone();
+        let start = Self::conditions(&mut variables, start);
+        let goal = Self::conditions(&mut variables, goal);
+
+        let mut initial = vec![0; variables.len()];
+        for &(variable, value) in &start {
+            initial[variable] = value;
+        }
+
+        // Each action fixes at most `max_effects` goals, so this never overestimates
+        let min_cost = self
+            .actions
+            .iter()
+            .map(|a| a.cost)
+            .fold(f32::INFINITY, f32::min);
+        let max_effects = self
+            .actions
+            .iter()
+            .map(|a| a.effects.len())
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let heuristic = |state: &State| -> f32 {
+            let unmet = goal.iter().filter(|&&(v, value)| state[v] != value).count();
+            if unmet == 0 {
+                0.0
+            } else {
+                unmet.div_ceil(max_effects) as f32 * min_cost
+            }
+        };
+
+        let mut nodes = vec![Node {
+            state: initial.clone(),
+            cost: 0.0,
+            parent: None,
+            action: None,
+        }];
+        let mut best_cost: HashMap<State, f32> = HashMap::from([(initial.clone(), 0.0)]);
+        let mut open = BinaryHeap::from([Open {
+            estimate: heuristic(&initial),
+            node: 0,
+        }]);
+        let mut expanded = 0;
+
+        while let Some(Open { node: current, .. }) = open.pop() {
+            let state = nodes[current].state.clone();
+            let cost = nodes[current].cost;
+            if best_cost.get(&state).is_some_and(|&best| cost > best) {
+                continue;
+            }
+            if goal.iter().all(|&(v, value)| state[v] == value) {
+                return Some(self.unwind(&nodes, current));
+            }
+            expanded += 1;
+            hooks::count("nodes_expanded", 1);
+            if expanded > max_nodes || hooks::cancelled() {
+                if expanded > max_nodes {
+                    hooks::log(
+                        Level::Info,
+                        format_args!("GOAP search gave up after expanding {} nodes", max_nodes),
+                    );
+                }
+                return None;
+            }
+
+            for (index, action) in self.actions.iter().enumerate() {
+                if !action
+                    .preconditions
+                    .iter()
+                    .all(|&(v, value)| state[v] == value)
+                {
+                    continue;
+                }
+                let mut next = state.clone();
+                for &(v, value) in &action.effects {
+                    next[v] = value;
+                }
+                let next_cost = cost + action.cost;
+                if best_cost.get(&next).is_some_and(|&best| best <= next_cost) {
+                    continue;
+                }
+                best_cost.insert(next.clone(), next_cost);
+                open.push(Open {
+                    estimate: next_cost + heuristic(&next),
+                    node: nodes.len(),
+                });
+                nodes.push(Node {
+                    state: next,
+                    cost: next_cost,
+                    parent: Some(current),
+                    action: Some(index),
+                });
+            }
+        }
+        None
+    }
+
+    fn unwind(&self, nodes: &[Node], mut current: usize) -> Vec<String> {
+        let mut plan = Vec::new();
+        while let (Some(parent), Some(action)) = (nodes[current].parent, nodes[current].action) {
+            plan.push(self.actions[action].name.clone());
+            current = parent;
+        }
+        plan.reverse();
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conditions(pairs: &[(&str, i64)]) -> HashMap<String, i64> {
+        pairs
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value))
+            .collect()
+    }
+
+    fn woodcutter() -> GoapPlanner {
+        let mut planner = GoapPlanner::new();
+        planner.add_action(
+            "get_axe".to_string(),
+            conditions(&[("has_axe", 0)]),
+            conditions(&[("has_axe", 1)]),
+            2.0,
+        );
+        planner.add_action(
+            "chop".to_string(),
+            conditions(&[("has_axe", 1)]),
+            conditions(&[("has_wood", 1)]),
+            1.0,
+        );
+        planner.add_action(
+            "gather_sticks".to_string(),
+            HashMap::new(),
+            conditions(&[("has_wood", 1)]),
+            5.0,
+        );
+        planner
+    }
+
+    #[test]
+    fn finds_the_cheapest_plan() {
+        let plan = woodcutter().plan(HashMap::new(), conditions(&[("has_wood", 1)]), 100);
+        assert_eq!(plan, Some(vec!["get_axe".to_string(), "chop".to_string()]));
+    }
+
+    #[test]
+    fn a_met_goal_needs_no_actions() {
+        let start = conditions(&[("has_wood", 1)]);
+        let plan = woodcutter().plan(start.clone(), start, 100);
+        assert_eq!(plan, Some(Vec::new()));
+    }
+
+    #[test]
+    fn unreachable_goals_and_budgets_give_up() {
+        let planner = woodcutter();
+        assert_eq!(
+            planner.plan(HashMap::new(), conditions(&[("has_gold", 1)]), 100),
+            None
+        );
+        assert_eq!(
+            planner.plan(HashMap::new(), conditions(&[("has_wood", 1)]), 0),
+            None
+        );
+        assert_eq!(planner.action_names(), ["get_axe", "chop", "gather_sticks"]);
+        assert_eq!(planner.len(), 3);
+    }
+}
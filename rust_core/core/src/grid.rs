@@ -0,0 +1,204 @@
+//! Helpers for row-major `[y][x]` grids
+
+/// Offsets of the four orthogonal neighbours
+pub const CARDINAL: [(isize, isize); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+/// Offsets of all eight neighbours, cardinals first
+pub const EIGHT_WAY: [(isize, isize); 8] = [
+    (0, -1),
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+    (1, -1),
+    (1, 1),
+    (-1, 1),
+    (-1, -1),
+];
+
+/// Width and height of a grid, treating an empty grid as 0x0
+pub fn dimensions<T>(grid: &[Vec<T>]) -> (usize, usize) {
+    let height = grid.len();
+    let width = if height > 0 { grid[0].len() } else { 0 };
+    (width, height)
+}
+
+/// Offset a cell by `(dx, dy)`, returning `None` if it leaves the grid
+pub fn offset(
+    x: usize,
+    y: usize,
+    dx: isize,
+    dy: isize,
+    width: usize,
+    height: usize,
+) -> Option<(usize, usize)> {
+    let nx = x as isize + dx;
+    let ny = y as isize + dy;
+    if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+        return None;
+    }
+    Some((nx as usize, ny as usize))
+}
+
+/// Read a boolean cell, treating anything outside the grid as `false`
+pub fn flag(grid: &[Vec<bool>], x: usize, y: usize) -> bool {
+    grid.get(y)
+        .and_then(|row| row.get(x))
+        .copied()
+        .unwrap_or(false)
+}
+
+/// Width and height of a grid whose rows all have the same length
+pub fn rectangular_dimensions<T>(grid: &[Vec<T>]) -> Option<(usize, usize)> {
+    let (width, height) = dimensions(grid);
+    if grid.iter().all(|row| row.len() == width) {
+        Some((width, height))
+    } else {
+        None
+    }
+}
+
+/// Why a grid, or a cell on one, was rejected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GridError {
+    /// A row's length differs from that of row 0
+    Ragged {
+        row: usize,
+        length: usize,
+        expected: usize,
+    },
+    /// The grid has no cells, being `width` by `height`
+    Empty { width: usize, height: usize },
+    /// The grid is not the size it has to match, such as a mask for a map
+    WrongSize {
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    /// The cell lies outside a grid of `size`
+    OutOfBounds {
+        cell: (usize, usize),
+        size: (usize, usize),
+    },
+}
+
+/// Width and height of a grid, rejecting ragged rows
+pub fn check_rectangular<T>(grid: &[Vec<T>]) -> Result<(usize, usize), GridError> {
+    let (width, height) = dimensions(grid);
+    match grid.iter().position(|row| row.len() != width) {
+        Some(row) => Err(GridError::Ragged {
+            row,
+            length: grid[row].len(),
+            expected: width,
+        }),
+        None => Ok((width, height)),
+    }
+}
+
+/// Like [`check_rectangular`], but a grid without any cells is rejected too
+pub fn check_non_empty<T>(grid: &[Vec<T>]) -> Result<(usize, usize), GridError> {
+    let (width, height) = check_rectangular(grid)?;
+    if width == 0 || height == 0 {
+        return Err(GridError::Empty { width, height });
+    }
+    Ok((width, height))
+}
+
+/// Check that a grid is rectangular and exactly `size` cells
+pub fn check_size<T>(grid: &[Vec<T>], size: (usize, usize)) -> Result<(), GridError> {
+    let found = check_rectangular(grid)?;
+    if found != size {
+        return Err(GridError::WrongSize {
+            expected: size,
+            found,
+        });
+    }
+    Ok(())
+}
+
+/// Check that `cell` lies inside a grid of `size`
+pub fn check_cell(cell: (usize, usize), size: (usize, usize)) -> Result<(), GridError> {
+    if cell.0 >= size.0 || cell.1 >= size.1 {
+        return Err(GridError::OutOfBounds { cell, size });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rectangular_grids_report_their_size() {
+        let grid = vec![vec![true; 3]; 2];
+        assert_eq!(check_rectangular(&grid), Ok((3, 2)));
+        assert_eq!(check_non_empty(&grid), Ok((3, 2)));
+        assert_eq!(check_size(&grid, (3, 2)), Ok(()));
+    }
+
+    #[test]
+    fn ragged_grids_name_the_first_odd_row() {
+        let grid = vec![vec![0; 4], vec![0; 4], vec![0; 2], vec![0; 5]];
+        let ragged = Err(GridError::Ragged {
+            row: 2,
+            length: 2,
+            expected: 4,
+        });
+        assert_eq!(check_rectangular(&grid), ragged);
+        assert_eq!(check_non_empty(&grid), ragged);
+        assert_eq!(check_size(&grid, (4, 4)).map(|_| (4, 4)), ragged);
+    }
+
+    #[test]
+    fn empty_grids_are_rectangular_but_not_non_empty() {
+        let no_rows: Vec<Vec<bool>> = Vec::new();
+        let no_columns: Vec<Vec<bool>> = vec![Vec::new(); 3];
+        assert_eq!(check_rectangular(&no_rows), Ok((0, 0)));
+        assert_eq!(check_rectangular(&no_columns), Ok((0, 3)));
+        assert_eq!(
+            check_non_empty(&no_rows),
+            Err(GridError::Empty {
+                width: 0,
+                height: 0
+            })
+        );
+        assert_eq!(
+            check_non_empty(&no_columns),
+            Err(GridError::Empty {
+                width: 0,
+                height: 3
+            })
+        );
+    }
+
+    #[test]
+    fn masks_must_match_their_map() {
+        let mask = vec![vec![false; 3]; 4];
+        assert_eq!(
+            check_size(&mask, (4, 3)),
+            Err(GridError::WrongSize {
+                expected: (4, 3),
+                found: (3, 4)
+            })
+        );
+        let empty: Vec<Vec<bool>> = Vec::new();
+        assert_eq!(
+            check_size(&empty, (1, 1)),
+            Err(GridError::WrongSize {
+                expected: (1, 1),
+                found: (0, 0)
+            })
+        );
+    }
+
+    #[test]
+    fn cells_must_lie_inside_the_grid() {
+        assert_eq!(check_cell((0, 0), (3, 2)), Ok(()));
+        assert_eq!(check_cell((2, 1), (3, 2)), Ok(()));
+        for cell in [(3, 0), (0, 2), (usize::MAX, usize::MAX)] {
+            assert_eq!(
+                check_cell(cell, (3, 2)),
+                Err(GridError::OutOfBounds { cell, size: (3, 2) })
+            );
+        }
+        assert!(check_cell((0, 0), (0, 0)).is_err());
+    }
+}
@@ -0,0 +1,362 @@
+//! Hexagonal grid coordinates.
+//!
+//! Hexes are stored in axial coordinates `(q, r)`; the third cube coordinate is
+//! always `s = -q - r`. Offset coordinates, as used for storing a hex map in a
+//! rectangular array, and pixel positions convert to and from axial ones.
+
+use std::ops::{Add, Mul, Sub};
+
+use crate::vec2::Vec2;
+
+/// Axial directions in counter-clockwise order, starting east (pointy-top) or
+/// south-east (flat-top)
+pub const DIRECTIONS: [Hex; 6] = [
+    Hex::new(1, 0),
+    Hex::new(1, -1),
+    Hex::new(0, -1),
+    Hex::new(-1, 0),
+    Hex::new(-1, 1),
+    Hex::new(0, 1),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Hex {
+    pub q: i32,
+    pub r: i32,
+}
+
+impl Hex {
+    pub const fn new(q: i32, r: i32) -> Self {
+        Hex { q, r }
+    }
+
+    /// The third cube coordinate
+    pub fn s(self) -> i32 {
+        -self.q - self.r
+    }
+
+    /// The hex containing fractional axial coordinates
+    pub fn round(q: f32, r: f32) -> Hex {
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        // Rounding can break q + r + s = 0; recompute whichever was rounded furthest
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+        Hex::new(rq as i32, rr as i32)
+    }
+
+    /// The adjacent hex in one of the six [`DIRECTIONS`]
+    pub fn neighbor(self, direction: usize) -> Hex {
+        self + DIRECTIONS[direction % 6]
+    }
+
+    pub fn neighbors(self) -> [Hex; 6] {
+        DIRECTIONS.map(|direction| self + direction)
+    }
+
+    /// Steps between two hexes
+    pub fn distance(self, other: Hex) -> i32 {
+        let (dq, dr) = (self.q - other.q, self.r - other.r);
+        (dq.abs() + dr.abs() + (dq + dr).abs()) / 2
+    }
+
+    /// Hexes exactly `radius` steps away, starting from the direction-4 corner and
+    /// going round counter-clockwise; a radius of 0 is the hex itself
+    pub fn ring(self, radius: u32) -> Vec<Hex> {
+        if radius == 0 {
+            return vec![self];
+        }
+        let radius = radius as i32;
+        let mut hex = self + DIRECTIONS[4] * radius;
+        let mut ring = Vec::with_capacity(6 * radius as usize);
+        for direction in 0..6 {
+            for _ in 0..radius {
+                ring.push(hex);
+                hex = hex.neighbor(direction);
+            }
+        }
+        ring
+    }
+
+    /// Every hex within `radius` steps, ring by ring from the centre outwards
+    pub fn spiral(self, radius: u32) -> Vec<Hex> {
+        (0..=radius).flat_map(|ring| self.ring(ring)).collect()
+    }
+
+    /// The hexes a straight line from `self` to `other` passes through, both ends
+    /// included
+    pub fn line(self, other: Hex) -> Vec<Hex> {
+        let steps = self.distance(other);
+        // Nudge off exact hex edges so ties round the same way along the line
+        let (q0, r0) = (self.q as f32 + 1e-6, self.r as f32 + 1e-6);
+        let (q1, r1) = (other.q as f32 + 1e-6, other.r as f32 + 1e-6);
+        (0..=steps)
+            .map(|step| {
+                let t = if steps == 0 {
+                    0.0
+                } else {
+                    step as f32 / steps as f32
+                };
+                Hex::round(q0 + (q1 - q0) * t, r0 + (r1 - r0) * t)
+            })
+            .collect()
+    }
+}
+
+impl Add for Hex {
+    type Output = Hex;
+
+    fn add(self, other: Hex) -> Hex {
+        Hex::new(self.q + other.q, self.r + other.r)
+    }
+}
+
+impl Sub for Hex {
+    type Output = Hex;
+
+    fn sub(self, other: Hex) -> Hex {
+        Hex::new(self.q - other.q, self.r - other.r)
+    }
+}
+
+impl Mul<i32> for Hex {
+    type Output = Hex;
+
+    fn mul(self, factor: i32) -> Hex {
+        Hex::new(self.q * factor, self.r * factor)
+    }
+}
+
+impl From<(i32, i32)> for Hex {
+    fn from((q, r): (i32, i32)) -> Self {
+        Hex::new(q, r)
+    }
+}
+
+impl From<Hex> for (i32, i32) {
+    fn from(hex: Hex) -> Self {
+        (hex.q, hex.r)
+    }
+}
+
+/// Which way hexes point, which fixes how they tile the plane
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    /// A corner at the top; rows are straight, columns zigzag
+    Pointy,
+    /// An edge at the top; columns are straight, rows zigzag
+    Flat,
+}
+
+impl Orientation {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pointy" => Some(Orientation::Pointy),
+            "flat" => Some(Orientation::Flat),
+            _ => None,
+        }
+    }
+}
+
+/// How hexes map onto the screen: `size` is the distance from a hex's centre to
+/// its corners, and `origin` the pixel position of hex `(0, 0)`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+    pub orientation: Orientation,
+    pub size: Vec2,
+    pub origin: Vec2,
+}
+
+const SQRT_3: f32 = 1.732_050_8;
+
+impl Layout {
+    /// Pixel position of a hex's centre
+    pub fn to_pixel(&self, hex: Hex) -> Vec2 {
+        let (q, r) = (hex.q as f32, hex.r as f32);
+        let (x, y) = match self.orientation {
+            Orientation::Pointy => (SQRT_3 * q + SQRT_3 / 2.0 * r, 1.5 * r),
+            Orientation::Flat => (1.5 * q, SQRT_3 / 2.0 * q + SQRT_3 * r),
+        };
+        Vec2::new(
+            x * self.size.x + self.origin.x,
+            y * self.size.y + self.origin.y,
+        )
+    }
+
+    /// The hex containing a pixel
+    pub fn hex_at(&self, point: Vec2) -> Hex {
+        let x = (point.x - self.origin.x) / self.size.x;
+        let y = (point.y - self.origin.y) / self.size.y;
+        let (q, r) = match self.orientation {
+            Orientation::Pointy => (SQRT_3 / 3.0 * x - y / 3.0, 2.0 / 3.0 * y),
+            Orientation::Flat => (2.0 / 3.0 * x, -x / 3.0 + SQRT_3 / 3.0 * y),
+        };
+        Hex::round(q, r)
+    }
+
+    /// Pixel positions of a hex's six corners, for drawing its outline
+    pub fn corners(&self, hex: Hex) -> [Vec2; 6] {
+        let center = self.to_pixel(hex);
+        let start = match self.orientation {
+            Orientation::Pointy => 30.0f32,
+            Orientation::Flat => 0.0,
+        };
+        std::array::from_fn(|corner| {
+            let angle = (start + 60.0 * corner as f32).to_radians();
+            Vec2::new(
+                center.x + self.size.x * angle.cos(),
+                center.y + self.size.y * angle.sin(),
+            )
+        })
+    }
+}
+
+/// How a hex map is stored in a rectangular `(col, row)` array: which rows
+/// (pointy-top) or columns (flat-top) are shoved half a hex over
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OffsetLayout {
+    OddR,
+    EvenR,
+    OddQ,
+    EvenQ,
+}
+
+impl OffsetLayout {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "odd-r" => Some(OffsetLayout::OddR),
+            "even-r" => Some(OffsetLayout::EvenR),
+            "odd-q" => Some(OffsetLayout::OddQ),
+            "even-q" => Some(OffsetLayout::EvenQ),
+            _ => None,
+        }
+    }
+
+    pub fn to_offset(self, hex: Hex) -> (i32, i32) {
+        match self {
+            OffsetLayout::OddR => (hex.q + (hex.r - (hex.r & 1)) / 2, hex.r),
+            OffsetLayout::EvenR => (hex.q + (hex.r + (hex.r & 1)) / 2, hex.r),
+            OffsetLayout::OddQ => (hex.q, hex.r + (hex.q - (hex.q & 1)) / 2),
+            OffsetLayout::EvenQ => (hex.q, hex.r + (hex.q + (hex.q & 1)) / 2),
+        }
+    }
+
+    pub fn to_axial(self, (col, row): (i32, i32)) -> Hex {
+        match self {
+            OffsetLayout::OddR => Hex::new(col - (row - (row & 1)) / 2, row),
+            OffsetLayout::EvenR => Hex::new(col - (row + (row & 1)) / 2, row),
+            OffsetLayout::OddQ => Hex::new(col, row - (col - (col & 1)) / 2),
+            OffsetLayout::EvenQ => Hex::new(col, row - (col + (col & 1)) / 2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rings_hold_six_hexes_per_step_of_radius() {
+        let center = Hex::new(2, -3);
+        assert_eq!(center.ring(0), [center]);
+        for radius in 1..=6 {
+            let ring = center.ring(radius);
+            assert_eq!(ring.len(), 6 * radius as usize);
+            assert!(ring
+                .iter()
+                .all(|&hex| center.distance(hex) == radius as i32));
+            assert_eq!(ring[0], center + DIRECTIONS[4] * radius as i32);
+            // Each hex is next to the one before, all the way round
+            for (i, &hex) in ring.iter().enumerate() {
+                assert_eq!(hex.distance(ring[(i + 1) % ring.len()]), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn spirals_hold_every_hex_within_the_radius_once() {
+        let center = Hex::new(-1, 4);
+        for radius in 0..=5u32 {
+            let mut spiral = center.spiral(radius);
+            assert_eq!(
+                spiral.len(),
+                1 + 3 * radius as usize * (radius as usize + 1)
+            );
+            assert_eq!(spiral[0], center);
+            assert!(spiral
+                .iter()
+                .all(|&hex| center.distance(hex) <= radius as i32));
+            spiral.sort_unstable();
+            spiral.dedup();
+            assert_eq!(
+                spiral.len(),
+                1 + 3 * radius as usize * (radius as usize + 1)
+            );
+        }
+    }
+
+    #[test]
+    fn lines_step_between_neighbours() {
+        let start = Hex::new(0, 0);
+        for end in [Hex::new(5, -2), Hex::new(-3, 7), Hex::new(0, -4), start] {
+            let line = start.line(end);
+            assert_eq!(line.len(), start.distance(end) as usize + 1);
+            assert_eq!((line[0], line[line.len() - 1]), (start, end));
+            assert!(line.windows(2).all(|pair| pair[0].distance(pair[1]) == 1));
+        }
+    }
+
+    #[test]
+    fn line_ties_break_the_same_way_in_both_directions() {
+        // The ideal line runs exactly along the edge between (1, 0) and (0, 1)
+        let (a, b) = (Hex::new(0, 0), Hex::new(1, 1));
+        assert_eq!(a.line(b), [a, Hex::new(1, 0), b]);
+        let mut back = b.line(a);
+        back.reverse();
+        assert_eq!(back, a.line(b));
+        // A longer line hugging edges the whole way
+        let (a, b) = (Hex::new(-2, 0), Hex::new(2, 2));
+        let mut back = b.line(a);
+        back.reverse();
+        assert_eq!(back, a.line(b));
+    }
+
+    #[test]
+    fn rounding_keeps_the_cube_constraint() {
+        assert_eq!(Hex::round(0.0, 0.0), Hex::new(0, 0));
+        assert_eq!(Hex::round(2.2, -0.9), Hex::new(2, -1));
+        // Rounded separately the coordinates sum to -1, so r, tied furthest off, is redone
+        assert_eq!(Hex::round(0.4, 0.4), Hex::new(0, 1));
+        let hex = Hex::round(-1.45, 2.7);
+        assert_eq!(hex.q + hex.r + hex.s(), 0);
+    }
+
+    #[test]
+    fn offset_and_pixel_coordinates_round_trip() {
+        let layouts = [
+            OffsetLayout::OddR,
+            OffsetLayout::EvenR,
+            OffsetLayout::OddQ,
+            OffsetLayout::EvenQ,
+        ];
+        let pixels = [Orientation::Pointy, Orientation::Flat].map(|orientation| Layout {
+            orientation,
+            size: Vec2::new(10.0, 8.0),
+            origin: Vec2::new(-3.0, 5.0),
+        });
+        for hex in Hex::new(0, 0).spiral(4) {
+            for layout in layouts {
+                assert_eq!(layout.to_axial(layout.to_offset(hex)), hex, "{:?}", layout);
+            }
+            for layout in &pixels {
+                assert_eq!(layout.hex_at(layout.to_pixel(hex)), hex);
+            }
+        }
+        assert_eq!(OffsetLayout::OddR.to_offset(Hex::new(0, 1)), (0, 1));
+        assert_eq!(OffsetLayout::EvenR.to_offset(Hex::new(0, 1)), (1, 1));
+    }
+}
@@ -0,0 +1,77 @@
+//! Callbacks through which long computations talk to whoever is running them.
+//!
+//! Algorithms poll [`cancelled`], call [`report`] with their progress, bump work
+//! counters with [`count`] and send diagnostics to [`log`]. All of these do nothing
+//! until an embedder calls [`install`]; the Python bindings install hooks that
+//! forward to `CancelToken`, `Progress`, the profiler and the `logging` module.
+
+use std::cell::Cell;
+use std::fmt::Arguments;
+use std::sync::OnceLock;
+
+/// Severity of a diagnostic, using the numeric levels of Python's `logging`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug = 10,
+    Info = 20,
+    Warning = 30,
+    Error = 40,
+}
+
+/// The embedder's callbacks; each one may be called from any thread
+#[derive(Clone, Copy)]
+pub struct Hooks {
+    /// Whether the computation running on this thread should stop early
+    pub cancelled: fn() -> bool,
+    /// The computation running on this thread has finished `fraction` of `stage`
+    pub report: fn(stage: &'static str, fraction: f32),
+    /// Add `amount` to a named work counter such as `"nodes_expanded"`
+    pub count: fn(counter: &'static str, amount: u64),
+    pub log: fn(level: Level, message: Arguments),
+}
+
+static HOOKS: OnceLock<Hooks> = OnceLock::new();
+
+thread_local! {
+    /// Set while `quiet` runs, so nested computations do not report
+    static QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Install the process-wide hooks, returning `false` if some were already installed
+pub fn install(hooks: Hooks) -> bool {
+    HOOKS.set(hooks).is_ok()
+}
+
+pub fn cancelled() -> bool {
+    HOOKS.get().is_some_and(|hooks| (hooks.cancelled)())
+}
+
+pub fn report(stage: &'static str, fraction: f32) {
+    if QUIET.with(Cell::get) {
+        return;
+    }
+    if let Some(hooks) = HOOKS.get() {
+        (hooks.report)(stage, fraction);
+    }
+}
+
+/// Run `work` without reporting progress, e.g. an inner search a caller reports on
+/// as a whole
+pub fn quiet<T>(work: impl FnOnce() -> T) -> T {
+    let was_quiet = QUIET.with(|quiet| quiet.replace(true));
+    let value = work();
+    QUIET.with(|quiet| quiet.set(was_quiet));
+    value
+}
+
+pub fn count(counter: &'static str, amount: u64) {
+    if let Some(hooks) = HOOKS.get() {
+        (hooks.count)(counter, amount);
+    }
+}
+
+pub fn log(level: Level, message: Arguments) {
+    if let Some(hooks) = HOOKS.get() {
+        (hooks.log)(level, message);
+    }
+}
@@ -0,0 +1,293 @@
+//! Influence maps for tactical AI: sources stamped around walls, spread outwards
+//! and combined cell by cell, so a unit can weigh threat against opportunity.
+
+use crate::dijkstra;
+use crate::grid;
+use crate::hooks;
+
+/// How a stamped source weakens with walking distance from its centre
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Falloff {
+    Constant,
+    Linear,
+    Quadratic,
+}
+
+impl Falloff {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "constant" => Some(Falloff::Constant),
+            "linear" => Some(Falloff::Linear),
+            "quadratic" => Some(Falloff::Quadratic),
+            _ => None,
+        }
+    }
+
+    /// The share of a source's strength felt `distance` steps away
+    pub fn weight(self, distance: f32, radius: f32) -> f32 {
+        if radius <= 0.0 {
+            return 1.0;
+        }
+        let t = (1.0 - distance / (radius + 1.0)).max(0.0);
+        match self {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => t,
+            Falloff::Quadratic => t * t,
+        }
+    }
+}
+
+/// Influence values over a row-major grid. Blocked cells always hold 0, so walls
+/// stop influence instead of letting it leak through to the other side.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InfluenceMap {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<f32>,
+    pub passable: Vec<bool>,
+}
+
+impl InfluenceMap {
+    /// An empty map with every cell passable
+    pub fn new(width: usize, height: usize) -> Self {
+        InfluenceMap {
+            width,
+            height,
+            values: vec![0.0; width * height],
+            passable: vec![true; width * height],
+        }
+    }
+
+    /// An empty map the size of a rectangular `[y][x]` walkable map
+    pub fn with_walkable(walkable_map: &[Vec<bool>]) -> Self {
+        let (width, height) = grid::dimensions(walkable_map);
+        InfluenceMap {
+            width,
+            height,
+            values: vec![0.0; width * height],
+            passable: walkable_map.concat(),
+        }
+    }
+
+    /// The index of a cell in `values`, or `None` outside the map
+    pub fn index(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| y * self.width + x)
+    }
+
+    /// Set a passable cell; walls keep holding 0
+    pub fn set(&mut self, index: usize, value: f32) {
+        if self.passable[index] {
+            self.values[index] = value;
+        }
+    }
+
+    /// Add a source at a cell, spreading up to `radius` steps around walls
+    pub fn stamp(&mut self, cell: (usize, usize), strength: f32, radius: f32, falloff: Falloff) {
+        let distances =
+            dijkstra::weighted_distances(&self.passable, self.width, self.height, cell, radius);
+        for (index, distance) in distances {
+            self.values[index] += strength * falloff.weight(distance, radius);
+        }
+    }
+
+    /// Multiply every value in place, e.g. to fade old influence each turn
+    pub fn scale(&mut self, factor: f32) {
+        for value in self.values.iter_mut() {
+            *value *= factor;
+        }
+    }
+
+    /// A copy with `op` applied to every value
+    pub fn map(&self, op: impl Fn(f32) -> f32) -> Self {
+        let mut result = self.clone();
+        for value in result.values.iter_mut() {
+            *value = op(*value);
+        }
+        result.mask_walls();
+        result
+    }
+
+    /// A copy combining each value with the same cell of `other`, or `None` if the
+    /// maps differ in size. Walls come from this map.
+    pub fn zip_with(&self, other: &InfluenceMap, op: impl Fn(f32, f32) -> f32) -> Option<Self> {
+        if (other.width, other.height) != (self.width, self.height) {
+            return None;
+        }
+        let mut result = self.clone();
+        for (value, &theirs) in result.values.iter_mut().zip(&other.values) {
+            *value = op(*value, theirs);
+        }
+        result.mask_walls();
+        Some(result)
+    }
+
+    fn mask_walls(&mut self) {
+        for (value, &passable) in self.values.iter_mut().zip(&self.passable) {
+            if !passable {
+                *value = 0.0;
+            }
+        }
+    }
+
+    /// Spread influence outwards `iterations` times, stopping early if cancelled;
+    /// `momentum` near 1 reacts quickly, near 0 keeps history
+    pub fn propagate(&mut self, decay: f32, momentum: f32, iterations: usize) {
+        for done in 0..iterations {
+            if hooks::cancelled() {
+                break;
+            }
+            hooks::report("propagate", done as f32 / iterations as f32);
+            self.propagate_once(decay, momentum.clamp(0.0, 1.0));
+        }
+    }
+
+    /// One propagation pass: each cell moves towards its strongest decayed neighbour
+    pub fn propagate_once(&mut self, decay: f32, momentum: f32) {
+        let mut next = self.values.clone();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y * self.width + x;
+                if !self.passable[index] {
+                    continue;
+                }
+                // Strongest by magnitude, so negative maps propagate the same way
+                let mut strongest = 0.0f32;
+                for &(dx, dy) in &grid::EIGHT_WAY {
+                    if let Some((nx, ny)) = grid::offset(x, y, dx, dy, self.width, self.height) {
+                        let neighbour = ny * self.width + nx;
+                        if !self.passable[neighbour] {
+                            continue;
+                        }
+                        let distance = if dx != 0 && dy != 0 {
+                            std::f32::consts::SQRT_2
+                        } else {
+                            1.0
+                        };
+                        let decayed = self.values[neighbour] * (-decay * distance).exp();
+                        if decayed.abs() > strongest.abs() {
+                            strongest = decayed;
+                        }
+                    }
+                }
+                next[index] = self.values[index] + (strongest - self.values[index]) * momentum;
+            }
+        }
+        self.values = next;
+    }
+
+    /// Smooth the map `iterations` times, stopping early if cancelled
+    pub fn blur(&mut self, iterations: usize) {
+        for done in 0..iterations {
+            if hooks::cancelled() {
+                break;
+            }
+            hooks::report("blur", done as f32 / iterations as f32);
+            self.blur_once();
+        }
+    }
+
+    /// One box blur pass over the walkable neighbourhood of every cell
+    pub fn blur_once(&mut self) {
+        let mut next = self.values.clone();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y * self.width + x;
+                if !self.passable[index] {
+                    continue;
+                }
+                let mut total = self.values[index];
+                let mut count = 1.0;
+                for &(dx, dy) in &grid::EIGHT_WAY {
+                    if let Some((nx, ny)) = grid::offset(x, y, dx, dy, self.width, self.height) {
+                        let neighbour = ny * self.width + nx;
+                        if self.passable[neighbour] {
+                            total += self.values[neighbour];
+                            count += 1.0;
+                        }
+                    }
+                }
+                next[index] = total / count;
+            }
+        }
+        self.values = next;
+    }
+
+    /// The passable cell with the highest value as `(x, y, value)`
+    pub fn max_cell(&self) -> Option<(usize, usize, f32)> {
+        self.extreme(|a, b| a > b)
+    }
+
+    /// The passable cell with the lowest value as `(x, y, value)`
+    pub fn min_cell(&self) -> Option<(usize, usize, f32)> {
+        self.extreme(|a, b| a < b)
+    }
+
+    fn extreme(&self, better: impl Fn(f32, f32) -> bool) -> Option<(usize, usize, f32)> {
+        let mut best: Option<(usize, f32)> = None;
+        for (index, &value) in self.values.iter().enumerate() {
+            if self.passable[index] && best.is_none_or(|(_, current)| better(value, current)) {
+                best = Some((index, value));
+            }
+        }
+        best.map(|(index, value)| (index % self.width, index / self.width, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 5x3 room split by a wall with a gap at the bottom
+    fn split_room() -> InfluenceMap {
+        let open = vec![true, true, true, true, true];
+        let wall = vec![true, true, false, true, true];
+        InfluenceMap::with_walkable(&[wall.clone(), wall, open])
+    }
+
+    #[test]
+    fn stamps_spread_around_walls_with_falloff() {
+        let mut map = split_room();
+        map.stamp((0, 0), 10.0, 8.0, Falloff::Linear);
+        assert_eq!(map.values[0], 10.0);
+        assert_eq!(map.values[2], 0.0, "walls hold nothing");
+        // The far side is reached through the gap, so it is weaker than the near side
+        assert!(map.values[3] > 0.0);
+        assert!(map.values[3] < map.values[1]);
+        assert_eq!(Falloff::Constant.weight(7.0, 8.0), 1.0);
+        assert_eq!(Falloff::Quadratic.weight(9.0, 8.0), 0.0);
+    }
+
+    #[test]
+    fn propagation_and_blur_keep_walls_empty() {
+        let mut map = split_room();
+        map.set(0, 5.0);
+        map.set(2, 5.0);
+        map.propagate(0.3, 0.8, 4);
+        map.blur(2);
+        assert_eq!(map.values[2], 0.0);
+        assert!(map.values[14] > 0.0);
+    }
+
+    #[test]
+    fn maps_combine_cell_by_cell() {
+        let mut a = split_room();
+        let mut b = split_room();
+        a.set(0, 2.0);
+        b.set(0, 3.0);
+        let sum = a.zip_with(&b, |a, b| a + b).unwrap();
+        assert_eq!(sum.values[0], 5.0);
+        assert_eq!(a.map(|value| value + 1.0).values[2], 0.0);
+        assert!(a.zip_with(&InfluenceMap::new(2, 2), |a, _| a).is_none());
+    }
+
+    #[test]
+    fn extremes_ignore_walls() {
+        let mut map = split_room();
+        map.values[2] = -50.0;
+        map.set(4, -1.0);
+        map.set(10, 3.0);
+        assert_eq!(map.min_cell(), Some((4, 0, -1.0)));
+        assert_eq!(map.max_cell(), Some((0, 2, 3.0)));
+        assert_eq!(InfluenceMap::new(0, 0).max_cell(), None);
+    }
+}
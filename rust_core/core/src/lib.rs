@@ -0,0 +1,56 @@
+//! Pathfinding, physics and geometry for LlamaQuest, with no Python dependency.
+//!
+//! The `llamaquest_core` Python module is a thin binding over this crate. Native
+//! code, such as a game server, can use it directly; long computations report
+//! progress and check for cancellation through [`hooks`].
+
+pub mod aoe;
+pub mod behavior_tree;
+pub mod camera;
+pub mod checksum;
+pub mod clock;
+pub mod codec;
+pub mod diffusion;
+pub mod dijkstra;
+pub mod distance;
+pub mod easing;
+pub mod events;
+pub mod explore;
+pub mod explosion;
+pub mod fluid;
+pub mod formation;
+pub mod fov;
+pub mod fsm;
+pub mod goap;
+pub mod grid;
+pub mod hex;
+pub mod hooks;
+pub mod influence;
+pub mod minimap;
+pub mod msgpack;
+pub mod orca;
+pub mod particles;
+pub mod pathfinding;
+pub mod physics;
+pub mod projection;
+pub mod raster;
+pub mod raycast;
+pub mod regions;
+pub mod replay;
+pub mod rng;
+pub mod scent;
+pub mod shapes;
+pub mod steering;
+pub mod temperature;
+pub mod threat;
+pub mod tiled;
+pub mod transform;
+pub mod turns;
+pub mod undo;
+pub mod utility;
+pub mod vec2;
+pub mod water;
+pub mod world;
+
+#[cfg(feature = "python")]
+pub mod python;
@@ -0,0 +1,205 @@
+//! Minimaps: tile grids downsampled into RGBA images, with fog of war composited
+//! on top.
+
+use std::collections::HashMap;
+
+use crate::grid;
+
+/// How a block of tiles is reduced to a single minimap pixel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reduce {
+    /// The most common explored tile in the block
+    Majority,
+    /// The highest-priority explored tile in the block, falling back to the majority
+    Priority,
+}
+
+impl Reduce {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "majority" => Some(Reduce::Majority),
+            "priority" => Some(Reduce::Priority),
+            _ => None,
+        }
+    }
+}
+
+pub type Rgba = (u8, u8, u8, u8);
+
+/// Downsamples tile grids into RGBA minimap images with fog of war composited on top
+#[derive(Clone, Debug)]
+pub struct MinimapRenderer {
+    pub palette: HashMap<u32, Rgba>,
+    /// Tiles per minimap pixel along each axis
+    pub block_size: usize,
+    pub mode: Reduce,
+    /// Rank of each tile in `Reduce::Priority` mode, lowest first; unlisted tiles
+    /// rank last
+    pub priority: HashMap<u32, usize>,
+    /// Colour for tiles missing from the palette
+    pub default_color: Rgba,
+    /// Colour of blocks with nothing explored
+    pub fog_color: Rgba,
+    /// Brightness multiplier for explored blocks that are not currently visible
+    pub remembered_dim: f32,
+}
+
+impl MinimapRenderer {
+    /// A renderer with magenta for unknown tiles, black fog and remembered blocks at
+    /// half brightness. `priority` lists tiles from most to least important.
+    pub fn new(
+        palette: HashMap<u32, Rgba>,
+        block_size: usize,
+        mode: Reduce,
+        priority: &[u32],
+    ) -> Self {
+        MinimapRenderer {
+            palette,
+            block_size,
+            mode,
+            priority: priority
+                .iter()
+                .enumerate()
+                .map(|(rank, &tile)| (tile, rank))
+                .collect(),
+            default_color: (255, 0, 255, 255),
+            fog_color: (0, 0, 0, 255),
+            remembered_dim: 0.5,
+        }
+    }
+
+    /// Render into a tightly packed RGBA buffer, returning its width and height
+    pub fn render_rgba(
+        &self,
+        tile_map: &[Vec<u32>],
+        explored_map: Option<&[Vec<bool>]>,
+        visible_map: Option<&[Vec<bool>]>,
+    ) -> (usize, usize, Vec<u8>) {
+        let (width, height) = grid::dimensions(tile_map);
+        let block = self.block_size.max(1);
+        let out_width = width.div_ceil(block);
+        let out_height = height.div_ceil(block);
+        let mut pixels = Vec::with_capacity(out_width * out_height * 4);
+
+        // Without an exploration or visibility map everything counts as seen
+        let explored = |x: usize, y: usize| explored_map.is_none_or(|m| grid::flag(m, x, y));
+        let visible = |x: usize, y: usize| visible_map.is_none_or(|m| grid::flag(m, x, y));
+
+        let mut counts: Vec<(u32, usize)> = Vec::new();
+        for block_y in 0..out_height {
+            for block_x in 0..out_width {
+                counts.clear();
+                let mut any_visible = false;
+                for y in block_y * block..((block_y + 1) * block).min(height) {
+                    for x in block_x * block..((block_x + 1) * block).min(width) {
+                        if !explored(x, y) && !visible(x, y) {
+                            continue;
+                        }
+                        any_visible |= visible(x, y);
+                        let tile = tile_map
+                            .get(y)
+                            .and_then(|row| row.get(x))
+                            .copied()
+                            .unwrap_or(0);
+                        match counts.iter_mut().find(|(id, _)| *id == tile) {
+                            Some((_, count)) => *count += 1,
+                            None => counts.push((tile, 1)),
+                        }
+                    }
+                }
+
+                let color = match self.pick(&counts) {
+                    None => self.fog_color,
+                    Some(tile) => {
+                        let color = self
+                            .palette
+                            .get(&tile)
+                            .copied()
+                            .unwrap_or(self.default_color);
+                        if any_visible {
+                            color
+                        } else {
+                            dim(color, self.remembered_dim)
+                        }
+                    }
+                };
+                pixels.extend_from_slice(&[color.0, color.1, color.2, color.3]);
+            }
+        }
+
+        (out_width, out_height, pixels)
+    }
+
+    fn pick(&self, counts: &[(u32, usize)]) -> Option<u32> {
+        if self.mode == Reduce::Priority {
+            let best = counts
+                .iter()
+                .filter_map(|&(tile, _)| self.priority.get(&tile).map(|&rank| (rank, tile)))
+                .min();
+            if let Some((_, tile)) = best {
+                return Some(tile);
+            }
+        }
+        majority(counts)
+    }
+}
+
+/// The most frequent tile, with ties going to the one seen first so frames stay stable
+fn majority(counts: &[(u32, usize)]) -> Option<u32> {
+    let mut best: Option<(u32, usize)> = None;
+    for &(tile, count) in counts {
+        if best.is_none_or(|(_, best_count)| count > best_count) {
+            best = Some((tile, count));
+        }
+    }
+    best.map(|(tile, _)| tile)
+}
+
+fn dim(color: Rgba, factor: f32) -> Rgba {
+    let scale = |channel: u8| (channel as f32 * factor.clamp(0.0, 1.0)).round() as u8;
+    (scale(color.0), scale(color.1), scale(color.2), color.3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLOOR: u32 = 1;
+    const DOOR: u32 = 2;
+    const RED: Rgba = (255, 0, 0, 255);
+    const BLUE: Rgba = (0, 0, 255, 255);
+
+    fn renderer(mode: Reduce) -> MinimapRenderer {
+        let palette = HashMap::from([(FLOOR, RED), (DOOR, BLUE)]);
+        MinimapRenderer::new(palette, 2, mode, &[DOOR])
+    }
+
+    /// A 3x2 map of floor with one door, so the right column is a partial block
+    fn map() -> Vec<Vec<u32>> {
+        vec![vec![FLOOR, FLOOR, FLOOR], vec![DOOR, FLOOR, 7]]
+    }
+
+    #[test]
+    fn blocks_take_the_majority_tile() {
+        let (width, height, pixels) = renderer(Reduce::Majority).render_rgba(&map(), None, None);
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(pixels, [255, 0, 0, 255, 255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn priority_tiles_win_over_the_majority() {
+        let (_, _, pixels) = renderer(Reduce::Priority).render_rgba(&map(), None, None);
+        assert_eq!(&pixels[..4], [0, 0, 255, 255]);
+        // Neither tile in the right block has a priority, so the first seen wins
+        assert_eq!(&pixels[4..], [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn fog_hides_unexplored_blocks_and_dims_remembered_ones() {
+        let explored = vec![vec![true, false, false], vec![false, false, false]];
+        let visible = vec![vec![false; 3]; 2];
+        let (_, _, pixels) =
+            renderer(Reduce::Majority).render_rgba(&map(), Some(&explored), Some(&visible));
+        assert_eq!(pixels, [128, 0, 0, 255, 0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,513 @@
+//! A small MessagePack encoder and decoder, for streaming state to tools outside
+//! the engine.
+//!
+//! Every MessagePack type is covered except extensions and timestamps. Integers
+//! and lengths are written in their shortest form, as other encoders do.
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    /// Negative integers; non-negative ones are always [`Value::UInt`]
+    Int(i64),
+    UInt(u64),
+    F32(f32),
+    F64(f64),
+    Str(String),
+    Bin(Vec<u8>),
+    Array(Vec<Value>),
+    /// Entries in order; keys need not be strings or unique
+    Map(Vec<(Value, Value)>),
+}
+
+/// Why [`decode`] rejected its input
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    Truncated,
+    /// A type byte that is reserved or an extension type
+    Unsupported(u8),
+    InvalidUtf8,
+    /// Arrays and maps nested deeper than [`MAX_DEPTH`]
+    TooDeep,
+    TrailingData(usize),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "MessagePack data is truncated"),
+            DecodeError::Unsupported(byte) => {
+                write!(f, "unsupported MessagePack type byte 0x{:02x}", byte)
+            }
+            DecodeError::InvalidUtf8 => write!(f, "MessagePack string is not valid UTF-8"),
+            DecodeError::TooDeep => write!(f, "MessagePack data is nested too deeply"),
+            DecodeError::TrailingData(count) => {
+                write!(f, "{} unexpected bytes after the MessagePack value", count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Nesting limit for [`decode`], so hostile input cannot exhaust the stack
+pub const MAX_DEPTH: usize = 64;
+
+impl Value {
+    /// Shorthand for a string, the usual map key
+    pub fn str(value: &str) -> Value {
+        Value::Str(value.to_string())
+    }
+
+    /// The entry for `key` in a map with string keys
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| matches!(k, Value::Str(k) if k == key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::UInt(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int(value) => Some(*value),
+            Value::UInt(value) => i64::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+
+    /// Any number, converted to `f64`
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::F32(value) => Some(f64::from(*value)),
+            Value::F64(value) => Some(*value),
+            Value::Int(value) => Some(*value as f64),
+            Value::UInt(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        if value < 0 {
+            Value::Int(value)
+        } else {
+            Value::UInt(value as u64)
+        }
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::UInt(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::F32(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+pub fn encode(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write(&mut out, value);
+    out
+}
+
+fn write(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Nil => out.push(0xC0),
+        Value::Bool(false) => out.push(0xC2),
+        Value::Bool(true) => out.push(0xC3),
+        Value::UInt(value) => write_uint(out, *value),
+        Value::Int(value) => write_int(out, *value),
+        Value::F32(value) => {
+            out.push(0xCA);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        Value::F64(value) => {
+            out.push(0xCB);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        Value::Str(value) => {
+            let length = value.len();
+            match length {
+                0..=31 => out.push(0xA0 | length as u8),
+                _ => write_length(out, length, [0xD9, 0xDA, 0xDB]),
+            }
+            out.extend_from_slice(value.as_bytes());
+        }
+        Value::Bin(value) => {
+            write_length(out, value.len(), [0xC4, 0xC5, 0xC6]);
+            out.extend_from_slice(value);
+        }
+        Value::Array(items) => {
+            match items.len() {
+                length @ 0..=15 => out.push(0x90 | length as u8),
+                length => write_length(out, length, [0, 0xDC, 0xDD]),
+            }
+            for item in items {
+                write(out, item);
+            }
+        }
+        Value::Map(entries) => {
+            match entries.len() {
+                length @ 0..=15 => out.push(0x80 | length as u8),
+                length => write_length(out, length, [0, 0xDE, 0xDF]),
+            }
+            for (key, value) in entries {
+                write(out, key);
+                write(out, value);
+            }
+        }
+    }
+}
+
+fn write_uint(out: &mut Vec<u8>, value: u64) {
+    if value < 0x80 {
+        out.push(value as u8);
+    } else if let Ok(value) = u8::try_from(value) {
+        out.extend_from_slice(&[0xCC, value]);
+    } else if let Ok(value) = u16::try_from(value) {
+        out.push(0xCD);
+        out.extend_from_slice(&value.to_be_bytes());
+    } else if let Ok(value) = u32::try_from(value) {
+        out.push(0xCE);
+        out.extend_from_slice(&value.to_be_bytes());
+    } else {
+        out.push(0xCF);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn write_int(out: &mut Vec<u8>, value: i64) {
+    if value >= 0 {
+        write_uint(out, value as u64);
+    } else if value >= -32 {
+        out.push(value as u8);
+    } else if let Ok(value) = i8::try_from(value) {
+        out.extend_from_slice(&[0xD0, value as u8]);
+    } else if let Ok(value) = i16::try_from(value) {
+        out.push(0xD1);
+        out.extend_from_slice(&value.to_be_bytes());
+    } else if let Ok(value) = i32::try_from(value) {
+        out.push(0xD2);
+        out.extend_from_slice(&value.to_be_bytes());
+    } else {
+        out.push(0xD3);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// A length with the 8-, 16- or 32-bit marker from `markers`; a zero marker means
+/// the type has no 8-bit form
+fn write_length(out: &mut Vec<u8>, length: usize, markers: [u8; 3]) {
+    if length <= 0xFF && markers[0] != 0 {
+        out.extend_from_slice(&[markers[0], length as u8]);
+    } else if let Ok(length) = u16::try_from(length) {
+        out.push(markers[1]);
+        out.extend_from_slice(&length.to_be_bytes());
+    } else {
+        out.push(markers[2]);
+        out.extend_from_slice(&(length as u32).to_be_bytes());
+    }
+}
+
+/// Decode exactly one value from `data`
+pub fn decode(data: &[u8]) -> Result<Value, DecodeError> {
+    let mut reader = Reader { data, at: 0 };
+    let value = reader.value(0)?;
+    match data.len() - reader.at {
+        0 => Ok(value),
+        left => Err(DecodeError::TrailingData(left)),
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self
+            .at
+            .checked_add(count)
+            .filter(|&end| end <= self.data.len())
+            .ok_or(DecodeError::Truncated)?;
+        let bytes = &self.data[self.at..end];
+        self.at = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        Ok(self.take(N)?.try_into().expect("took exactly N bytes"))
+    }
+
+    /// A big-endian length of `width` bytes
+    fn length(&mut self, width: usize) -> Result<usize, DecodeError> {
+        let length = self
+            .take(width)?
+            .iter()
+            .fold(0u64, |length, &byte| length << 8 | u64::from(byte));
+        usize::try_from(length).map_err(|_| DecodeError::Truncated)
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, DecodeError> {
+        if depth > MAX_DEPTH {
+            return Err(DecodeError::TooDeep);
+        }
+        let marker = self.take(1)?[0];
+        Ok(match marker {
+            0x00..=0x7F => Value::UInt(u64::from(marker)),
+            0x80..=0x8F => self.map((marker & 0x0F) as usize, depth)?,
+            0x90..=0x9F => self.items((marker & 0x0F) as usize, depth)?,
+            0xA0..=0xBF => self.string((marker & 0x1F) as usize)?,
+            0xC0 => Value::Nil,
+            0xC2 => Value::Bool(false),
+            0xC3 => Value::Bool(true),
+            0xC4..=0xC6 => {
+                let length = self.length(1 << (marker - 0xC4))?;
+                Value::Bin(self.take(length)?.to_vec())
+            }
+            0xCA => Value::F32(f32::from_be_bytes(self.array()?)),
+            0xCB => Value::F64(f64::from_be_bytes(self.array()?)),
+            0xCC..=0xCF => Value::UInt(self.length(1 << (marker - 0xCC))? as u64),
+            0xD0 => Value::from(i64::from(i8::from_be_bytes(self.array()?))),
+            0xD1 => Value::from(i64::from(i16::from_be_bytes(self.array()?))),
+            0xD2 => Value::from(i64::from(i32::from_be_bytes(self.array()?))),
+            0xD3 => Value::from(i64::from_be_bytes(self.array()?)),
+            0xD9..=0xDB => {
+                let length = self.length(1 << (marker - 0xD9))?;
+                self.string(length)?
+            }
+            0xDC | 0xDD => {
+                let length = self.length(2 << (marker - 0xDC))?;
+                self.items(length, depth)?
+            }
+            0xDE | 0xDF => {
+                let length = self.length(2 << (marker - 0xDE))?;
+                self.map(length, depth)?
+            }
+            0xE0..=0xFF => Value::Int(i64::from(marker as i8)),
+            _ => return Err(DecodeError::Unsupported(marker)),
+        })
+    }
+
+    fn string(&mut self, length: usize) -> Result<Value, DecodeError> {
+        let bytes = self.take(length)?;
+        std::str::from_utf8(bytes)
+            .map(|text| Value::Str(text.to_string()))
+            .map_err(|_| DecodeError::InvalidUtf8)
+    }
+
+    fn items(&mut self, length: usize, depth: usize) -> Result<Value, DecodeError> {
+        // Every item takes at least a byte, which bounds the allocation
+        let mut items = Vec::with_capacity(length.min(self.data.len() - self.at));
+        for _ in 0..length {
+            items.push(self.value(depth + 1)?);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn map(&mut self, length: usize, depth: usize) -> Result<Value, DecodeError> {
+        let mut entries = Vec::with_capacity(length.min(self.data.len() - self.at));
+        for _ in 0..length {
+            let key = self.value(depth + 1)?;
+            entries.push((key, self.value(depth + 1)?));
+        }
+        Ok(Value::Map(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(value: &Value) {
+        assert_eq!(decode(&encode(value)).as_ref(), Ok(value), "{:?}", value);
+    }
+
+    #[test]
+    fn every_kind_of_value_round_trips() {
+        let integers = [
+            0,
+            1,
+            127,
+            128,
+            255,
+            256,
+            65_535,
+            65_536,
+            u32::MAX as i64,
+            i64::MAX,
+        ];
+        for value in integers {
+            round_trip(&Value::from(value));
+            round_trip(&Value::from(-value - 1));
+        }
+        round_trip(&Value::UInt(u64::MAX));
+        for value in [
+            Value::Nil,
+            Value::Bool(false),
+            Value::Bool(true),
+            Value::F32(-0.25),
+            Value::F64(1e300),
+            Value::str(""),
+            Value::str("a"),
+            Value::Str("x".repeat(31)),
+            Value::Str("é".repeat(200)),
+            Value::Str("y".repeat(70_000)),
+            Value::Bin(vec![]),
+            Value::Bin(vec![7; 300]),
+            Value::Array((0..20u64).map(Value::from).collect()),
+        ] {
+            round_trip(&value);
+        }
+        round_trip(&Value::Map(vec![
+            (
+                Value::str("pos"),
+                Value::Array(vec![1.5f32.into(), (-2i64).into()]),
+            ),
+            (Value::UInt(3), Value::Map(vec![])),
+            (Value::str("pos"), Value::Nil),
+        ]));
+        let wide = Value::Map((0..16u64).map(|i| (i.into(), true.into())).collect());
+        round_trip(&wide);
+    }
+
+    #[test]
+    fn values_are_written_in_their_shortest_form() {
+        let cases: [(Value, &[u8]); 10] = [
+            (Value::from(5i64), &[0x05]),
+            (Value::from(-5i64), &[0xFB]),
+            (Value::from(200i64), &[0xCC, 0xC8]),
+            (Value::from(-33i64), &[0xD0, 0xDF]),
+            (Value::from(70_000i64), &[0xCE, 0x00, 0x01, 0x11, 0x70]),
+            (Value::str("hi"), &[0xA2, b'h', b'i']),
+            (Value::Bin(vec![1]), &[0xC4, 0x01, 0x01]),
+            (Value::Array(vec![Value::Nil]), &[0x91, 0xC0]),
+            (
+                Value::Map(vec![(true.into(), false.into())]),
+                &[0x81, 0xC3, 0xC2],
+            ),
+            (Value::F32(1.0), &[0xCA, 0x3F, 0x80, 0x00, 0x00]),
+        ];
+        for (value, bytes) in cases {
+            assert_eq!(encode(&value), bytes, "{:?}", value);
+        }
+        // Sixteen items no longer fit the short array form
+        assert_eq!(
+            encode(&Value::Array(vec![Value::Nil; 16]))[..3],
+            [0xDC, 0x00, 0x10]
+        );
+    }
+
+    #[test]
+    fn foreign_encodings_decode_to_the_same_values() {
+        // Other encoders may pick wider forms than needed
+        assert_eq!(decode(&[0xD3, 0, 0, 0, 0, 0, 0, 0, 9]), Ok(Value::UInt(9)));
+        assert_eq!(decode(&[0xCD, 0x00, 0x07]), Ok(Value::UInt(7)));
+        assert_eq!(decode(&[0xDA, 0x00, 0x01, b'z']), Ok(Value::str("z")));
+        assert_eq!(
+            decode(&[0xDD, 0, 0, 0, 1, 0xC0]),
+            Ok(Value::Array(vec![Value::Nil]))
+        );
+    }
+
+    #[test]
+    fn malformed_data_is_refused() {
+        assert_eq!(decode(&[]), Err(DecodeError::Truncated));
+        assert_eq!(decode(&[0xCB, 0, 0]), Err(DecodeError::Truncated));
+        assert_eq!(decode(&[0x92, 0x01]), Err(DecodeError::Truncated));
+        assert_eq!(
+            decode(&[0xDB, 0xFF, 0xFF, 0xFF, 0xFF]),
+            Err(DecodeError::Truncated)
+        );
+        assert_eq!(decode(&[0xC1]), Err(DecodeError::Unsupported(0xC1)));
+        assert_eq!(
+            decode(&[0xD4, 0x01, 0x00]),
+            Err(DecodeError::Unsupported(0xD4))
+        );
+        assert_eq!(decode(&[0xA1, 0xFF]), Err(DecodeError::InvalidUtf8));
+        assert_eq!(
+            decode(&[0xC0, 0xC0, 0xC0]),
+            Err(DecodeError::TrailingData(2))
+        );
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested =
+            |depth: usize| (0..depth).fold(Value::Nil, |inner, _| Value::Array(vec![inner]));
+        round_trip(&nested(MAX_DEPTH));
+        assert_eq!(
+            decode(&encode(&nested(MAX_DEPTH + 1))),
+            Err(DecodeError::TooDeep)
+        );
+        // A huge claimed length is not allocated up front
+        assert_eq!(
+            decode(&[0xDD, 0xFF, 0xFF, 0xFF, 0xFF]),
+            Err(DecodeError::Truncated)
+        );
+    }
+
+    #[test]
+    fn accessors_read_the_matching_kinds() {
+        let value = decode(&encode(&Value::Map(vec![
+            (Value::str("name"), Value::str("orc")),
+            (Value::str("hp"), Value::from(12i64)),
+            (Value::str("dx"), Value::from(-3i64)),
+            (Value::str("alive"), true.into()),
+        ])))
+        .unwrap();
+        assert_eq!(value.get("name").and_then(Value::as_str), Some("orc"));
+        assert_eq!(value.get("hp").and_then(Value::as_u64), Some(12));
+        assert_eq!(value.get("dx").and_then(Value::as_u64), None);
+        assert_eq!(value.get("dx").and_then(Value::as_i64), Some(-3));
+        assert_eq!(value.get("hp").and_then(Value::as_f64), Some(12.0));
+        assert_eq!(value.get("alive").and_then(Value::as_bool), Some(true));
+        assert_eq!(value.get("missing"), None);
+        assert_eq!(Value::UInt(u64::MAX).as_i64(), None);
+    }
+}
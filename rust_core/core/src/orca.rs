@@ -0,0 +1,351 @@
+//! Optimal reciprocal collision avoidance (ORCA), after the RVO2 library.
+//!
+//! Each agent gets one half-plane constraint per neighbour in velocity space and
+//! picks the velocity closest to its preferred one that satisfies all of them.
+
+use crate::hooks::{self, Level};
+use crate::vec2::Vec2;
+
+const EPSILON: f32 = 1.0e-5;
+
+#[derive(Clone, Copy, Debug)]
+pub struct OrcaAgent {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub preferred_velocity: Vec2,
+    pub radius: f32,
+    pub max_speed: f32,
+}
+
+/// Directed line bounding the permitted half-plane, which lies to its left
+#[derive(Clone, Copy, Debug)]
+struct Line {
+    point: Vec2,
+    direction: Vec2,
+}
+
+/// Parameters shared by every agent in a crowd step
+#[derive(Clone, Copy, Debug)]
+pub struct OrcaSettings {
+    /// How far ahead, in seconds, collisions with other agents are avoided
+    pub time_horizon: f32,
+    /// Length of the step the new velocities will be used for
+    pub time_step: f32,
+    pub neighbor_distance: f32,
+    pub max_neighbors: usize,
+}
+
+/// Neighbour ids for each agent: the closest `max_neighbors` within `neighbor_distance`
+pub fn nearest_neighbors(agents: &[OrcaAgent], settings: &OrcaSettings) -> Vec<Vec<usize>> {
+    let range_squared = settings.neighbor_distance * settings.neighbor_distance;
+    let count = agents.len() as u64;
+    hooks::count("pairs_tested", count * count.saturating_sub(1));
+    agents
+        .iter()
+        .enumerate()
+        .map(|(i, agent)| {
+            let mut candidates: Vec<(f32, usize)> = agents
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(j, other)| ((other.position - agent.position).length_squared(), j))
+                .filter(|&(distance_squared, _)| distance_squared < range_squared)
+                .collect();
+            candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+            candidates.truncate(settings.max_neighbors);
+            candidates.into_iter().map(|(_, j)| j).collect()
+        })
+        .collect()
+}
+
+/// Collision-free velocity for every agent given each one's neighbour set
+pub fn orca_velocities(
+    agents: &[OrcaAgent],
+    neighbors: &[Vec<usize>],
+    settings: &OrcaSettings,
+) -> Vec<Vec2> {
+    agents
+        .iter()
+        .zip(neighbors)
+        .map(|(agent, ids)| {
+            let lines: Vec<Line> = ids
+                .iter()
+                .filter_map(|&j| agents.get(j))
+                .map(|other| agent_line(agent, other, settings))
+                .collect();
+
+            let mut velocity = Vec2::ZERO;
+            let failed = linear_program2(
+                &lines,
+                agent.max_speed,
+                agent.preferred_velocity,
+                false,
+                &mut velocity,
+            );
+            if failed < lines.len() {
+                // Too crowded to satisfy every constraint; minimise the worst violation
+                hooks::log(
+                    Level::Debug,
+                    format_args!(
+                        "ORCA constraints for an agent are infeasible, using the fallback program"
+                    ),
+                );
+                linear_program3(&lines, 0, failed, agent.max_speed, &mut velocity);
+            }
+            velocity
+        })
+        .collect()
+}
+
+/// Half-plane of velocities that avoid `other`, taking half the responsibility
+fn agent_line(agent: &OrcaAgent, other: &OrcaAgent, settings: &OrcaSettings) -> Line {
+    let relative_position = other.position - agent.position;
+    let relative_velocity = agent.velocity - other.velocity;
+    let distance_squared = relative_position.length_squared();
+    let combined_radius = agent.radius + other.radius;
+    let combined_radius_squared = combined_radius * combined_radius;
+
+    let direction;
+    let u;
+    if distance_squared > combined_radius_squared {
+        let inverse_horizon = 1.0 / settings.time_horizon;
+        // Vector from the cutoff centre to the relative velocity
+        let w = relative_velocity - relative_position * inverse_horizon;
+        let w_length_squared = w.length_squared();
+        let dot = w.dot(relative_position);
+
+        if dot < 0.0 && dot * dot > combined_radius_squared * w_length_squared {
+            // Project on the cutoff circle
+            let w_length = w_length_squared.sqrt();
+            let unit_w = w * (1.0 / w_length);
+            direction = Vec2::new(unit_w.y, -unit_w.x);
+            u = unit_w * (combined_radius * inverse_horizon - w_length);
+        } else {
+            // Project on the nearer leg of the velocity obstacle cone
+            let leg = (distance_squared - combined_radius_squared).sqrt();
+            direction = if relative_position.cross(w) > 0.0 {
+                Vec2::new(
+                    relative_position.x * leg - relative_position.y * combined_radius,
+                    relative_position.x * combined_radius + relative_position.y * leg,
+                ) * (1.0 / distance_squared)
+            } else {
+                -Vec2::new(
+                    relative_position.x * leg + relative_position.y * combined_radius,
+                    -relative_position.x * combined_radius + relative_position.y * leg,
+                ) * (1.0 / distance_squared)
+            };
+            u = direction * relative_velocity.dot(direction) - relative_velocity;
+        }
+    } else {
+        // Already overlapping: separate within a single time step
+        let inverse_step = 1.0 / settings.time_step;
+        let w = relative_velocity - relative_position * inverse_step;
+        let w_length = w.length();
+        let unit_w = if w_length > EPSILON {
+            w * (1.0 / w_length)
+        } else {
+            Vec2::new(1.0, 0.0)
+        };
+        direction = Vec2::new(unit_w.y, -unit_w.x);
+        u = unit_w * (combined_radius * inverse_step - w_length);
+    }
+
+    Line {
+        point: agent.velocity + u * 0.5,
+        direction,
+    }
+}
+
+/// Optimise along line `line_no` subject to the earlier lines and the speed circle
+fn linear_program1(
+    lines: &[Line],
+    line_no: usize,
+    radius: f32,
+    optimal: Vec2,
+    direction_optimal: bool,
+    result: &mut Vec2,
+) -> bool {
+    let line = lines[line_no];
+    let dot = line.point.dot(line.direction);
+    let discriminant = dot * dot + radius * radius - line.point.length_squared();
+    if discriminant < 0.0 {
+        // The speed circle does not reach this line at all
+        return false;
+    }
+
+    let root = discriminant.sqrt();
+    let mut t_left = -dot - root;
+    let mut t_right = -dot + root;
+
+    for other in &lines[..line_no] {
+        let denominator = line.direction.cross(other.direction);
+        let numerator = other.direction.cross(line.point - other.point);
+        if denominator.abs() <= EPSILON {
+            // Parallel lines
+            if numerator < 0.0 {
+                return false;
+            }
+            continue;
+        }
+        let t = numerator / denominator;
+        if denominator >= 0.0 {
+            t_right = t_right.min(t);
+        } else {
+            t_left = t_left.max(t);
+        }
+        if t_left > t_right {
+            return false;
+        }
+    }
+
+    *result = if direction_optimal {
+        if optimal.dot(line.direction) > 0.0 {
+            line.point + line.direction * t_right
+        } else {
+            line.point + line.direction * t_left
+        }
+    } else {
+        let t = line.direction.dot(optimal - line.point);
+        line.point + line.direction * t.clamp(t_left, t_right)
+    };
+    true
+}
+
+/// Returns the index of the first line that could not be satisfied, or `lines.len()`
+fn linear_program2(
+    lines: &[Line],
+    radius: f32,
+    optimal: Vec2,
+    direction_optimal: bool,
+    result: &mut Vec2,
+) -> usize {
+    *result = if direction_optimal {
+        optimal * radius
+    } else if optimal.length_squared() > radius * radius {
+        optimal.normalize() * radius
+    } else {
+        optimal
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.direction.cross(line.point - *result) > 0.0 {
+            let previous = *result;
+            if !linear_program1(lines, i, radius, optimal, direction_optimal, result) {
+                *result = previous;
+                return i;
+            }
+        }
+    }
+    lines.len()
+}
+
+/// Infeasible case: minimise the largest violation over the remaining lines
+fn linear_program3(
+    lines: &[Line],
+    obstacle_lines: usize,
+    begin: usize,
+    radius: f32,
+    result: &mut Vec2,
+) {
+    let mut distance = 0.0;
+
+    for i in begin..lines.len() {
+        if lines[i].direction.cross(lines[i].point - *result) <= distance {
+            continue;
+        }
+
+        let mut projected: Vec<Line> = lines[..obstacle_lines].to_vec();
+        for j in obstacle_lines..i {
+            let determinant = lines[i].direction.cross(lines[j].direction);
+            let point = if determinant.abs() <= EPSILON {
+                if lines[i].direction.dot(lines[j].direction) > 0.0 {
+                    // Same direction, already covered
+                    continue;
+                }
+                (lines[i].point + lines[j].point) * 0.5
+            } else {
+                lines[i].point
+                    + lines[i].direction
+                        * (lines[j].direction.cross(lines[i].point - lines[j].point) / determinant)
+            };
+            projected.push(Line {
+                point,
+                direction: (lines[j].direction - lines[i].direction).normalize(),
+            });
+        }
+
+        let previous = *result;
+        let perpendicular = Vec2::new(-lines[i].direction.y, lines[i].direction.x);
+        if linear_program2(&projected, radius, perpendicular, true, result) < projected.len() {
+            // Only fails through floating point error; keep the previous answer
+            *result = previous;
+        }
+        distance = lines[i].direction.cross(lines[i].point - *result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: OrcaSettings = OrcaSettings {
+        time_horizon: 2.0,
+        time_step: 0.1,
+        neighbor_distance: 10.0,
+        max_neighbors: 10,
+    };
+
+    fn agent(x: f32, preferred: Vec2) -> OrcaAgent {
+        OrcaAgent {
+            position: Vec2::new(x, 0.0),
+            velocity: preferred,
+            preferred_velocity: preferred,
+            radius: 0.5,
+            max_speed: 1.0,
+        }
+    }
+
+    #[test]
+    fn lone_agents_keep_their_preferred_velocity() {
+        let agents = [agent(0.0, Vec2::new(0.5, 0.25))];
+        let neighbors = nearest_neighbors(&agents, &SETTINGS);
+        assert_eq!(neighbors, [Vec::<usize>::new()]);
+        assert_eq!(
+            orca_velocities(&agents, &neighbors, &SETTINGS),
+            [Vec2::new(0.5, 0.25)]
+        );
+    }
+
+    #[test]
+    fn head_on_agents_slow_down_or_turn_aside() {
+        let agents = [
+            agent(0.0, Vec2::new(1.0, 0.0)),
+            agent(2.0, Vec2::new(-1.0, 0.0)),
+        ];
+        let neighbors = nearest_neighbors(&agents, &SETTINGS);
+        let velocities = orca_velocities(&agents, &neighbors, &SETTINGS);
+        // Closing at the full two units per second would touch within the horizon
+        let closing = velocities[0].x - velocities[1].x;
+        assert!(closing < 2.0 - 1e-3, "closing speed {}", closing);
+        assert!(velocities
+            .iter()
+            .all(|velocity| velocity.length() <= 1.0 + 1e-4));
+    }
+
+    #[test]
+    fn only_the_nearest_neighbours_within_range_count() {
+        let settings = OrcaSettings {
+            max_neighbors: 1,
+            neighbor_distance: 5.0,
+            ..SETTINGS
+        };
+        let agents = [
+            agent(0.0, Vec2::ZERO),
+            agent(3.0, Vec2::ZERO),
+            agent(1.0, Vec2::ZERO),
+            agent(20.0, Vec2::ZERO),
+        ];
+        let neighbors = nearest_neighbors(&agents, &settings);
+        assert_eq!(neighbors, [vec![2], vec![2], vec![0], vec![]]);
+    }
+}
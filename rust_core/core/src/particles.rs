@@ -0,0 +1,249 @@
+//! CPU particles spawned by emitters and simulated in bulk.
+
+use crate::hooks::{self, Level};
+use crate::rng::Rng;
+use crate::vec2::Vec2;
+
+pub type Rgba = (u8, u8, u8, u8);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Emitter {
+    pub position: Vec2,
+    /// Particles spawned per second
+    pub rate: f32,
+    pub lifetime: (f32, f32),
+    pub speed: (f32, f32),
+    /// Emission angle range in radians
+    pub angle: (f32, f32),
+    pub gravity: Vec2,
+    /// Fraction of velocity lost per second
+    pub drag: f32,
+    /// Colour stops spread evenly from birth to death
+    pub colors: Vec<Rgba>,
+    /// Fractional particles carried over between updates
+    pub pending: f32,
+    /// Removed emitters stop spawning but keep driving the particles they made
+    pub active: bool,
+}
+
+impl Emitter {
+    /// The colour `t` of the way through a particle's life, white without stops
+    pub fn color_at(&self, t: f32) -> Rgba {
+        match self.colors.len() {
+            0 => (255, 255, 255, 255),
+            1 => self.colors[0],
+            stops => {
+                let scaled = t.clamp(0.0, 1.0) * (stops - 1) as f32;
+                let index = (scaled as usize).min(stops - 2);
+                let f = scaled - index as f32;
+                let (a, b) = (self.colors[index], self.colors[index + 1]);
+                let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * f).round() as u8;
+                (mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2), mix(a.3, b.3))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub age: f32,
+    pub lifetime: f32,
+    /// Index of the emitter that spawned it
+    pub emitter: usize,
+}
+
+/// Emitters and the particles they spawned. Emitter ids are indices into
+/// `emitters` and stay valid after removal.
+#[derive(Clone, Debug)]
+pub struct ParticleSystem {
+    pub emitters: Vec<Emitter>,
+    pub particles: Vec<Particle>,
+    /// Live particles never exceed this; spawns beyond it are dropped
+    pub capacity: usize,
+    pub rng: Rng,
+}
+
+impl ParticleSystem {
+    pub fn new(capacity: usize, seed: u64) -> Self {
+        ParticleSystem {
+            emitters: Vec::new(),
+            particles: Vec::new(),
+            capacity,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Add an emitter and return its id
+    pub fn add_emitter(&mut self, emitter: Emitter) -> usize {
+        self.emitters.push(emitter);
+        self.emitters.len() - 1
+    }
+
+    /// An emitter that has not been removed
+    pub fn emitter_mut(&mut self, id: usize) -> Option<&mut Emitter> {
+        self.emitters.get_mut(id).filter(|emitter| emitter.active)
+    }
+
+    /// Stop an emitter, returning whether it was active; particles it already
+    /// spawned live out their lifetime
+    pub fn remove_emitter(&mut self, id: usize) -> bool {
+        match self.emitter_mut(id) {
+            Some(emitter) => {
+                emitter.active = false;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Spawn `count` particles from an active emitter at once, e.g. for explosions,
+    /// returning whether the emitter was active
+    pub fn burst(&mut self, id: usize, count: usize) -> bool {
+        if self.emitter_mut(id).is_none() {
+            return false;
+        }
+        self.spawn(id, count);
+        true
+    }
+
+    fn spawn(&mut self, id: usize, count: usize) {
+        let emitter = &self.emitters[id];
+        for _ in 0..count {
+            if self.particles.len() >= self.capacity {
+                hooks::log(
+                    Level::Debug,
+                    format_args!(
+                        "particle system is full at {} particles, dropping new ones",
+                        self.capacity
+                    ),
+                );
+                break;
+            }
+            let angle = self.rng.range_f32(emitter.angle.0, emitter.angle.1);
+            let speed = self.rng.range_f32(emitter.speed.0, emitter.speed.1);
+            self.particles.push(Particle {
+                position: emitter.position,
+                velocity: Vec2::new(angle.cos(), angle.sin()) * speed,
+                age: 0.0,
+                lifetime: self
+                    .rng
+                    .range_f32(emitter.lifetime.0, emitter.lifetime.1)
+                    .max(f32::EPSILON),
+                emitter: id,
+            });
+        }
+    }
+
+    /// Spawn due particles, then age, accelerate and move every particle
+    pub fn update(&mut self, delta_time: f32) {
+        for id in 0..self.emitters.len() {
+            let emitter = &mut self.emitters[id];
+            if !emitter.active {
+                continue;
+            }
+            emitter.pending += emitter.rate.max(0.0) * delta_time;
+            let count = emitter.pending.floor();
+            emitter.pending -= count;
+            self.spawn(id, count as usize);
+        }
+
+        hooks::count("particles_stepped", self.particles.len() as u64);
+        let emitters = &self.emitters;
+        self.particles.retain_mut(|particle| {
+            particle.age += delta_time;
+            if particle.age >= particle.lifetime {
+                return false;
+            }
+            let emitter = &emitters[particle.emitter];
+            particle.velocity += emitter.gravity * delta_time;
+            particle.velocity = particle.velocity * (1.0 - emitter.drag * delta_time).max(0.0);
+            particle.position += particle.velocity * delta_time;
+            true
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    /// A particle's current colour from its emitter's stops
+    pub fn color(&self, particle: &Particle) -> Rgba {
+        self.emitters[particle.emitter].color_at(particle.age / particle.lifetime)
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fountain(rate: f32) -> Emitter {
+        Emitter {
+            position: Vec2::new(1.0, 2.0),
+            rate,
+            lifetime: (1.0, 1.0),
+            speed: (0.0, 0.0),
+            angle: (0.0, 0.0),
+            gravity: Vec2::new(0.0, -10.0),
+            drag: 0.0,
+            colors: vec![(0, 0, 0, 255), (200, 100, 0, 0)],
+            pending: 0.0,
+            active: true,
+        }
+    }
+
+    #[test]
+    fn emitters_spawn_at_their_rate() {
+        let mut system = ParticleSystem::new(100, 1);
+        system.add_emitter(fountain(10.0));
+        system.update(0.25);
+        system.update(0.25);
+        assert_eq!(system.len(), 5);
+        system.update(0.75);
+        // The first batches expired, leaving the seven spawned this update
+        assert_eq!(system.len(), 7);
+    }
+
+    #[test]
+    fn particles_fall_and_fade() {
+        let mut system = ParticleSystem::new(100, 1);
+        let id = system.add_emitter(fountain(0.0));
+        assert!(system.burst(id, 1));
+        system.update(0.5);
+        let particle = system.particles[0];
+        assert!(particle.position.y < 2.0);
+        assert_eq!(system.color(&particle), (100, 50, 0, 128));
+        assert_eq!(fountain(0.0).color_at(2.0), (200, 100, 0, 0));
+    }
+
+    #[test]
+    fn capacity_drops_new_particles() {
+        let mut system = ParticleSystem::new(3, 1);
+        let id = system.add_emitter(fountain(0.0));
+        system.burst(id, 10);
+        assert_eq!(system.len(), 3);
+        system.clear();
+        assert!(system.is_empty());
+    }
+
+    #[test]
+    fn removed_emitters_stop_but_their_particles_live_on() {
+        let mut system = ParticleSystem::new(100, 1);
+        let id = system.add_emitter(fountain(10.0));
+        system.update(0.5);
+        assert!(system.remove_emitter(id));
+        assert!(!system.remove_emitter(id));
+        assert!(!system.burst(id, 1));
+        system.update(0.25);
+        assert_eq!(system.len(), 5);
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+
+use crate::grid;
+use crate::hooks;
+
+/// What stopped [`find_path`] short of the end cell
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    /// The next cell of the walk is not walkable
+    Blocked((usize, usize)),
+    /// This many moves were taken without arriving
+    OutOfSteps(usize),
+    /// The walk left the map, which only happens when the end cell is off it
+    OffMap,
+}
+
+/// Why [`find_path`] could not reach the end cell
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NoPath {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub stop: Stop,
+}
+
+impl fmt::Display for NoPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no path from ({}, {}) to ({}, {}): ",
+            self.start.0, self.start.1, self.end.0, self.end.1
+        )?;
+        match self.stop {
+            Stop::Blocked((x, y)) => write!(f, "cell ({}, {}) is blocked", x, y),
+            Stop::OutOfSteps(steps) => write!(f, "not reached within {} steps", steps),
+            Stop::OffMap => write!(f, "the walk left the map"),
+        }
+    }
+}
+
+impl std::error::Error for NoPath {}
+
+/// Walk from `start` straight towards `end` over a `[y][x]` walkable map, taking
+/// at most `max_steps` moves (1000 by default), and return the cells walked,
+/// `start` and `end` included.
+///
+/// This is a direct walk only, stepping diagonally until level with `end`; it
+/// does not search for a way around obstacles. Any walk that stops short of
+/// `end` is a [`NoPath`], except one cancelled through [`hooks`], which returns
+/// the cells walked so far for the caller to discard.
+pub fn find_path(
+    (start_x, start_y): (usize, usize),
+    (end_x, end_y): (usize, usize),
+    walkable_map: &[Vec<bool>],
+    max_steps: Option<usize>,
+) -> Result<Vec<(usize, usize)>, NoPath> {
+    // To be replaced with a proper A* search
+    let (width, height) = grid::dimensions(walkable_map);
+    let no_path = |stop| NoPath {
+        start: (start_x, start_y),
+        end: (end_x, end_y),
+        stop,
+    };
+    let mut path = Vec::new();
+    let steps = max_steps.unwrap_or(1000);
+
+    let dx = (end_x as isize - start_x as isize).signum();
+    let dy = (end_y as isize - start_y as isize).signum();
+
+    let mut current_x = start_x as isize;
+    let mut current_y = start_y as isize;
+
+    path.push((start_x, start_y));
+
+    for _ in 0..steps {
+        if current_x == end_x as isize && current_y == end_y as isize || hooks::cancelled() {
+            return Ok(path);
+        }
+
+        if current_x != end_x as isize {
+            current_x += dx;
+        }
+
+        if current_y != end_y as isize {
+            current_y += dy;
+        }
+
+        if current_x < 0
+            || current_y < 0
+            || current_x >= width as isize
+            || current_y >= height as isize
+        {
+            return Err(no_path(Stop::OffMap));
+        }
+        let cell = (current_x as usize, current_y as usize);
+        if !walkable_map[cell.1][cell.0] {
+            return Err(no_path(Stop::Blocked(cell)));
+        }
+        path.push(cell);
+    }
+
+    if path.last() == Some(&(end_x, end_y)) || hooks::cancelled() {
+        Ok(path)
+    } else {
+        Err(no_path(Stop::OutOfSteps(steps)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(width: usize, height: usize) -> Vec<Vec<bool>> {
+        vec![vec![true; width]; height]
+    }
+
+    #[test]
+    fn walks_diagonally_then_straight() {
+        let path = find_path((0, 0), (3, 1), &open(4, 2), None).unwrap();
+        assert_eq!(path, [(0, 0), (1, 1), (2, 1), (3, 1)]);
+        let back = find_path((3, 1), (0, 0), &open(4, 2), None).unwrap();
+        assert_eq!(back, [(3, 1), (2, 0), (1, 0), (0, 0)]);
+        assert_eq!(
+            find_path((2, 1), (2, 1), &open(4, 2), Some(0)),
+            Ok(vec![(2, 1)])
+        );
+    }
+
+    #[test]
+    fn every_early_stop_is_an_error() {
+        let mut walls = open(4, 1);
+        walls[0][2] = false;
+        let stop = |result: Result<_, NoPath>| result.unwrap_err().stop;
+        assert_eq!(
+            stop(find_path((0, 0), (3, 0), &walls, None)),
+            Stop::Blocked((2, 0))
+        );
+        assert_eq!(
+            stop(find_path((0, 0), (3, 0), &open(4, 1), Some(2))),
+            Stop::OutOfSteps(2)
+        );
+        assert_eq!(
+            stop(find_path((0, 0), (6, 0), &open(4, 1), None)),
+            Stop::OffMap
+        );
+        assert_eq!(
+            find_path((0, 0), (3, 0), &open(4, 1), Some(3)).map(|p| p.len()),
+            Ok(4)
+        );
+    }
+
+    #[test]
+    fn errors_name_the_reason() {
+        let error = find_path((0, 0), (3, 0), &open(4, 1), Some(1)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "no path from (0, 0) to (3, 0): not reached within 1 steps"
+        );
+    }
+}
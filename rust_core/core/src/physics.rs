@@ -0,0 +1,172 @@
+use crate::hooks;
+use crate::shapes::{Rect, Shape};
+use crate::vec2::Vec2;
+
+/// Names accepted by [`Physics::preset`]
+pub const PRESETS: [&str; 3] = ["platformer", "top_down", "space"];
+
+/// Tuning for simple side-view physics: gravity pulls towards +y.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Physics {
+    pub gravity: f32,
+    /// Horizontal deceleration applied while on the ground
+    pub friction: f32,
+    /// Fraction of horizontal speed projectiles lose per second
+    pub air_resistance: f32,
+    /// Fastest horizontal speed in either direction, or `None` for no limit
+    pub max_velocity_x: Option<f32>,
+    /// Fastest vertical speed in either direction, or `None` for no limit
+    pub max_velocity_y: Option<f32>,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Physics {
+            gravity: 9.8,
+            friction: 0.1,
+            air_resistance: 0.01,
+            max_velocity_x: None,
+            max_velocity_y: None,
+        }
+    }
+}
+
+impl Physics {
+    /// Settings tuned for a kind of game, one of [`PRESETS`]
+    pub fn preset(name: &str) -> Option<Self> {
+        let (gravity, friction, air_resistance, max_velocity_x, max_velocity_y) = match name {
+            // Snappy ground control and a terminal falling speed
+            "platformer" => (30.0, 20.0, 0.01, Some(10.0), Some(20.0)),
+            // Seen from above: nothing falls and entities are always "on the ground"
+            "top_down" => (0.0, 15.0, 0.0, Some(8.0), Some(8.0)),
+            // Bodies drift until something stops them
+            "space" => (0.0, 0.0, 0.0, None, None),
+            _ => return None,
+        };
+        Some(Physics {
+            gravity,
+            friction,
+            air_resistance,
+            max_velocity_x,
+            max_velocity_y,
+        })
+    }
+
+    /// One gravity, friction and integration step for a single entity,
+    /// returning its new position and velocity
+    pub fn integrate(
+        &self,
+        position: Vec2,
+        velocity: Vec2,
+        is_on_ground: bool,
+        delta_time: f32,
+    ) -> (Vec2, Vec2) {
+        // Apply gravity if not on ground
+        let mut new_velocity = velocity;
+        if !is_on_ground {
+            new_velocity.y += self.gravity * delta_time;
+        }
+
+        // Apply friction only when on ground
+        if is_on_ground {
+            if velocity.x > 0.0 {
+                new_velocity.x = (velocity.x - self.friction * delta_time).max(0.0);
+            } else if velocity.x < 0.0 {
+                new_velocity.x = (velocity.x + self.friction * delta_time).min(0.0);
+            }
+        }
+
+        let new_velocity = self.limit_velocity(new_velocity);
+        (position + new_velocity * delta_time, new_velocity)
+    }
+
+    /// [`Physics::integrate`] repeated for `steps` fixed steps of `step` seconds
+    pub fn integrate_steps(
+        &self,
+        position: Vec2,
+        velocity: Vec2,
+        is_on_ground: bool,
+        steps: u32,
+        step: f32,
+    ) -> (Vec2, Vec2) {
+        let mut state = (position, velocity);
+        for _ in 0..steps {
+            state = self.integrate(state.0, state.1, is_on_ground, step);
+        }
+        state
+    }
+
+    /// [`Physics::integrate`] every entity of flat `[x, y, x, y, ...]` position and
+    /// velocity arrays in place; entity `i` is on the ground when `on_ground(i)` is
+    pub fn integrate_all(
+        &self,
+        positions: &mut [f32],
+        velocities: &mut [f32],
+        on_ground: impl Fn(usize) -> bool,
+        delta_time: f32,
+    ) {
+        let entities = positions
+            .chunks_exact_mut(2)
+            .zip(velocities.chunks_exact_mut(2));
+        let mut stepped = 0;
+        for (i, (position, velocity)) in entities.enumerate() {
+            let (new_position, new_velocity) = self.integrate(
+                Vec2::new(position[0], position[1]),
+                Vec2::new(velocity[0], velocity[1]),
+                on_ground(i),
+                delta_time,
+            );
+            position.copy_from_slice(&[new_position.x, new_position.y]);
+            velocity.copy_from_slice(&[new_velocity.x, new_velocity.y]);
+            stepped += 1;
+        }
+        hooks::count("bodies_stepped", stepped);
+    }
+
+    /// Clamp each velocity component to its configured maximum speed
+    pub fn limit_velocity(&self, velocity: Vec2) -> Vec2 {
+        let clamp = |velocity: f32, limit: Option<f32>| match limit {
+            Some(limit) => velocity.clamp(-limit.abs(), limit.abs()),
+            None => velocity,
+        };
+        Vec2::new(
+            clamp(velocity.x, self.max_velocity_x),
+            clamp(velocity.y, self.max_velocity_y),
+        )
+    }
+
+    /// Positions of a projectile under gravity and air resistance, starting at
+    /// `start` and followed for `time_steps` steps
+    pub fn projectile_path(
+        &self,
+        start: Vec2,
+        velocity: Vec2,
+        time_steps: usize,
+        delta_time: f32,
+    ) -> Vec<Vec2> {
+        let mut path = Vec::with_capacity(time_steps + 1);
+        let mut position = start;
+        let mut velocity = velocity;
+        path.push(position);
+        for _ in 0..time_steps {
+            // Air resistance only slows horizontal motion, a deliberate simplification
+            velocity.x *= 1.0 - self.air_resistance * delta_time;
+            velocity.y += self.gravity * delta_time;
+            velocity = self.limit_velocity(velocity);
+            position += velocity * delta_time;
+            path.push(position);
+        }
+        path
+    }
+}
+
+/// Whether the `entity` rectangle could move its top-left corner to `new_position`
+/// without overlapping any of `obstacles`
+pub fn can_move_to(entity: Rect, new_position: Vec2, obstacles: &[Shape]) -> bool {
+    let moved = Shape::Rect(Rect {
+        x: new_position.x,
+        y: new_position.y,
+        ..entity
+    });
+    !obstacles.iter().any(|&obstacle| moved.intersects(obstacle))
+}
@@ -0,0 +1,85 @@
+//! Conversion between tile coordinates and screen pixels, for top-down and
+//! isometric views.
+
+use crate::vec2::Vec2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProjectionKind {
+    /// Tiles are axis-aligned rectangles
+    Orthographic,
+    /// Tiles are diamonds: `+x` runs down-right and `+y` down-left on screen
+    Isometric,
+}
+
+impl ProjectionKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "orthographic" => Some(ProjectionKind::Orthographic),
+            "isometric" => Some(ProjectionKind::Isometric),
+            _ => None,
+        }
+    }
+}
+
+/// How the map is drawn: `tile_size` is a tile's width and height in pixels at zoom
+/// 1 (for isometric tiles, the size of the diamond), and `camera` the unzoomed pixel
+/// position that appears at the screen's top-left corner
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Projection {
+    pub kind: ProjectionKind,
+    pub tile_size: Vec2,
+    pub zoom: f32,
+    pub camera: Vec2,
+}
+
+impl Projection {
+    /// Screen pixel of a point in tile coordinates
+    pub fn to_screen(&self, tile: Vec2) -> Vec2 {
+        let (half_width, half_height) = (self.tile_size.x / 2.0, self.tile_size.y / 2.0);
+        let world = match self.kind {
+            ProjectionKind::Orthographic => {
+                Vec2::new(tile.x * self.tile_size.x, tile.y * self.tile_size.y)
+            }
+            ProjectionKind::Isometric => Vec2::new(
+                (tile.x - tile.y) * half_width,
+                (tile.x + tile.y) * half_height,
+            ),
+        };
+        (world - self.camera) * self.zoom
+    }
+
+    /// Tile coordinates of a screen pixel; floor them to get the tile it falls in
+    pub fn to_tile(&self, screen: Vec2) -> Vec2 {
+        let world = screen * (1.0 / self.zoom) + self.camera;
+        match self.kind {
+            ProjectionKind::Orthographic => {
+                Vec2::new(world.x / self.tile_size.x, world.y / self.tile_size.y)
+            }
+            ProjectionKind::Isometric => {
+                let (u, v) = (
+                    world.x / (self.tile_size.x / 2.0),
+                    world.y / (self.tile_size.y / 2.0),
+                );
+                Vec2::new((v + u) / 2.0, (v - u) / 2.0)
+            }
+        }
+    }
+
+    /// Draw-order key of a point in tile coordinates: draw things with smaller keys
+    /// first so nearer ones cover them
+    pub fn depth_key(&self, tile: Vec2) -> f32 {
+        match self.kind {
+            ProjectionKind::Orthographic => tile.y,
+            ProjectionKind::Isometric => tile.x + tile.y,
+        }
+    }
+
+    /// Indices of `tiles` in back-to-front draw order; points with equal keys keep
+    /// their original order
+    pub fn depth_order(&self, tiles: &[Vec2]) -> Vec<usize> {
+        let keys: Vec<f32> = tiles.iter().map(|&tile| self.depth_key(tile)).collect();
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        order.sort_by(|&a, &b| keys[a].total_cmp(&keys[b]));
+        order
+    }
+}
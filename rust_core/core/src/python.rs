@@ -0,0 +1,537 @@
+//! Python classes for the value types shared with the bindings crate.
+//!
+//! Only built with the `python` feature. Everywhere the bindings take a vector or
+//! shape they also accept the equivalent tuple, through the `*Like` argument types.
+//! State machine blackboard values and Tiled properties convert to plain Python
+//! values.
+
+// pyo3 0.18 expands binary operators into nested impls that newer compilers flag
+#![allow(non_local_definitions)]
+
+use pyo3::exceptions::{PyIndexError, PyTypeError, PyZeroDivisionError};
+use pyo3::prelude::*;
+use pyo3::pyclass::CompareOp;
+use pyo3::types::PyTuple;
+
+use crate::fsm::BlackboardValue;
+use crate::shapes::{Circle, Rect, Shape};
+use crate::tiled::PropertyValue;
+use crate::vec2::Vec2;
+
+/// A vector argument from Python: a `Vec2` or an `(x, y)` pair
+#[derive(FromPyObject)]
+pub enum VecLike {
+    Vector(Vec2),
+    Pair((f32, f32)),
+}
+
+impl From<VecLike> for Vec2 {
+    fn from(value: VecLike) -> Self {
+        match value {
+            VecLike::Vector(vector) => vector,
+            VecLike::Pair(pair) => pair.into(),
+        }
+    }
+}
+
+/// A rectangle argument from Python: a `Rect` or an `(x, y, width, height)` tuple
+#[derive(FromPyObject)]
+pub enum RectLike {
+    Rect(Rect),
+    Tuple((f32, f32, f32, f32)),
+}
+
+impl From<RectLike> for Rect {
+    fn from(value: RectLike) -> Self {
+        match value {
+            RectLike::Rect(rect) => rect,
+            RectLike::Tuple((x, y, width, height)) => Rect::new(x, y, width, height),
+        }
+    }
+}
+
+/// A circle argument from Python: a `Circle` or an `(x, y, radius)` tuple
+#[derive(FromPyObject)]
+pub enum CircleLike {
+    Circle(Circle),
+    Tuple((f32, f32, f32)),
+}
+
+impl From<CircleLike> for Circle {
+    fn from(value: CircleLike) -> Self {
+        match value {
+            CircleLike::Circle(circle) => circle,
+            CircleLike::Tuple((x, y, radius)) => Circle::new(x, y, radius),
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for Shape {
+    fn extract(value: &'source PyAny) -> PyResult<Self> {
+        if let Ok(circle) = value.extract::<Circle>() {
+            return Ok(Shape::Circle(circle));
+        }
+        match value.extract::<RectLike>() {
+            Ok(rect) => Ok(Shape::Rect(rect.into())),
+            Err(_) => Err(PyTypeError::new_err(format!(
+                "expected a Rect, a Circle or an (x, y, width, height) tuple, got {}",
+                value.get_type().name()?
+            ))),
+        }
+    }
+}
+
+impl<'source> FromPyObject<'source> for BlackboardValue {
+    fn extract(value: &'source PyAny) -> PyResult<Self> {
+        // Booleans first: Python's `True` would also extract as a number
+        if let Ok(flag) = value.extract::<bool>() {
+            return Ok(BlackboardValue::Bool(flag));
+        }
+        if let Ok(number) = value.extract::<f64>() {
+            return Ok(BlackboardValue::Number(number));
+        }
+        match value.extract::<String>() {
+            Ok(text) => Ok(BlackboardValue::Text(text)),
+            Err(_) => Err(PyTypeError::new_err(format!(
+                "expected a bool, number or string blackboard value, got {}",
+                value.get_type().name()?
+            ))),
+        }
+    }
+}
+
+impl IntoPy<PyObject> for BlackboardValue {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        match self {
+            BlackboardValue::Bool(value) => value.into_py(py),
+            BlackboardValue::Number(value) => value.into_py(py),
+            BlackboardValue::Text(value) => value.into_py(py),
+        }
+    }
+}
+
+impl IntoPy<PyObject> for PropertyValue {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        match self {
+            PropertyValue::Bool(value) => value.into_py(py),
+            PropertyValue::Int(value) => value.into_py(py),
+            PropertyValue::Float(value) => value.into_py(py),
+            PropertyValue::Str(value) => value.into_py(py),
+        }
+    }
+}
+
+fn equality<T: PartialEq>(a: T, b: T, op: CompareOp, py: Python<'_>) -> PyObject {
+    match op {
+        CompareOp::Eq => (a == b).into_py(py),
+        CompareOp::Ne => (a != b).into_py(py),
+        _ => py.NotImplemented(),
+    }
+}
+
+// A `Vec2` unpacks like the `(x, y)` pairs it replaces: `x, y = agents.position(id)`
+#[pymethods]
+impl Vec2 {
+    #[new]
+    #[pyo3(signature = (x = 0.0, y = 0.0))]
+    fn py_new(x: f32, y: f32) -> Self {
+        Vec2::new(x, y)
+    }
+
+    /// Unit vector pointing at `radians` anticlockwise from +x
+    #[staticmethod]
+    #[pyo3(name = "from_angle")]
+    fn py_from_angle(radians: f32) -> Vec2 {
+        Vec2::from_angle(radians)
+    }
+
+    #[getter]
+    fn get_x(&self) -> f32 {
+        self.x
+    }
+
+    #[setter]
+    fn set_x(&mut self, x: f32) {
+        self.x = x;
+    }
+
+    #[getter]
+    fn get_y(&self) -> f32 {
+        self.y
+    }
+
+    #[setter]
+    fn set_y(&mut self, y: f32) {
+        self.y = y;
+    }
+
+    #[pyo3(name = "dot")]
+    fn py_dot(&self, other: VecLike) -> f32 {
+        self.dot(other.into())
+    }
+
+    /// Z component of the 3D cross product, positive when `other` is counter-clockwise
+    #[pyo3(name = "cross")]
+    fn py_cross(&self, other: VecLike) -> f32 {
+        self.cross(other.into())
+    }
+
+    #[pyo3(name = "length")]
+    fn py_length(&self) -> f32 {
+        self.length()
+    }
+
+    #[pyo3(name = "length_squared")]
+    fn py_length_squared(&self) -> f32 {
+        self.length_squared()
+    }
+
+    /// Unit vector in the same direction, or zero for a zero-length vector
+    #[pyo3(name = "normalize")]
+    fn py_normalize(&self) -> Vec2 {
+        self.normalize()
+    }
+
+    /// Scale the vector down so its length does not exceed `max_length`
+    #[pyo3(name = "truncate")]
+    fn py_truncate(&self, max_length: f32) -> Vec2 {
+        self.truncate(max_length)
+    }
+
+    #[pyo3(name = "distance")]
+    fn py_distance(&self, other: VecLike) -> f32 {
+        self.distance(other.into())
+    }
+
+    #[pyo3(name = "distance_squared")]
+    fn py_distance_squared(&self, other: VecLike) -> f32 {
+        self.distance_squared(other.into())
+    }
+
+    /// The vector turned anticlockwise by `radians`
+    #[pyo3(name = "rotate")]
+    fn py_rotate(&self, radians: f32) -> Vec2 {
+        self.rotate(radians)
+    }
+
+    /// The point `t` of the way from this vector to `other`; `t` is not clamped
+    #[pyo3(name = "lerp")]
+    fn py_lerp(&self, other: VecLike, t: f32) -> Vec2 {
+        self.lerp(other.into(), t)
+    }
+
+    /// Direction in radians anticlockwise from +x, in `[-pi, pi]`
+    #[pyo3(name = "angle")]
+    fn py_angle(&self) -> f32 {
+        self.angle()
+    }
+
+    /// Signed angle in radians that turns this vector's direction onto `other`'s
+    #[pyo3(name = "angle_to")]
+    fn py_angle_to(&self, other: VecLike) -> f32 {
+        self.angle_to(other.into())
+    }
+
+    fn __add__(&self, other: VecLike) -> Vec2 {
+        *self + other.into()
+    }
+
+    fn __radd__(&self, other: VecLike) -> Vec2 {
+        Vec2::from(other) + *self
+    }
+
+    fn __sub__(&self, other: VecLike) -> Vec2 {
+        *self - other.into()
+    }
+
+    fn __rsub__(&self, other: VecLike) -> Vec2 {
+        Vec2::from(other) - *self
+    }
+
+    fn __mul__(&self, scale: f32) -> Vec2 {
+        *self * scale
+    }
+
+    fn __rmul__(&self, scale: f32) -> Vec2 {
+        *self * scale
+    }
+
+    fn __truediv__(&self, scale: f32) -> PyResult<Vec2> {
+        if scale == 0.0 {
+            return Err(PyZeroDivisionError::new_err("Vec2 division by zero"));
+        }
+        Ok(*self * (1.0 / scale))
+    }
+
+    fn __neg__(&self) -> Vec2 {
+        -*self
+    }
+
+    fn __abs__(&self) -> f32 {
+        self.length()
+    }
+
+    fn __bool__(&self) -> bool {
+        *self != Vec2::ZERO
+    }
+
+    fn __richcmp__(&self, other: VecLike, op: CompareOp, py: Python<'_>) -> PyObject {
+        equality(*self, other.into(), op, py)
+    }
+
+    fn __len__(&self) -> usize {
+        2
+    }
+
+    fn __getitem__(&self, index: isize) -> PyResult<f32> {
+        match index {
+            0 | -2 => Ok(self.x),
+            1 | -1 => Ok(self.y),
+            _ => Err(PyIndexError::new_err("Vec2 index out of range")),
+        }
+    }
+
+    fn __iter__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let pair = PyTuple::new(py, [self.x, self.y]);
+        Ok(pair.as_ref().iter()?.into())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Vec2({}, {})", self.x, self.y)
+    }
+}
+
+#[pymethods]
+impl Rect {
+    #[new]
+    fn py_new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Rect::new(x, y, width, height)
+    }
+
+    /// A rectangle of the given size centred on `center`
+    #[staticmethod]
+    #[pyo3(name = "from_center")]
+    fn py_from_center(center: VecLike, width: f32, height: f32) -> Self {
+        Rect::from_center(center.into(), width, height)
+    }
+
+    #[getter]
+    fn get_x(&self) -> f32 {
+        self.x
+    }
+
+    #[setter]
+    fn set_x(&mut self, x: f32) {
+        self.x = x;
+    }
+
+    #[getter]
+    fn get_y(&self) -> f32 {
+        self.y
+    }
+
+    #[setter]
+    fn set_y(&mut self, y: f32) {
+        self.y = y;
+    }
+
+    #[getter]
+    fn get_width(&self) -> f32 {
+        self.width
+    }
+
+    #[setter]
+    fn set_width(&mut self, width: f32) {
+        self.width = width;
+    }
+
+    #[getter]
+    fn get_height(&self) -> f32 {
+        self.height
+    }
+
+    #[setter]
+    fn set_height(&mut self, height: f32) {
+        self.height = height;
+    }
+
+    #[getter(right)]
+    fn py_right(&self) -> f32 {
+        self.right()
+    }
+
+    #[getter(bottom)]
+    fn py_bottom(&self) -> f32 {
+        self.bottom()
+    }
+
+    #[getter(center)]
+    fn py_center(&self) -> Vec2 {
+        self.center()
+    }
+
+    #[getter(size)]
+    fn py_size(&self) -> Vec2 {
+        self.size()
+    }
+
+    /// Whether a point lies inside the rectangle
+    #[pyo3(name = "contains")]
+    fn py_contains(&self, point: VecLike) -> bool {
+        self.contains(point.into())
+    }
+
+    /// Whether `other` lies entirely inside this rectangle
+    #[pyo3(name = "contains_rect")]
+    fn py_contains_rect(&self, other: RectLike) -> bool {
+        self.contains_rect(other.into())
+    }
+
+    /// Whether the rectangle overlaps another `Rect` or a `Circle`
+    #[pyo3(name = "intersects")]
+    fn py_intersects(&self, other: Shape) -> bool {
+        Shape::Rect(*self).intersects(other)
+    }
+
+    /// The overlapping area of two rectangles, or `None` if they do not intersect
+    #[pyo3(name = "intersection")]
+    fn py_intersection(&self, other: RectLike) -> Option<Rect> {
+        self.intersection(other.into())
+    }
+
+    /// The smallest rectangle covering both rectangles
+    #[pyo3(name = "union")]
+    fn py_union(&self, other: RectLike) -> Rect {
+        self.union(other.into())
+    }
+
+    /// Grow the rectangle by `dx` on the left and right and `dy` (default `dx`) on
+    /// the top and bottom, keeping its centre; negative amounts shrink it
+    #[pyo3(name = "inflate", signature = (dx, dy = None))]
+    fn py_inflate(&self, dx: f32, dy: Option<f32>) -> Rect {
+        self.inflate(dx, dy.unwrap_or(dx))
+    }
+
+    /// The point inside the rectangle nearest to `point`
+    #[pyo3(name = "clamp")]
+    fn py_clamp(&self, point: VecLike) -> Vec2 {
+        self.clamp(point.into())
+    }
+
+    /// This rectangle moved the least distance needed to lie inside `bounds`,
+    /// centred on it along any axis where it is too big to fit
+    #[pyo3(name = "clamp_within")]
+    fn py_clamp_within(&self, bounds: RectLike) -> Rect {
+        self.clamp_within(bounds.into())
+    }
+
+    /// The rectangle moved by `offset`
+    #[pyo3(name = "translate")]
+    fn py_translate(&self, offset: VecLike) -> Rect {
+        self.translate(offset.into())
+    }
+
+    fn __richcmp__(&self, other: RectLike, op: CompareOp, py: Python<'_>) -> PyObject {
+        equality(*self, other.into(), op, py)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Rect(x={}, y={}, width={}, height={})",
+            self.x, self.y, self.width, self.height
+        )
+    }
+}
+
+#[pymethods]
+impl Circle {
+    #[new]
+    fn py_new(x: f32, y: f32, radius: f32) -> Self {
+        Circle::new(x, y, radius)
+    }
+
+    #[getter]
+    fn get_x(&self) -> f32 {
+        self.x
+    }
+
+    #[setter]
+    fn set_x(&mut self, x: f32) {
+        self.x = x;
+    }
+
+    #[getter]
+    fn get_y(&self) -> f32 {
+        self.y
+    }
+
+    #[setter]
+    fn set_y(&mut self, y: f32) {
+        self.y = y;
+    }
+
+    #[getter]
+    fn get_radius(&self) -> f32 {
+        self.radius
+    }
+
+    #[setter]
+    fn set_radius(&mut self, radius: f32) {
+        self.radius = radius;
+    }
+
+    #[getter(center)]
+    fn py_center(&self) -> Vec2 {
+        self.center()
+    }
+
+    /// The smallest `Rect` containing the circle
+    #[getter(bounds)]
+    fn py_bounds(&self) -> Rect {
+        self.bounds()
+    }
+
+    /// Whether a point lies inside the circle
+    #[pyo3(name = "contains")]
+    fn py_contains(&self, point: VecLike) -> bool {
+        self.contains(point.into())
+    }
+
+    /// Whether the circle overlaps another `Circle` or a `Rect`
+    #[pyo3(name = "intersects")]
+    fn py_intersects(&self, other: Shape) -> bool {
+        Shape::Circle(*self).intersects(other)
+    }
+
+    /// The smallest circle enclosing both circles
+    #[pyo3(name = "union")]
+    fn py_union(&self, other: CircleLike) -> Circle {
+        self.union(other.into())
+    }
+
+    /// Grow the radius by `amount`, or shrink it for a negative amount
+    #[pyo3(name = "inflate")]
+    fn py_inflate(&self, amount: f32) -> Circle {
+        self.inflate(amount)
+    }
+
+    /// The point inside the circle nearest to `point`
+    #[pyo3(name = "clamp")]
+    fn py_clamp(&self, point: VecLike) -> Vec2 {
+        self.clamp(point.into())
+    }
+
+    /// The circle moved by `offset`
+    #[pyo3(name = "translate")]
+    fn py_translate(&self, offset: VecLike) -> Circle {
+        self.translate(offset.into())
+    }
+
+    fn __richcmp__(&self, other: CircleLike, op: CompareOp, py: Python<'_>) -> PyObject {
+        equality(*self, other.into(), op, py)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Circle(x={}, y={}, radius={})", self.x, self.y, self.radius)
+    }
+}
@@ -0,0 +1,275 @@
+//! Rasterizing lines, circles and ellipses onto grid cells.
+//!
+//! Cells are `(x, y)` pairs on an unbounded grid, so shapes may extend past a map's
+//! edges; callers clip them to the map they draw on.
+
+pub type Cell = (i32, i32);
+
+/// Bresenham line from `start` to `end`, both included, in order from `start`.
+///
+/// Consecutive cells touch by an edge or a corner, and the line from `end` back to
+/// `start` may pick different cells where the ideal line passes through a corner.
+pub fn line(start: Cell, end: Cell) -> Vec<Cell> {
+    let (x0, y0) = (i64::from(start.0), i64::from(start.1));
+    let (x1, y1) = (i64::from(end.0), i64::from(end.1));
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (step_x, step_y) = ((x1 - x0).signum(), (y1 - y0).signum());
+    let mut cells = Vec::with_capacity(dx.max(-dy) as usize + 1);
+    let (mut x, mut y, mut error) = (x0, y0, dx + dy);
+    loop {
+        cells.push((x as i32, y as i32));
+        if x == x1 && y == y1 {
+            return cells;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
+
+/// Cells whose centres lie within `width / 2` of the segment from `start` to `end`,
+/// row by row; a width of 1 or less is the plain [`line`]
+pub fn thick_line(start: Cell, end: Cell, width: f32) -> Vec<Cell> {
+    if width.is_nan() || width <= 1.0 {
+        return line(start, end);
+    }
+    let radius = width / 2.0;
+    let reach = radius.ceil() as i32;
+    let (ax, ay) = (start.0 as f32, start.1 as f32);
+    let (dx, dy) = (end.0 as f32 - ax, end.1 as f32 - ay);
+    let length_squared = dx * dx + dy * dy;
+    let mut cells = Vec::new();
+    for y in start.1.min(end.1) - reach..=start.1.max(end.1) + reach {
+        for x in start.0.min(end.0) - reach..=start.0.max(end.0) + reach {
+            let (px, py) = (x as f32 - ax, y as f32 - ay);
+            // Closest point on the segment, as a fraction of the way along it
+            let t = if length_squared > 0.0 {
+                ((px * dx + py * dy) / length_squared).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let (ox, oy) = (px - t * dx, py - t * dy);
+            if ox * ox + oy * oy <= radius * radius {
+                cells.push((x, y));
+            }
+        }
+    }
+    cells
+}
+
+/// Outline of a circle, row by row
+pub fn circle(center: Cell, radius: u32) -> Vec<Cell> {
+    ellipse(center, radius, radius)
+}
+
+/// Every cell inside a circle's [`circle`] outline, outline included, row by row
+pub fn filled_circle(center: Cell, radius: u32) -> Vec<Cell> {
+    filled_ellipse(center, radius, radius)
+}
+
+/// Outline of an axis-aligned ellipse, row by row, drawn with the midpoint algorithm;
+/// a zero radius gives a straight line
+pub fn ellipse(center: Cell, radius_x: u32, radius_y: u32) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    ellipse_quadrant(radius_x, radius_y, |x, y| {
+        for (sx, sy) in [(1, 1), (-1, 1), (1, -1), (-1, -1)] {
+            cells.push(offset(center, sx * x, sy * y));
+        }
+    });
+    cells.sort_unstable_by_key(|&(x, y)| (y, x));
+    cells.dedup();
+    cells
+}
+
+/// Every cell inside an [`ellipse`] outline, outline included, row by row
+pub fn filled_ellipse(center: Cell, radius_x: u32, radius_y: u32) -> Vec<Cell> {
+    // Widest reach of the outline on each row above and below the centre
+    let mut spans = vec![0i64; radius_y as usize + 1];
+    ellipse_quadrant(radius_x, radius_y, |x, y| {
+        let span = &mut spans[y as usize];
+        *span = (*span).max(x);
+    });
+    let mut cells = Vec::new();
+    for dy in -i64::from(radius_y)..=i64::from(radius_y) {
+        let span = spans[dy.unsigned_abs() as usize];
+        cells.extend((-span..=span).map(|dx| offset(center, dx, dy)));
+    }
+    cells
+}
+
+fn offset(center: Cell, dx: i64, dy: i64) -> Cell {
+    (
+        (i64::from(center.0) + dx) as i32,
+        (i64::from(center.1) + dy) as i32,
+    )
+}
+
+/// Visit the outline points of an ellipse's lower-right quadrant, as offsets from
+/// its centre
+fn ellipse_quadrant(radius_x: u32, radius_y: u32, mut plot: impl FnMut(i64, i64)) {
+    let (rx, ry) = (i64::from(radius_x), i64::from(radius_y));
+    if rx == 0 || ry == 0 {
+        // Degenerate: a straight segment along the other axis
+        for x in 0..=rx {
+            plot(x, 0);
+        }
+        for y in 0..=ry {
+            plot(0, y);
+        }
+        return;
+    }
+    let (rx2, ry2) = (rx * rx, ry * ry);
+    let (mut x, mut y) = (0, ry);
+    let (mut dx, mut dy) = (0, 2 * rx2 * y);
+    // Decision variables are scaled by 4 to stay in integers
+    let mut decision = 4 * ry2 - 4 * rx2 * ry + rx2;
+    while dx < dy {
+        plot(x, y);
+        x += 1;
+        dx += 2 * ry2;
+        if decision < 0 {
+            decision += 4 * (dx + ry2);
+        } else {
+            y -= 1;
+            dy -= 2 * rx2;
+            decision += 4 * (dx - dy + ry2);
+        }
+    }
+    let mut decision =
+        ry2 * (2 * x + 1) * (2 * x + 1) + 4 * rx2 * (y - 1) * (y - 1) - 4 * rx2 * ry2;
+    while y >= 0 {
+        plot(x, y);
+        y -= 1;
+        dy -= 2 * rx2;
+        if decision > 0 {
+            decision += 4 * (rx2 - dy);
+        } else {
+            x += 1;
+            dx += 2 * ry2;
+            decision += 4 * (dx - dy + rx2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check `line(start, end)` steps once along the major axis per cell and never
+    /// strays more than half a cell from the ideal line
+    fn assert_follows(start: Cell, end: Cell) {
+        let cells = line(start, end);
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        let major = dx.abs().max(dy.abs());
+        assert_eq!(cells.len(), major as usize + 1, "{:?} to {:?}", start, end);
+        assert_eq!((cells[0], cells[cells.len() - 1]), (start, end));
+        for (i, pair) in cells.windows(2).enumerate() {
+            let step = (pair[1].0 - pair[0].0, pair[1].1 - pair[0].1);
+            assert!(step.0.abs() <= 1 && step.1.abs() <= 1, "{:?} jumps", pair);
+            assert_eq!(step.0.abs().max(step.1.abs()), 1, "{:?} stalls", pair);
+            let t = (i + 1) as f32 / major as f32;
+            let ideal = (
+                start.0 as f32 + t * dx as f32,
+                start.1 as f32 + t * dy as f32,
+            );
+            let off = (pair[1].0 as f32 - ideal.0)
+                .abs()
+                .max((pair[1].1 as f32 - ideal.1).abs());
+            assert!(off <= 0.5, "{:?} is {} off the line", pair[1], off);
+        }
+    }
+
+    #[test]
+    fn lines_follow_every_octant_in_both_directions() {
+        let ends = [
+            (5, 2),
+            (2, 5),
+            (-2, 5),
+            (-5, 2),
+            (-5, -2),
+            (-2, -5),
+            (2, -5),
+            (5, -2),
+        ];
+        for end in ends {
+            assert_follows((1, 1), (end.0 + 1, end.1 + 1));
+            assert_follows((end.0 + 1, end.1 + 1), (1, 1));
+        }
+        for end in [(7, 0), (0, -7), (4, 4), (-4, 4)] {
+            assert_follows((0, 0), end);
+            assert_follows(end, (0, 0));
+        }
+    }
+
+    #[test]
+    fn shallow_and_steep_lines_pick_the_expected_cells() {
+        let shallow = [(0, 0), (1, 0), (2, 1), (3, 1), (4, 2), (5, 2)];
+        assert_eq!(line((0, 0), (5, 2)), shallow);
+        let steep: Vec<Cell> = shallow.iter().map(|&(x, y)| (y, x)).collect();
+        assert_eq!(line((0, 0), (2, 5)), steep);
+        assert_eq!(line((3, -4), (3, -4)), [(3, -4)]);
+    }
+
+    #[test]
+    fn thick_lines_cover_cells_near_the_segment() {
+        assert_eq!(thick_line((0, 0), (5, 2), 1.0), line((0, 0), (5, 2)));
+        assert_eq!(thick_line((0, 0), (5, 2), f32::NAN), line((0, 0), (5, 2)));
+        // Within 1.5 of (0, 0)-(4, 0): three rows reaching one cell past each end
+        let cells = thick_line((0, 0), (4, 0), 3.0);
+        let expected: Vec<Cell> = (-1..=1)
+            .flat_map(|y| (-1..=5).map(move |x| (x, y)))
+            .collect();
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn zero_radii_give_points_and_segments() {
+        assert_eq!(ellipse((2, 3), 0, 0), [(2, 3)]);
+        assert_eq!(filled_ellipse((2, 3), 0, 0), [(2, 3)]);
+        let across: Vec<Cell> = (-3..=3).map(|x| (x, 0)).collect();
+        assert_eq!(ellipse((0, 0), 3, 0), across);
+        assert_eq!(filled_ellipse((0, 0), 3, 0), across);
+        let down: Vec<Cell> = (-2..=2).map(|y| (5, y)).collect();
+        assert_eq!(ellipse((5, 0), 0, 2), down);
+        assert_eq!(filled_ellipse((5, 0), 0, 2), down);
+    }
+
+    #[test]
+    fn small_circles_are_a_diamond_and_a_plus() {
+        assert_eq!(circle((0, 0), 1), [(0, -1), (-1, 0), (1, 0), (0, 1)]);
+        assert_eq!(
+            filled_circle((0, 0), 1),
+            [(0, -1), (-1, 0), (0, 0), (1, 0), (0, 1)]
+        );
+    }
+
+    #[test]
+    fn filled_ellipses_hold_their_outline_symmetrically() {
+        for (rx, ry) in [(4, 4), (6, 2), (1, 5), (9, 7)] {
+            let outline = ellipse((0, 0), rx, ry);
+            let filled = filled_ellipse((0, 0), rx, ry);
+            for &(x, y) in &outline {
+                assert!(
+                    filled.contains(&(x, y)),
+                    "{:?} missing for {}x{}",
+                    (x, y),
+                    rx,
+                    ry
+                );
+                assert!(outline.contains(&(-x, y)) && outline.contains(&(x, -y)));
+            }
+            assert!(filled
+                .iter()
+                .all(|&(x, y)| x.unsigned_abs() <= rx && y.unsigned_abs() <= ry));
+            assert!(outline
+                .windows(2)
+                .all(|pair| (pair[0].1, pair[0].0) < (pair[1].1, pair[1].0)));
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Grid raycasting with DDA, for first-person column renderers.
+//!
+//! Maps are `[y][x]` grids of tile ids, 0 for empty space. Positions are in cell
+//! units with `y` pointing down, and angles in radians, 0 facing `+x`.
+
+use crate::vec2::Vec2;
+
+/// Side of a wall cell a ray struck
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Face {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Face {
+    pub fn name(self) -> &'static str {
+        match self {
+            Face::North => "north",
+            Face::East => "east",
+            Face::South => "south",
+            Face::West => "west",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    /// Length along the ray in multiples of its direction vector
+    pub distance: f32,
+    pub face: Face,
+    /// Where on the face the ray struck, from 0 at the viewer's left to 1 at their
+    /// right, for picking a texture column
+    pub offset: f32,
+    pub cell: (usize, usize),
+    pub tile: u32,
+}
+
+/// The first non-empty tile a ray from `origin` along `direction` strikes within
+/// `max_distance`, if any before it leaves the map.
+///
+/// The cell `origin` lies in is never hit, so a viewer standing inside a wall
+/// sees out of it.
+pub fn cast_ray(
+    tiles: &[Vec<u32>],
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: f32,
+) -> Option<Hit> {
+    let (mut x, mut y) = (origin.x.floor() as i64, origin.y.floor() as i64);
+    // Ray length between successive vertical and horizontal grid lines
+    let delta_x = if direction.x == 0.0 {
+        f32::INFINITY
+    } else {
+        (1.0 / direction.x).abs()
+    };
+    let delta_y = if direction.y == 0.0 {
+        f32::INFINITY
+    } else {
+        (1.0 / direction.y).abs()
+    };
+    let (step_x, mut side_x) = if direction.x < 0.0 {
+        (-1, (origin.x - x as f32) * delta_x)
+    } else {
+        (1, (x as f32 + 1.0 - origin.x) * delta_x)
+    };
+    let (step_y, mut side_y) = if direction.y < 0.0 {
+        (-1, (origin.y - y as f32) * delta_y)
+    } else {
+        (1, (y as f32 + 1.0 - origin.y) * delta_y)
+    };
+    loop {
+        let (distance, crossed_x) = if side_x < side_y {
+            x += step_x;
+            side_x += delta_x;
+            (side_x - delta_x, true)
+        } else {
+            y += step_y;
+            side_y += delta_y;
+            (side_y - delta_y, false)
+        };
+        if distance > max_distance || x < 0 || y < 0 {
+            return None;
+        }
+        let tile = *tiles.get(y as usize)?.get(x as usize)?;
+        if tile == 0 {
+            continue;
+        }
+        let point = origin + direction * distance;
+        let (face, along, flipped) = if crossed_x {
+            let face = if step_x > 0 { Face::West } else { Face::East };
+            (face, point.y, direction.x < 0.0)
+        } else {
+            let face = if step_y > 0 { Face::North } else { Face::South };
+            (face, point.x, direction.y > 0.0)
+        };
+        let offset = along - along.floor();
+        return Some(Hit {
+            distance,
+            face,
+            offset: if flipped { 1.0 - offset } else { offset },
+            cell: (x as usize, y as usize),
+            tile,
+        });
+    }
+}
+
+/// One ray per screen column, left to right, for a viewer at `origin` facing
+/// `angle` with a horizontal field of view of `fov` radians.
+///
+/// Distances are perpendicular to the view plane rather than Euclidean, so walls
+/// drawn `height / distance` pixels tall come out straight, without fisheye.
+pub fn cast_columns(
+    tiles: &[Vec<u32>],
+    origin: Vec2,
+    angle: f32,
+    fov: f32,
+    max_distance: f32,
+    columns: usize,
+) -> Vec<Option<Hit>> {
+    let direction = Vec2::from_angle(angle);
+    // Half-width of the view plane one unit in front of the viewer, pointing right
+    let plane = Vec2::new(-direction.y, direction.x) * (fov / 2.0).tan();
+    (0..columns)
+        .map(|column| {
+            let camera_x = 2.0 * (column as f32 + 0.5) / columns as f32 - 1.0;
+            cast_ray(tiles, origin, direction + plane * camera_x, max_distance)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `width` by `height` room walled with tile 1 on every side
+    fn room(width: usize, height: usize) -> Vec<Vec<u32>> {
+        (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| u32::from(x == 0 || y == 0 || x == width - 1 || y == height - 1))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rays_report_the_face_and_where_on_it_they_struck() {
+        let tiles = room(5, 5);
+        let origin = Vec2::new(2.25, 2.75);
+        let east = cast_ray(&tiles, origin, Vec2::new(1.0, 0.0), 10.0).unwrap();
+        assert_eq!((east.cell, east.face, east.tile), ((4, 2), Face::West, 1));
+        assert_eq!((east.distance, east.offset), (1.75, 0.75));
+        let west = cast_ray(&tiles, origin, Vec2::new(-1.0, 0.0), 10.0).unwrap();
+        assert_eq!((west.cell, west.face), ((0, 2), Face::East));
+        assert_eq!((west.distance, west.offset), (1.25, 0.25));
+        let north = cast_ray(&tiles, origin, Vec2::new(0.0, -1.0), 10.0).unwrap();
+        assert_eq!((north.cell, north.face), ((2, 0), Face::South));
+        assert_eq!((north.distance, north.offset), (1.75, 0.25));
+        let south = cast_ray(&tiles, origin, Vec2::new(0.0, 1.0), 10.0).unwrap();
+        assert_eq!((south.cell, south.face), ((2, 4), Face::North));
+        assert_eq!((south.distance, south.offset), (1.25, 0.75));
+    }
+
+    #[test]
+    fn rays_stop_at_their_range_and_the_map_edge() {
+        let tiles = room(5, 5);
+        let origin = Vec2::new(2.5, 2.5);
+        assert_eq!(cast_ray(&tiles, origin, Vec2::new(1.0, 0.0), 1.0), None);
+        let open = vec![vec![0; 4]; 4];
+        assert_eq!(cast_ray(&open, origin, Vec2::new(1.0, 1.0), 100.0), None);
+        // Standing in a wall, the viewer sees out to the next one
+        let inside = cast_ray(&tiles, Vec2::new(0.5, 2.5), Vec2::new(1.0, 0.0), 10.0).unwrap();
+        assert_eq!(inside.cell, (4, 2));
+    }
+
+    #[test]
+    fn columns_see_a_flat_wall_without_fisheye() {
+        let tiles = room(6, 40);
+        let hits = cast_columns(&tiles, Vec2::new(1.5, 20.0), 0.0, 1.0, 50.0, 9);
+        assert_eq!(hits.len(), 9);
+        for hit in hits {
+            let hit = hit.expect("every column hits the far wall");
+            assert_eq!(hit.cell.0, 5);
+            assert!((hit.distance - 3.5).abs() < 1e-4, "{}", hit.distance);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Region graphs: the rooms and corridors of a labelled map and the doors between
+//! them, for planning routes at the level of rooms rather than cells.
+//!
+//! Every non-negative label is a region; negative labels (walls, void) belong to
+//! none. Two regions are adjacent when any of their cells touch orthogonally, and
+//! the touching cells on both sides are recorded as the door cells of that edge.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use crate::grid;
+
+/// Summary of one labelled region
+#[derive(Clone, Debug)]
+pub struct Region {
+    pub area: usize,
+    pub min: (usize, usize),
+    pub max: (usize, usize),
+    sum_x: f64,
+    sum_y: f64,
+}
+
+impl Region {
+    fn new(x: usize, y: usize) -> Self {
+        Region {
+            area: 0,
+            min: (x, y),
+            max: (x, y),
+            sum_x: 0.0,
+            sum_y: 0.0,
+        }
+    }
+
+    fn add(&mut self, x: usize, y: usize) {
+        self.area += 1;
+        self.min = (self.min.0.min(x), self.min.1.min(y));
+        self.max = (self.max.0.max(x), self.max.1.max(y));
+        self.sum_x += x as f64;
+        self.sum_y += y as f64;
+    }
+
+    pub fn centroid(&self) -> (f32, f32) {
+        (
+            (self.sum_x / self.area as f64) as f32,
+            (self.sum_y / self.area as f64) as f32,
+        )
+    }
+}
+
+/// Regions keyed by label, and the door cells between each adjacent pair
+#[derive(Clone, Debug)]
+pub struct RegionGraph {
+    regions: BTreeMap<i64, Region>,
+    doors: BTreeMap<(i64, i64), Vec<(usize, usize)>>,
+    neighbours: HashMap<i64, BTreeSet<i64>>,
+}
+
+impl RegionGraph {
+    pub fn build(label_map: &[Vec<i64>]) -> Self {
+        let (width, height) = grid::dimensions(label_map);
+        let label_at = |x: usize, y: usize| label_map.get(y).and_then(|row| row.get(x)).copied();
+
+        let mut regions: BTreeMap<i64, Region> = BTreeMap::new();
+        let mut doors: BTreeMap<(i64, i64), BTreeSet<(usize, usize)>> = BTreeMap::new();
+
+        for (y, row) in label_map.iter().enumerate() {
+            for (x, &label) in row.iter().enumerate() {
+                if label < 0 {
+                    continue;
+                }
+                regions
+                    .entry(label)
+                    .or_insert_with(|| Region::new(x, y))
+                    .add(x, y);
+
+                // Looking right and down visits every touching pair exactly once
+                for (dx, dy) in [(1, 0), (0, 1)] {
+                    let Some((nx, ny)) = grid::offset(x, y, dx, dy, width, height) else {
+                        continue;
+                    };
+                    match label_at(nx, ny) {
+                        Some(other) if other >= 0 && other != label => {
+                            let key = (label.min(other), label.max(other));
+                            let cells = doors.entry(key).or_default();
+                            cells.insert((x, y));
+                            cells.insert((nx, ny));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let mut neighbours: HashMap<i64, BTreeSet<i64>> = HashMap::new();
+        for &(a, b) in doors.keys() {
+            neighbours.entry(a).or_default().insert(b);
+            neighbours.entry(b).or_default().insert(a);
+        }
+
+        RegionGraph {
+            regions,
+            doors: doors
+                .into_iter()
+                .map(|(key, cells)| (key, cells.into_iter().collect()))
+                .collect(),
+            neighbours,
+        }
+    }
+
+    pub fn region(&self, label: i64) -> Option<&Region> {
+        self.regions.get(&label)
+    }
+
+    /// All region labels in ascending order
+    pub fn labels(&self) -> impl ExactSizeIterator<Item = i64> + '_ {
+        self.regions.keys().copied()
+    }
+
+    /// Labels of the regions adjacent to `label`, in ascending order
+    pub fn neighbours(&self, label: i64) -> impl Iterator<Item = i64> + '_ {
+        self.neighbours.get(&label).into_iter().flatten().copied()
+    }
+
+    /// Every adjacency as a `(lower_label, higher_label)` pair
+    pub fn edges(&self) -> impl ExactSizeIterator<Item = (i64, i64)> + '_ {
+        self.doors.keys().copied()
+    }
+
+    /// Cells where two regions touch, empty if they are not adjacent
+    pub fn doors(&self, a: i64, b: i64) -> &[(usize, usize)] {
+        self.doors
+            .get(&(a.min(b), a.max(b)))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Fewest-hops route between two regions, including both ends
+    pub fn route(&self, from: i64, to: i64) -> Option<Vec<i64>> {
+        let mut previous: HashMap<i64, i64> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        previous.insert(from, from);
+
+        while let Some(label) = queue.pop_front() {
+            if label == to {
+                let mut route = vec![to];
+                let mut current = to;
+                while current != from {
+                    current = previous[&current];
+                    route.push(current);
+                }
+                route.reverse();
+                return Some(route);
+            }
+            for &next in self.neighbours.get(&label).into_iter().flatten() {
+                if let Entry::Vacant(entry) = previous.entry(next) {
+                    entry.insert(label);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two rooms joined by a one-cell corridor, and a closet off the second room
+    fn rooms() -> RegionGraph {
+        RegionGraph::build(&[
+            vec![0, 0, -1, 2, 2],
+            vec![0, 0, 1, 2, 2],
+            vec![-1, -1, -1, 3, -1],
+        ])
+    }
+
+    #[test]
+    fn regions_summarise_their_cells() {
+        let graph = rooms();
+        assert_eq!(graph.labels().collect::<Vec<_>>(), [0, 1, 2, 3]);
+        let room = graph.region(2).unwrap();
+        assert_eq!((room.area, room.min, room.max), (4, (3, 0), (4, 1)));
+        assert_eq!(room.centroid(), (3.5, 0.5));
+        assert!(graph.region(-1).is_none());
+    }
+
+    #[test]
+    fn touching_regions_share_door_cells() {
+        let graph = rooms();
+        assert_eq!(graph.edges().collect::<Vec<_>>(), [(0, 1), (1, 2), (2, 3)]);
+        assert_eq!(graph.doors(2, 1), [(2, 1), (3, 1)]);
+        assert!(graph.doors(0, 2).is_empty());
+        assert_eq!(graph.neighbours(2).collect::<Vec<_>>(), [1, 3]);
+    }
+
+    #[test]
+    fn routes_take_the_fewest_hops() {
+        let graph = rooms();
+        assert_eq!(graph.route(0, 3), Some(vec![0, 1, 2, 3]));
+        assert_eq!(graph.route(1, 1), Some(vec![1]));
+        let split = RegionGraph::build(&[vec![0, -1, 1]]);
+        assert_eq!(split.route(0, 1), None);
+    }
+}
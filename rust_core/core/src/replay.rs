@@ -0,0 +1,222 @@
+//! Recorded inputs for reproducing a simulation run tick by tick.
+//!
+//! A [`Replay`] stores the seed and fixed step a run started with and every
+//! command fed to the simulation on each tick. Feeding the same commands back, with
+//! RNG-driven systems seeded from [`Replay::seed`] and the simulation stepped at
+//! [`Replay::fixed_step`], reproduces the run exactly.
+//!
+//! The file format is compact: command names are stored once in a string table and
+//! referred to by index, and all counts and lengths are varints (see [`crate::codec`]).
+
+use std::collections::HashMap;
+
+use crate::codec::{DecodeError, Reader, Writer};
+
+/// Leading bytes of every encoded replay
+const MAGIC: &[u8; 4] = b"LQRP";
+/// Format version written after the magic, bumped on incompatible changes
+const VERSION: u8 = 1;
+
+/// One input fed to the simulation, such as `("move", b"{\"dx\": 1}")`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Command {
+    pub name: String,
+    /// Opaque argument bytes, interpreted by whoever handles `name`
+    pub payload: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    /// Seconds of game time per tick
+    pub fixed_step: f64,
+    ticks: Vec<Vec<Command>>,
+    /// Commands recorded since the last [`Replay::end_tick`]
+    current: Vec<Command>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, fixed_step: f64) -> Self {
+        Replay {
+            seed,
+            fixed_step,
+            ticks: Vec::new(),
+            current: Vec::new(),
+        }
+    }
+
+    /// Record a command for the tick in progress
+    pub fn record(&mut self, name: &str, payload: Vec<u8>) {
+        self.current.push(Command {
+            name: name.to_string(),
+            payload,
+        });
+    }
+
+    /// Close the tick in progress, even if no commands were recorded for it
+    pub fn end_tick(&mut self) {
+        self.ticks.push(std::mem::take(&mut self.current));
+    }
+
+    /// Number of completed ticks
+    pub fn len(&self) -> usize {
+        self.ticks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+
+    /// Commands of a completed tick, in the order they were recorded
+    pub fn tick(&self, tick: usize) -> Option<&[Command]> {
+        self.ticks.get(tick).map(Vec::as_slice)
+    }
+
+    /// Drop every tick from `tick` on, along with the tick in progress, so recording
+    /// can resume from an earlier point
+    pub fn truncate(&mut self, tick: usize) {
+        self.ticks.truncate(tick);
+        self.current.clear();
+    }
+
+    /// The completed ticks in the replay file format; the tick in progress is left out
+    pub fn encode(&self) -> Vec<u8> {
+        let mut names: Vec<&str> = Vec::new();
+        let mut indices: HashMap<&str, usize> = HashMap::new();
+        for command in self.ticks.iter().flatten() {
+            indices.entry(&command.name).or_insert_with(|| {
+                names.push(&command.name);
+                names.len() - 1
+            });
+        }
+
+        let mut out = Writer::with_header(MAGIC, VERSION);
+        out.u64(self.seed);
+        out.f64(self.fixed_step);
+        out.usize(names.len());
+        for name in &names {
+            out.str(name);
+        }
+        out.usize(self.ticks.len());
+        for tick in &self.ticks {
+            out.usize(tick.len());
+            for command in tick {
+                out.usize(indices[command.name.as_str()]);
+                out.bytes(&command.payload);
+            }
+        }
+        out.into_bytes()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        let mut input = Reader::with_header(data, MAGIC, VERSION, "a replay")?;
+        let seed = input.u64()?;
+        let fixed_step = input.f64()?;
+
+        let mut names = Vec::new();
+        for _ in 0..input.usize()? {
+            names.push(input.str()?.to_string());
+        }
+
+        let mut ticks = Vec::new();
+        for _ in 0..input.usize()? {
+            let mut tick = Vec::new();
+            for _ in 0..input.usize()? {
+                let index = input.usize()?;
+                let name = names.get(index).ok_or_else(|| {
+                    DecodeError::Invalid(format!("command refers to unknown name #{}", index))
+                })?;
+                tick.push(Command {
+                    name: name.clone(),
+                    payload: input.bytes()?.to_vec(),
+                });
+            }
+            ticks.push(tick);
+        }
+        input.finish()?;
+
+        Ok(Replay {
+            seed,
+            fixed_step,
+            ticks,
+            current: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorded() -> Replay {
+        let mut replay = Replay::new(42, 1.0 / 60.0);
+        replay.record("move", b"{\"dx\": 1}".to_vec());
+        replay.record("fire", Vec::new());
+        replay.end_tick();
+        replay.end_tick();
+        replay.record("move", b"{\"dx\": -1}".to_vec());
+        replay.end_tick();
+        replay
+    }
+
+    #[test]
+    fn replays_round_trip_with_names_stored_once() {
+        let replay = recorded();
+        let bytes = replay.encode();
+        assert_eq!(Replay::decode(&bytes), Ok(replay.clone()));
+        assert_eq!(
+            bytes.windows(4).filter(|window| window == b"move").count(),
+            1
+        );
+        assert_eq!(replay.len(), 3);
+        assert_eq!(replay.tick(1), Some(&[][..]));
+        assert_eq!(replay.tick(2).unwrap()[0].payload, b"{\"dx\": -1}");
+        assert_eq!(replay.tick(3), None);
+    }
+
+    #[test]
+    fn the_tick_in_progress_is_not_saved() {
+        let mut replay = recorded();
+        replay.record("wait", Vec::new());
+        let decoded = Replay::decode(&replay.encode()).unwrap();
+        assert_eq!(decoded, recorded());
+        replay.truncate(1);
+        replay.end_tick();
+        assert_eq!(replay.len(), 2);
+        assert_eq!(
+            replay.tick(1),
+            Some(&[][..]),
+            "truncating drops the open tick"
+        );
+    }
+
+    #[test]
+    fn malformed_replays_are_refused() {
+        let bytes = recorded().encode();
+        for end in 0..bytes.len() {
+            assert!(Replay::decode(&bytes[..end]).is_err(), "cut at {}", end);
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(Replay::decode(&trailing), Err(DecodeError::TrailingData(1)));
+        let mut other = bytes.clone();
+        other[..4].copy_from_slice(b"LQSV");
+        assert_eq!(
+            Replay::decode(&other),
+            Err(DecodeError::WrongKind("a replay"))
+        );
+
+        // The first command of the first tick refers to name #0; point it past the table
+        let mut unknown = Replay::new(1, 1.0);
+        unknown.record("only", Vec::new());
+        unknown.end_tick();
+        let mut bytes = unknown.encode();
+        let at = bytes.len() - 2;
+        assert_eq!(bytes[at], 0);
+        bytes[at] = 5;
+        assert!(matches!(
+            Replay::decode(&bytes),
+            Err(DecodeError::Invalid(reason)) if reason.contains("#5")
+        ));
+    }
+}
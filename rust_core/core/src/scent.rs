@@ -0,0 +1,151 @@
+//! Scent trails that spread through open cells and fade over time, for monsters
+//! that track the player around corners.
+
+use crate::diffusion;
+use crate::grid;
+use crate::hooks;
+
+/// Scent below this is treated as gone so old trails do not linger forever
+const TRACE: f32 = 1e-4;
+
+/// Scent values over a row-major grid, with walls holding none
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScentMap {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<f32>,
+    /// 1 for open cells and 0 for walls
+    pub conductance: Vec<f32>,
+    /// Fraction of the difference to neighbours exchanged per step, 0 to 1
+    pub diffusion: f32,
+    /// Fraction of scent lost per step
+    pub decay: f32,
+}
+
+impl ScentMap {
+    /// An empty trail over the open cells of a `[y][x]` walkable map
+    pub fn new(walkable_map: &[Vec<bool>], diffusion: f32, decay: f32) -> Self {
+        let (width, height, conductance) = diffusion::open_cells(walkable_map);
+        ScentMap {
+            width,
+            height,
+            values: vec![0.0; width * height],
+            conductance,
+            diffusion,
+            decay,
+        }
+    }
+
+    /// The index of a cell in `values`, or `None` outside the map
+    pub fn index(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| y * self.width + x)
+    }
+
+    /// Add scent to an open cell; walls never hold scent
+    pub fn deposit(&mut self, index: usize, amount: f32) {
+        if self.conductance[index] > 0.0 {
+            self.values[index] += amount;
+        }
+    }
+
+    /// Diffuse and decay the scent for `turns` turns, stopping early if cancelled
+    pub fn step(&mut self, turns: usize) {
+        for done in 0..turns {
+            if hooks::cancelled() {
+                break;
+            }
+            hooks::report("step", done as f32 / turns as f32);
+            self.step_once();
+        }
+    }
+
+    pub fn step_once(&mut self) {
+        diffusion::diffuse(
+            &mut self.values,
+            &self.conductance,
+            self.width,
+            self.height,
+            self.diffusion.clamp(0.0, 1.0),
+        );
+        let keep = 1.0 - self.decay.clamp(0.0, 1.0);
+        for value in self.values.iter_mut() {
+            *value *= keep;
+            if *value < TRACE {
+                *value = 0.0;
+            }
+        }
+    }
+
+    /// The neighbour of `(x, y)` with the strongest scent, if it is stronger than
+    /// that cell
+    pub fn gradient(&self, x: usize, y: usize, diagonal: bool) -> Option<(usize, usize)> {
+        let mut best_value = self.values[y * self.width + x];
+        let neighbours: &[(isize, isize)] = if diagonal {
+            &grid::EIGHT_WAY
+        } else {
+            &grid::CARDINAL
+        };
+        let mut best = None;
+        for &(dx, dy) in neighbours {
+            if let Some((nx, ny)) = grid::offset(x, y, dx, dy, self.width, self.height) {
+                let value = self.values[ny * self.width + nx];
+                if value > best_value {
+                    best_value = value;
+                    best = Some((nx, ny));
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 5x1 corridor with a wall in the middle
+    fn corridor() -> ScentMap {
+        ScentMap::new(&[vec![true, true, false, true, true]], 0.5, 0.0)
+    }
+
+    #[test]
+    fn scent_spreads_but_not_through_walls() {
+        let mut trail = corridor();
+        trail.deposit(1, 1.0);
+        trail.step(10);
+        assert!(trail.values[0] > 0.0);
+        assert_eq!(&trail.values[2..], [0.0, 0.0, 0.0]);
+        let total: f32 = trail.values.iter().sum();
+        assert!(
+            (total - 1.0).abs() < 1e-5,
+            "scent is conserved without decay"
+        );
+    }
+
+    #[test]
+    fn walls_never_hold_scent() {
+        let mut trail = corridor();
+        trail.deposit(2, 1.0);
+        assert_eq!(trail.values[2], 0.0);
+    }
+
+    #[test]
+    fn decay_fades_the_trail_to_nothing() {
+        let mut trail = corridor();
+        trail.decay = 0.5;
+        trail.deposit(0, 1.0);
+        trail.step(20);
+        assert!(trail.values.iter().all(|&value| value == 0.0));
+    }
+
+    #[test]
+    fn gradient_climbs_towards_the_source() {
+        let mut trail = ScentMap::new(&[vec![true; 3], vec![true; 3]], 0.5, 0.0);
+        trail.deposit(trail.index(2, 1).unwrap(), 1.0);
+        trail.step_once();
+        assert_eq!(trail.gradient(1, 1, false), Some((2, 1)));
+        assert_eq!(trail.gradient(1, 0, true), Some((2, 1)));
+        assert_eq!(trail.gradient(2, 1, true), None);
+        assert_eq!(trail.index(3, 0), None);
+    }
+}
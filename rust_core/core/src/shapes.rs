@@ -0,0 +1,244 @@
+use crate::vec2::Vec2;
+
+/// An axis-aligned rectangle with its top-left corner at `(x, y)`.
+///
+/// Edges are half-open like tile ranges: a rectangle contains its left and top
+/// edges but not its right and bottom ones, and rectangles that only touch do
+/// not intersect.
+#[cfg_attr(feature = "python", pyo3::pyclass)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// A circle around `(x, y)`
+#[cfg_attr(feature = "python", pyo3::pyclass)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Circle {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+}
+
+/// Either shape, for APIs such as collision tests that accept both
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    Rect(Rect),
+    Circle(Circle),
+}
+
+impl Shape {
+    pub fn intersects(self, other: Shape) -> bool {
+        match (self, other) {
+            (Shape::Rect(a), Shape::Rect(b)) => a.intersects(b),
+            (Shape::Circle(a), Shape::Circle(b)) => a.intersects_circle(b),
+            (Shape::Rect(rect), Shape::Circle(circle))
+            | (Shape::Circle(circle), Shape::Rect(rect)) => circle.intersects_rect(rect),
+        }
+    }
+
+    pub fn contains(self, point: Vec2) -> bool {
+        match self {
+            Shape::Rect(rect) => rect.contains(point),
+            Shape::Circle(circle) => circle.contains(point),
+        }
+    }
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// A rectangle of the given size centred on `center`
+    pub fn from_center(center: Vec2, width: f32, height: f32) -> Self {
+        Rect::new(
+            center.x - width / 2.0,
+            center.y - height / 2.0,
+            width,
+            height,
+        )
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width, self.height)
+    }
+
+    /// Whether a point lies inside the rectangle
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.x && point.x < self.right() && point.y >= self.y && point.y < self.bottom()
+    }
+
+    /// Whether `other` lies entirely inside this rectangle
+    pub fn contains_rect(&self, other: Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+
+    pub fn intersects(&self, other: Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    /// The overlapping area of two rectangles, or `None` if they do not intersect
+    pub fn intersection(&self, other: Rect) -> Option<Rect> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        Some(Rect::new(
+            x,
+            y,
+            self.right().min(other.right()) - x,
+            self.bottom().min(other.bottom()) - y,
+        ))
+    }
+
+    /// The smallest rectangle covering both rectangles
+    pub fn union(&self, other: Rect) -> Rect {
+        let (x, y) = (self.x.min(other.x), self.y.min(other.y));
+        Rect::new(
+            x,
+            y,
+            self.right().max(other.right()) - x,
+            self.bottom().max(other.bottom()) - y,
+        )
+    }
+
+    /// Grow the rectangle by `dx` on the left and right and `dy` on the top and
+    /// bottom, keeping its centre; negative amounts shrink it
+    pub fn inflate(&self, dx: f32, dy: f32) -> Rect {
+        Rect::new(
+            self.x - dx,
+            self.y - dy,
+            self.width + 2.0 * dx,
+            self.height + 2.0 * dy,
+        )
+    }
+
+    /// The point inside the rectangle nearest to `point`
+    pub fn clamp(&self, point: Vec2) -> Vec2 {
+        Vec2::new(
+            point.x.clamp(self.x, self.right().max(self.x)),
+            point.y.clamp(self.y, self.bottom().max(self.y)),
+        )
+    }
+
+    /// This rectangle moved the least distance needed to lie inside `bounds`,
+    /// centred on it along any axis where it is too big to fit
+    pub fn clamp_within(&self, bounds: Rect) -> Rect {
+        let fit = |start: f32, length: f32, min: f32, span: f32| {
+            if length >= span {
+                min + (span - length) / 2.0
+            } else {
+                start.clamp(min, min + span - length)
+            }
+        };
+        Rect::new(
+            fit(self.x, self.width, bounds.x, bounds.width),
+            fit(self.y, self.height, bounds.y, bounds.height),
+            self.width,
+            self.height,
+        )
+    }
+
+    /// The rectangle moved by `offset`
+    pub fn translate(&self, offset: Vec2) -> Rect {
+        Rect::new(
+            self.x + offset.x,
+            self.y + offset.y,
+            self.width,
+            self.height,
+        )
+    }
+}
+
+impl Circle {
+    pub fn new(x: f32, y: f32, radius: f32) -> Self {
+        Circle { x, y, radius }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    /// The smallest `Rect` containing the circle
+    pub fn bounds(&self) -> Rect {
+        Rect::new(
+            self.x - self.radius,
+            self.y - self.radius,
+            2.0 * self.radius,
+            2.0 * self.radius,
+        )
+    }
+
+    /// Whether a point lies inside the circle
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.center().distance_squared(point) <= self.radius * self.radius
+    }
+
+    pub fn intersects_circle(&self, other: Circle) -> bool {
+        let reach = self.radius + other.radius;
+        self.center().distance_squared(other.center()) < reach * reach
+    }
+
+    pub fn intersects_rect(&self, rect: Rect) -> bool {
+        let center = self.center();
+        center.distance_squared(rect.clamp(center)) < self.radius * self.radius
+    }
+
+    /// The smallest circle enclosing both circles
+    pub fn union(&self, other: Circle) -> Circle {
+        let offset = other.center() - self.center();
+        let distance = offset.length();
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return other;
+        }
+        let radius = (distance + self.radius + other.radius) / 2.0;
+        let center = self.center() + offset * ((radius - self.radius) / distance);
+        Circle::new(center.x, center.y, radius)
+    }
+
+    /// Grow the radius by `amount`, or shrink it for a negative amount
+    pub fn inflate(&self, amount: f32) -> Circle {
+        Circle::new(self.x, self.y, (self.radius + amount).max(0.0))
+    }
+
+    /// The point inside the circle nearest to `point`
+    pub fn clamp(&self, point: Vec2) -> Vec2 {
+        let center = self.center();
+        center + (point - center).truncate(self.radius)
+    }
+
+    /// The circle moved by `offset`
+    pub fn translate(&self, offset: Vec2) -> Circle {
+        Circle::new(self.x + offset.x, self.y + offset.y, self.radius)
+    }
+}
@@ -0,0 +1,403 @@
+//! Steering behaviours for moving agents: seek, flee, arrive, pursue, evade,
+//! wander, obstacle avoidance and path following, blended by weight.
+
+use crate::hooks;
+use crate::rng::Rng;
+use crate::vec2::Vec2;
+
+/// A single steering behaviour attached to an agent
+#[derive(Clone, Debug, PartialEq)]
+pub enum Behavior {
+    Seek(Vec2),
+    Flee {
+        target: Vec2,
+        panic_distance: Option<f32>,
+    },
+    Arrive {
+        target: Vec2,
+        slowing_radius: f32,
+    },
+    Pursue(usize),
+    Evade(usize),
+    Wander {
+        radius: f32,
+        distance: f32,
+        jitter: f32,
+    },
+    AvoidObstacles {
+        look_ahead: f32,
+    },
+    FollowPath {
+        points: Vec<Vec2>,
+        waypoint_radius: f32,
+        looped: bool,
+    },
+}
+
+/// Force that steers towards `target`, slowing down inside `slowing_radius`
+pub fn arrive_force(
+    position: Vec2,
+    velocity: Vec2,
+    max_speed: f32,
+    target: Vec2,
+    slowing_radius: f32,
+) -> Vec2 {
+    let offset = target - position;
+    let distance = offset.length();
+    if distance <= f32::EPSILON {
+        return -velocity;
+    }
+    let speed = if slowing_radius > 0.0 {
+        max_speed * (distance / slowing_radius).min(1.0)
+    } else {
+        max_speed
+    };
+    offset * (speed / distance) - velocity
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Agent {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub max_speed: f32,
+    pub max_force: f32,
+    pub radius: f32,
+    /// Behaviours and their weights, summed in order
+    pub behaviors: Vec<(Behavior, f32)>,
+    pub wander_angle: f32,
+    /// The `FollowPath` point being steered towards
+    pub waypoint: usize,
+}
+
+impl Agent {
+    /// An agent at rest with no behaviours
+    pub fn new(position: Vec2, max_speed: f32, max_force: f32, radius: f32) -> Self {
+        Agent {
+            position,
+            velocity: Vec2::ZERO,
+            max_speed,
+            max_force,
+            radius,
+            behaviors: Vec::new(),
+            wander_angle: 0.0,
+            waypoint: 0,
+        }
+    }
+
+    /// Forward direction, falling back to +x for an agent at rest
+    fn heading(&self) -> Vec2 {
+        let heading = self.velocity.normalize();
+        if heading == Vec2::ZERO {
+            Vec2::new(1.0, 0.0)
+        } else {
+            heading
+        }
+    }
+
+    fn seek(&self, target: Vec2) -> Vec2 {
+        (target - self.position).normalize() * self.max_speed - self.velocity
+    }
+
+    fn flee(&self, target: Vec2) -> Vec2 {
+        (self.position - target).normalize() * self.max_speed - self.velocity
+    }
+
+    fn arrive(&self, target: Vec2, slowing_radius: f32) -> Vec2 {
+        arrive_force(
+            self.position,
+            self.velocity,
+            self.max_speed,
+            target,
+            slowing_radius,
+        )
+    }
+
+    /// Seek the current waypoint, advancing once within `waypoint_radius` of it
+    fn follow_path(&mut self, points: &[Vec2], waypoint_radius: f32, looped: bool) -> Vec2 {
+        if points.is_empty() {
+            return Vec2::ZERO;
+        }
+        self.waypoint = self.waypoint.min(points.len() - 1);
+        if self.position.distance(points[self.waypoint]) <= waypoint_radius {
+            if self.waypoint + 1 < points.len() {
+                self.waypoint += 1;
+            } else if looped {
+                self.waypoint = 0;
+            }
+        }
+
+        let target = points[self.waypoint];
+        if !looped && self.waypoint == points.len() - 1 {
+            self.arrive(target, waypoint_radius * 2.0)
+        } else {
+            self.seek(target)
+        }
+    }
+
+    /// Where another agent will be by the time this one could reach it
+    fn predict(&self, target: &Agent) -> Vec2 {
+        let distance = self.position.distance(target.position);
+        let closing_speed = self.max_speed + target.velocity.length();
+        let look_ahead = if closing_speed > 0.0 {
+            distance / closing_speed
+        } else {
+            0.0
+        };
+        target.position + target.velocity * look_ahead
+    }
+}
+
+/// A batch of steering agents whose forces are computed together each tick.
+///
+/// An agent's id is its slot in `agents`; removed agents leave an empty slot so
+/// ids are never reused. Each agent combines its behaviours as a weighted sum
+/// truncated to `max_force`.
+#[derive(Clone, Debug)]
+pub struct SteeringAgents {
+    pub agents: Vec<Option<Agent>>,
+    /// Circular obstacles as `(centre, radius)`
+    pub obstacles: Vec<(Vec2, f32)>,
+    /// Drives `Wander`
+    pub rng: Rng,
+}
+
+impl SteeringAgents {
+    pub fn new(seed: u64) -> Self {
+        SteeringAgents {
+            agents: Vec::new(),
+            obstacles: Vec::new(),
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Add an agent and return its id
+    pub fn add(&mut self, agent: Agent) -> usize {
+        self.agents.push(Some(agent));
+        self.agents.len() - 1
+    }
+
+    /// Remove an agent, returning whether it was there
+    pub fn remove(&mut self, id: usize) -> bool {
+        self.agents.get_mut(id).and_then(Option::take).is_some()
+    }
+
+    pub fn agent(&self, id: usize) -> Option<&Agent> {
+        self.agents.get(id).and_then(Option::as_ref)
+    }
+
+    pub fn agent_mut(&mut self, id: usize) -> Option<&mut Agent> {
+        self.agents.get_mut(id).and_then(Option::as_mut)
+    }
+
+    /// Number of agents that have not been removed
+    pub fn len(&self) -> usize {
+        self.agents.iter().filter(|agent| agent.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Apply this tick's steering forces and integrate velocities and positions,
+    /// returning the forces
+    pub fn update(&mut self, delta_time: f32) -> Vec<Vec2> {
+        let forces = self.forces();
+        for (slot, force) in self.agents.iter_mut().zip(&forces) {
+            if let Some(agent) = slot {
+                agent.velocity = (agent.velocity + *force * delta_time).truncate(agent.max_speed);
+                agent.position += agent.velocity * delta_time;
+            }
+        }
+        forces
+    }
+
+    /// Steering force for every agent slot, zero for removed agents
+    pub fn forces(&mut self) -> Vec<Vec2> {
+        let mut forces = Vec::with_capacity(self.agents.len());
+        for id in 0..self.agents.len() {
+            let force = match &self.agents[id] {
+                Some(_) => self.agent_force(id),
+                None => Vec2::ZERO,
+            };
+            forces.push(force);
+        }
+        hooks::count("agents_steered", forces.len() as u64);
+        forces
+    }
+
+    fn agent_force(&mut self, id: usize) -> Vec2 {
+        let mut agent = self.agents[id]
+            .take()
+            .expect("agent slot checked by caller");
+        let behaviors = std::mem::take(&mut agent.behaviors);
+        let mut total = Vec2::ZERO;
+
+        for (behavior, weight) in &behaviors {
+            let force = match behavior {
+                Behavior::Seek(target) => agent.seek(*target),
+                Behavior::Flee {
+                    target,
+                    panic_distance,
+                } => match panic_distance {
+                    Some(panic) if agent.position.distance(*target) > *panic => Vec2::ZERO,
+                    _ => agent.flee(*target),
+                },
+                Behavior::Arrive {
+                    target,
+                    slowing_radius,
+                } => agent.arrive(*target, *slowing_radius),
+                Behavior::Pursue(other) => match self.agents.get(*other).and_then(Option::as_ref) {
+                    Some(target) => agent.seek(agent.predict(target)),
+                    None => Vec2::ZERO,
+                },
+                Behavior::Evade(other) => match self.agents.get(*other).and_then(Option::as_ref) {
+                    Some(target) => agent.flee(agent.predict(target)),
+                    None => Vec2::ZERO,
+                },
+                Behavior::Wander {
+                    radius,
+                    distance,
+                    jitter,
+                } => {
+                    agent.wander_angle += self.rng.range_f32(-1.0, 1.0) * jitter;
+                    let centre = agent.position + agent.heading() * *distance;
+                    let offset =
+                        Vec2::new(agent.wander_angle.cos(), agent.wander_angle.sin()) * *radius;
+                    agent.seek(centre + offset)
+                }
+                Behavior::AvoidObstacles { look_ahead } => {
+                    self.obstacle_avoidance(&agent, *look_ahead)
+                }
+                Behavior::FollowPath {
+                    points,
+                    waypoint_radius,
+                    looped,
+                } => agent.follow_path(points, *waypoint_radius, *looped),
+            };
+            total += force * *weight;
+        }
+
+        let force = total.truncate(agent.max_force);
+        agent.behaviors = behaviors;
+        self.agents[id] = Some(agent);
+        force
+    }
+
+    /// Push sideways away from the nearest obstacle crossing the look-ahead segment
+    fn obstacle_avoidance(&self, agent: &Agent, look_ahead: f32) -> Vec2 {
+        let speed_ratio = if agent.max_speed > 0.0 {
+            (agent.velocity.length() / agent.max_speed).min(1.0)
+        } else {
+            0.0
+        };
+        let heading = agent.heading();
+        let reach = look_ahead * speed_ratio.max(0.25);
+
+        let mut nearest: Option<(f32, Vec2)> = None;
+        for &(centre, radius) in &self.obstacles {
+            let offset = centre - agent.position;
+            let along = offset.dot(heading);
+            if along < 0.0 || along > reach + radius {
+                continue;
+            }
+            let lateral = offset.cross(heading);
+            if lateral.abs() >= radius + agent.radius {
+                continue;
+            }
+            if nearest.is_none_or(|(best, _)| along < best) {
+                nearest = Some((along, centre));
+            }
+        }
+
+        match nearest {
+            Some((along, centre)) => {
+                let ahead = agent.position + heading * along;
+                let away = (ahead - centre).normalize();
+                // Dead ahead there is no preferred side, so pick a consistent one
+                let away = if away == Vec2::ZERO {
+                    Vec2::new(-heading.y, heading.x)
+                } else {
+                    away
+                };
+                away * agent.max_force
+            }
+            None => Vec2::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent_at(x: f32, y: f32) -> Agent {
+        Agent::new(Vec2::new(x, y), 1.0, 0.5, 0.5)
+    }
+
+    #[test]
+    fn seek_pulls_towards_the_target_within_max_force() {
+        let mut batch = SteeringAgents::new(0);
+        let id = batch.add(agent_at(0.0, 0.0));
+        batch.agents[id].as_mut().unwrap().behaviors =
+            vec![(Behavior::Seek(Vec2::new(10.0, 0.0)), 1.0)];
+        let forces = batch.update(1.0);
+        assert_eq!(forces, [Vec2::new(0.5, 0.0)]);
+        assert_eq!(batch.agent(id).unwrap().position, Vec2::new(0.5, 0.0));
+    }
+
+    #[test]
+    fn arrive_stops_on_the_target() {
+        assert_eq!(
+            arrive_force(
+                Vec2::new(3.0, 0.0),
+                Vec2::new(1.0, 0.0),
+                2.0,
+                Vec2::new(4.0, 0.0),
+                2.0
+            ),
+            Vec2::ZERO
+        );
+        let at_rest = arrive_force(Vec2::ZERO, Vec2::new(0.5, 0.0), 2.0, Vec2::ZERO, 2.0);
+        assert_eq!(at_rest, Vec2::new(-0.5, 0.0));
+    }
+
+    #[test]
+    fn removed_ids_stay_empty_and_are_not_reused() {
+        let mut batch = SteeringAgents::new(0);
+        let first = batch.add(agent_at(0.0, 0.0));
+        let hunter = batch.add(agent_at(5.0, 0.0));
+        batch.agent_mut(hunter).unwrap().behaviors = vec![(Behavior::Pursue(first), 1.0)];
+        assert!(batch.update(1.0)[hunter].x < 0.0);
+        assert!(batch.remove(first));
+        assert!(!batch.remove(first));
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch.update(1.0)[..1], [Vec2::ZERO]);
+        assert_eq!(batch.add(agent_at(1.0, 1.0)), 2);
+    }
+
+    #[test]
+    fn paths_advance_through_their_waypoints() {
+        let mut batch = SteeringAgents::new(0);
+        let id = batch.add(agent_at(0.0, 0.0));
+        let points = vec![Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0)];
+        let path = Behavior::FollowPath {
+            points,
+            waypoint_radius: 0.5,
+            looped: false,
+        };
+        batch.agent_mut(id).unwrap().behaviors = vec![(path, 1.0)];
+        batch.update(1.0);
+        assert_eq!(batch.agent(id).unwrap().waypoint, 1);
+        for _ in 0..50 {
+            batch.update(0.5);
+        }
+        assert!(
+            batch
+                .agent(id)
+                .unwrap()
+                .position
+                .distance(Vec2::new(4.0, 0.0))
+                < 0.25
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Temperatures spreading between cells, driven by the tiles they sit on.
+//!
+//! Each tile may insulate its cell and give it a base temperature it slowly drifts
+//! back to, so a mountain stays cold and a lava room stays hot while heat sources
+//! warm or chill their surroundings.
+
+use std::collections::HashMap;
+
+use crate::diffusion;
+use crate::grid;
+use crate::hooks;
+
+/// A fixed-temperature cell such as a campfire, lava pool or ice block
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeatSource {
+    pub index: usize,
+    pub temperature: f32,
+    /// How far towards its temperature the cell is pulled each step, 0 to 1
+    pub strength: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TemperatureGrid {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<f32>,
+    pub base: Vec<f32>,
+    /// 1 minus each cell's insulation
+    pub conductance: Vec<f32>,
+    pub sources: Vec<HeatSource>,
+    /// Share of a temperature difference exchanged with neighbours per step
+    pub conductivity: f32,
+    /// Share of the way back to the base temperature each cell moves per step
+    pub relaxation: f32,
+}
+
+impl TemperatureGrid {
+    /// Build from a rectangular `[y][x]` tile map, starting every cell at its base
+    /// temperature. Tiles missing from the tables use `ambient` and no insulation
+    /// (0 conducts freely, 1 blocks all heat).
+    pub fn new(
+        tile_map: &[Vec<u32>],
+        insulation: &HashMap<u32, f32>,
+        base_temperature: &HashMap<u32, f32>,
+        ambient: f32,
+        conductivity: f32,
+        relaxation: f32,
+    ) -> Self {
+        let (width, height) = grid::dimensions(tile_map);
+        let mut base = vec![ambient; width * height];
+        let mut conductance = vec![1.0; width * height];
+        for (y, row) in tile_map.iter().enumerate() {
+            for (x, &tile) in row.iter().enumerate().take(width) {
+                let index = y * width + x;
+                if let Some(&value) = insulation.get(&tile) {
+                    conductance[index] = 1.0 - value.clamp(0.0, 1.0);
+                }
+                if let Some(&value) = base_temperature.get(&tile) {
+                    base[index] = value;
+                }
+            }
+        }
+        TemperatureGrid {
+            width,
+            height,
+            values: base.clone(),
+            base,
+            conductance,
+            sources: Vec::new(),
+            conductivity,
+            relaxation,
+        }
+    }
+
+    /// The index of a cell in `values`, or `None` outside the grid
+    pub fn index(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| y * self.width + x)
+    }
+
+    /// Hold a cell near `temperature`; replaces any source already on that cell
+    pub fn set_source(&mut self, index: usize, temperature: f32, strength: f32) {
+        self.remove_source(index);
+        self.sources.push(HeatSource {
+            index,
+            temperature,
+            strength,
+        });
+    }
+
+    /// Remove a heat source, returning whether there was one
+    pub fn remove_source(&mut self, index: usize) -> bool {
+        let before = self.sources.len();
+        self.sources.retain(|source| source.index != index);
+        self.sources.len() != before
+    }
+
+    /// Change a cell's insulation, e.g. when a wall is built or a door opens
+    pub fn set_insulation(&mut self, index: usize, insulation: f32) {
+        self.conductance[index] = 1.0 - insulation.clamp(0.0, 1.0);
+    }
+
+    /// Advance the simulation `steps` times, stopping early if cancelled
+    pub fn step(&mut self, steps: usize) {
+        for done in 0..steps {
+            if hooks::cancelled() {
+                break;
+            }
+            hooks::report("step", done as f32 / steps as f32);
+            self.step_once();
+        }
+    }
+
+    pub fn step_once(&mut self) {
+        diffusion::diffuse(
+            &mut self.values,
+            &self.conductance,
+            self.width,
+            self.height,
+            self.conductivity.clamp(0.0, 1.0),
+        );
+        let relaxation = self.relaxation.clamp(0.0, 1.0);
+        for (value, &base) in self.values.iter_mut().zip(&self.base) {
+            *value += (base - *value) * relaxation;
+        }
+        for source in &self.sources {
+            let value = &mut self.values[source.index];
+            *value += (source.temperature - *value) * source.strength.clamp(0.0, 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLOOR: u32 = 0;
+    const WALL: u32 = 1;
+    const LAVA: u32 = 2;
+
+    fn cave() -> TemperatureGrid {
+        let insulation = HashMap::from([(WALL, 1.0)]);
+        let base = HashMap::from([(LAVA, 500.0)]);
+        let map = [
+            vec![FLOOR, FLOOR, WALL, FLOOR],
+            vec![LAVA, FLOOR, WALL, FLOOR],
+        ];
+        TemperatureGrid::new(&map, &insulation, &base, 20.0, 0.5, 0.0)
+    }
+
+    #[test]
+    fn tiles_set_the_base_temperature_and_insulation() {
+        let grid = cave();
+        assert_eq!(grid.values, grid.base);
+        assert_eq!(grid.values[4], 500.0);
+        assert_eq!(grid.values[0], 20.0);
+        assert_eq!(grid.conductance[2], 0.0);
+    }
+
+    #[test]
+    fn heat_spreads_but_not_through_walls() {
+        let mut grid = cave();
+        grid.step(50);
+        assert!(grid.values[1] > 20.0);
+        assert_eq!(grid.values[3], 20.0);
+        assert_eq!(grid.values[7], 20.0);
+    }
+
+    #[test]
+    fn sources_pull_their_cell_and_can_be_replaced() {
+        let mut grid = cave();
+        let index = grid.index(3, 0).unwrap();
+        grid.set_source(index, -10.0, 0.5);
+        grid.set_source(index, 100.0, 1.0);
+        assert_eq!(grid.sources.len(), 1);
+        grid.step_once();
+        assert_eq!(grid.values[index], 100.0);
+        assert!(grid.remove_source(index));
+        assert!(!grid.remove_source(index));
+    }
+
+    #[test]
+    fn relaxation_returns_cells_to_their_base() {
+        let mut grid = cave();
+        grid.relaxation = 1.0;
+        grid.values[7] = 90.0;
+        grid.step_once();
+        assert!((grid.values[7] - 20.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,181 @@
+//! Threat maps for tactical AI: how many enemies can reach and attack each cell
+//! this turn and by the next.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::distance::Metric;
+use crate::grid;
+use crate::hooks::{self, Level};
+
+/// An enemy's position and reach, in cells
+#[derive(Clone, Copy, Debug)]
+pub struct Threat {
+    pub position: (usize, usize),
+    pub move_range: usize,
+    pub attack_range: usize,
+}
+
+/// Per-cell counts of how many enemies can attack it this turn and by next turn.
+///
+/// Enemies move over walkable cells (their own cell always counts as walkable) and
+/// can then attack anything within `attack_range` of where they end up, measured
+/// with `metric`. "Next turn" means after two moves, and includes this turn.
+pub fn threat_counts(
+    threats: &[Threat],
+    walkable_map: &[Vec<bool>],
+    diagonal: bool,
+    metric: Metric,
+) -> (Vec<u32>, Vec<u32>) {
+    let (width, height) = grid::dimensions(walkable_map);
+    let mut this_turn = vec![0u32; width * height];
+    let mut next_turn = vec![0u32; width * height];
+    let neighbours: &[(isize, isize)] = if diagonal {
+        &grid::EIGHT_WAY
+    } else {
+        &grid::CARDINAL
+    };
+
+    let mut steps = vec![usize::MAX; width * height];
+    let mut now_mask = vec![false; width * height];
+    let mut next_mask = vec![false; width * height];
+    // Enemies of a kind share a reach, so their offsets are only worked out once
+    let mut offsets_by_range: HashMap<usize, Vec<(isize, isize)>> = HashMap::new();
+
+    for (done, threat) in threats.iter().enumerate() {
+        if hooks::cancelled() {
+            break;
+        }
+        hooks::report("threats", done as f32 / threats.len() as f32);
+        let (sx, sy) = threat.position;
+        if sx >= width || sy >= height {
+            hooks::log(
+                Level::Warning,
+                format_args!(
+                    "ignoring threat at ({}, {}): it is outside the {}x{} map",
+                    sx, sy, width, height
+                ),
+            );
+            continue;
+        }
+
+        // Walking distance from the enemy, out to two turns of movement
+        steps.fill(usize::MAX);
+        let mut reachable = Vec::new();
+        let mut queue = VecDeque::from([(sx, sy)]);
+        steps[sy * width + sx] = 0;
+        let limit = threat.move_range.saturating_mul(2);
+        while let Some((x, y)) = queue.pop_front() {
+            let step = steps[y * width + x];
+            reachable.push((x, y, step));
+            if step == limit {
+                continue;
+            }
+            for &(dx, dy) in neighbours {
+                if let Some((nx, ny)) = grid::offset(x, y, dx, dy, width, height) {
+                    let index = ny * width + nx;
+                    if steps[index] == usize::MAX && grid::flag(walkable_map, nx, ny) {
+                        steps[index] = step + 1;
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+        }
+
+        now_mask.fill(false);
+        next_mask.fill(false);
+        // A longer reach than this already covers the whole map under every metric
+        let range = threat.attack_range.min(width + height);
+        let offsets = offsets_by_range
+            .entry(range)
+            .or_insert_with(|| attack_offsets(range, metric, width, height));
+        for &(x, y, step) in &reachable {
+            for &(dx, dy) in offsets.iter() {
+                if let Some((tx, ty)) = grid::offset(x, y, dx, dy, width, height) {
+                    let index = ty * width + tx;
+                    next_mask[index] = true;
+                    if step <= threat.move_range {
+                        now_mask[index] = true;
+                    }
+                }
+            }
+        }
+
+        for index in 0..width * height {
+            this_turn[index] += u32::from(now_mask[index]);
+            next_turn[index] += u32::from(next_mask[index]);
+        }
+    }
+
+    // Nobody needs to worry about being attacked inside a wall
+    for (index, (now, next)) in this_turn.iter_mut().zip(next_turn.iter_mut()).enumerate() {
+        if !grid::flag(walkable_map, index % width, index / width) {
+            *now = 0;
+            *next = 0;
+        }
+    }
+
+    (this_turn, next_turn)
+}
+
+/// Every cell offset within `range` of the origin under `metric`, origin included,
+/// leaving out offsets too long to stay on a `width` by `height` map
+fn attack_offsets(
+    range: usize,
+    metric: Metric,
+    width: usize,
+    height: usize,
+) -> Vec<(isize, isize)> {
+    let r = range as isize;
+    let reach_x = r.min(width.saturating_sub(1) as isize);
+    let reach_y = r.min(height.saturating_sub(1) as isize);
+    let mut offsets = Vec::new();
+    for dy in -reach_y..=reach_y {
+        for dx in -reach_x..=reach_x {
+            let inside = match metric {
+                Metric::Chebyshev => true,
+                Metric::Manhattan => dx.abs() + dy.abs() <= r,
+                Metric::Euclidean => dx * dx + dy * dy <= r.saturating_mul(r),
+            };
+            if inside {
+                offsets.push((dx, dy));
+            }
+        }
+    }
+    offsets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threat(position: (usize, usize), move_range: usize, attack_range: usize) -> Threat {
+        Threat {
+            position,
+            move_range,
+            attack_range,
+        }
+    }
+
+    #[test]
+    fn reach_grows_by_a_move_for_next_turn() {
+        let open = vec![vec![true; 7]];
+        let (now, next) = threat_counts(&[threat((0, 0), 2, 1)], &open, false, Metric::Manhattan);
+        assert_eq!(now, [1, 1, 1, 1, 0, 0, 0]);
+        assert_eq!(next, [1, 1, 1, 1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn enemies_add_up_but_walls_stay_safe() {
+        let map = vec![vec![true, true, false, true]];
+        let threats = [threat((0, 0), 0, 3), threat((3, 0), 0, 1)];
+        let (now, _) = threat_counts(&threats, &map, false, Metric::Chebyshev);
+        assert_eq!(now, [1, 1, 0, 2]);
+    }
+
+    #[test]
+    fn threats_off_the_map_are_ignored() {
+        let map = vec![vec![true; 2]; 2];
+        let (now, next) = threat_counts(&[threat((5, 5), 9, 9)], &map, true, Metric::Euclidean);
+        assert!(now.iter().chain(&next).all(|&count| count == 0));
+    }
+}
@@ -0,0 +1,524 @@
+//! Maps exported from the Tiled editor in its JSON format: tile layers, object
+//! layers split into colliders, triggers and markers, and custom properties.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::shapes::Rect;
+
+/// Tiled stores flip and rotation flags in the top four bits of every gid
+const GID_MASK: u32 = 0x0FFF_FFFF;
+/// Largest map or layer accepted, 8192x8192 tiles; this bounds what a small file
+/// can make us allocate or decompress
+const MAX_TILES: usize = 1 << 26;
+
+/// Tile count of a `width` by `height` map or layer, if it is within `MAX_TILES`
+fn tile_count(what: &str, width: usize, height: usize) -> Result<usize, String> {
+    width
+        .checked_mul(height)
+        .filter(|&tiles| tiles <= MAX_TILES)
+        .ok_or_else(|| {
+            format!(
+                "{} is {}x{} tiles, more than the {} supported",
+                what, width, height, MAX_TILES
+            )
+        })
+}
+
+#[derive(Deserialize)]
+struct RawMap {
+    width: usize,
+    height: usize,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default)]
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+    #[serde(default)]
+    layers: Vec<RawLayer>,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+}
+
+#[derive(Deserialize)]
+struct RawLayer {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    class: String,
+    width: Option<usize>,
+    height: Option<usize>,
+    data: Option<Value>,
+    encoding: Option<String>,
+    compression: Option<String>,
+    #[serde(default)]
+    objects: Vec<RawObject>,
+    #[serde(default)]
+    layers: Vec<RawLayer>,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+}
+
+#[derive(Deserialize)]
+struct RawObject {
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    // Tiled 1.9 renamed `type` to `class`, accept either
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default)]
+    ellipse: bool,
+    #[serde(default)]
+    point: bool,
+    polygon: Option<Vec<RawPoint>>,
+    polyline: Option<Vec<RawPoint>>,
+    gid: Option<u32>,
+    #[serde(default)]
+    properties: Vec<RawProperty>,
+}
+
+#[derive(Deserialize)]
+struct RawPoint {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct RawProperty {
+    name: String,
+    value: Value,
+}
+
+/// A custom property value attached to a map, layer or object
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+fn convert_properties(raw: Vec<RawProperty>) -> HashMap<String, PropertyValue> {
+    raw.into_iter()
+        .map(|property| {
+            let value = match property.value {
+                Value::Bool(value) => PropertyValue::Bool(value),
+                Value::Number(number) => match number.as_i64() {
+                    Some(value) => PropertyValue::Int(value),
+                    None => PropertyValue::Float(number.as_f64().unwrap_or(0.0)),
+                },
+                Value::String(value) => PropertyValue::Str(value),
+                // Class-typed properties are nested objects, keep them as JSON text
+                other => PropertyValue::Str(other.to_string()),
+            };
+            (property.name, value)
+        })
+        .collect()
+}
+
+/// A tile layer with flip flags stripped from every gid; 0 means empty
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileLayer {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub gids: Vec<u32>,
+    pub properties: HashMap<String, PropertyValue>,
+}
+
+impl TileLayer {
+    /// The gids as a `[y][x]` grid
+    pub fn rows(&self) -> Vec<Vec<u32>> {
+        if self.width == 0 {
+            return vec![Vec::new(); self.height];
+        }
+        self.gids
+            .chunks(self.width)
+            .map(|row| row.to_vec())
+            .collect()
+    }
+}
+
+/// An object from an object layer, in map pixel coordinates
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledObject {
+    pub id: u32,
+    pub name: String,
+    /// The object's class (or `type` in files from before Tiled 1.9)
+    pub kind: String,
+    /// One of `rect`, `ellipse`, `point`, `polygon` or `polyline`
+    pub shape: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub rotation: f32,
+    /// Polygon and polyline vertices, already offset by the object position
+    pub points: Vec<(f32, f32)>,
+    pub layer: String,
+    pub properties: HashMap<String, PropertyValue>,
+}
+
+impl TiledObject {
+    /// The object's unrotated bounding box
+    pub fn bounds(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+}
+
+/// A map exported from the Tiled editor in its JSON format
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledMap {
+    pub width: usize,
+    pub height: usize,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub orientation: String,
+    pub properties: HashMap<String, PropertyValue>,
+    /// Solid objects that should block movement
+    pub colliders: Vec<TiledObject>,
+    /// Objects that fire events when entered rather than blocking
+    pub triggers: Vec<TiledObject>,
+    /// Point objects such as spawn locations, which have no area
+    pub markers: Vec<TiledObject>,
+    /// Tile layers in drawing order, with groups flattened
+    pub layers: Vec<TileLayer>,
+}
+
+impl TiledMap {
+    /// Parse a map from Tiled JSON, describing what is wrong on failure
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let raw: RawMap =
+            serde_json::from_str(json).map_err(|e| format!("invalid Tiled JSON: {}", e))?;
+        if raw.infinite {
+            return Err(
+                "infinite Tiled maps are not supported, resize the map to fixed bounds".to_string(),
+            );
+        }
+        tile_count("the map", raw.width, raw.height)?;
+
+        let mut map = TiledMap {
+            width: raw.width,
+            height: raw.height,
+            tile_width: raw.tilewidth,
+            tile_height: raw.tileheight,
+            orientation: if raw.orientation.is_empty() {
+                "orthogonal".to_string()
+            } else {
+                raw.orientation
+            },
+            properties: convert_properties(raw.properties),
+            colliders: Vec::new(),
+            triggers: Vec::new(),
+            markers: Vec::new(),
+            layers: Vec::new(),
+        };
+        for layer in raw.layers {
+            map.add_layer(layer)?;
+        }
+        Ok(map)
+    }
+
+    fn add_layer(&mut self, layer: RawLayer) -> Result<(), String> {
+        match layer.kind.as_str() {
+            "tilelayer" => {
+                let width = layer.width.unwrap_or(self.width);
+                let height = layer.height.unwrap_or(self.height);
+                let tiles = tile_count(&format!("tile layer '{}'", layer.name), width, height)?;
+                let gids = decode_layer_data(&layer, tiles)?;
+                if gids.len() != tiles {
+                    return Err(format!(
+                        "tile layer '{}' has {} tiles, expected {}x{}",
+                        layer.name,
+                        gids.len(),
+                        width,
+                        height
+                    ));
+                }
+                self.layers.push(TileLayer {
+                    name: layer.name,
+                    width,
+                    height,
+                    gids,
+                    properties: convert_properties(layer.properties),
+                });
+            }
+            "objectgroup" => {
+                let layer_is_trigger = layer.class.eq_ignore_ascii_case("trigger");
+                for raw in layer.objects {
+                    let object = convert_object(raw, &layer.name);
+                    if object.shape == "point" {
+                        self.markers.push(object);
+                    } else if layer_is_trigger || is_trigger(&object) {
+                        self.triggers.push(object);
+                    } else {
+                        self.colliders.push(object);
+                    }
+                }
+            }
+            "group" => {
+                for child in layer.layers {
+                    self.add_layer(child)?;
+                }
+            }
+            // Image layers carry nothing the engine needs
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Names of all tile layers, in drawing order
+    pub fn layer_names(&self) -> Vec<String> {
+        self.layers.iter().map(|layer| layer.name.clone()).collect()
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&TileLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    /// Walkability grid where any tile in one of the given layers blocks movement
+    pub fn walkable_map(&self, blocking_layers: &[&TileLayer]) -> Vec<Vec<bool>> {
+        let mut walkable = vec![vec![true; self.width]; self.height];
+        for layer in blocking_layers {
+            for (index, &gid) in layer.gids.iter().enumerate() {
+                let (x, y) = (index % layer.width, index / layer.width);
+                if gid != 0 && x < self.width && y < self.height {
+                    walkable[y][x] = false;
+                }
+            }
+        }
+        walkable
+    }
+}
+
+fn is_trigger(object: &TiledObject) -> bool {
+    object.kind.eq_ignore_ascii_case("trigger")
+        || object.properties.get("trigger") == Some(&PropertyValue::Bool(true))
+}
+
+fn convert_object(raw: RawObject, layer: &str) -> TiledObject {
+    let (shape, points) = if let Some(polygon) = &raw.polygon {
+        ("polygon", polygon)
+    } else if let Some(polyline) = &raw.polyline {
+        ("polyline", polyline)
+    } else if raw.point {
+        ("point", &Vec::new())
+    } else if raw.ellipse {
+        ("ellipse", &Vec::new())
+    } else {
+        ("rect", &Vec::new())
+    };
+    let points = points.iter().map(|p| (raw.x + p.x, raw.y + p.y)).collect();
+
+    // Tile objects are anchored at their bottom-left corner
+    let y = if raw.gid.is_some() {
+        raw.y - raw.height
+    } else {
+        raw.y
+    };
+
+    TiledObject {
+        id: raw.id,
+        name: raw.name,
+        kind: if raw.class.is_empty() {
+            raw.kind
+        } else {
+            raw.class
+        },
+        shape: shape.to_string(),
+        x: raw.x,
+        y,
+        width: raw.width,
+        height: raw.height,
+        rotation: raw.rotation,
+        points,
+        layer: layer.to_string(),
+        properties: convert_properties(raw.properties),
+    }
+}
+
+/// Decode a tile layer's data, either a plain array or base64 with optional
+/// compression; compressed data is only inflated as far as `tiles` gids need
+fn decode_layer_data(layer: &RawLayer, tiles: usize) -> Result<Vec<u32>, String> {
+    match &layer.data {
+        None => Ok(Vec::new()),
+        Some(Value::Array(values)) => values
+            .iter()
+            .map(|value| {
+                value
+                    .as_u64()
+                    .map(|gid| gid as u32 & GID_MASK)
+                    .ok_or_else(|| {
+                        format!("tile layer '{}' contains a non-integer gid", layer.name)
+                    })
+            })
+            .collect(),
+        Some(Value::String(encoded)) => {
+            if layer.encoding.as_deref() != Some("base64") {
+                return Err(format!(
+                    "tile layer '{}' uses an unsupported encoding",
+                    layer.name
+                ));
+            }
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(encoded.trim())
+                .map_err(|e| {
+                    format!("tile layer '{}' has invalid base64 data: {}", layer.name, e)
+                })?;
+            // Within MAX_TILES, so this cannot overflow
+            let expected = tiles * 4;
+            let bytes = decompress(&bytes, layer.compression.as_deref().unwrap_or(""), expected)
+                .map_err(|e| format!("tile layer '{}': {}", layer.name, e))?;
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) & GID_MASK)
+                .collect())
+        }
+        Some(_) => Err(format!("tile layer '{}' has malformed data", layer.name)),
+    }
+}
+
+/// Inflate tile data that should come to exactly `expected` bytes, reading no
+/// more than one byte past that, so a tiny file cannot expand to gigabytes
+fn decompress(bytes: &[u8], compression: &str, expected: usize) -> Result<Vec<u8>, String> {
+    let limit = expected as u64 + 1;
+    let mut out = Vec::new();
+    let result = match compression {
+        "" => return Ok(bytes.to_vec()),
+        "zlib" => flate2::read::ZlibDecoder::new(bytes)
+            .take(limit)
+            .read_to_end(&mut out),
+        "gzip" => flate2::read::GzDecoder::new(bytes)
+            .take(limit)
+            .read_to_end(&mut out),
+        other => return Err(format!("unsupported compression '{}'", other)),
+    };
+    result.map_err(|e| format!("failed to decompress tile data: {}", e))?;
+    if out.len() != expected {
+        return Err(format!(
+            "tile data inflates to {} bytes, expected {}",
+            if out.len() > expected {
+                format!("more than {}", expected)
+            } else {
+                out.len().to_string()
+            },
+            expected
+        ));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn gids(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|gid| gid.to_le_bytes()).collect()
+    }
+
+    fn zlib(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn base64_map(data: &[u8], compression: &str) -> String {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+        format!(
+            r#"{{"width": 2, "height": 1, "tilewidth": 16, "tileheight": 16, "layers": [
+                {{"type": "tilelayer", "name": "ground", "encoding": "base64",
+                  "compression": "{}", "data": "{}"}}]}}"#,
+            compression, encoded
+        )
+    }
+
+    #[test]
+    fn tile_layers_strip_flip_flags_and_block_movement() {
+        let json = r#"{"width": 3, "height": 2, "tilewidth": 16, "tileheight": 16,
+            "properties": [{"name": "music", "value": "cave"}],
+            "layers": [{"type": "group", "layers": [
+                {"type": "tilelayer", "name": "walls", "data": [0, 2147483649, 0, 0, 0, 3]}
+            ]}]}"#;
+        let map = TiledMap::parse(json).unwrap();
+        assert_eq!(map.orientation, "orthogonal");
+        assert_eq!(
+            map.properties["music"],
+            PropertyValue::Str("cave".to_string())
+        );
+        let walls = map.layer("walls").unwrap();
+        assert_eq!(walls.rows(), [[0, 1, 0], [0, 0, 3]]);
+        assert_eq!(
+            map.walkable_map(&[walls]),
+            [[true, false, true], [true, true, false]]
+        );
+        assert_eq!(map.layer_names(), ["walls"]);
+    }
+
+    #[test]
+    fn objects_are_sorted_into_colliders_triggers_and_markers() {
+        let json = r#"{"width": 1, "height": 1, "tilewidth": 16, "tileheight": 16,
+            "layers": [{"type": "objectgroup", "name": "things", "objects": [
+                {"id": 1, "x": 0, "y": 0, "width": 8, "height": 8},
+                {"id": 2, "x": 4, "y": 4, "class": "Trigger"},
+                {"id": 3, "x": 1, "y": 2, "point": true},
+                {"id": 4, "x": 10, "y": 10, "polygon": [{"x": 0, "y": 0}, {"x": 2, "y": 1}],
+                 "properties": [{"name": "trigger", "value": true}]},
+                {"id": 5, "x": 0, "y": 20, "width": 16, "height": 16, "gid": 7}
+            ]}]}"#;
+        let map = TiledMap::parse(json).unwrap();
+        let ids = |objects: &[TiledObject]| objects.iter().map(|o| o.id).collect::<Vec<_>>();
+        assert_eq!(ids(&map.colliders), [1, 5]);
+        assert_eq!(ids(&map.triggers), [2, 4]);
+        assert_eq!(ids(&map.markers), [3]);
+        assert_eq!(map.triggers[1].points, [(10.0, 10.0), (12.0, 11.0)]);
+        // Tile objects are anchored at their bottom-left corner
+        assert_eq!(map.colliders[1].bounds(), Rect::new(0.0, 4.0, 16.0, 16.0));
+    }
+
+    #[test]
+    fn compressed_data_must_inflate_to_the_layer_size() {
+        let map = TiledMap::parse(&base64_map(&zlib(&gids(&[5, 6])), "zlib")).unwrap();
+        assert_eq!(map.layers[0].gids, [5, 6]);
+        let short = TiledMap::parse(&base64_map(&zlib(&gids(&[5])), "zlib"));
+        assert!(short
+            .unwrap_err()
+            .contains("inflates to 4 bytes, expected 8"));
+        let bomb = TiledMap::parse(&base64_map(&zlib(&[0; 4096]), "zlib"));
+        assert!(bomb.unwrap_err().contains("more than 8"));
+    }
+
+    #[test]
+    fn unsupported_maps_are_refused() {
+        let infinite = r#"{"width": 1, "height": 1, "tilewidth": 1, "tileheight": 1,
+            "infinite": true}"#;
+        assert!(TiledMap::parse(infinite).unwrap_err().contains("infinite"));
+        let huge = r#"{"width": 100000, "height": 100000, "tilewidth": 1, "tileheight": 1}"#;
+        assert!(TiledMap::parse(huge).unwrap_err().contains("more than"));
+        assert!(TiledMap::parse("[]")
+            .unwrap_err()
+            .starts_with("invalid Tiled JSON"));
+        let lz4 = TiledMap::parse(&base64_map(&gids(&[1, 2]), "zstd"));
+        assert!(lz4.unwrap_err().contains("unsupported compression 'zstd'"));
+    }
+}
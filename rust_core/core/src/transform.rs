@@ -0,0 +1,98 @@
+//! Whole-grid edits for map tools: quarter-turn rotation, mirroring, cropping and
+//! pasting. Grids are `[y][x]` rows of any cell type.
+
+use crate::grid;
+
+/// Rotate a grid clockwise by a number of quarter turns
+pub fn rotate<T: Clone>(cells: &[Vec<T>], quarter_turns: u32) -> Vec<Vec<T>> {
+    let (width, height) = grid::dimensions(cells);
+    match quarter_turns % 4 {
+        0 => cells.to_vec(),
+        1 => (0..width)
+            .map(|y| {
+                (0..height)
+                    .map(|x| cells[height - 1 - x][y].clone())
+                    .collect()
+            })
+            .collect(),
+        2 => cells
+            .iter()
+            .rev()
+            .map(|row| row.iter().rev().cloned().collect())
+            .collect(),
+        _ => (0..width)
+            .map(|y| {
+                (0..height)
+                    .map(|x| cells[x][width - 1 - y].clone())
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+/// Mirror a grid left-to-right and/or top-to-bottom
+pub fn flip<T: Clone>(cells: &[Vec<T>], horizontal: bool, vertical: bool) -> Vec<Vec<T>> {
+    let mirror_row = |row: &Vec<T>| -> Vec<T> {
+        if horizontal {
+            row.iter().rev().cloned().collect()
+        } else {
+            row.clone()
+        }
+    };
+    if vertical {
+        cells.iter().rev().map(mirror_row).collect()
+    } else {
+        cells.iter().map(mirror_row).collect()
+    }
+}
+
+/// Cut out a rectangle, clipped to the grid bounds
+pub fn crop<T: Clone>(
+    cells: &[Vec<T>],
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> Vec<Vec<T>> {
+    let (grid_width, grid_height) = grid::dimensions(cells);
+    let right = x.saturating_add(width).min(grid_width);
+    let bottom = y.saturating_add(height).min(grid_height);
+    if x >= right || y >= bottom {
+        return Vec::new();
+    }
+    cells[y..bottom]
+        .iter()
+        .map(|row| row[x..right].to_vec())
+        .collect()
+}
+
+/// Copy `source` onto `target` with its top-left corner at `(x, y)`.
+///
+/// Cells falling outside the target are dropped, and when a mask is given only
+/// source cells whose mask entry is `true` are written.
+pub fn paste<T: Clone>(
+    target: &mut [Vec<T>],
+    source: &[Vec<T>],
+    x: isize,
+    y: isize,
+    mask: Option<&[Vec<bool>]>,
+) {
+    let (width, height) = grid::dimensions(target);
+    for (source_y, row) in source.iter().enumerate() {
+        for (source_x, cell) in row.iter().enumerate() {
+            if mask.is_some_and(|mask| !grid::flag(mask, source_x, source_y)) {
+                continue;
+            }
+            let target_x = x + source_x as isize;
+            let target_y = y + source_y as isize;
+            if target_x < 0
+                || target_y < 0
+                || target_x >= width as isize
+                || target_y >= height as isize
+            {
+                continue;
+            }
+            target[target_y as usize][target_x as usize] = cell.clone();
+        }
+    }
+}
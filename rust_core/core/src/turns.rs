@@ -0,0 +1,258 @@
+//! Energy-based turn order for roguelike actors.
+//!
+//! Every game tick each actor gains its speed in energy, and whoever has reached the
+//! threshold gets to act. Time is skipped straight to the next tick on which someone
+//! can act, so slow actors cost nothing while they wait.
+
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Actor {
+    pub speed: u32,
+    pub energy: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Scheduler {
+    /// Keyed by id, which is also insertion order
+    actors: BTreeMap<u64, Actor>,
+    next_id: u64,
+    threshold: i64,
+    time: u64,
+}
+
+impl Scheduler {
+    /// A scheduler with no actors; `threshold` must be positive
+    pub fn new(threshold: i64) -> Self {
+        Scheduler::from_parts(threshold, 0, 0, BTreeMap::new())
+    }
+
+    /// A scheduler as read back from its accessors; `next_id` must exceed every id in
+    /// `actors`
+    pub fn from_parts(
+        threshold: i64,
+        time: u64,
+        next_id: u64,
+        actors: BTreeMap<u64, Actor>,
+    ) -> Self {
+        Scheduler {
+            actors,
+            next_id,
+            threshold,
+            time,
+        }
+    }
+
+    /// Energy needed to act, and the default cost of an action
+    pub fn threshold(&self) -> i64 {
+        self.threshold
+    }
+
+    /// Game ticks elapsed
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// The id the next added actor will get
+    pub fn next_id(&self) -> u64 {
+        self.next_id
+    }
+
+    /// Actors by id, in the order they were added
+    pub fn actors(&self) -> impl ExactSizeIterator<Item = (u64, &Actor)> {
+        self.actors.iter().map(|(&id, actor)| (id, actor))
+    }
+
+    pub fn actor(&self, id: u64) -> Option<&Actor> {
+        self.actors.get(&id)
+    }
+
+    pub fn actor_mut(&mut self, id: u64) -> Option<&mut Actor> {
+        self.actors.get_mut(&id)
+    }
+
+    /// Add an actor and return its id; it can be added at any point, even mid-turn
+    pub fn add(&mut self, speed: u32, energy: i64) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.actors.insert(id, Actor { speed, energy });
+        id
+    }
+
+    /// Remove an actor, returning whether it was scheduled
+    pub fn remove(&mut self, id: u64) -> bool {
+        self.actors.remove(&id).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.actors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actors.is_empty()
+    }
+
+    /// The ready actor with the most energy, the lowest id among equals
+    fn ready(&self) -> Option<u64> {
+        self.actors
+            .iter()
+            .filter(|(_, actor)| actor.energy >= self.threshold)
+            // max_by_key keeps the last maximum, so break ties on the lower id explicitly
+            .max_by(|(a_id, a), (b_id, b)| a.energy.cmp(&b.energy).then(b_id.cmp(a_id)))
+            .map(|(&id, _)| id)
+    }
+
+    /// The actor whose turn it is, advancing time as far as needed; `None` when no
+    /// actor can ever act. An actor holding enough energy keeps being returned until
+    /// it spends.
+    pub fn next_actor(&mut self) -> Option<u64> {
+        if let Some(id) = self.ready() {
+            return Some(id);
+        }
+        // Jump straight to the first tick on which someone can act. Nobody is ready,
+        // so every actor is missing at least one point of energy.
+        let ticks = self
+            .actors
+            .values()
+            .filter(|actor| actor.speed > 0)
+            .map(|actor| {
+                let missing = self.threshold.saturating_sub(actor.energy);
+                (missing - 1) / i64::from(actor.speed) + 1
+            })
+            .min()?;
+        for actor in self.actors.values_mut() {
+            let gained = i64::from(actor.speed).saturating_mul(ticks);
+            actor.energy = actor.energy.saturating_add(gained);
+        }
+        self.time = self.time.saturating_add(ticks as u64);
+        self.ready()
+    }
+
+    /// Pay `cost` energy for an action, or the threshold if `None`; energy may go
+    /// negative. Returns whether the actor was scheduled.
+    pub fn spend(&mut self, id: u64, cost: Option<i64>) -> bool {
+        let cost = cost.unwrap_or(self.threshold);
+        match self.actors.get_mut(&id) {
+            Some(actor) => {
+                actor.energy = actor.energy.saturating_sub(cost);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The next `count` actors to act, each spending the default cost
+    fn turns(scheduler: &mut Scheduler, count: usize) -> Vec<u64> {
+        (0..count)
+            .map(|_| {
+                let id = scheduler.next_actor().expect("someone can act");
+                scheduler.spend(id, None);
+                id
+            })
+            .collect()
+    }
+
+    #[test]
+    fn energy_accumulates_and_time_skips_to_the_next_turn() {
+        let mut scheduler = Scheduler::new(100);
+        let slow = scheduler.add(30, 0);
+        let fast = scheduler.add(60, 0);
+        // 60 per tick reaches 100 after two ticks, when the slow actor has 60
+        assert_eq!(scheduler.next_actor(), Some(fast));
+        assert_eq!(scheduler.time(), 2);
+        assert_eq!(scheduler.actor(slow).unwrap().energy, 60);
+        assert_eq!(scheduler.actor(fast).unwrap().energy, 120);
+        // Still ready, so asking again does not advance time
+        assert_eq!(scheduler.next_actor(), Some(fast));
+        assert_eq!(scheduler.time(), 2);
+        // Both reach the threshold two ticks later, and the one with more goes first
+        scheduler.spend(fast, None);
+        assert_eq!(scheduler.next_actor(), Some(fast));
+        assert_eq!(scheduler.time(), 4);
+        assert_eq!(scheduler.actor(slow).unwrap().energy, 120);
+        scheduler.spend(fast, None);
+        assert_eq!(scheduler.next_actor(), Some(slow));
+        assert_eq!(scheduler.time(), 4);
+    }
+
+    #[test]
+    fn double_speed_acts_twice_as_often() {
+        let mut scheduler = Scheduler::new(100);
+        let slow = scheduler.add(50, 0);
+        let fast = scheduler.add(100, 0);
+        let order = turns(&mut scheduler, 30);
+        let count = |id| order.iter().filter(|&&actor| actor == id).count();
+        assert_eq!((count(fast), count(slow)), (20, 10));
+    }
+
+    #[test]
+    fn ties_go_to_more_energy_then_the_lower_id() {
+        let mut scheduler = Scheduler::new(100);
+        let first = scheduler.add(10, 100);
+        let second = scheduler.add(10, 100);
+        let richest = scheduler.add(10, 150);
+        assert_eq!(turns(&mut scheduler, 3), [richest, first, second]);
+    }
+
+    #[test]
+    fn spend_takes_a_custom_cost() {
+        let mut scheduler = Scheduler::new(100);
+        let actor = scheduler.add(10, 100);
+        assert!(scheduler.spend(actor, Some(25)));
+        assert_eq!(scheduler.actor(actor).unwrap().energy, 75);
+        assert!(scheduler.spend(actor, Some(200)));
+        assert_eq!(scheduler.actor(actor).unwrap().energy, -125);
+        assert!(scheduler.spend(actor, Some(-25)));
+        assert_eq!(scheduler.actor(actor).unwrap().energy, -100);
+        assert!(!scheduler.spend(actor + 1, None));
+    }
+
+    #[test]
+    fn actors_can_join_and_leave_mid_turn() {
+        let mut scheduler = Scheduler::new(100);
+        let a = scheduler.add(50, 0);
+        let b = scheduler.add(50, 0);
+        assert_eq!(scheduler.next_actor(), Some(a));
+        // b is also ready; removing it before its turn skips it
+        assert!(scheduler.remove(b));
+        assert!(!scheduler.remove(b));
+        scheduler.spend(a, None);
+        let late = scheduler.add(100, 100);
+        assert_eq!(scheduler.next_actor(), Some(late));
+        assert_eq!(scheduler.time(), 2);
+        scheduler.spend(late, None);
+        assert_eq!(turns(&mut scheduler, 3), [late, a, late]);
+        assert_eq!(scheduler.len(), 2);
+        assert_ne!(scheduler.add(1, 0), b, "ids are never reused");
+    }
+
+    #[test]
+    fn nobody_can_act_without_speed() {
+        let mut scheduler = Scheduler::new(100);
+        assert_eq!(scheduler.next_actor(), None);
+        scheduler.add(0, 99);
+        assert_eq!(scheduler.next_actor(), None);
+        assert_eq!(scheduler.time(), 0);
+    }
+
+    #[test]
+    fn extreme_energy_saturates_instead_of_overflowing() {
+        let mut scheduler = Scheduler::new(i64::MAX);
+        let starved = scheduler.add(1, i64::MIN);
+        let fast = scheduler.add(u32::MAX, 0);
+        assert_eq!(scheduler.next_actor(), Some(fast));
+        assert!(scheduler.actor(starved).unwrap().energy > i64::MIN);
+        for _ in 0..3 {
+            scheduler.spend(fast, Some(i64::MAX));
+        }
+        assert_eq!(scheduler.actor(fast).unwrap().energy, i64::MIN);
+        let rich = scheduler.add(0, i64::MAX - 1);
+        scheduler.spend(rich, Some(-5));
+        assert_eq!(scheduler.actor(rich).unwrap().energy, i64::MAX);
+    }
+}
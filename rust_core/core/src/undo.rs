@@ -0,0 +1,362 @@
+//! Undo history for grid edits, stored as per-cell diffs.
+//!
+//! Each entry is a group of changed cells with their values before and after, so
+//! an edit costs memory in proportion to the cells it touched rather than the map.
+
+use std::collections::{HashMap, VecDeque};
+
+pub type Cell = (usize, usize);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change<T> {
+    pub cell: Cell,
+    pub before: T,
+    pub after: T,
+}
+
+/// Changes that are undone and redone together, such as one brush stroke
+#[derive(Clone, Debug, PartialEq)]
+pub struct Group<T> {
+    pub label: String,
+    /// At most one change per cell, in the order the cells were first edited
+    pub changes: Vec<Change<T>>,
+}
+
+impl<T> Group<T> {
+    /// `(cell, value)` pairs that revert the group, in the order to apply them
+    pub fn undo_values(&self) -> impl Iterator<Item = (Cell, &T)> {
+        self.changes
+            .iter()
+            .rev()
+            .map(|change| (change.cell, &change.before))
+    }
+
+    /// `(cell, value)` pairs that reapply the group, in the order to apply them
+    pub fn redo_values(&self) -> impl Iterator<Item = (Cell, &T)> {
+        self.changes
+            .iter()
+            .map(|change| (change.cell, &change.after))
+    }
+}
+
+/// The group being recorded, with where each of its cells is in `changes`
+struct OpenGroup<T> {
+    group: Group<T>,
+    cells: HashMap<Cell, usize>,
+    depth: usize,
+}
+
+pub struct History<T> {
+    undo: VecDeque<Group<T>>,
+    redo: Vec<Group<T>>,
+    open: Option<OpenGroup<T>>,
+    /// Cell changes held across both stacks
+    stored: usize,
+    /// Oldest groups are dropped once more cell changes than this are stored
+    pub max_changes: usize,
+}
+
+impl<T> History<T> {
+    pub fn new(max_changes: usize) -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            open: None,
+            stored: 0,
+            max_changes,
+        }
+    }
+
+    /// A history holding `undo` groups, oldest first, and `redo` groups, most
+    /// recently undone last, as read back from [`History::undo_groups`] and
+    /// [`History::redo_groups`]
+    pub fn from_groups(max_changes: usize, undo: Vec<Group<T>>, redo: Vec<Group<T>>) -> Self {
+        let stored = undo
+            .iter()
+            .chain(&redo)
+            .map(|group| group.changes.len())
+            .sum();
+        let mut history = History {
+            undo: undo.into(),
+            redo,
+            open: None,
+            stored,
+            max_changes,
+        };
+        history.trim();
+        history
+    }
+
+    /// Groups that can be undone, oldest first
+    pub fn undo_groups(&self) -> impl ExactSizeIterator<Item = &Group<T>> {
+        self.undo.iter()
+    }
+
+    /// Groups that can be redone, the one [`History::redo`] returns last
+    pub fn redo_groups(&self) -> impl ExactSizeIterator<Item = &Group<T>> {
+        self.redo.iter()
+    }
+
+    /// Start a group; groups nest, and only the outermost [`History::end_group`]
+    /// closes it, under the outermost label
+    pub fn begin_group(&mut self, label: &str) {
+        match &mut self.open {
+            Some(open) => open.depth += 1,
+            None => {
+                self.open = Some(OpenGroup {
+                    group: Group {
+                        label: label.to_string(),
+                        changes: Vec::new(),
+                    },
+                    cells: HashMap::new(),
+                    depth: 1,
+                })
+            }
+        }
+    }
+
+    /// Close the innermost group, committing the outermost one when it closes.
+    /// Returns `false` if no group was open.
+    pub fn end_group(&mut self) -> bool {
+        let Some(open) = &mut self.open else {
+            return false;
+        };
+        open.depth -= 1;
+        if open.depth == 0 {
+            let open = self.open.take().expect("group is open");
+            self.commit(open.group);
+        }
+        true
+    }
+
+    pub fn is_grouping(&self) -> bool {
+        self.open.is_some()
+    }
+
+    /// Record that `cell` changed from `before` to `after`. Inside a group, editing
+    /// a cell again only updates its final value. Recording clears the redo stack.
+    pub fn record(&mut self, cell: Cell, before: T, after: T) {
+        match &mut self.open {
+            Some(open) => match open.cells.get(&cell) {
+                Some(&index) => open.group.changes[index].after = after,
+                None => {
+                    open.cells.insert(cell, open.group.changes.len());
+                    open.group.changes.push(Change {
+                        cell,
+                        before,
+                        after,
+                    });
+                }
+            },
+            None => self.commit(Group {
+                label: "edit".to_string(),
+                changes: vec![Change {
+                    cell,
+                    before,
+                    after,
+                }],
+            }),
+        }
+    }
+
+    fn commit(&mut self, group: Group<T>) {
+        if group.changes.is_empty() {
+            return;
+        }
+        self.stored -= self
+            .redo
+            .drain(..)
+            .map(|group| group.changes.len())
+            .sum::<usize>();
+        self.stored += group.changes.len();
+        self.undo.push_back(group);
+        self.trim();
+    }
+
+    /// Drop the oldest groups until the limit is met, always keeping the newest
+    fn trim(&mut self) {
+        while self.stored > self.max_changes && self.undo.len() > 1 {
+            let dropped = self.undo.pop_front().expect("undo stack has groups");
+            self.stored -= dropped.changes.len();
+        }
+    }
+
+    /// Move the newest group to the redo stack and return it, for the caller to
+    /// apply its [`Group::undo_values`]; `None` if there is nothing to undo or a
+    /// group is still open
+    pub fn undo(&mut self) -> Option<&Group<T>> {
+        if self.open.is_some() {
+            return None;
+        }
+        let group = self.undo.pop_back()?;
+        self.redo.push(group);
+        self.redo.last()
+    }
+
+    /// Move the most recently undone group back and return it, for the caller to
+    /// apply its [`Group::redo_values`]
+    pub fn redo(&mut self) -> Option<&Group<T>> {
+        if self.open.is_some() {
+            return None;
+        }
+        let group = self.redo.pop()?;
+        self.undo.push_back(group);
+        self.undo.back()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.open.is_none() && !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.open.is_none() && !self.redo.is_empty()
+    }
+
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo.back().map(|group| group.label.as_str())
+    }
+
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo.last().map(|group| group.label.as_str())
+    }
+
+    /// Number of groups that can be undone
+    pub fn len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.undo.is_empty()
+    }
+
+    /// Cell changes held for undo and redo together
+    pub fn stored_changes(&self) -> usize {
+        self.stored
+    }
+
+    /// Change the limit, dropping old groups if it is now exceeded
+    pub fn set_max_changes(&mut self, max_changes: usize) {
+        self.max_changes = max_changes;
+        self.trim();
+    }
+
+    /// Forget everything, including any open group
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = None;
+        self.stored = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values<'a>(pairs: impl Iterator<Item = (Cell, &'a u8)>) -> Vec<(Cell, u8)> {
+        pairs.map(|(cell, &value)| (cell, value)).collect()
+    }
+
+    #[test]
+    fn nested_groups_commit_once_under_the_outer_label() {
+        let mut history = History::new(100);
+        history.begin_group("stroke");
+        history.record((0, 0), 0, 1);
+        history.begin_group("inner");
+        history.record((1, 0), 0, 2);
+        // Editing a cell again only moves its final value
+        history.record((0, 0), 1, 3);
+        assert!(history.end_group());
+        assert!(history.is_grouping());
+        assert!(!history.can_undo(), "nothing can be undone mid-group");
+        assert!(history.undo().is_none());
+        assert!(history.end_group());
+        assert!(!history.end_group());
+
+        assert_eq!((history.len(), history.stored_changes()), (1, 2));
+        assert_eq!(history.undo_label(), Some("stroke"));
+        let group = history.undo().unwrap();
+        assert_eq!(values(group.undo_values()), [((1, 0), 0), ((0, 0), 0)]);
+        assert_eq!(values(group.redo_values()), [((0, 0), 3), ((1, 0), 2)]);
+        assert_eq!(history.redo_label(), Some("stroke"));
+        assert_eq!(history.redo().map(|group| group.changes.len()), Some(2));
+        assert_eq!(history.stored_changes(), 2);
+    }
+
+    #[test]
+    fn empty_groups_are_not_kept() {
+        let mut history: History<u8> = History::new(10);
+        history.begin_group("nothing");
+        history.end_group();
+        assert!(history.is_empty());
+        assert_eq!(history.undo_label(), None);
+    }
+
+    #[test]
+    fn recording_drops_the_redo_stack_from_the_count() {
+        let mut history = History::new(100);
+        history.record((0, 0), 0, 1);
+        history.begin_group("pair");
+        history.record((1, 1), 0, 1);
+        history.record((2, 2), 0, 1);
+        history.end_group();
+        history.undo();
+        history.undo();
+        assert!(history.can_redo());
+        assert_eq!(history.stored_changes(), 3);
+        history.record((3, 3), 0, 1);
+        assert!(!history.can_redo());
+        assert_eq!((history.len(), history.stored_changes()), (1, 1));
+        assert_eq!(history.undo_label(), Some("edit"));
+    }
+
+    #[test]
+    fn trimming_drops_the_oldest_groups_but_keeps_the_newest() {
+        let mut history = History::new(3);
+        for x in 0..3 {
+            history.record((x, 0), 0, 1);
+        }
+        assert_eq!((history.len(), history.stored_changes()), (3, 3));
+        history.record((9, 0), 0, 1);
+        assert_eq!((history.len(), history.stored_changes()), (3, 3));
+        assert_eq!(
+            history.undo_groups().next().unwrap().changes[0].cell,
+            (1, 0)
+        );
+
+        // A single group over the limit is still kept, on its own
+        history.begin_group("flood");
+        for x in 0..5 {
+            history.record((x, 1), 0, 1);
+        }
+        history.end_group();
+        assert_eq!((history.len(), history.stored_changes()), (1, 5));
+
+        history.set_max_changes(0);
+        assert_eq!(history.len(), 1);
+        history.clear();
+        assert_eq!((history.len(), history.stored_changes()), (0, 0));
+    }
+
+    #[test]
+    fn rebuilt_histories_count_both_stacks_and_trim() {
+        let group = |label: &str, cells: usize| Group {
+            label: label.to_string(),
+            changes: (0..cells)
+                .map(|x| Change {
+                    cell: (x, 0),
+                    before: 0u8,
+                    after: 1,
+                })
+                .collect(),
+        };
+        let history =
+            History::from_groups(10, vec![group("a", 2), group("b", 3)], vec![group("c", 4)]);
+        assert_eq!((history.len(), history.stored_changes()), (2, 9));
+        assert_eq!(history.redo_groups().len(), 1);
+        let trimmed =
+            History::from_groups(6, vec![group("a", 2), group("b", 3)], vec![group("c", 4)]);
+        assert_eq!((trimmed.len(), trimmed.stored_changes()), (1, 7));
+        assert_eq!(trimmed.undo_label(), Some("b"));
+    }
+}
@@ -0,0 +1,316 @@
+//! Utility AI: actions scored from response curves over normalised inputs, so
+//! agents pick whatever currently matters most to them.
+
+use std::collections::HashMap;
+
+use crate::rng::Rng;
+
+/// Shape of a response curve mapping a normalised input to a score
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CurveKind {
+    /// `slope * (x - x_shift) + y_shift`
+    Linear,
+    /// `slope * (x - x_shift)^exponent + y_shift`
+    Polynomial,
+    /// S-curve centred on `x_shift`, steepness `slope`
+    Logistic,
+    /// 1 once `x >= x_shift`, otherwise 0
+    Step,
+}
+
+impl CurveKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(CurveKind::Linear),
+            "polynomial" | "quadratic" => Some(CurveKind::Polynomial),
+            "logistic" => Some(CurveKind::Logistic),
+            "step" => Some(CurveKind::Step),
+            _ => None,
+        }
+    }
+}
+
+/// A response curve over one input of an action
+#[derive(Clone, Debug, PartialEq)]
+pub struct Consideration {
+    /// Index of the input in each agent's vector
+    pub input: usize,
+    pub kind: CurveKind,
+    pub slope: f32,
+    pub exponent: f32,
+    pub x_shift: f32,
+    pub y_shift: f32,
+    pub invert: bool,
+}
+
+impl Consideration {
+    /// Score one input, clamping both the input and the result to `[0, 1]`
+    pub fn score(&self, value: f32) -> f32 {
+        let x = value.clamp(0.0, 1.0);
+        let y = match self.kind {
+            CurveKind::Linear => self.slope * (x - self.x_shift) + self.y_shift,
+            CurveKind::Polynomial => {
+                self.slope * (x - self.x_shift).abs().powf(self.exponent) + self.y_shift
+            }
+            CurveKind::Logistic => {
+                1.0 / (1.0 + (-self.slope * (x - self.x_shift)).exp()) + self.y_shift
+            }
+            CurveKind::Step => {
+                if x >= self.x_shift {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        };
+        let y = if y.is_finite() {
+            y.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        if self.invert {
+            1.0 - y
+        } else {
+            y
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Action {
+    pub name: String,
+    pub weight: f32,
+    pub considerations: Vec<Consideration>,
+}
+
+impl Action {
+    /// Weighted product of the consideration scores.
+    ///
+    /// Multiplying many scores below 1 drags actions with more considerations down,
+    /// so each score is compensated by how many others it is multiplied with.
+    pub fn score(&self, inputs: &[f32]) -> f32 {
+        if self.considerations.is_empty() {
+            return self.weight;
+        }
+        let modification = 1.0 - 1.0 / self.considerations.len() as f32;
+        let mut total = 1.0;
+        for consideration in &self.considerations {
+            let score = consideration.score(inputs[consideration.input]);
+            total *= score + (1.0 - score) * modification * score;
+            if total == 0.0 {
+                break;
+            }
+        }
+        total * self.weight
+    }
+}
+
+/// Scores actions for many agents at once from per-agent input vectors.
+///
+/// Inputs are named when the evaluator is created and each agent supplies one
+/// vector of values in that order, normalised to `[0, 1]`.
+#[derive(Clone, Debug)]
+pub struct UtilityEvaluator {
+    /// Index of each named input
+    pub inputs: HashMap<String, usize>,
+    pub input_count: usize,
+    pub actions: Vec<Action>,
+    pub rng: Rng,
+}
+
+impl UtilityEvaluator {
+    /// An evaluator with no actions, or `None` if the input names are not unique
+    pub fn new(inputs: Vec<String>, seed: u64) -> Option<Self> {
+        let input_count = inputs.len();
+        let names: HashMap<String, usize> = inputs
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| (name, index))
+            .collect();
+        (names.len() == input_count).then(|| UtilityEvaluator {
+            inputs: names,
+            input_count,
+            actions: Vec::new(),
+            rng: Rng::new(seed),
+        })
+    }
+
+    /// Register an action, returning `false` if one with that name already exists
+    pub fn add_action(&mut self, name: String, weight: f32) -> bool {
+        if self.actions.iter().any(|action| action.name == name) {
+            return false;
+        }
+        self.actions.push(Action {
+            name,
+            weight,
+            considerations: Vec::new(),
+        });
+        true
+    }
+
+    pub fn action_mut(&mut self, name: &str) -> Option<&mut Action> {
+        self.actions.iter_mut().find(|action| action.name == name)
+    }
+
+    pub fn action_names(&self) -> Vec<String> {
+        self.actions
+            .iter()
+            .map(|action| action.name.clone())
+            .collect()
+    }
+
+    /// The first agent whose input vector has the wrong length, with that length
+    pub fn mismatched_inputs(&self, inputs: &[Vec<f32>]) -> Option<(usize, usize)> {
+        inputs
+            .iter()
+            .position(|row| row.len() != self.input_count)
+            .map(|agent| (agent, inputs[agent].len()))
+    }
+
+    /// Score every action for every agent, one row per agent in action order
+    pub fn score_rows(&self, inputs: &[Vec<f32>]) -> Vec<Vec<f32>> {
+        inputs
+            .iter()
+            .map(|row| {
+                self.actions
+                    .iter()
+                    .map(|action| action.score(row))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Pick an action index from one agent's scores, sampling a softmax when `temperature` is set
+    pub fn choose(&mut self, scores: &[f32], temperature: Option<f32>) -> Option<usize> {
+        let best = scores.iter().enumerate().fold(
+            None,
+            |best: Option<(usize, f32)>, (index, &score)| match best {
+                Some((_, best_score)) if best_score >= score => best,
+                _ => Some((index, score)),
+            },
+        )?;
+        let temperature = match temperature {
+            Some(t) if t > 0.0 => t,
+            _ => return Some(best.0),
+        };
+
+        // Subtracting the maximum keeps the exponentials from overflowing
+        let weights: Vec<f32> = scores
+            .iter()
+            .map(|&score| ((score - best.1) / temperature).exp())
+            .collect();
+        let mut pick = self.rng.next_f32() * weights.iter().sum::<f32>();
+        for (index, weight) in weights.iter().enumerate() {
+            if pick < *weight {
+                return Some(index);
+            }
+            pick -= weight;
+        }
+        Some(best.0)
+    }
+
+    /// The chosen action name for every agent; see [`UtilityEvaluator::choose`]
+    pub fn best_actions(
+        &mut self,
+        inputs: &[Vec<f32>],
+        temperature: Option<f32>,
+    ) -> Vec<Option<String>> {
+        let scores = self.score_rows(inputs);
+        scores
+            .iter()
+            .map(|row| {
+                self.choose(row, temperature)
+                    .map(|index| self.actions[index].name.clone())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(kind: CurveKind, input: usize) -> Consideration {
+        Consideration {
+            input,
+            kind,
+            slope: 1.0,
+            exponent: 2.0,
+            x_shift: 0.0,
+            y_shift: 0.0,
+            invert: false,
+        }
+    }
+
+    /// Eat when hungry, otherwise rest when tired
+    fn camp() -> UtilityEvaluator {
+        let inputs = vec!["hunger".to_string(), "fatigue".to_string()];
+        let mut evaluator = UtilityEvaluator::new(inputs, 7).unwrap();
+        assert!(evaluator.add_action("eat".to_string(), 1.0));
+        assert!(evaluator.add_action("rest".to_string(), 0.8));
+        evaluator
+            .action_mut("eat")
+            .unwrap()
+            .considerations
+            .push(curve(CurveKind::Linear, 0));
+        evaluator
+            .action_mut("rest")
+            .unwrap()
+            .considerations
+            .push(curve(CurveKind::Polynomial, 1));
+        evaluator
+    }
+
+    #[test]
+    fn curves_clamp_and_invert() {
+        let mut linear = curve(CurveKind::Linear, 0);
+        linear.slope = 2.0;
+        assert_eq!(linear.score(0.75), 1.0);
+        assert_eq!(linear.score(-1.0), 0.0);
+        linear.invert = true;
+        assert_eq!(linear.score(0.25), 0.5);
+        let mut step = curve(CurveKind::Step, 0);
+        step.x_shift = 0.5;
+        assert_eq!((step.score(0.4), step.score(0.5)), (0.0, 1.0));
+        assert_eq!(
+            CurveKind::from_name("quadratic"),
+            Some(CurveKind::Polynomial)
+        );
+    }
+
+    #[test]
+    fn the_best_action_wins_without_a_temperature() {
+        let mut evaluator = camp();
+        let inputs = vec![vec![0.9, 0.2], vec![0.1, 1.0]];
+        let scores = evaluator.score_rows(&inputs);
+        assert_eq!(scores[0][0], 0.9);
+        assert!((scores[1][1] - 0.8).abs() < 1e-6);
+        assert_eq!(
+            evaluator.best_actions(&inputs, None),
+            [Some("eat".to_string()), Some("rest".to_string())]
+        );
+    }
+
+    #[test]
+    fn sampling_follows_the_scores() {
+        let mut evaluator = camp();
+        let row = [1.0, 0.0];
+        let eats = (0..200)
+            .filter(|_| evaluator.choose(&row, Some(0.1)) == Some(0))
+            .count();
+        assert!(eats > 180, "eat was chosen {} times", eats);
+        assert_eq!(evaluator.choose(&[], Some(1.0)), None);
+    }
+
+    #[test]
+    fn names_and_input_lengths_are_checked() {
+        let mut evaluator = camp();
+        assert!(!evaluator.add_action("eat".to_string(), 2.0));
+        assert!(UtilityEvaluator::new(vec!["a".to_string(), "a".to_string()], 0).is_none());
+        assert_eq!(
+            evaluator.mismatched_inputs(&[vec![0.0; 2], vec![0.0]]),
+            Some((1, 1))
+        );
+        assert_eq!(evaluator.action_names(), ["eat", "rest"]);
+    }
+}
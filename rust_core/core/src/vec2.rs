@@ -0,0 +1,140 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// A 2D vector of `f32` components
+#[cfg_attr(feature = "python", pyo3::pyclass)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Vec2 {
+    pub const ZERO: Vec2 = Vec2 { x: 0.0, y: 0.0 };
+
+    pub fn new(x: f32, y: f32) -> Self {
+        Vec2 { x, y }
+    }
+
+    /// Unit vector pointing at `radians` anticlockwise from +x
+    pub fn from_angle(radians: f32) -> Vec2 {
+        Vec2::new(radians.cos(), radians.sin())
+    }
+
+    pub fn dot(self, other: Vec2) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Z component of the 3D cross product, positive when `other` is counter-clockwise
+    pub fn cross(self, other: Vec2) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn length(self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    /// Unit vector in the same direction, or zero for a zero-length vector
+    pub fn normalize(self) -> Vec2 {
+        let length = self.length();
+        if length > f32::EPSILON {
+            self * (1.0 / length)
+        } else {
+            Vec2::ZERO
+        }
+    }
+
+    /// Scale the vector down so its length does not exceed `max_length`
+    pub fn truncate(self, max_length: f32) -> Vec2 {
+        let length_squared = self.length_squared();
+        if length_squared > max_length * max_length {
+            self * (max_length / length_squared.sqrt())
+        } else {
+            self
+        }
+    }
+
+    pub fn distance(self, other: Vec2) -> f32 {
+        (other - self).length()
+    }
+
+    pub fn distance_squared(self, other: Vec2) -> f32 {
+        (other - self).length_squared()
+    }
+
+    /// The vector turned anticlockwise by `radians`
+    pub fn rotate(self, radians: f32) -> Vec2 {
+        let (sin, cos) = radians.sin_cos();
+        Vec2::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+
+    /// The point `t` of the way from this vector to `other`; `t` is not clamped
+    pub fn lerp(self, other: Vec2, t: f32) -> Vec2 {
+        self + (other - self) * t
+    }
+
+    /// Direction in radians anticlockwise from +x, in `[-pi, pi]`
+    pub fn angle(self) -> f32 {
+        self.y.atan2(self.x)
+    }
+
+    /// Signed angle in radians that turns this vector's direction onto `other`'s
+    pub fn angle_to(self, other: Vec2) -> f32 {
+        self.cross(other).atan2(self.dot(other))
+    }
+}
+
+impl From<(f32, f32)> for Vec2 {
+    fn from((x, y): (f32, f32)) -> Self {
+        Vec2 { x, y }
+    }
+}
+
+impl From<Vec2> for (f32, f32) {
+    fn from(v: Vec2) -> Self {
+        (v.x, v.y)
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Vec2;
+    fn add(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, other: Vec2) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Vec2;
+    fn sub(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl SubAssign for Vec2 {
+    fn sub_assign(&mut self, other: Vec2) {
+        *self = *self - other;
+    }
+}
+
+impl Mul<f32> for Vec2 {
+    type Output = Vec2;
+    fn mul(self, scale: f32) -> Vec2 {
+        Vec2::new(self.x * scale, self.y * scale)
+    }
+}
+
+impl Neg for Vec2 {
+    type Output = Vec2;
+    fn neg(self) -> Vec2 {
+        Vec2::new(-self.x, -self.y)
+    }
+}
@@ -0,0 +1,149 @@
+//! A water surface made of springs: each column bobs around its rest height and
+//! tugs on its neighbours, so splashes ripple outwards and die down.
+
+use crate::grid;
+use crate::hooks;
+
+/// Spring columns over a row-major grid. A height of 1 gives the usual side-on
+/// strip of columns; larger heights make a 2D surface seen from above.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaterSurface {
+    pub width: usize,
+    pub height: usize,
+    /// Offsets from the rest height
+    pub heights: Vec<f32>,
+    pub velocities: Vec<f32>,
+    /// Spring stiffness pulling each column back to rest
+    pub tension: f32,
+    /// Share of velocity lost per step
+    pub damping: f32,
+    /// How strongly columns pull on their neighbours
+    pub spread: f32,
+    /// Neighbour propagation passes per step; more makes waves travel faster
+    pub passes: usize,
+}
+
+impl WaterSurface {
+    /// A calm surface with every column at rest
+    pub fn new(
+        width: usize,
+        height: usize,
+        tension: f32,
+        damping: f32,
+        spread: f32,
+        passes: usize,
+    ) -> Self {
+        WaterSurface {
+            width,
+            height,
+            heights: vec![0.0; width * height],
+            velocities: vec![0.0; width * height],
+            tension,
+            damping,
+            spread,
+            passes,
+        }
+    }
+
+    /// The index of a column in `heights`, or `None` outside the surface
+    pub fn index(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| y * self.width + x)
+    }
+
+    /// Kick a column with a vertical speed; positive pushes the surface up
+    pub fn splash(&mut self, index: usize, speed: f32) {
+        self.velocities[index] += speed;
+    }
+
+    /// Flatten the surface back to rest
+    pub fn calm(&mut self) {
+        self.heights.fill(0.0);
+        self.velocities.fill(0.0);
+    }
+
+    /// Advance the springs `steps` times, stopping early if cancelled
+    pub fn step(&mut self, steps: usize) {
+        for done in 0..steps {
+            if hooks::cancelled() {
+                break;
+            }
+            hooks::report("step", done as f32 / steps as f32);
+            self.step_once();
+        }
+    }
+
+    pub fn step_once(&mut self) {
+        for (height, velocity) in self.heights.iter_mut().zip(self.velocities.iter_mut()) {
+            *velocity += -self.tension * *height - self.damping * *velocity;
+            *height += *velocity;
+        }
+
+        // The 2D stencil has twice the neighbours of a strip, so share the pull
+        let share = if self.height > 1 && self.width > 1 {
+            0.5
+        } else {
+            1.0
+        };
+        let mut deltas = vec![0.0; self.heights.len()];
+        for _ in 0..self.passes {
+            deltas.fill(0.0);
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let index = y * self.width + x;
+                    for &(dx, dy) in &grid::CARDINAL {
+                        if let Some((nx, ny)) = grid::offset(x, y, dx, dy, self.width, self.height)
+                        {
+                            let neighbour = ny * self.width + nx;
+                            deltas[neighbour] +=
+                                self.spread * (self.heights[index] - self.heights[neighbour]);
+                        }
+                    }
+                }
+            }
+            for (index, delta) in deltas.iter().enumerate() {
+                self.velocities[index] += delta * share;
+                self.heights[index] += delta * share;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip() -> WaterSurface {
+        WaterSurface::new(32, 1, 0.025, 0.025, 0.25, 8)
+    }
+
+    #[test]
+    fn a_splash_ripples_outwards() {
+        let mut surface = strip();
+        surface.splash(16, 1.0);
+        surface.step(3);
+        assert!(surface.heights[16] > 0.0);
+        assert!(surface.heights[12] != 0.0);
+    }
+
+    #[test]
+    fn damping_settles_the_surface() {
+        let mut surface = strip();
+        surface.splash(5, 1.0);
+        surface.step(2000);
+        assert!(surface.heights.iter().all(|height| height.abs() < 1e-3));
+    }
+
+    #[test]
+    fn calm_resets_every_column() {
+        let mut surface = WaterSurface::new(4, 4, 0.025, 0.025, 0.25, 8);
+        surface.splash(surface.index(1, 2).unwrap(), -2.0);
+        surface.step_once();
+        surface.calm();
+        assert!(surface
+            .heights
+            .iter()
+            .chain(&surface.velocities)
+            .all(|&value| value == 0.0));
+        assert_eq!(surface.index(4, 0), None);
+    }
+}
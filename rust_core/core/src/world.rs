@@ -0,0 +1,856 @@
+//! Entities with builtin components, stored as flat per-slot columns.
+//!
+//! Hot component data lives in one column per field (structure of arrays) so the
+//! systems stream through memory; which components a slot has is recorded in its
+//! `flags` bits. Physics, steering and collisions run over the columns directly.
+
+use crate::hooks::{self, Level};
+use crate::msgpack::Value;
+use crate::shapes::{Rect, Shape};
+use crate::steering;
+use crate::vec2::Vec2;
+
+pub type Rgba = (u8, u8, u8, u8);
+
+/// Bits of the per-slot `flags` column
+pub const ALIVE: u8 = 1;
+pub const POSITION: u8 = 1 << 1;
+pub const VELOCITY: u8 = 1 << 2;
+pub const COLLIDER: u8 = 1 << 3;
+/// Collider that is never moved when collisions are resolved
+pub const STATIC: u8 = 1 << 4;
+pub const RENDERABLE: u8 = 1 << 5;
+pub const STEERING: u8 = 1 << 6;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Renderable {
+    pub sprite: u32,
+    pub layer: i32,
+    pub color: Rgba,
+}
+
+/// Arrive-style steering towards an optional target
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Steering {
+    pub max_speed: f32,
+    pub max_force: f32,
+    pub slowing_radius: f32,
+    pub target: Option<Vec2>,
+}
+
+/// Builtin component kinds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Component {
+    Position,
+    Velocity,
+    Collider,
+    Renderable,
+    Steering,
+}
+
+impl Component {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "position" => Some(Component::Position),
+            "velocity" => Some(Component::Velocity),
+            "collider" => Some(Component::Collider),
+            "renderable" => Some(Component::Renderable),
+            "steering" => Some(Component::Steering),
+            _ => None,
+        }
+    }
+
+    pub fn flag(self) -> u8 {
+        match self {
+            Component::Position => POSITION,
+            Component::Velocity => VELOCITY,
+            Component::Collider => COLLIDER,
+            Component::Renderable => RENDERABLE,
+            Component::Steering => STEERING,
+        }
+    }
+}
+
+/// Entity handles pack a slot index in the low 32 bits and the slot's generation
+/// in the high 32, so a handle to a despawned entity never aliases a new one
+pub fn handle(index: usize, generation: u32) -> u64 {
+    ((generation as u64) << 32) | index as u64
+}
+
+/// Names the format of [`World::export`] in its envelope
+pub const EXPORT_SCHEMA: &str = "llamaquest.world";
+/// Bumped on any change to the exported fields that older readers would misread
+pub const EXPORT_VERSION: u64 = 1;
+/// An export only lists live entities, so a slot index is believed only up to this
+/// many slots per listed entity (or `MIN_EXPORT_SLOTS`); that leaves room for a
+/// world thinned out by despawning while stopping a corrupt id from allocating
+/// billions of slots
+const EXPORT_SLOTS_PER_ENTITY: usize = 16;
+const MIN_EXPORT_SLOTS: usize = 4096;
+
+/// Every slot's columns, live or free. Methods taking an `index` expect the slot
+/// of a live entity, as returned by [`World::slot`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct World {
+    pub generations: Vec<u32>,
+    pub flags: Vec<u8>,
+    /// Free slots, reused last-freed first
+    pub free: Vec<usize>,
+    pub xs: Vec<f32>,
+    pub ys: Vec<f32>,
+    pub vxs: Vec<f32>,
+    pub vys: Vec<f32>,
+    pub widths: Vec<f32>,
+    pub heights: Vec<f32>,
+    pub renderables: Vec<Option<Renderable>>,
+    pub steering: Vec<Option<Steering>>,
+    /// Acceleration applied to every moving entity
+    pub gravity: Vec2,
+    /// Fraction of velocity lost per second
+    pub drag: f32,
+}
+
+impl World {
+    /// An empty world with room for `capacity` entity slots before it grows
+    pub fn new(gravity: Vec2, drag: f32, capacity: usize) -> Self {
+        World {
+            generations: Vec::with_capacity(capacity),
+            flags: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            xs: Vec::with_capacity(capacity),
+            ys: Vec::with_capacity(capacity),
+            vxs: Vec::with_capacity(capacity),
+            vys: Vec::with_capacity(capacity),
+            widths: Vec::with_capacity(capacity),
+            heights: Vec::with_capacity(capacity),
+            renderables: Vec::with_capacity(capacity),
+            steering: Vec::with_capacity(capacity),
+            gravity,
+            drag,
+        }
+    }
+
+    /// Slot of a live entity
+    pub fn slot(&self, entity: u64) -> Option<usize> {
+        let index = (entity & 0xFFFF_FFFF) as usize;
+        let generation = (entity >> 32) as u32;
+        (self
+            .flags
+            .get(index)
+            .is_some_and(|flags| flags & ALIVE != 0)
+            && self.generations[index] == generation)
+            .then_some(index)
+    }
+
+    pub fn handle_of(&self, index: usize) -> u64 {
+        handle(index, self.generations[index])
+    }
+
+    pub fn live_slots(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.flags.len()).filter(|&index| self.flags[index] & ALIVE != 0)
+    }
+
+    /// Number of live entities
+    pub fn len(&self) -> usize {
+        self.live_slots().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live_slots().next().is_none()
+    }
+
+    pub fn has(&self, index: usize, component: Component) -> bool {
+        self.flags[index] & component.flag() != 0
+    }
+
+    pub fn set_flag(&mut self, index: usize, flag: u8, on: bool) {
+        if on {
+            self.flags[index] |= flag;
+        } else {
+            self.flags[index] &= !flag;
+        }
+    }
+
+    pub fn position_of(&self, index: usize) -> Option<Vec2> {
+        self.has(index, Component::Position)
+            .then(|| Vec2::new(self.xs[index], self.ys[index]))
+    }
+
+    pub fn velocity_of(&self, index: usize) -> Option<Vec2> {
+        self.has(index, Component::Velocity)
+            .then(|| Vec2::new(self.vxs[index], self.vys[index]))
+    }
+
+    /// Width, height and whether it is static, for entities with a collider
+    pub fn collider_of(&self, index: usize) -> Option<(f32, f32, bool)> {
+        self.has(index, Component::Collider).then(|| {
+            (
+                self.widths[index],
+                self.heights[index],
+                self.flags[index] & STATIC != 0,
+            )
+        })
+    }
+
+    /// Slots that fit before the position, velocity or flags columns reallocate.
+    ///
+    /// Columns of different element sizes grow by different steps, so this is the
+    /// smallest of their capacities rather than that of `flags` alone.
+    pub fn column_capacity(&self) -> usize {
+        [
+            self.xs.capacity(),
+            self.ys.capacity(),
+            self.vxs.capacity(),
+            self.vys.capacity(),
+            self.flags.capacity(),
+        ]
+        .into_iter()
+        .min()
+        .unwrap_or(0)
+    }
+
+    /// Whether the next spawn has to add a slot that does not fit the columns
+    pub fn spawn_reallocates(&self) -> bool {
+        self.free.is_empty() && self.flags.len() >= self.column_capacity()
+    }
+
+    /// A recycled slot, or a new one at the end of the columns
+    fn allocate(&mut self) -> usize {
+        if let Some(index) = self.free.pop() {
+            return index;
+        }
+        if self.flags.len() == self.flags.capacity() {
+            hooks::log(
+                Level::Debug,
+                format_args!(
+                    "world is out of entity slots at {}, reallocating its columns",
+                    self.flags.len()
+                ),
+            );
+        }
+        self.generations.push(0);
+        self.flags.push(0);
+        for column in [
+            &mut self.xs,
+            &mut self.ys,
+            &mut self.vxs,
+            &mut self.vys,
+            &mut self.widths,
+            &mut self.heights,
+        ] {
+            column.push(0.0);
+        }
+        self.renderables.push(None);
+        self.steering.push(None);
+        self.flags.len() - 1
+    }
+
+    /// Create an entity with any of the builtin components and return its handle
+    pub fn spawn(
+        &mut self,
+        position: Option<Vec2>,
+        velocity: Option<Vec2>,
+        collider: Option<(f32, f32)>,
+        is_static: bool,
+        renderable: Option<(u32, i32)>,
+    ) -> u64 {
+        let index = self.allocate();
+        self.flags[index] = ALIVE;
+        let Vec2 { x, y } = position.unwrap_or_default();
+        let Vec2 { x: vx, y: vy } = velocity.unwrap_or_default();
+        let (width, height) = collider.unwrap_or_default();
+        self.xs[index] = x;
+        self.ys[index] = y;
+        self.vxs[index] = vx;
+        self.vys[index] = vy;
+        self.widths[index] = width;
+        self.heights[index] = height;
+        self.set_flag(index, POSITION, position.is_some());
+        self.set_flag(index, VELOCITY, velocity.is_some());
+        self.set_flag(index, COLLIDER, collider.is_some());
+        self.set_flag(index, STATIC, is_static);
+        self.set_flag(index, RENDERABLE, renderable.is_some());
+        self.renderables[index] = renderable.map(|(sprite, layer)| Renderable {
+            sprite,
+            layer,
+            color: (255, 255, 255, 255),
+        });
+        self.steering[index] = None;
+        self.handle_of(index)
+    }
+
+    /// Destroy an entity, returning whether it was alive
+    pub fn despawn(&mut self, entity: u64) -> bool {
+        let Some(index) = self.slot(entity) else {
+            return false;
+        };
+        self.flags[index] = 0;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(index);
+        true
+    }
+
+    /// Handles of every live entity
+    pub fn entities(&self) -> Vec<u64> {
+        self.live_slots()
+            .map(|index| self.handle_of(index))
+            .collect()
+    }
+
+    /// Handle of every slot's current entity, or `None` for free slots
+    pub fn handles(&self) -> Vec<Option<u64>> {
+        (0..self.flags.len())
+            .map(|index| (self.flags[index] & ALIVE != 0).then(|| self.handle_of(index)))
+            .collect()
+    }
+
+    pub fn set_position(&mut self, index: usize, position: Vec2) {
+        self.xs[index] = position.x;
+        self.ys[index] = position.y;
+        self.set_flag(index, POSITION, true);
+    }
+
+    pub fn set_velocity(&mut self, index: usize, velocity: Vec2) {
+        self.vxs[index] = velocity.x;
+        self.vys[index] = velocity.y;
+        self.set_flag(index, VELOCITY, true);
+    }
+
+    pub fn set_collider(&mut self, index: usize, width: f32, height: f32, is_static: bool) {
+        self.widths[index] = width;
+        self.heights[index] = height;
+        self.set_flag(index, COLLIDER, true);
+        self.set_flag(index, STATIC, is_static);
+    }
+
+    pub fn set_renderable(&mut self, index: usize, renderable: Renderable) {
+        self.renderables[index] = Some(renderable);
+        self.set_flag(index, RENDERABLE, true);
+    }
+
+    /// Give an entity steering limits, keeping any target it already had
+    pub fn set_steering(
+        &mut self,
+        index: usize,
+        max_speed: f32,
+        max_force: f32,
+        slowing_radius: f32,
+    ) {
+        let target = self.steering[index].and_then(|steer| steer.target);
+        self.steering[index] = Some(Steering {
+            max_speed,
+            max_force,
+            slowing_radius,
+            target,
+        });
+        self.set_flag(index, STEERING, true);
+    }
+
+    /// Point a steering entity at a target, or `None` to stop steering; returns
+    /// `false` if the entity has no steering component
+    pub fn set_steering_target(&mut self, index: usize, target: Option<Vec2>) -> bool {
+        match &mut self.steering[index] {
+            Some(steer) => {
+                steer.target = target;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Detach a component, returning whether the entity had it
+    pub fn remove_component(&mut self, index: usize, component: Component) -> bool {
+        let had = self.has(index, component);
+        self.set_flag(index, component.flag(), false);
+        match component {
+            Component::Collider => self.set_flag(index, STATIC, false),
+            Component::Renderable => self.renderables[index] = None,
+            Component::Steering => self.steering[index] = None,
+            Component::Position | Component::Velocity => {}
+        }
+        had
+    }
+
+    /// Slots of every live entity with all of `components`
+    pub fn query<'a>(&'a self, components: &'a [Component]) -> impl Iterator<Item = usize> + 'a {
+        self.live_slots()
+            .filter(move |&index| components.iter().all(|&c| self.has(index, c)))
+    }
+
+    /// Handles of entities overlapping `area`: by their collider box if they have
+    /// one, otherwise by their position
+    pub fn entities_in(&self, area: Shape) -> Vec<u64> {
+        self.live_slots()
+            .filter(|&index| {
+                if self.has(index, Component::Collider) {
+                    let bounds = Rect::new(
+                        self.xs[index],
+                        self.ys[index],
+                        self.widths[index],
+                        self.heights[index],
+                    );
+                    area.intersects(Shape::Rect(bounds))
+                } else {
+                    self.position_of(index)
+                        .is_some_and(|position| area.contains(position))
+                }
+            })
+            .map(|index| self.handle_of(index))
+            .collect()
+    }
+
+    /// Copy every column of `other` into this world's storage, which is reused
+    /// wherever it is large enough
+    pub fn restore(&mut self, other: &World) {
+        self.generations.clone_from(&other.generations);
+        self.flags.clone_from(&other.flags);
+        self.free.clone_from(&other.free);
+        self.xs.clone_from(&other.xs);
+        self.ys.clone_from(&other.ys);
+        self.vxs.clone_from(&other.vxs);
+        self.vys.clone_from(&other.vys);
+        self.widths.clone_from(&other.widths);
+        self.heights.clone_from(&other.heights);
+        self.renderables.clone_from(&other.renderables);
+        self.steering.clone_from(&other.steering);
+        self.gravity = other.gravity;
+        self.drag = other.drag;
+    }
+
+    /// Integrate velocities and positions
+    pub fn physics_system(&mut self, delta_time: f32) {
+        let (gx, gy) = (self.gravity.x * delta_time, self.gravity.y * delta_time);
+        let keep = (1.0 - self.drag * delta_time).max(0.0);
+        let moving = ALIVE | POSITION | VELOCITY;
+        let columns = self
+            .flags
+            .iter()
+            .zip(self.xs.iter_mut())
+            .zip(self.ys.iter_mut())
+            .zip(self.vxs.iter_mut())
+            .zip(self.vys.iter_mut());
+        let mut stepped = 0;
+        for ((((&flags, x), y), vx), vy) in columns {
+            if flags & moving == moving {
+                stepped += 1;
+                *vx = (*vx + gx) * keep;
+                *vy = (*vy + gy) * keep;
+                *x += *vx * delta_time;
+                *y += *vy * delta_time;
+            }
+        }
+        hooks::count("bodies_stepped", stepped);
+    }
+
+    /// Accelerate steering entities towards their targets
+    pub fn steering_system(&mut self, delta_time: f32) {
+        let needed = ALIVE | POSITION | VELOCITY | STEERING;
+        for index in 0..self.flags.len() {
+            if self.flags[index] & needed != needed {
+                continue;
+            }
+            let Some(Steering {
+                max_speed,
+                max_force,
+                slowing_radius,
+                target: Some(target),
+            }) = self.steering[index]
+            else {
+                continue;
+            };
+            let position = Vec2::new(self.xs[index], self.ys[index]);
+            let velocity = Vec2::new(self.vxs[index], self.vys[index]);
+            let force =
+                steering::arrive_force(position, velocity, max_speed, target, slowing_radius)
+                    .truncate(max_force);
+            let velocity = (velocity + force * delta_time).truncate(max_speed);
+            self.vxs[index] = velocity.x;
+            self.vys[index] = velocity.y;
+            hooks::count("agents_steered", 1);
+        }
+    }
+
+    /// Overlapping collider pairs as handles, pushed apart first when `resolve` is set
+    pub fn collision_system(&mut self, resolve: bool) -> Vec<(u64, u64)> {
+        let pairs = self.overlapping_pairs();
+        if resolve {
+            for &(a, b) in &pairs {
+                self.resolve(a, b);
+            }
+        }
+        pairs
+            .iter()
+            .map(|&(a, b)| (self.handle_of(a), self.handle_of(b)))
+            .collect()
+    }
+
+    /// Steering, physics and resolved collisions, returning the colliding pairs
+    pub fn step(&mut self, delta_time: f32) -> Vec<(u64, u64)> {
+        self.steering_system(delta_time);
+        self.physics_system(delta_time);
+        self.collision_system(true)
+    }
+
+    /// Overlapping collider pairs as slot indices, lower slot first
+    pub fn overlapping_pairs(&self) -> Vec<(usize, usize)> {
+        let needed = ALIVE | POSITION | COLLIDER;
+        let mut boxes: Vec<usize> = (0..self.flags.len())
+            .filter(|&index| self.flags[index] & needed == needed)
+            .collect();
+        // Sort and sweep along x: only boxes whose x ranges overlap are compared
+        boxes.sort_by(|&a, &b| self.xs[a].total_cmp(&self.xs[b]));
+        let mut pairs = Vec::new();
+        let mut tested = 0;
+        for (i, &a) in boxes.iter().enumerate() {
+            for &b in &boxes[i + 1..] {
+                if self.xs[b] >= self.xs[a] + self.widths[a] {
+                    break;
+                }
+                tested += 1;
+                if self.flags[a] & self.flags[b] & STATIC != 0 {
+                    continue;
+                }
+                if self.ys[a] < self.ys[b] + self.heights[b]
+                    && self.ys[a] + self.heights[a] > self.ys[b]
+                {
+                    pairs.push((a.min(b), a.max(b)));
+                }
+            }
+        }
+        hooks::count("pairs_tested", tested);
+        pairs.sort_unstable();
+        pairs
+    }
+
+    /// Push overlapping boxes apart along the axis of least penetration
+    pub fn resolve(&mut self, a: usize, b: usize) {
+        let overlap_x = (self.xs[a] + self.widths[a]).min(self.xs[b] + self.widths[b])
+            - self.xs[a].max(self.xs[b]);
+        let overlap_y = (self.ys[a] + self.heights[a]).min(self.ys[b] + self.heights[b])
+            - self.ys[a].max(self.ys[b]);
+        if overlap_x <= 0.0 || overlap_y <= 0.0 {
+            return;
+        }
+        let centre = |index: usize| {
+            Vec2::new(
+                self.xs[index] + self.widths[index] * 0.5,
+                self.ys[index] + self.heights[index] * 0.5,
+            )
+        };
+        let (a_centre, b_centre) = (centre(a), centre(b));
+        let (push, along_x) = if overlap_x < overlap_y {
+            let sign = if a_centre.x < b_centre.x { -1.0 } else { 1.0 };
+            (Vec2::new(overlap_x * sign, 0.0), true)
+        } else {
+            let sign = if a_centre.y < b_centre.y { -1.0 } else { 1.0 };
+            (Vec2::new(0.0, overlap_y * sign), false)
+        };
+        let (a_share, b_share) = match (self.flags[a] & STATIC != 0, self.flags[b] & STATIC != 0) {
+            (true, true) => return,
+            (true, false) => (0.0, 1.0),
+            (false, true) => (1.0, 0.0),
+            (false, false) => (0.5, 0.5),
+        };
+        for (index, share) in [(a, a_share), (b, -b_share)] {
+            if share == 0.0 {
+                continue;
+            }
+            self.xs[index] += push.x * share;
+            self.ys[index] += push.y * share;
+            // Stop moving into whatever was hit
+            if self.flags[index] & VELOCITY != 0 {
+                if along_x && self.vxs[index] * push.x * share < 0.0 {
+                    self.vxs[index] = 0.0;
+                } else if !along_x && self.vys[index] * push.y * share < 0.0 {
+                    self.vys[index] = 0.0;
+                }
+            }
+        }
+    }
+
+    /// Every live entity and its components as a MessagePack value, for tools and
+    /// spectators.
+    ///
+    /// The envelope is a map with `schema` ([`EXPORT_SCHEMA`]), `version`,
+    /// `gravity`, `drag` and `entities`. Each entity is a map with its `id` handle
+    /// and whichever of `position`, `velocity`, `collider`, `renderable` and
+    /// `steering` it has.
+    pub fn export(&self) -> Value {
+        let point = |point: Vec2| Value::Array(vec![point.x.into(), point.y.into()]);
+        let entities = self
+            .live_slots()
+            .map(|index| {
+                let mut entity = vec![(Value::str("id"), self.handle_of(index).into())];
+                if let Some(position) = self.position_of(index) {
+                    entity.push((Value::str("position"), point(position)));
+                }
+                if let Some(velocity) = self.velocity_of(index) {
+                    entity.push((Value::str("velocity"), point(velocity)));
+                }
+                if let Some((width, height, is_static)) = self.collider_of(index) {
+                    let collider = vec![
+                        (Value::str("width"), width.into()),
+                        (Value::str("height"), height.into()),
+                        (Value::str("static"), is_static.into()),
+                    ];
+                    entity.push((Value::str("collider"), Value::Map(collider)));
+                }
+                if let Some(renderable) = self.renderables[index] {
+                    let (r, g, b, a) = renderable.color;
+                    let color = [r, g, b, a].map(|channel| u64::from(channel).into());
+                    let renderable = vec![
+                        (Value::str("sprite"), u64::from(renderable.sprite).into()),
+                        (Value::str("layer"), i64::from(renderable.layer).into()),
+                        (Value::str("color"), Value::Array(color.to_vec())),
+                    ];
+                    entity.push((Value::str("renderable"), Value::Map(renderable)));
+                }
+                if let Some(steering) = self.steering[index] {
+                    let steering = vec![
+                        (Value::str("max_speed"), steering.max_speed.into()),
+                        (Value::str("max_force"), steering.max_force.into()),
+                        (Value::str("slowing_radius"), steering.slowing_radius.into()),
+                        (
+                            Value::str("target"),
+                            steering.target.map_or(Value::Nil, point),
+                        ),
+                    ];
+                    entity.push((Value::str("steering"), Value::Map(steering)));
+                }
+                Value::Map(entity)
+            })
+            .collect();
+        Value::Map(vec![
+            (Value::str("schema"), Value::str(EXPORT_SCHEMA)),
+            (Value::str("version"), EXPORT_VERSION.into()),
+            (Value::str("gravity"), point(self.gravity)),
+            (Value::str("drag"), self.drag.into()),
+            (Value::str("entities"), Value::Array(entities)),
+        ])
+    }
+
+    /// Rebuild a world from [`World::export`] output. Live entities keep their
+    /// handles, but handles of entities despawned before the export may be handed
+    /// out again.
+    pub fn import(export: &Value) -> Result<World, String> {
+        fn field<'a>(map: &'a Value, key: &str) -> Result<&'a Value, String> {
+            map.get(key).ok_or_else(|| format!("missing '{}'", key))
+        }
+        fn number(map: &Value, key: &str) -> Result<f32, String> {
+            field(map, key)?
+                .as_f64()
+                .map(|value| value as f32)
+                .ok_or_else(|| format!("'{}' must be a number", key))
+        }
+        fn point(value: &Value, key: &str) -> Result<Vec2, String> {
+            match value.as_array() {
+                Some([x, y]) => match (x.as_f64(), y.as_f64()) {
+                    (Some(x), Some(y)) => Ok(Vec2::new(x as f32, y as f32)),
+                    _ => Err(format!("'{}' must hold two numbers", key)),
+                },
+                _ => Err(format!("'{}' must be an [x, y] array", key)),
+            }
+        }
+
+        match field(export, "schema")?.as_str() {
+            Some(EXPORT_SCHEMA) => {}
+            _ => return Err(format!("schema is not '{}'", EXPORT_SCHEMA)),
+        }
+        match field(export, "version")?.as_u64() {
+            Some(EXPORT_VERSION) => {}
+            other => {
+                return Err(format!(
+                    "unsupported version {:?}, expected {}",
+                    other, EXPORT_VERSION
+                ))
+            }
+        }
+        let gravity = point(field(export, "gravity")?, "gravity")?;
+        let mut world = World::new(gravity, number(export, "drag")?, 0);
+
+        let entities = field(export, "entities")?
+            .as_array()
+            .ok_or("'entities' must be an array")?;
+        let max_slots = entities
+            .len()
+            .saturating_mul(EXPORT_SLOTS_PER_ENTITY)
+            .max(MIN_EXPORT_SLOTS);
+        for entity in entities {
+            let id = field(entity, "id")?
+                .as_u64()
+                .ok_or("entity 'id' must be an integer")?;
+            let (index, generation) = ((id & 0xFFFF_FFFF) as usize, (id >> 32) as u32);
+            if index >= max_slots {
+                return Err(format!(
+                    "entity {} is in slot {}, but {} entities use at most {} slots",
+                    id,
+                    index,
+                    entities.len(),
+                    max_slots
+                ));
+            }
+            // Handles stay valid across an export, so slots are recreated where they were
+            while world.flags.len() <= index {
+                world.allocate();
+            }
+            if world.flags[index] & ALIVE != 0 {
+                return Err(format!("entity {} appears twice", id));
+            }
+            world.generations[index] = generation;
+            world.flags[index] = ALIVE;
+            if let Some(position) = entity.get("position") {
+                world.set_position(index, point(position, "position")?);
+            }
+            if let Some(velocity) = entity.get("velocity") {
+                world.set_velocity(index, point(velocity, "velocity")?);
+            }
+            if let Some(collider) = entity.get("collider") {
+                let width = number(collider, "width")?;
+                let height = number(collider, "height")?;
+                let is_static = field(collider, "static")?
+                    .as_bool()
+                    .ok_or("'static' must be a boolean")?;
+                world.set_collider(index, width, height, is_static);
+            }
+            if let Some(renderable) = entity.get("renderable") {
+                let sprite = field(renderable, "sprite")?
+                    .as_u64()
+                    .and_then(|sprite| u32::try_from(sprite).ok())
+                    .ok_or("'sprite' must be a 32-bit unsigned integer")?;
+                let layer = field(renderable, "layer")?
+                    .as_i64()
+                    .and_then(|layer| i32::try_from(layer).ok())
+                    .ok_or("'layer' must be a 32-bit integer")?;
+                let channels: Option<Vec<u8>> = field(renderable, "color")?
+                    .as_array()
+                    .map(|color| {
+                        color
+                            .iter()
+                            .map(|channel| channel.as_u64().and_then(|c| u8::try_from(c).ok()))
+                            .collect()
+                    })
+                    .unwrap_or(None);
+                let color = match channels.as_deref() {
+                    Some(&[r, g, b, a]) => (r, g, b, a),
+                    _ => return Err("'color' must be four bytes".to_string()),
+                };
+                world.set_renderable(
+                    index,
+                    Renderable {
+                        sprite,
+                        layer,
+                        color,
+                    },
+                );
+            }
+            if let Some(steering) = entity.get("steering") {
+                let target = match field(steering, "target")? {
+                    Value::Nil => None,
+                    target => Some(point(target, "target")?),
+                };
+                world.steering[index] = Some(Steering {
+                    max_speed: number(steering, "max_speed")?,
+                    max_force: number(steering, "max_force")?,
+                    slowing_radius: number(steering, "slowing_radius")?,
+                    target,
+                });
+                world.set_flag(index, STEERING, true);
+            }
+        }
+        // Slots nobody lives in are free, handed out lowest first like a fresh world's
+        world.free = (0..world.flags.len())
+            .rev()
+            .filter(|&index| world.flags[index] & ALIVE == 0)
+            .collect();
+        Ok(world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::Circle;
+
+    #[test]
+    fn despawned_handles_never_alias_their_slot() {
+        let mut world = World::new(Vec2::ZERO, 0.0, 4);
+        let first = world.spawn(Some(Vec2::new(1.0, 2.0)), None, None, false, None);
+        assert!(world.despawn(first));
+        assert!(!world.despawn(first));
+        let second = world.spawn(None, None, None, false, Some((3, 1)));
+        assert_eq!(world.slot(second), Some(0));
+        assert_eq!(world.slot(first), None);
+        assert_eq!(world.handles(), [Some(second)]);
+        assert_eq!(world.position_of(0), None);
+        assert!(world.has(0, Component::Renderable));
+    }
+
+    #[test]
+    fn physics_moves_bodies_and_collisions_push_them_apart() {
+        let mut world = World::new(Vec2::new(0.0, 10.0), 0.0, 4);
+        let faller = world.spawn(
+            Some(Vec2::new(0.0, 0.0)),
+            Some(Vec2::ZERO),
+            Some((1.0, 1.0)),
+            false,
+            None,
+        );
+        let floor = world.spawn(
+            Some(Vec2::new(-5.0, 1.0)),
+            None,
+            Some((10.0, 1.0)),
+            true,
+            None,
+        );
+        assert_eq!(world.step(0.1), [(faller, floor)]);
+        let index = world.slot(faller).unwrap();
+        // Resting on the floor, no longer falling into it
+        assert!(world.ys[index].abs() < 1e-5);
+        assert_eq!(world.vys[index], 0.0);
+    }
+
+    #[test]
+    fn queries_and_areas_see_only_matching_entities() {
+        let mut world = World::new(Vec2::ZERO, 0.0, 4);
+        let near = world.spawn(Some(Vec2::new(1.0, 1.0)), None, None, false, None);
+        let boxed = world.spawn(
+            Some(Vec2::new(-6.0, -1.0)),
+            None,
+            Some((2.0, 2.0)),
+            false,
+            None,
+        );
+        world.spawn(
+            Some(Vec2::new(20.0, 0.0)),
+            Some(Vec2::ZERO),
+            None,
+            false,
+            None,
+        );
+        let area = Shape::Circle(Circle::new(0.0, 0.0, 4.5));
+        assert_eq!(world.entities_in(area), [near, boxed]);
+        let moving: Vec<_> = world
+            .query(&[Component::Position, Component::Velocity])
+            .collect();
+        assert_eq!(moving, [2]);
+        assert!(world.remove_component(1, Component::Collider));
+        assert!(!world.remove_component(1, Component::Collider));
+        assert_eq!(world.entities_in(area), [near]);
+    }
+
+    #[test]
+    fn exports_round_trip_with_their_handles() {
+        let mut world = World::new(Vec2::new(0.0, 9.8), 0.5, 4);
+        let gone = world.spawn(None, None, None, false, None);
+        let kept = world.spawn(Some(Vec2::new(1.0, 2.0)), None, None, false, Some((7, -1)));
+        world.set_steering(1, 3.0, 1.0, 2.0);
+        assert!(world.set_steering_target(1, Some(Vec2::new(5.0, 5.0))));
+        world.despawn(gone);
+        let copy = World::import(&world.export()).unwrap();
+        assert_eq!(copy.entities(), [kept]);
+        assert_eq!(copy.renderables[1], world.renderables[1]);
+        assert_eq!(copy.steering[1], world.steering[1]);
+        assert_eq!(copy.free, [0]);
+        assert!(World::import(&Value::Map(Vec::new())).is_err());
+    }
+}
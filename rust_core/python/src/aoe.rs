@@ -0,0 +1,120 @@
+//! Area-of-effect templates for previewing and resolving blasts, cones, beams and
+//! rings. Every function takes an optional `wall_map`; with one, cells off the map
+//! or out of the origin's line of sight are left out.
+
+use llamaquest::aoe::{affected_cells, Template};
+use llamaquest::raster::Cell;
+use pyo3::prelude::*;
+
+use crate::errors::OutOfBoundsError;
+use crate::grid;
+use crate::profiling;
+
+fn cells(
+    py: Python<'_>,
+    origin: Cell,
+    template: Template,
+    wall_map: Option<Vec<Vec<bool>>>,
+) -> PyResult<Vec<Cell>> {
+    if let Some(wall_map) = &wall_map {
+        let (width, height) = grid::require_rectangular(wall_map, "wall_map")?;
+        if origin.0 < 0 || origin.1 < 0 {
+            return Err(OutOfBoundsError::new_err(format!(
+                "origin ({}, {}) is outside the {}x{} wall_map",
+                origin.0, origin.1, width, height
+            )));
+        }
+        grid::require_cell(
+            "origin",
+            (origin.0 as usize, origin.1 as usize),
+            (width, height),
+            "wall_map",
+        )?;
+    }
+    Ok(py.allow_threads(|| affected_cells(origin, &template, wall_map.as_deref())))
+}
+
+/// Cells within `radius` of `origin`, origin included
+#[pyfunction]
+#[pyo3(signature = (origin, radius, wall_map = None))]
+pub fn blast_cells(
+    py: Python<'_>,
+    origin: Cell,
+    radius: u32,
+    wall_map: Option<Vec<Vec<bool>>>,
+) -> PyResult<Vec<Cell>> {
+    let _scope = profiling::scope("blast_cells");
+    cells(py, origin, Template::Blast { radius }, wall_map)
+}
+
+/// Cells within `radius` of `origin` and within `angle / 2` radians of the direction
+/// towards `toward`, origin excluded
+#[pyfunction]
+#[pyo3(signature = (origin, toward, radius, angle = std::f32::consts::FRAC_PI_2, wall_map = None))]
+pub fn cone_cells(
+    py: Python<'_>,
+    origin: Cell,
+    toward: Cell,
+    radius: u32,
+    angle: f32,
+    wall_map: Option<Vec<Vec<bool>>>,
+) -> PyResult<Vec<Cell>> {
+    let _scope = profiling::scope("cone_cells");
+    cells(
+        py,
+        origin,
+        Template::Cone {
+            toward,
+            radius,
+            angle,
+        },
+        wall_map,
+    )
+}
+
+/// Cells of a line `width` cells wide from `origin` through `toward`, nearest first
+/// and origin excluded. It stops at `toward`, or `length` cells out when given.
+#[pyfunction]
+#[pyo3(signature = (origin, toward, width = 1.0, length = None, wall_map = None))]
+pub fn beam_cells(
+    py: Python<'_>,
+    origin: Cell,
+    toward: Cell,
+    width: f32,
+    length: Option<u32>,
+    wall_map: Option<Vec<Vec<bool>>>,
+) -> PyResult<Vec<Cell>> {
+    let _scope = profiling::scope("beam_cells");
+    cells(
+        py,
+        origin,
+        Template::Beam {
+            toward,
+            width,
+            length,
+        },
+        wall_map,
+    )
+}
+
+/// Cells within `outer_radius` of `origin` but not within `inner_radius`
+#[pyfunction]
+#[pyo3(signature = (origin, inner_radius, outer_radius, wall_map = None))]
+pub fn ring_cells(
+    py: Python<'_>,
+    origin: Cell,
+    inner_radius: u32,
+    outer_radius: u32,
+    wall_map: Option<Vec<Vec<bool>>>,
+) -> PyResult<Vec<Cell>> {
+    let _scope = profiling::scope("ring_cells");
+    cells(
+        py,
+        origin,
+        Template::Ring {
+            inner_radius,
+            outer_radius,
+        },
+        wall_map,
+    )
+}
@@ -0,0 +1,349 @@
+use llamaquest::behavior_tree::{self, Agent, Node, NodeKind, NodeState, Status, MAX_DEPTH};
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use pyo3::exceptions::{PyIndexError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use serde_json::Value;
+
+use crate::profiling;
+use crate::pyjson;
+use crate::state::Persist;
+
+/// Leaf callbacks may return a bool, `None` for running, or a status name
+fn status_from_callback(value: &PyAny) -> PyResult<Status> {
+    if value.is_none() {
+        return Ok(Status::Running);
+    }
+    if let Ok(success) = value.extract::<bool>() {
+        return Ok(if success {
+            Status::Success
+        } else {
+            Status::Failure
+        });
+    }
+    match value.extract::<&str>() {
+        Ok("success") => Ok(Status::Success),
+        Ok("failure") => Ok(Status::Failure),
+        Ok("running") => Ok(Status::Running),
+        _ => Err(PyTypeError::new_err(format!(
+            "behavior tree callbacks must return a bool, None, or 'success'/'failure'/'running', got {}",
+            value.repr()?
+        ))),
+    }
+}
+
+/// A behaviour tree definition shared by many agents, each with its own runtime state.
+///
+/// Trees are described as nested dicts (or the equivalent JSON), for example
+/// `{"type": "selector", "children": [...]}`. Composite and decorator nodes run in
+/// Rust; `action` and `condition` leaves call back into Python by name.
+#[pyclass]
+pub struct BehaviorTree {
+    tree: behavior_tree::BehaviorTree,
+}
+
+fn parse_node(value: &Value, nodes: &mut Vec<Node>, path: &str, depth: usize) -> PyResult<usize> {
+    let invalid = |message: String| PyValueError::new_err(format!("{}: {}", path, message));
+    if depth > MAX_DEPTH {
+        return Err(invalid(format!("tree is deeper than {} nodes", MAX_DEPTH)));
+    }
+    let object = value
+        .as_object()
+        .ok_or_else(|| invalid("node must be an object".to_string()))?;
+    let kind_name = object
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("node is missing its 'type'".to_string()))?;
+    let number = |key: &str| object.get(key).and_then(Value::as_f64);
+    let name = || {
+        object
+            .get("name")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| invalid(format!("'{}' node needs a 'name'", kind_name)))
+    };
+
+    let kind = match kind_name {
+        "sequence" => NodeKind::Sequence,
+        "selector" => NodeKind::Selector,
+        "parallel" => NodeKind::Parallel {
+            success_threshold: number("success_threshold").map_or(usize::MAX, |n| n as usize),
+        },
+        "inverter" => NodeKind::Inverter,
+        "succeeder" => NodeKind::Succeeder,
+        "repeat" => NodeKind::Repeat {
+            count: number("count").map(|n| n as usize),
+        },
+        "cooldown" => NodeKind::Cooldown {
+            seconds: number("seconds")
+                .ok_or_else(|| invalid("'cooldown' needs 'seconds'".to_string()))?,
+        },
+        "wait" => NodeKind::Wait {
+            seconds: number("seconds")
+                .ok_or_else(|| invalid("'wait' needs 'seconds'".to_string()))?,
+        },
+        "action" => NodeKind::Action(name()?),
+        "condition" => NodeKind::Condition(name()?),
+        other => return Err(invalid(format!("unknown node type '{}'", other))),
+    };
+
+    let child_values: Vec<&Value> = match (&kind, object.get("children"), object.get("child")) {
+        (
+            NodeKind::Sequence | NodeKind::Selector | NodeKind::Parallel { .. },
+            Some(Value::Array(children)),
+            _,
+        ) => children.iter().collect(),
+        (NodeKind::Sequence | NodeKind::Selector | NodeKind::Parallel { .. }, _, _) => {
+            return Err(invalid(format!("'{}' needs a 'children' list", kind_name)))
+        }
+        (
+            NodeKind::Inverter
+            | NodeKind::Succeeder
+            | NodeKind::Repeat { .. }
+            | NodeKind::Cooldown { .. },
+            _,
+            Some(child),
+        ) => vec![child],
+        (
+            NodeKind::Inverter
+            | NodeKind::Succeeder
+            | NodeKind::Repeat { .. }
+            | NodeKind::Cooldown { .. },
+            _,
+            None,
+        ) => return Err(invalid(format!("'{}' needs a 'child'", kind_name))),
+        _ => Vec::new(),
+    };
+
+    let index = nodes.len();
+    nodes.push(Node {
+        kind,
+        children: Vec::new(),
+    });
+    let mut children = Vec::with_capacity(child_values.len());
+    for (i, child) in child_values.into_iter().enumerate() {
+        children.push(parse_node(
+            child,
+            nodes,
+            &format!("{}/{}[{}]", path, kind_name, i),
+            depth + 1,
+        )?);
+    }
+    nodes[index].children = children;
+    Ok(index)
+}
+
+fn no_agent(id: usize) -> PyErr {
+    PyIndexError::new_err(format!("no behavior tree agent with id {}", id))
+}
+
+fn save_kind(out: &mut Writer, kind: &NodeKind) {
+    match kind {
+        NodeKind::Sequence => out.u8(0),
+        NodeKind::Selector => out.u8(1),
+        NodeKind::Parallel { success_threshold } => {
+            out.u8(2);
+            out.usize(*success_threshold);
+        }
+        NodeKind::Inverter => out.u8(3),
+        NodeKind::Succeeder => out.u8(4),
+        NodeKind::Repeat { count } => {
+            out.u8(5);
+            out.option(*count, Writer::usize);
+        }
+        NodeKind::Cooldown { seconds } => {
+            out.u8(6);
+            out.f64(*seconds);
+        }
+        NodeKind::Wait { seconds } => {
+            out.u8(7);
+            out.f64(*seconds);
+        }
+        NodeKind::Action(name) => {
+            out.u8(8);
+            out.str(name);
+        }
+        NodeKind::Condition(name) => {
+            out.u8(9);
+            out.str(name);
+        }
+    }
+}
+
+fn load_kind(input: &mut Reader) -> Result<NodeKind, DecodeError> {
+    Ok(match input.u8()? {
+        0 => NodeKind::Sequence,
+        1 => NodeKind::Selector,
+        2 => NodeKind::Parallel {
+            success_threshold: input.usize()?,
+        },
+        3 => NodeKind::Inverter,
+        4 => NodeKind::Succeeder,
+        5 => NodeKind::Repeat {
+            count: input.option(Reader::usize)?,
+        },
+        6 => NodeKind::Cooldown {
+            seconds: input.f64()?,
+        },
+        7 => NodeKind::Wait {
+            seconds: input.f64()?,
+        },
+        8 => NodeKind::Action(input.str()?.to_string()),
+        9 => NodeKind::Condition(input.str()?.to_string()),
+        other => return Err(DecodeError::Invalid(format!("unknown node kind {}", other))),
+    })
+}
+
+impl Persist for BehaviorTree {
+    const KIND: &'static str = "behavior_tree";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let tree = &self.tree;
+        out.usize(tree.nodes.len());
+        for node in &tree.nodes {
+            save_kind(out, &node.kind);
+            out.usize(node.children.len());
+            for &child in &node.children {
+                out.usize(child);
+            }
+        }
+        out.usize(tree.agents.len());
+        for agent in &tree.agents {
+            out.option(agent.as_ref(), |out, agent| {
+                out.f64(agent.time);
+                for state in &agent.nodes {
+                    out.usize(state.cursor);
+                    out.usize(state.counter);
+                    out.f64(state.ready_at);
+                    out.option(state.started_at, Writer::f64);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let invalid = |message: String| DecodeError::Invalid(message);
+        let count = input.usize()?;
+        if count == 0 {
+            return Err(invalid("behavior tree has no nodes".to_string()));
+        }
+        let mut nodes = Vec::new();
+        // Every parent comes first, so a node's depth is final by the time it is read
+        let mut depths = vec![1; count];
+        for index in 0..count {
+            let kind = load_kind(input)?;
+            let children = (0..input.usize()?)
+                .map(|_| input.usize())
+                .collect::<Result<Vec<_>, _>>()?;
+            // Children always come after their parent, which also rules out cycles
+            if let Some(&child) = children.iter().find(|&&c| c <= index || c >= count) {
+                return Err(invalid(format!(
+                    "node {} has invalid child {} in a tree of {} nodes",
+                    index, child, count
+                )));
+            }
+            if kind.is_decorator() && children.len() != 1 {
+                return Err(invalid(format!(
+                    "decorator node {} has {} children, expected 1",
+                    index,
+                    children.len()
+                )));
+            }
+            if depths[index] > MAX_DEPTH {
+                return Err(invalid(format!(
+                    "node {} is deeper than {} nodes",
+                    index, MAX_DEPTH
+                )));
+            }
+            for &child in &children {
+                depths[child] = depths[child].max(depths[index] + 1);
+            }
+            nodes.push(Node { kind, children });
+        }
+        let mut agents = Vec::new();
+        for _ in 0..input.usize()? {
+            agents.push(input.option(|input| {
+                let time = input.f64()?;
+                let nodes = (0..count)
+                    .map(|_| {
+                        Ok(NodeState {
+                            cursor: input.usize()?,
+                            counter: input.usize()?,
+                            ready_at: input.f64()?,
+                            started_at: input.option(Reader::f64)?,
+                        })
+                    })
+                    .collect::<Result<_, DecodeError>>()?;
+                Ok(Agent { time, nodes })
+            })?);
+        }
+        let tree = behavior_tree::BehaviorTree { nodes, agents };
+        Ok(BehaviorTree { tree })
+    }
+}
+
+#[pymethods]
+impl BehaviorTree {
+    /// Build a tree from a nested dict description or a JSON string
+    #[new]
+    fn new(py: Python<'_>, description: &PyAny) -> PyResult<Self> {
+        let value = pyjson::to_json_value(py, description)?;
+        let mut nodes = Vec::new();
+        parse_node(&value, &mut nodes, "root", 1)?;
+        Ok(BehaviorTree {
+            tree: behavior_tree::BehaviorTree::new(nodes),
+        })
+    }
+
+    /// Add an agent running this tree and return its id
+    fn add_agent(&mut self) -> usize {
+        self.tree.add_agent()
+    }
+
+    fn remove_agent(&mut self, id: usize) -> PyResult<()> {
+        if !self.tree.remove_agent(id) {
+            return Err(no_agent(id));
+        }
+        Ok(())
+    }
+
+    /// Forget an agent's running nodes, timers and cooldowns
+    fn reset_agent(&mut self, id: usize) -> PyResult<()> {
+        if !self.tree.reset_agent(id) {
+            return Err(no_agent(id));
+        }
+        Ok(())
+    }
+
+    /// Tick one agent; `callback(agent_id, leaf_name)` decides each leaf's result
+    fn tick_agent(
+        &mut self,
+        id: usize,
+        delta_time: f64,
+        callback: &PyAny,
+    ) -> PyResult<&'static str> {
+        let _scope = profiling::scope("BehaviorTree.tick_agent");
+        let mut leaf = |name: &str| status_from_callback(callback.call1((id, name))?);
+        let status = self
+            .tree
+            .tick_agent(id, delta_time, &mut leaf)
+            .ok_or_else(|| no_agent(id))??;
+        Ok(status.name())
+    }
+
+    /// Tick every agent, returning `(agent_id, status)` pairs
+    fn tick(&mut self, delta_time: f64, callback: &PyAny) -> PyResult<Vec<(usize, &'static str)>> {
+        let _scope = profiling::scope("BehaviorTree.tick");
+        let results = self.tree.tick(delta_time, |id, name| {
+            status_from_callback(callback.call1((id, name))?)
+        })?;
+        Ok(results
+            .into_iter()
+            .map(|(id, status)| (id, status.name()))
+            .collect())
+    }
+
+    fn __len__(&self) -> usize {
+        self.tree.len()
+    }
+}
@@ -0,0 +1,67 @@
+//! Bulk arrays from Python (numpy arrays, `array.array`, ...) via the buffer protocol
+
+use std::ffi::CStr;
+
+use pyo3::buffer::{Element, ElementType, PyBuffer};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// One byte per item read as a boolean, accepting both bool (`?`) and byte arrays
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(transparent)]
+pub struct Flag(u8);
+
+impl Flag {
+    pub fn get(self) -> bool {
+        self.0 != 0
+    }
+}
+
+// SAFETY: `Flag` is a transparent `u8`, and only one-byte formats are accepted
+unsafe impl Element for Flag {
+    fn is_compatible_format(format: &CStr) -> bool {
+        matches!(
+            ElementType::from_format(format),
+            ElementType::Bool
+                | ElementType::UnsignedInteger { bytes: 1 }
+                | ElementType::SignedInteger { bytes: 1 }
+        )
+    }
+}
+
+/// A contiguous buffer of `rows x columns` items, checked to be writable when asked
+pub fn rows<T: Element>(
+    obj: &PyAny,
+    name: &str,
+    kind: &str,
+    columns: usize,
+    writable: bool,
+) -> PyResult<(PyBuffer<T>, usize)> {
+    let buffer = PyBuffer::<T>::get(obj).map_err(|_| {
+        PyValueError::new_err(format!("{} must be a contiguous {} array", name, kind))
+    })?;
+    if !buffer.is_c_contiguous() {
+        return Err(PyValueError::new_err(format!(
+            "{} must be C-contiguous",
+            name
+        )));
+    }
+    if writable && buffer.readonly() {
+        return Err(PyValueError::new_err(format!("{} must be writable", name)));
+    }
+    let shape_matches = match buffer.shape() {
+        [count] => count % columns == 0,
+        [_, width] => *width == columns,
+        _ => false,
+    };
+    if !shape_matches {
+        return Err(PyValueError::new_err(format!(
+            "{} must have shape (n, {}), got {:?}",
+            name,
+            columns,
+            buffer.shape()
+        )));
+    }
+    let count = buffer.item_count() / columns;
+    Ok((buffer, count))
+}
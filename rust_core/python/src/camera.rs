@@ -0,0 +1,256 @@
+//! `Camera2D`: a follow camera with smoothing, deadzone, look-ahead, bounds and shake
+
+use llamaquest::camera::Camera;
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::python::{RectLike, VecLike};
+use llamaquest::shapes::Rect;
+use llamaquest::vec2::Vec2;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::profiling;
+use crate::state::Persist;
+
+fn check_viewport(viewport: Vec2) -> PyResult<Vec2> {
+    if viewport.x > 0.0 && viewport.y > 0.0 {
+        Ok(viewport)
+    } else {
+        Err(PyValueError::new_err(format!(
+            "viewport must have a positive size, got ({}, {})",
+            viewport.x, viewport.y
+        )))
+    }
+}
+
+/// A camera that follows a target smoothly, updated once per rendered frame.
+///
+/// The camera only moves once the target leaves the `deadzone` box (half-sizes in
+/// world units) around its centre, leads the target by `look_ahead` seconds of its
+/// velocity, and eases towards its goal at `smoothing` per second. The view stays
+/// inside `bounds`, a `Rect` or `(x, y, width, height)`, when given.
+///
+/// `add_trauma` shakes the camera: trauma from 0 to 1 decays by `trauma_decay` per
+/// second, and the shake, up to `max_shake_offset` and `max_shake_angle` radians,
+/// follows smooth noise and scales with trauma squared. Render from `view_center`
+/// rotated by `shake_angle`, or use `view_rect`.
+///
+/// With fixed-timestep physics, pass the target position interpolated with the
+/// clock's `alpha` so the camera moves every frame rather than every step.
+#[pyclass]
+pub struct Camera2D {
+    camera: Camera,
+}
+
+impl Persist for Camera2D {
+    const KIND: &'static str = "camera_2d";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let camera = &self.camera;
+        out.vec2(camera.position);
+        out.vec2(camera.viewport);
+        out.f32(camera.smoothing);
+        out.vec2(camera.deadzone);
+        out.f32(camera.look_ahead);
+        out.option(camera.bounds, |out, bounds| {
+            out.vec2(Vec2::new(bounds.x, bounds.y));
+            out.vec2(Vec2::new(bounds.width, bounds.height));
+        });
+        out.f32(camera.trauma);
+        out.f32(camera.trauma_decay);
+        out.vec2(camera.max_shake_offset);
+        out.f32(camera.max_shake_angle);
+        out.f32(camera.shake_frequency);
+        out.u64(camera.seed);
+        out.f32(camera.time());
+        out.vec2(camera.shake_offset());
+        out.f32(camera.shake_angle());
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let position = input.vec2()?;
+        let mut camera = Camera::new(position, input.vec2()?);
+        camera.smoothing = input.f32()?;
+        camera.deadzone = input.vec2()?;
+        camera.look_ahead = input.f32()?;
+        camera.bounds = input.option(|input| {
+            let (origin, size) = (input.vec2()?, input.vec2()?);
+            Ok(Rect::new(origin.x, origin.y, size.x, size.y))
+        })?;
+        camera.trauma = input.f32()?;
+        camera.trauma_decay = input.f32()?;
+        camera.max_shake_offset = input.vec2()?;
+        camera.max_shake_angle = input.f32()?;
+        camera.shake_frequency = input.f32()?;
+        camera.seed = input.u64()?;
+        let (time, offset, angle) = (input.f32()?, input.vec2()?, input.f32()?);
+        camera.restore_shake(time, offset, angle);
+        Ok(Camera2D { camera })
+    }
+}
+
+#[pymethods]
+impl Camera2D {
+    #[new]
+    #[pyo3(signature = (viewport, position = None, smoothing = 8.0, deadzone = None, look_ahead = 0.0, bounds = None, trauma_decay = 1.0, max_shake_offset = None, max_shake_angle = 0.05, shake_frequency = 15.0, seed = 0))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        viewport: VecLike,
+        position: Option<VecLike>,
+        smoothing: f32,
+        deadzone: Option<VecLike>,
+        look_ahead: f32,
+        bounds: Option<RectLike>,
+        trauma_decay: f32,
+        max_shake_offset: Option<VecLike>,
+        max_shake_angle: f32,
+        shake_frequency: f32,
+        seed: u64,
+    ) -> PyResult<Self> {
+        let mut camera = Camera::new(
+            position.map_or(Vec2::ZERO, Vec2::from),
+            check_viewport(viewport.into())?,
+        );
+        camera.smoothing = smoothing.max(0.0);
+        camera.deadzone = deadzone.map_or(Vec2::ZERO, Vec2::from);
+        camera.look_ahead = look_ahead;
+        camera.bounds = bounds.map(Rect::from);
+        camera.trauma_decay = trauma_decay.max(0.0);
+        if let Some(offset) = max_shake_offset {
+            camera.max_shake_offset = offset.into();
+        }
+        camera.max_shake_angle = max_shake_angle;
+        camera.shake_frequency = shake_frequency.max(0.0);
+        camera.seed = seed;
+        camera.snap_to(camera.position);
+        Ok(Camera2D { camera })
+    }
+
+    /// Follow `target` for `delta` seconds; pass its `velocity` for look-ahead
+    #[pyo3(signature = (target, delta, velocity = None))]
+    fn update(&mut self, target: VecLike, delta: f32, velocity: Option<VecLike>) {
+        let _scope = profiling::scope("Camera2D.update");
+        self.camera.update(
+            target.into(),
+            velocity.map_or(Vec2::ZERO, Vec2::from),
+            delta,
+        );
+    }
+
+    /// Centre on `target` immediately, e.g. after a level change
+    fn snap_to(&mut self, target: VecLike) {
+        self.camera.snap_to(target.into());
+    }
+
+    /// Add shake trauma, capped at 1: around 0.3 for a hit, 1.0 for an explosion
+    fn add_trauma(&mut self, amount: f32) {
+        self.camera.add_trauma(amount);
+    }
+
+    /// Centre of the view before shake
+    #[getter]
+    fn position(&self) -> Vec2 {
+        self.camera.position
+    }
+
+    #[getter]
+    fn view_center(&self) -> Vec2 {
+        self.camera.view_center()
+    }
+
+    #[getter]
+    fn view_rect(&self) -> Rect {
+        self.camera.view_rect()
+    }
+
+    #[getter]
+    fn shake_offset(&self) -> Vec2 {
+        self.camera.shake_offset()
+    }
+
+    #[getter]
+    fn shake_angle(&self) -> f32 {
+        self.camera.shake_angle()
+    }
+
+    #[getter]
+    fn viewport(&self) -> Vec2 {
+        self.camera.viewport
+    }
+
+    #[setter]
+    fn set_viewport(&mut self, viewport: VecLike) -> PyResult<()> {
+        self.camera.viewport = check_viewport(viewport.into())?;
+        Ok(())
+    }
+
+    #[getter]
+    fn smoothing(&self) -> f32 {
+        self.camera.smoothing
+    }
+
+    #[setter]
+    fn set_smoothing(&mut self, smoothing: f32) {
+        self.camera.smoothing = smoothing.max(0.0);
+    }
+
+    #[getter]
+    fn deadzone(&self) -> Vec2 {
+        self.camera.deadzone
+    }
+
+    #[setter]
+    fn set_deadzone(&mut self, deadzone: VecLike) {
+        self.camera.deadzone = deadzone.into();
+    }
+
+    #[getter]
+    fn look_ahead(&self) -> f32 {
+        self.camera.look_ahead
+    }
+
+    #[setter]
+    fn set_look_ahead(&mut self, look_ahead: f32) {
+        self.camera.look_ahead = look_ahead;
+    }
+
+    #[getter]
+    fn bounds(&self) -> Option<Rect> {
+        self.camera.bounds
+    }
+
+    #[setter]
+    fn set_bounds(&mut self, bounds: Option<RectLike>) {
+        self.camera.bounds = bounds.map(Rect::from);
+    }
+
+    #[getter]
+    fn trauma(&self) -> f32 {
+        self.camera.trauma
+    }
+
+    #[setter]
+    fn set_trauma(&mut self, trauma: f32) {
+        self.camera.trauma = trauma.clamp(0.0, 1.0);
+    }
+
+    #[getter]
+    fn trauma_decay(&self) -> f32 {
+        self.camera.trauma_decay
+    }
+
+    #[setter]
+    fn set_trauma_decay(&mut self, trauma_decay: f32) {
+        self.camera.trauma_decay = trauma_decay.max(0.0);
+    }
+
+    fn __repr__(&self) -> String {
+        let Camera {
+            position, viewport, ..
+        } = self.camera;
+        format!(
+            "Camera2D(position=({}, {}), viewport=({}, {}), trauma={})",
+            position.x, position.y, viewport.x, viewport.y, self.camera.trauma
+        )
+    }
+}
@@ -0,0 +1,111 @@
+//! Cooperative cancellation of long-running computations.
+//!
+//! A [`CancelToken`] wraps a shared flag that any Python thread can trip. Entry
+//! points hand it to [`run`], which releases the GIL and makes the flag visible to
+//! the algorithm through [`requested`]; loops poll that and bail out early, and
+//! `run` then discards the partial result and raises `Cancelled` instead.
+//!
+//! Methods that advance an object one step at a time, such as `FluidGrid.step` or
+//! `InfluenceMap.propagate`, have no partial result to discard: they stop between
+//! steps, so the object keeps the steps already done and is never left half-way
+//! through one.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use pyo3::prelude::*;
+
+use crate::errors::Cancelled;
+
+thread_local! {
+    /// Flag of the token watched by the computation running on this thread
+    static ACTIVE: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// A flag that stops any computation it was passed to, from whichever thread trips it
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+#[pymethods]
+impl CancelToken {
+    #[new]
+    fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Ask every computation watching this token to stop as soon as it can
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Clear the flag so the token can be reused for the next computation
+    fn reset(&self) {
+        self.flag.store(false, Ordering::Relaxed);
+    }
+
+    #[getter]
+    fn cancelled(&self) -> bool {
+        self.is_cancelled()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("CancelToken(cancelled={})", self.is_cancelled())
+    }
+}
+
+/// Restores the previously watched flag when a nested [`run`] finishes
+struct Watch(Option<Arc<AtomicBool>>);
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        ACTIVE.with(|active| *active.borrow_mut() = previous);
+    }
+}
+
+/// Whether the computation running on this thread has been asked to stop
+pub fn requested() -> bool {
+    ACTIVE.with(|active| {
+        active
+            .borrow()
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    })
+}
+
+/// Run `work` with the GIL released, raising `Cancelled` if `token` trips meanwhile
+pub fn run<T: Send>(
+    py: Python<'_>,
+    token: Option<&CancelToken>,
+    what: &str,
+    work: impl FnOnce() -> T + Send,
+) -> PyResult<T> {
+    py.allow_threads(|| guarded(token, what, work))
+}
+
+/// [`run`] for threads that do not hold the GIL in the first place
+pub fn guarded<T>(
+    token: Option<&CancelToken>,
+    what: &str,
+    work: impl FnOnce() -> T,
+) -> PyResult<T> {
+    let value = {
+        let flag = token.map(|token| token.flag.clone());
+        let _watch = Watch(ACTIVE.with(|active| active.replace(flag)));
+        work()
+    };
+    if token.is_some_and(CancelToken::is_cancelled) {
+        return Err(Cancelled::new_err(format!("{} was cancelled", what)));
+    }
+    Ok(value)
+}
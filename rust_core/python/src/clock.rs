@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+
+use llamaquest::clock::{self, Timer};
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::profiling;
+use crate::state::Persist;
+
+/// Game time for every Rust-side subsystem: pausing, time scaling, fixed-step
+/// accumulation and named timers all advance from the same `tick()`.
+///
+/// `tick(real_delta)` returns how many fixed steps are due; run the simulation that
+/// many times at `fixed_step` and interpolate rendering with `alpha`. The event
+/// scheduler and physics engine can follow a clock directly.
+#[pyclass]
+pub struct GameClock {
+    clock: clock::GameClock,
+}
+
+impl GameClock {
+    /// Fixed steps produced by the last tick
+    pub fn steps(&self) -> u32 {
+        self.clock.steps
+    }
+
+    pub fn fixed_step(&self) -> f64 {
+        self.clock.fixed_step
+    }
+
+    pub fn time(&self) -> f64 {
+        self.clock.time
+    }
+
+    fn timer(&self, name: &str) -> PyResult<&Timer> {
+        self.clock
+            .timers
+            .get(name)
+            .ok_or_else(|| PyKeyError::new_err(format!("no timer named '{}'", name)))
+    }
+
+    fn timer_mut(&mut self, name: &str) -> PyResult<&mut Timer> {
+        self.clock
+            .timers
+            .get_mut(name)
+            .ok_or_else(|| PyKeyError::new_err(format!("no timer named '{}'", name)))
+    }
+}
+
+fn check_positive(name: &str, value: f64) -> PyResult<f64> {
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err(PyValueError::new_err(format!("{} must be positive", name)))
+    }
+}
+
+/// Negative scales stop time, like 0; infinite or NaN ones are refused
+fn check_time_scale(time_scale: f64) -> PyResult<f64> {
+    if time_scale.is_finite() {
+        Ok(time_scale.max(0.0))
+    } else {
+        Err(PyValueError::new_err("time_scale must be finite"))
+    }
+}
+
+impl Persist for GameClock {
+    const KIND: &'static str = "game_clock";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let clock = &self.clock;
+        for value in [
+            clock.time,
+            clock.real_time,
+            clock.delta,
+            clock.time_scale,
+            clock.fixed_step,
+            clock.accumulator,
+        ] {
+            out.f64(value);
+        }
+        out.varint(clock.frame);
+        out.bool(clock.paused);
+        out.varint(clock.max_steps.into());
+        out.varint(clock.steps.into());
+        // Sorted so equal clocks always encode to the same bytes
+        let mut timers: Vec<_> = clock.timers.iter().collect();
+        timers.sort_by(|a, b| a.0.cmp(b.0));
+        out.usize(timers.len());
+        for (name, timer) in timers {
+            out.str(name);
+            match *timer {
+                Timer::Countdown {
+                    duration,
+                    remaining,
+                    repeat,
+                    fired,
+                } => {
+                    out.u8(0);
+                    out.f64(duration);
+                    out.f64(remaining);
+                    out.bool(repeat);
+                    out.varint(fired.into());
+                }
+                Timer::Stopwatch { elapsed, running } => {
+                    out.u8(1);
+                    out.f64(elapsed);
+                    out.bool(running);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let count = |input: &mut Reader| {
+            u32::try_from(input.varint()?)
+                .map_err(|_| DecodeError::Invalid("clock step count is out of range".to_string()))
+        };
+        let mut clock = clock::GameClock {
+            time: input.f64()?,
+            real_time: input.f64()?,
+            delta: input.f64()?,
+            time_scale: input.f64()?,
+            fixed_step: input.f64()?,
+            accumulator: input.f64()?,
+            frame: input.varint()?,
+            paused: input.bool()?,
+            max_steps: count(input)?,
+            steps: count(input)?,
+            timers: HashMap::new(),
+        };
+        if !(clock.fixed_step.is_finite() && clock.fixed_step > 0.0) {
+            return Err(DecodeError::Invalid(
+                "clock fixed_step must be positive".to_string(),
+            ));
+        }
+        if !(clock.time_scale.is_finite() && clock.time_scale >= 0.0) {
+            return Err(DecodeError::Invalid(
+                "clock time_scale must be finite and not negative".to_string(),
+            ));
+        }
+        for _ in 0..input.usize()? {
+            let name = input.str()?.to_string();
+            let timer = match input.u8()? {
+                0 => Timer::Countdown {
+                    duration: input.f64()?,
+                    remaining: input.f64()?,
+                    repeat: input.bool()?,
+                    fired: count(input)?,
+                },
+                1 => Timer::Stopwatch {
+                    elapsed: input.f64()?,
+                    running: input.bool()?,
+                },
+                other => {
+                    return Err(DecodeError::Invalid(format!(
+                        "unknown timer kind {}",
+                        other
+                    )))
+                }
+            };
+            if let Timer::Countdown {
+                duration,
+                remaining,
+                ..
+            } = timer
+            {
+                if !(duration.is_finite() && duration > 0.0 && remaining.is_finite()) {
+                    return Err(DecodeError::Invalid(format!(
+                        "timer '{}' must have a positive duration and finite time left",
+                        name
+                    )));
+                }
+            }
+            clock.timers.insert(name, timer);
+        }
+        Ok(GameClock { clock })
+    }
+}
+
+#[pymethods]
+impl GameClock {
+    #[new]
+    #[pyo3(signature = (fixed_step = 1.0 / 60.0, time_scale = 1.0, max_steps = 8))]
+    fn new(fixed_step: f64, time_scale: f64, max_steps: u32) -> PyResult<Self> {
+        let time_scale = check_time_scale(time_scale)?;
+        let fixed_step = check_positive("fixed_step", fixed_step)?;
+        Ok(GameClock {
+            clock: clock::GameClock::new(fixed_step, time_scale, max_steps),
+        })
+    }
+
+    /// Scaled game time elapsed
+    #[getter(time)]
+    fn get_time(&self) -> f64 {
+        self.clock.time
+    }
+
+    /// Unscaled time passed to `tick`, including while paused
+    #[getter]
+    fn real_time(&self) -> f64 {
+        self.clock.real_time
+    }
+
+    /// Scaled time added by the last tick
+    #[getter]
+    fn delta(&self) -> f64 {
+        self.clock.delta
+    }
+
+    #[getter]
+    fn frame(&self) -> u64 {
+        self.clock.frame
+    }
+
+    #[getter]
+    fn paused(&self) -> bool {
+        self.clock.paused
+    }
+
+    #[getter]
+    fn time_scale(&self) -> f64 {
+        self.clock.time_scale
+    }
+
+    #[setter]
+    fn set_time_scale(&mut self, time_scale: f64) -> PyResult<()> {
+        self.clock.time_scale = check_time_scale(time_scale)?;
+        Ok(())
+    }
+
+    #[getter(fixed_step)]
+    fn get_fixed_step(&self) -> f64 {
+        self.clock.fixed_step
+    }
+
+    #[setter]
+    fn set_fixed_step(&mut self, fixed_step: f64) -> PyResult<()> {
+        self.clock.fixed_step = check_positive("fixed_step", fixed_step)?;
+        Ok(())
+    }
+
+    /// Fixed steps a single tick may produce; leftover time beyond it is dropped so a
+    /// long hitch cannot snowball into ever longer catch-up frames
+    #[getter]
+    fn max_steps(&self) -> u32 {
+        self.clock.max_steps
+    }
+
+    #[setter]
+    fn set_max_steps(&mut self, max_steps: u32) {
+        self.clock.max_steps = max_steps;
+    }
+
+    /// Interpolation factor between the last two fixed steps, 0 to 1
+    #[getter]
+    fn alpha(&self) -> f64 {
+        self.clock.alpha()
+    }
+
+    fn pause(&mut self) {
+        self.clock.paused = true;
+    }
+
+    fn resume(&mut self) {
+        self.clock.paused = false;
+    }
+
+    /// Advance by a real frame time, returning the number of fixed steps due
+    fn tick(&mut self, real_delta: f64) -> PyResult<u32> {
+        let _scope = profiling::scope("GameClock.tick");
+        self.clock
+            .advance(real_delta)
+            .map_err(|error| PyValueError::new_err(error.to_string()))
+    }
+
+    /// Start (or restart) a countdown of `duration` game seconds
+    #[pyo3(signature = (name, duration, repeat = false))]
+    fn start_timer(&mut self, name: String, duration: f64, repeat: bool) -> PyResult<()> {
+        let duration = check_positive("timer duration", duration)?;
+        self.clock
+            .timers
+            .insert(name, Timer::countdown(duration, repeat));
+        Ok(())
+    }
+
+    /// Start (or restart) a stopwatch counting game seconds from zero
+    fn start_stopwatch(&mut self, name: String) {
+        self.clock.timers.insert(name, Timer::stopwatch());
+    }
+
+    /// Pause or resume a stopwatch
+    fn set_stopwatch_running(&mut self, name: &str, running: bool) -> PyResult<()> {
+        match self.timer_mut(name)? {
+            Timer::Stopwatch { running: state, .. } => {
+                *state = running;
+                Ok(())
+            }
+            Timer::Countdown { .. } => Err(PyValueError::new_err(format!(
+                "'{}' is a countdown, not a stopwatch",
+                name
+            ))),
+        }
+    }
+
+    /// Remove a timer or stopwatch, returning whether it existed
+    fn cancel_timer(&mut self, name: &str) -> bool {
+        self.clock.timers.remove(name).is_some()
+    }
+
+    /// Game seconds left on a countdown
+    fn remaining(&self, name: &str) -> PyResult<f64> {
+        self.timer(name)?.remaining().ok_or_else(|| {
+            PyValueError::new_err(format!("'{}' is a stopwatch, not a countdown", name))
+        })
+    }
+
+    /// Game seconds counted by a stopwatch, or run so far by a countdown
+    fn elapsed(&self, name: &str) -> PyResult<f64> {
+        Ok(self.timer(name)?.elapsed())
+    }
+
+    /// Whether a one-shot countdown has run out
+    fn finished(&self, name: &str) -> PyResult<bool> {
+        Ok(self.timer(name)?.finished())
+    }
+
+    /// Countdowns that ran out during the last tick, with how many times each did
+    fn fired(&self) -> Vec<(String, u32)> {
+        self.clock.fired()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "GameClock(time={:.3}, time_scale={}, paused={})",
+            self.clock.time, self.clock.time_scale, self.clock.paused
+        )
+    }
+}
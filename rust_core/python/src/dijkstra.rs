@@ -0,0 +1,69 @@
+use pyo3::prelude::*;
+
+pub use llamaquest::dijkstra::DijkstraMap;
+
+use crate::cancel::{self, CancelToken};
+use crate::grid;
+use crate::profiling;
+use crate::progress::{self, Sink};
+
+/// Check a walkable map and the seed cells that will be scanned from
+pub fn require_cells(
+    walkable_map: &[Vec<bool>],
+    cells: &[(usize, usize)],
+    what: &str,
+) -> PyResult<()> {
+    let size = grid::require_rectangular(walkable_map, "walkable_map")?;
+    for &cell in cells {
+        grid::require_cell(what, cell, size, "walkable_map")?;
+    }
+    Ok(())
+}
+
+/// Calculate the walking distance from every cell to the nearest goal
+#[pyfunction]
+pub fn calculate_dijkstra_map(
+    py: Python<'_>,
+    goals: Vec<(usize, usize)>,
+    walkable_map: Vec<Vec<bool>>,
+    diagonal: Option<bool>,
+    cancel: Option<PyRef<CancelToken>>,
+    progress: Option<&PyAny>,
+) -> PyResult<Vec<Vec<f32>>> {
+    let _scope = profiling::scope("calculate_dijkstra_map");
+    let diagonal = diagonal.unwrap_or(false);
+    require_cells(&walkable_map, &goals, "goal")?;
+    let sink = Sink::from_arg(progress)?;
+    cancel::run(py, cancel.as_deref(), "calculate_dijkstra_map", || {
+        progress::watch(sink, || {
+            DijkstraMap::from_goals(&walkable_map, &goals, diagonal).to_rows()
+        })
+    })?
+}
+
+/// Calculate a flee map that leads away from the given threats towards safe ground.
+///
+/// Rolling downhill on the result moves away from danger; `coefficient` defaults to
+/// the usual -1.2.
+#[pyfunction]
+pub fn calculate_flee_map(
+    py: Python<'_>,
+    threats: Vec<(usize, usize)>,
+    walkable_map: Vec<Vec<bool>>,
+    diagonal: Option<bool>,
+    coefficient: Option<f32>,
+    cancel: Option<PyRef<CancelToken>>,
+    progress: Option<&PyAny>,
+) -> PyResult<Vec<Vec<f32>>> {
+    let _scope = profiling::scope("calculate_flee_map");
+    let (diagonal, coefficient) = (diagonal.unwrap_or(false), coefficient.unwrap_or(-1.2));
+    require_cells(&walkable_map, &threats, "threat")?;
+    let sink = Sink::from_arg(progress)?;
+    cancel::run(py, cancel.as_deref(), "calculate_flee_map", || {
+        progress::watch(sink, || {
+            let mut map = DijkstraMap::from_goals(&walkable_map, &threats, diagonal);
+            map.invert(coefficient);
+            map.to_rows()
+        })
+    })?
+}
@@ -0,0 +1,36 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+pub use llamaquest::distance::{wall_distance, Metric};
+
+use crate::cancel::{self, CancelToken};
+use crate::grid;
+use crate::profiling;
+
+/// Interpret a `metric` argument
+pub fn parse_metric(name: &str) -> PyResult<Metric> {
+    Metric::from_name(name).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "unknown distance metric '{}', expected 'chebyshev', 'manhattan' or 'euclidean'",
+            name
+        ))
+    })
+}
+
+/// Calculate, for every cell, the distance to the nearest non-walkable cell
+#[pyfunction]
+pub fn calculate_wall_distance(
+    py: Python<'_>,
+    walkable_map: Vec<Vec<bool>>,
+    metric: Option<&str>,
+    edges_block: Option<bool>,
+    cancel: Option<PyRef<CancelToken>>,
+) -> PyResult<Vec<Vec<f32>>> {
+    let _scope = profiling::scope("calculate_wall_distance");
+    let metric = parse_metric(metric.unwrap_or("chebyshev"))?;
+    let edges_block = edges_block.unwrap_or(true);
+    grid::require_rectangular(&walkable_map, "walkable_map")?;
+    cancel::run(py, cancel.as_deref(), "calculate_wall_distance", || {
+        wall_distance(&walkable_map, metric, edges_block)
+    })
+}
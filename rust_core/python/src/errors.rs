@@ -0,0 +1,41 @@
+//! Exception types raised by the engine.
+//!
+//! Each one subclasses the built-in exception that was raised before it existed, so
+//! existing `except ValueError` or `except IndexError` handlers keep working.
+
+// pyo3 0.18's `create_exception!` checks a cfg that newer compilers do not know about
+#![allow(unexpected_cfgs)]
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyIndexError, PyRuntimeError, PyValueError};
+
+create_exception!(
+    llamaquest_core,
+    MapShapeError,
+    PyValueError,
+    "A grid argument is ragged, empty or does not match the size it must have."
+);
+create_exception!(
+    llamaquest_core,
+    OutOfBoundsError,
+    PyIndexError,
+    "A cell or coordinate lies outside the grid it refers to."
+);
+create_exception!(
+    llamaquest_core,
+    NoPathError,
+    PyRuntimeError,
+    "The requested destination cannot be reached from the start."
+);
+create_exception!(
+    llamaquest_core,
+    SerializationError,
+    PyValueError,
+    "Serialized data such as a JSON description or a Tiled map could not be decoded."
+);
+create_exception!(
+    llamaquest_core,
+    Cancelled,
+    PyRuntimeError,
+    "A computation was stopped early because its `CancelToken` was tripped."
+);
@@ -0,0 +1,146 @@
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::events;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::clock::GameClock;
+use crate::profiling;
+use crate::pyjson;
+use crate::state::Persist;
+
+/// Pending game-time events (buff expiries, fuses, respawns) with arbitrary
+/// Python payloads.
+///
+/// Events are scheduled at an absolute time or after a delay from `now`, handed
+/// back in time order once they fall due, and can be cancelled by their handle.
+/// Events due at the same time come out in the order they were scheduled.
+#[pyclass]
+pub struct EventScheduler {
+    events: events::EventScheduler<PyObject>,
+}
+
+fn finite(handle: Option<u64>) -> PyResult<u64> {
+    handle.ok_or_else(|| PyValueError::new_err("event time must be finite"))
+}
+
+impl Persist for EventScheduler {
+    const KIND: &'static str = "event_scheduler";
+
+    fn save(&self, py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let events = &self.events;
+        out.f64(events.now());
+        out.varint(events.next_handle());
+        // By handle, which is scheduling order, so ties still come out in order after a load
+        let pending = events.events();
+        out.usize(pending.len());
+        for (handle, time, payload) in pending {
+            out.varint(handle);
+            out.f64(time);
+            out.str(&pyjson::dumps(py, payload.as_ref(py))?);
+        }
+        Ok(())
+    }
+
+    fn load(py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let now = input.f64()?;
+        if !now.is_finite() {
+            return Err(DecodeError::Invalid(
+                "event scheduler time must be finite".to_string(),
+            ));
+        }
+        let mut events = events::EventScheduler::resume(now, input.varint()?);
+        for _ in 0..input.usize()? {
+            let handle = input.varint()?;
+            let time = input.f64()?;
+            // A handle at or past next_handle would be handed out again by the next schedule
+            if handle >= events.next_handle() || events.contains(handle) {
+                return Err(DecodeError::Invalid(format!(
+                    "event handle {} is repeated or was never handed out",
+                    handle
+                )));
+            }
+            if !time.is_finite() {
+                return Err(DecodeError::Invalid(format!(
+                    "event {} must have a finite time",
+                    handle
+                )));
+            }
+            let payload = pyjson::loads(py, input.str()?)
+                .map_err(|e| DecodeError::Invalid(format!("event payload: {}", e)))?;
+            events.restore(handle, time, payload);
+        }
+        Ok(EventScheduler { events })
+    }
+}
+
+#[pymethods]
+impl EventScheduler {
+    #[new]
+    #[pyo3(signature = (now = 0.0))]
+    fn new(now: f64) -> Self {
+        EventScheduler {
+            events: events::EventScheduler::new(now),
+        }
+    }
+
+    #[getter]
+    fn now(&self) -> f64 {
+        self.events.now()
+    }
+
+    /// Schedule an event at an absolute game time and return its handle
+    fn schedule_at(&mut self, time: f64, payload: PyObject) -> PyResult<u64> {
+        finite(self.events.schedule_at(time, payload))
+    }
+
+    /// Schedule an event `delay` after the current time and return its handle
+    fn schedule_in(&mut self, delay: f64, payload: PyObject) -> PyResult<u64> {
+        finite(self.events.schedule_in(delay, payload))
+    }
+
+    /// Cancel a pending event, returning whether it was still pending
+    fn cancel(&mut self, handle: u64) -> bool {
+        self.events.cancel(handle)
+    }
+
+    /// Game time of a pending event
+    fn time_of(&self, handle: u64) -> Option<f64> {
+        self.events.time_of(handle)
+    }
+
+    /// Time of the next pending event
+    fn peek_time(&mut self) -> Option<f64> {
+        self.events.peek_time()
+    }
+
+    /// Move time forward by `delta_time` and return the events that fell due as
+    /// `(handle, time, payload)` in order
+    fn advance(&mut self, delta_time: f64) -> Vec<(u64, f64, PyObject)> {
+        let _scope = profiling::scope("EventScheduler.advance");
+        self.events.advance(delta_time)
+    }
+
+    /// Set the current time and return the events due by then, as `advance` does
+    fn advance_to(&mut self, time: f64) -> Vec<(u64, f64, PyObject)> {
+        let _scope = profiling::scope("EventScheduler.advance_to");
+        self.events.advance_to(time)
+    }
+
+    /// Catch up with a game clock, returning the events due by its current time
+    fn sync(&mut self, clock: PyRef<GameClock>) -> Vec<(u64, f64, PyObject)> {
+        self.advance_to(clock.time())
+    }
+
+    /// Drop every pending event
+    fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    fn __len__(&self) -> usize {
+        self.events.len()
+    }
+
+    fn __contains__(&self, handle: u64) -> bool {
+        self.events.contains(handle)
+    }
+}
@@ -0,0 +1,41 @@
+use llamaquest::explore::{autoexplore, ExploreTarget};
+use pyo3::prelude::*;
+
+use crate::cancel::{self, CancelToken};
+use crate::grid;
+use crate::profiling;
+use crate::progress::{self, Sink};
+
+/// Find the nearest unexplored frontier tile and the path to it
+#[allow(clippy::too_many_arguments)]
+#[pyfunction]
+pub fn calculate_autoexplore(
+    py: Python<'_>,
+    start_x: usize,
+    start_y: usize,
+    explored_map: Vec<Vec<bool>>,
+    visible_map: Vec<Vec<bool>>,
+    walkable_map: Vec<Vec<bool>>,
+    diagonal: Option<bool>,
+    cancel: Option<PyRef<CancelToken>>,
+    progress: Option<&PyAny>,
+) -> PyResult<Option<ExploreTarget>> {
+    let _scope = profiling::scope("calculate_autoexplore");
+    let diagonal = diagonal.unwrap_or(false);
+    let size = grid::require_non_empty(&walkable_map, "walkable_map")?;
+    grid::require_size(&explored_map, "explored_map", size)?;
+    grid::require_size(&visible_map, "visible_map", size)?;
+    grid::require_cell("start", (start_x, start_y), size, "walkable_map")?;
+    let sink = Sink::from_arg(progress)?;
+    cancel::run(py, cancel.as_deref(), "calculate_autoexplore", || {
+        progress::watch(sink, || {
+            autoexplore(
+                (start_x, start_y),
+                &explored_map,
+                &visible_map,
+                &walkable_map,
+                diagonal,
+            )
+        })
+    })?
+}
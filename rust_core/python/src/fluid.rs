@@ -0,0 +1,207 @@
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::fluid::{self, Medium};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::cancel::{self, CancelToken};
+use crate::errors::OutOfBoundsError;
+use crate::grid;
+use crate::profiling;
+use crate::progress::{self, Sink};
+use crate::state::{self, Persist};
+
+/// Gas or liquid spreading over a grid, contained by walls.
+///
+/// Gas flows from high to low concentration and thins out by `evaporation` (a
+/// fraction) each step. Liquid behaves like water finding its level: cells only
+/// push to lower neighbours while deeper than `min_depth`, so floods settle into
+/// puddles, and `evaporation` is removed as a fixed depth per step.
+#[pyclass]
+pub struct FluidGrid {
+    grid: fluid::FluidGrid,
+}
+
+impl FluidGrid {
+    fn index(&self, x: usize, y: usize) -> PyResult<usize> {
+        self.grid.index(x, y).ok_or_else(|| {
+            OutOfBoundsError::new_err(format!(
+                "cell ({}, {}) is outside the {}x{} fluid grid",
+                x, y, self.grid.width, self.grid.height
+            ))
+        })
+    }
+}
+
+impl Persist for FluidGrid {
+    const KIND: &'static str = "fluid_grid";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let grid = &self.grid;
+        out.usize(grid.width);
+        out.usize(grid.height);
+        out.u8(match grid.medium {
+            Medium::Gas => 0,
+            Medium::Liquid => 1,
+        });
+        out.f32(grid.flow_rate);
+        out.f32(grid.evaporation);
+        out.f32(grid.min_depth);
+        state::write_f32s(out, &grid.values);
+        state::write_f32s(out, &grid.conductance);
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let (width, height, cells) = state::read_grid_size(input)?;
+        let medium = match input.u8()? {
+            0 => Medium::Gas,
+            1 => Medium::Liquid,
+            other => {
+                return Err(DecodeError::Invalid(format!(
+                    "unknown fluid medium {}",
+                    other
+                )))
+            }
+        };
+        let grid = fluid::FluidGrid {
+            width,
+            height,
+            medium,
+            flow_rate: input.f32()?,
+            evaporation: input.f32()?,
+            min_depth: input.f32()?,
+            values: state::read_f32s(input, cells)?,
+            conductance: state::read_f32s(input, cells)?,
+        };
+        Ok(FluidGrid { grid })
+    }
+}
+
+#[pymethods]
+impl FluidGrid {
+    #[new]
+    #[pyo3(signature = (walkable_map, medium = "gas", flow_rate = 0.8, evaporation = 0.02, min_depth = 0.05))]
+    fn new(
+        walkable_map: Vec<Vec<bool>>,
+        medium: &str,
+        flow_rate: f32,
+        evaporation: f32,
+        min_depth: f32,
+    ) -> PyResult<Self> {
+        let medium = Medium::from_name(medium).ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "unknown medium '{}', expected 'gas' or 'liquid'",
+                medium
+            ))
+        })?;
+        grid::require_rectangular(&walkable_map, "walkable_map")?;
+        Ok(FluidGrid {
+            grid: fluid::FluidGrid::new(&walkable_map, medium, flow_rate, evaporation, min_depth),
+        })
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.grid.width
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.grid.height
+    }
+
+    /// Share of a pressure difference that flows per step, 0 to 1
+    #[getter]
+    fn flow_rate(&self) -> f32 {
+        self.grid.flow_rate
+    }
+
+    #[setter]
+    fn set_flow_rate(&mut self, flow_rate: f32) {
+        self.grid.flow_rate = flow_rate;
+    }
+
+    #[getter]
+    fn evaporation(&self) -> f32 {
+        self.grid.evaporation
+    }
+
+    #[setter]
+    fn set_evaporation(&mut self, evaporation: f32) {
+        self.grid.evaporation = evaporation;
+    }
+
+    #[getter]
+    fn min_depth(&self) -> f32 {
+        self.grid.min_depth
+    }
+
+    #[setter]
+    fn set_min_depth(&mut self, min_depth: f32) {
+        self.grid.min_depth = min_depth;
+    }
+
+    /// Pour fluid into an open cell; walls never hold any
+    fn add(&mut self, x: usize, y: usize, amount: f32) -> PyResult<()> {
+        let index = self.index(x, y)?;
+        self.grid.add(index, amount);
+        Ok(())
+    }
+
+    /// Open or close a cell, e.g. for doors; closing it removes the fluid inside
+    fn set_open(&mut self, x: usize, y: usize, open: bool) -> PyResult<()> {
+        let index = self.index(x, y)?;
+        self.grid.set_open(index, open);
+        Ok(())
+    }
+
+    /// Advance the simulation, once per turn or frame
+    #[pyo3(signature = (steps = 1, cancel = None, progress = None))]
+    fn step(
+        &mut self,
+        py: Python<'_>,
+        steps: usize,
+        cancel: Option<PyRef<CancelToken>>,
+        progress: Option<&PyAny>,
+    ) -> PyResult<()> {
+        let _scope = profiling::scope("FluidGrid.step");
+        let sink = Sink::from_arg(progress)?;
+        cancel::run(py, cancel.as_deref(), "FluidGrid.step", || {
+            progress::watch(sink, || self.grid.step(steps))
+        })?
+    }
+
+    fn get(&self, x: usize, y: usize) -> PyResult<f32> {
+        Ok(self.grid.values[self.index(x, y)?])
+    }
+
+    /// Total amount of fluid on the grid
+    fn total(&self) -> f32 {
+        self.grid.total()
+    }
+
+    fn clear(&mut self) {
+        self.grid.values.fill(0.0);
+    }
+
+    /// The concentrations as a `[y][x]` grid
+    fn to_list(&self) -> Vec<Vec<f32>> {
+        let grid = &self.grid;
+        if grid.width == 0 {
+            return vec![Vec::new(); grid.height];
+        }
+        grid.values
+            .chunks(grid.width)
+            .map(|row| row.to_vec())
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "FluidGrid(width={}, height={}, medium='{}')",
+            self.grid.width,
+            self.grid.height,
+            self.grid.medium.name()
+        )
+    }
+}
@@ -0,0 +1,109 @@
+use llamaquest::formation::{self, formation_slots, plan_move, Shape};
+use llamaquest::vec2::Vec2;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::cancel::{self, CancelToken};
+use crate::grid;
+use crate::profiling;
+use crate::progress::{self, Sink};
+
+fn parse_shape(name: &str) -> PyResult<Shape> {
+    Shape::from_name(name).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "unknown formation '{}', expected 'line', 'wedge' or 'box'",
+            name
+        ))
+    })
+}
+
+/// A planned group move: where each unit ends up, how it gets there and when it leaves
+#[pyclass]
+pub struct FormationMove {
+    plan: formation::FormationMove,
+}
+
+#[pymethods]
+impl FormationMove {
+    /// Destination cell of each unit, in the order the units were given
+    #[getter]
+    fn slots(&self) -> Vec<Option<(usize, usize)>> {
+        self.plan.slots.clone()
+    }
+
+    /// Path of each unit to its slot, including both ends, or `None` if unreachable
+    #[getter]
+    fn paths(&self) -> Vec<Option<Vec<(usize, usize)>>> {
+        self.plan.paths.clone()
+    }
+
+    /// Unit indices from the front of the formation to the back; units earlier in
+    /// the list should be moved first so they are not blocked by those behind them
+    #[getter]
+    fn arrival_order(&self) -> Vec<usize> {
+        self.plan.arrival_order.clone()
+    }
+
+    /// Turns each unit should wait before setting off so the group arrives together
+    #[getter]
+    fn delays(&self) -> Vec<usize> {
+        self.plan.delays.clone()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("FormationMove(units={})", self.plan.slots.len())
+    }
+}
+
+/// Calculate formation slot positions around an anchor, front slots first
+#[pyfunction]
+#[pyo3(signature = (shape, count, x, y, facing_x, facing_y, spacing = 1.0))]
+pub fn calculate_formation_slots(
+    shape: &str,
+    count: usize,
+    x: f32,
+    y: f32,
+    facing_x: f32,
+    facing_y: f32,
+    spacing: f32,
+) -> PyResult<Vec<Vec2>> {
+    let _scope = profiling::scope("calculate_formation_slots");
+    let slots = formation_slots(
+        parse_shape(shape)?,
+        count,
+        Vec2::new(x, y),
+        Vec2::new(facing_x, facing_y),
+        spacing,
+    );
+    Ok(slots)
+}
+
+/// Plan a group move: the formation faces from the group's centre towards `destination`
+#[allow(clippy::too_many_arguments)]
+#[pyfunction]
+#[pyo3(signature = (units, destination, walkable_map, shape = "line", spacing = 1.0, diagonal = false, cancel = None, progress = None))]
+pub fn calculate_formation_move(
+    py: Python<'_>,
+    units: Vec<(usize, usize)>,
+    destination: (usize, usize),
+    walkable_map: Vec<Vec<bool>>,
+    shape: &str,
+    spacing: f32,
+    diagonal: bool,
+    cancel: Option<PyRef<CancelToken>>,
+    progress: Option<&PyAny>,
+) -> PyResult<FormationMove> {
+    let _scope = profiling::scope("calculate_formation_move");
+    let shape = parse_shape(shape)?;
+    let size = grid::require_non_empty(&walkable_map, "walkable_map")?;
+    grid::require_cell("destination", destination, size, "walkable_map")?;
+    for &unit in &units {
+        grid::require_cell("unit", unit, size, "walkable_map")?;
+    }
+    let sink = Sink::from_arg(progress)?;
+    cancel::run(py, cancel.as_deref(), "calculate_formation_move", || {
+        progress::watch(sink, || FormationMove {
+            plan: plan_move(&units, destination, &walkable_map, shape, spacing, diagonal),
+        })
+    })?
+}
@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::fsm::{self, BlackboardValue, Check, Comparison, Entity, State, Transition};
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use serde_json::Value;
+
+use crate::profiling;
+use crate::pyjson;
+use crate::state::Persist;
+
+fn value_from_json(value: &Value) -> Option<BlackboardValue> {
+    match value {
+        Value::Bool(value) => Some(BlackboardValue::Bool(*value)),
+        Value::Number(value) => value.as_f64().map(BlackboardValue::Number),
+        Value::String(value) => Some(BlackboardValue::Text(value.clone())),
+        _ => None,
+    }
+}
+
+/// Every comparison, in the order their tags are saved
+const COMPARISONS: [Comparison; 6] = [
+    Comparison::Less,
+    Comparison::LessOrEqual,
+    Comparison::Greater,
+    Comparison::GreaterOrEqual,
+    Comparison::Equal,
+    Comparison::NotEqual,
+];
+
+/// Many entities running the same state machine definition.
+///
+/// A definition names its `initial` state and, for each state, an ordered list of
+/// transitions that fire on time in state (`after`), on an event sent from Python
+/// (`event`), and/or on blackboard comparisons (`when`). The first transition whose
+/// conditions all hold is taken, at most one per entity per tick.
+#[pyclass]
+pub struct StateMachines {
+    machines: fsm::StateMachines,
+}
+
+fn parse_checks(value: &Value, path: &str) -> PyResult<Vec<Check>> {
+    let invalid = |message: &str| PyValueError::new_err(format!("{}: {}", path, message));
+    let items: Vec<&Value> = match value {
+        Value::Array(items) => items.iter().collect(),
+        single => vec![single],
+    };
+    items
+        .into_iter()
+        .map(|item| {
+            let key = item
+                .get("key")
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid("'when' needs a 'key'"))?;
+            let op = item.get("op").and_then(Value::as_str).unwrap_or("==");
+            let comparison = Comparison::from_op(op)
+                .ok_or_else(|| invalid(&format!("unknown comparison '{}'", op)))?;
+            let value = item
+                .get("value")
+                .and_then(value_from_json)
+                .ok_or_else(|| invalid("'when' needs a bool, number or string 'value'"))?;
+            Ok(Check {
+                key: key.to_string(),
+                comparison,
+                value,
+            })
+        })
+        .collect()
+}
+
+fn parse_definition(value: &Value) -> PyResult<(Vec<State>, usize)> {
+    let invalid = |message: String| PyValueError::new_err(message);
+    let states = value
+        .get("states")
+        .and_then(Value::as_object)
+        .ok_or_else(|| invalid("state machine needs a 'states' object".to_string()))?;
+    let names: Vec<&String> = states.keys().collect();
+    let index_of = |name: &str| names.iter().position(|candidate| *candidate == name);
+
+    let initial_name = value
+        .get("initial")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("state machine needs an 'initial' state".to_string()))?;
+    let initial = index_of(initial_name)
+        .ok_or_else(|| invalid(format!("initial state '{}' is not defined", initial_name)))?;
+
+    let mut parsed = Vec::with_capacity(states.len());
+    for (name, state) in states {
+        let mut transitions = Vec::new();
+        let empty = Vec::new();
+        let list = match state.get("transitions") {
+            None => &empty,
+            Some(Value::Array(list)) => list,
+            Some(_) => return Err(invalid(format!("{}: 'transitions' must be a list", name))),
+        };
+        for (i, transition) in list.iter().enumerate() {
+            let path = format!("{}/transitions[{}]", name, i);
+            let to_name = transition
+                .get("to")
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid(format!("{}: transition needs a 'to' state", path)))?;
+            let to = index_of(to_name).ok_or_else(|| {
+                invalid(format!(
+                    "{}: target state '{}' is not defined",
+                    path, to_name
+                ))
+            })?;
+            transitions.push(Transition {
+                to,
+                after: transition.get("after").and_then(Value::as_f64),
+                event: transition
+                    .get("event")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                checks: match transition.get("when") {
+                    Some(when) => parse_checks(when, &path)?,
+                    None => Vec::new(),
+                },
+            });
+        }
+        parsed.push(State {
+            name: name.clone(),
+            transitions,
+        });
+    }
+    Ok((parsed, initial))
+}
+
+fn no_entity(id: usize) -> PyErr {
+    PyIndexError::new_err(format!("no state machine entity with id {}", id))
+}
+
+impl StateMachines {
+    fn entity(&self, id: usize) -> PyResult<&Entity> {
+        self.machines.entity(id).ok_or_else(|| no_entity(id))
+    }
+
+    fn entity_mut(&mut self, id: usize) -> PyResult<&mut Entity> {
+        self.machines.entity_mut(id).ok_or_else(|| no_entity(id))
+    }
+
+    fn state_index(&self, name: &str) -> PyResult<usize> {
+        self.machines
+            .state_index(name)
+            .ok_or_else(|| PyKeyError::new_err(format!("no state named '{}'", name)))
+    }
+}
+
+fn save_value(out: &mut Writer, value: &BlackboardValue) {
+    match value {
+        BlackboardValue::Bool(value) => {
+            out.u8(0);
+            out.bool(*value);
+        }
+        BlackboardValue::Number(value) => {
+            out.u8(1);
+            out.f64(*value);
+        }
+        BlackboardValue::Text(value) => {
+            out.u8(2);
+            out.str(value);
+        }
+    }
+}
+
+fn load_value(input: &mut Reader) -> Result<BlackboardValue, DecodeError> {
+    Ok(match input.u8()? {
+        0 => BlackboardValue::Bool(input.bool()?),
+        1 => BlackboardValue::Number(input.f64()?),
+        2 => BlackboardValue::Text(input.str()?.to_string()),
+        other => {
+            return Err(DecodeError::Invalid(format!(
+                "unknown blackboard value kind {}",
+                other
+            )))
+        }
+    })
+}
+
+impl Persist for StateMachines {
+    const KIND: &'static str = "state_machines";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let machines = &self.machines;
+        out.usize(machines.states.len());
+        for state in &machines.states {
+            out.str(&state.name);
+            out.usize(state.transitions.len());
+            for transition in &state.transitions {
+                out.usize(transition.to);
+                out.option(transition.after, Writer::f64);
+                out.option(transition.event.as_deref(), Writer::str);
+                out.usize(transition.checks.len());
+                for check in &transition.checks {
+                    out.str(&check.key);
+                    let tag = COMPARISONS.iter().position(|&c| c == check.comparison);
+                    out.u8(tag.expect("every comparison has a tag") as u8);
+                    save_value(out, &check.value);
+                }
+            }
+        }
+        out.usize(machines.initial);
+        out.usize(machines.entities.len());
+        for entity in &machines.entities {
+            out.option(entity.as_ref(), |out, entity| {
+                out.usize(entity.state);
+                out.f64(entity.time_in_state);
+                // Sorted, so equal machines always encode (and checksum) the same
+                let mut blackboard: Vec<_> = entity.blackboard.iter().collect();
+                blackboard.sort_by(|a, b| a.0.cmp(b.0));
+                out.usize(blackboard.len());
+                for (key, value) in blackboard {
+                    out.str(key);
+                    save_value(out, value);
+                }
+                let mut events: Vec<_> = entity.events.iter().collect();
+                events.sort();
+                out.usize(events.len());
+                for event in events {
+                    out.str(event);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let mut states = Vec::new();
+        for _ in 0..input.usize()? {
+            let name = input.str()?.to_string();
+            let mut transitions = Vec::new();
+            for _ in 0..input.usize()? {
+                let to = input.usize()?;
+                let after = input.option(Reader::f64)?;
+                let event = input.option(|input| Ok(input.str()?.to_string()))?;
+                let mut checks = Vec::new();
+                for _ in 0..input.usize()? {
+                    let key = input.str()?.to_string();
+                    let tag = input.u8()?;
+                    let comparison = *COMPARISONS.get(tag as usize).ok_or_else(|| {
+                        DecodeError::Invalid(format!("unknown comparison {}", tag))
+                    })?;
+                    checks.push(Check {
+                        key,
+                        comparison,
+                        value: load_value(input)?,
+                    });
+                }
+                transitions.push(Transition {
+                    to,
+                    after,
+                    event,
+                    checks,
+                });
+            }
+            states.push(State { name, transitions });
+        }
+        let count = states.len();
+        let check_state = |index: usize, what: &str| {
+            if index < count {
+                Ok(index)
+            } else {
+                Err(DecodeError::Invalid(format!(
+                    "{} state {} is out of range for {} states",
+                    what, index, count
+                )))
+            }
+        };
+        for state in &states {
+            for transition in &state.transitions {
+                check_state(transition.to, "transition target")?;
+            }
+        }
+        let initial = check_state(input.usize()?, "initial")?;
+        let mut entities = Vec::new();
+        for _ in 0..input.usize()? {
+            entities.push(input.option(|input| {
+                let state = check_state(input.usize()?, "entity")?;
+                let time_in_state = input.f64()?;
+                let mut blackboard = HashMap::new();
+                for _ in 0..input.usize()? {
+                    let key = input.str()?.to_string();
+                    blackboard.insert(key, load_value(input)?);
+                }
+                let events = (0..input.usize()?)
+                    .map(|_| Ok(input.str()?.to_string()))
+                    .collect::<Result<_, DecodeError>>()?;
+                Ok(Entity {
+                    state,
+                    time_in_state,
+                    blackboard,
+                    events,
+                })
+            })?);
+        }
+        let machines = fsm::StateMachines {
+            states,
+            initial,
+            entities,
+        };
+        Ok(StateMachines { machines })
+    }
+}
+
+#[pymethods]
+impl StateMachines {
+    /// Build from a dict definition or the equivalent JSON string
+    #[new]
+    fn new(py: Python<'_>, definition: &PyAny) -> PyResult<Self> {
+        let value = pyjson::to_json_value(py, definition)?;
+        let (states, initial) = parse_definition(&value)?;
+        Ok(StateMachines {
+            machines: fsm::StateMachines::new(states, initial),
+        })
+    }
+
+    /// Add an entity in the initial state and return its id
+    fn add_entity(&mut self, blackboard: Option<HashMap<String, BlackboardValue>>) -> usize {
+        self.machines.add_entity(blackboard.unwrap_or_default())
+    }
+
+    fn remove_entity(&mut self, id: usize) -> PyResult<()> {
+        if !self.machines.remove_entity(id) {
+            return Err(no_entity(id));
+        }
+        Ok(())
+    }
+
+    fn set_value(&mut self, id: usize, key: String, value: BlackboardValue) -> PyResult<()> {
+        self.entity_mut(id)?.blackboard.insert(key, value);
+        Ok(())
+    }
+
+    fn get_value(&self, id: usize, key: &str) -> PyResult<Option<BlackboardValue>> {
+        Ok(self.entity(id)?.blackboard.get(key).cloned())
+    }
+
+    /// Queue an event for one entity's next tick
+    fn send_event(&mut self, id: usize, event: String) -> PyResult<()> {
+        self.entity_mut(id)?.events.insert(event);
+        Ok(())
+    }
+
+    /// Queue an event for every entity's next tick
+    fn broadcast(&mut self, event: &str) {
+        self.machines.broadcast(event);
+    }
+
+    fn state(&self, id: usize) -> PyResult<String> {
+        Ok(self.machines.states[self.entity(id)?.state].name.clone())
+    }
+
+    fn time_in_state(&self, id: usize) -> PyResult<f64> {
+        Ok(self.entity(id)?.time_in_state)
+    }
+
+    /// Move an entity to a state directly, resetting its time in state
+    fn force_state(&mut self, id: usize, state: &str) -> PyResult<()> {
+        let state = self.state_index(state)?;
+        let entity = self.entity_mut(id)?;
+        entity.state = state;
+        entity.time_in_state = 0.0;
+        Ok(())
+    }
+
+    fn state_names(&self) -> Vec<String> {
+        self.machines.state_names()
+    }
+
+    /// Tick every entity, returning `(id, from_state, to_state)` for those that changed
+    fn tick(&mut self, delta_time: f64) -> Vec<(usize, String, String)> {
+        let _scope = profiling::scope("StateMachines.tick");
+        let changes = self.machines.advance(delta_time);
+        let states = &self.machines.states;
+        changes
+            .into_iter()
+            .map(|(id, from, to)| (id, states[from].name.clone(), states[to].name.clone()))
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.machines.len()
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use llamaquest::goap;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::cancel::{self, CancelToken};
+use crate::profiling;
+
+/// Goal-oriented action planner over symbolic world states.
+///
+/// Variables are named integers (booleans work as 0/1). An action applies when all of
+/// its preconditions hold and then overwrites the variables in its effects. Plans are
+/// found with A* and are the cheapest sequence reaching every goal condition.
+#[pyclass]
+pub struct GoapPlanner {
+    planner: goap::GoapPlanner,
+}
+
+#[pymethods]
+impl GoapPlanner {
+    #[new]
+    fn new() -> Self {
+        GoapPlanner {
+            planner: goap::GoapPlanner::new(),
+        }
+    }
+
+    /// Register an action with its preconditions, effects and (positive) cost
+    #[pyo3(signature = (name, preconditions, effects, cost = 1.0))]
+    fn add_action(
+        &mut self,
+        name: String,
+        preconditions: HashMap<String, i64>,
+        effects: HashMap<String, i64>,
+        cost: f32,
+    ) -> PyResult<()> {
+        if !cost.is_finite() || cost <= 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "action '{}' must have a positive cost, got {}",
+                name, cost
+            )));
+        }
+        self.planner.add_action(name, preconditions, effects, cost);
+        Ok(())
+    }
+
+    /// Names of the registered actions, in registration order
+    fn action_names(&self) -> Vec<String> {
+        self.planner.action_names()
+    }
+
+    /// Plan from `state` to `goal`, returning action names or `None` if no plan was found
+    #[pyo3(signature = (state, goal, max_nodes = 10000, cancel = None))]
+    fn plan(
+        &self,
+        py: Python<'_>,
+        state: HashMap<String, i64>,
+        goal: HashMap<String, i64>,
+        max_nodes: usize,
+        cancel: Option<PyRef<CancelToken>>,
+    ) -> PyResult<Option<Vec<String>>> {
+        let _scope = profiling::scope("GoapPlanner.plan");
+        cancel::run(py, cancel.as_deref(), "GoapPlanner.plan", || {
+            self.planner.plan(state, goal, max_nodes)
+        })
+    }
+
+    fn __len__(&self) -> usize {
+        self.planner.len()
+    }
+}
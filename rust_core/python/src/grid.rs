@@ -0,0 +1,60 @@
+//! Shared helpers for the row-major `[y][x]` grids passed in from Python
+
+use pyo3::prelude::*;
+
+use llamaquest::grid::{check_cell, check_non_empty, check_rectangular, check_size, GridError};
+pub use llamaquest::grid::rectangular_dimensions;
+
+use crate::errors::{MapShapeError, OutOfBoundsError};
+
+/// The Python exception for a grid called `name` that failed a check; `what` names
+/// the cell for out-of-range errors
+fn grid_error(error: GridError, what: &str, name: &str) -> PyErr {
+    match error {
+        GridError::Ragged {
+            row,
+            length,
+            expected,
+        } => MapShapeError::new_err(format!(
+            "{} rows must all have the same length, but row 0 has length {} and row {} has length {}",
+            name, expected, row, length
+        )),
+        GridError::Empty { width, height } => MapShapeError::new_err(format!(
+            "{} must have at least one cell, got {}x{}",
+            name, width, height
+        )),
+        GridError::WrongSize { expected, found } => MapShapeError::new_err(format!(
+            "{} must be a {}x{} grid to match the map, got {}x{}",
+            name, expected.0, expected.1, found.0, found.1
+        )),
+        GridError::OutOfBounds { cell, size } => OutOfBoundsError::new_err(format!(
+            "{} ({}, {}) is outside the {}x{} {}",
+            what, cell.0, cell.1, size.0, size.1, name
+        )),
+    }
+}
+
+/// Width and height of a rectangular grid argument, rejecting ragged rows
+pub fn require_rectangular<T>(grid: &[Vec<T>], name: &str) -> PyResult<(usize, usize)> {
+    check_rectangular(grid).map_err(|error| grid_error(error, "cell", name))
+}
+
+/// Like [`require_rectangular`], but a grid without any cells is rejected too
+pub fn require_non_empty<T>(grid: &[Vec<T>], name: &str) -> PyResult<(usize, usize)> {
+    check_non_empty(grid).map_err(|error| grid_error(error, "cell", name))
+}
+
+/// Check that a grid argument is exactly `size` cells, e.g. a mask matching its map
+pub fn require_size<T>(grid: &[Vec<T>], name: &str, size: (usize, usize)) -> PyResult<()> {
+    check_size(grid, size).map_err(|error| grid_error(error, "cell", name))
+}
+
+/// Check that the cell called `what` lies inside a `size` grid called `name`
+pub fn require_cell(
+    what: &str,
+    cell: (usize, usize),
+    size: (usize, usize),
+    name: &str,
+) -> PyResult<()> {
+    check_cell(cell, size).map_err(|error| grid_error(error, what, name))
+}
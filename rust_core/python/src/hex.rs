@@ -0,0 +1,209 @@
+//! Python bindings for hex grid math. Hexes are `(q, r)` axial tuples throughout.
+
+use llamaquest::hex::{Hex, Layout, OffsetLayout, Orientation};
+use llamaquest::python::VecLike;
+use llamaquest::vec2::Vec2;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::profiling;
+
+type Axial = (i32, i32);
+
+fn to_tuples(hexes: Vec<Hex>) -> Vec<Axial> {
+    hexes.into_iter().map(Axial::from).collect()
+}
+
+fn parse_orientation(name: &str) -> PyResult<Orientation> {
+    Orientation::from_name(name).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "unknown hex orientation '{}', expected 'pointy' or 'flat'",
+            name
+        ))
+    })
+}
+
+fn parse_offset_layout(name: &str) -> PyResult<OffsetLayout> {
+    OffsetLayout::from_name(name).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "unknown offset layout '{}', expected 'odd-r', 'even-r', 'odd-q' or 'even-q'",
+            name
+        ))
+    })
+}
+
+/// The six hexes around `hex`, counter-clockwise from east (pointy-top)
+#[pyfunction]
+pub fn hex_neighbors(hex: Axial) -> Vec<Axial> {
+    let _scope = profiling::scope("hex_neighbors");
+    Hex::from(hex).neighbors().map(Axial::from).to_vec()
+}
+
+/// Steps between two hexes
+#[pyfunction]
+pub fn hex_distance(a: Axial, b: Axial) -> i32 {
+    let _scope = profiling::scope("hex_distance");
+    Hex::from(a).distance(b.into())
+}
+
+/// Hexes exactly `radius` steps from `center`
+#[pyfunction]
+pub fn hex_ring(center: Axial, radius: u32) -> Vec<Axial> {
+    let _scope = profiling::scope("hex_ring");
+    to_tuples(Hex::from(center).ring(radius))
+}
+
+/// Every hex within `radius` steps of `center`, ring by ring from the centre outwards
+#[pyfunction]
+pub fn hex_spiral(center: Axial, radius: u32) -> Vec<Axial> {
+    let _scope = profiling::scope("hex_spiral");
+    to_tuples(Hex::from(center).spiral(radius))
+}
+
+/// Hexes on the straight line from `a` to `b`, both included
+#[pyfunction]
+pub fn hex_line(a: Axial, b: Axial) -> Vec<Axial> {
+    let _scope = profiling::scope("hex_line");
+    to_tuples(Hex::from(a).line(b.into()))
+}
+
+/// The hex containing fractional axial coordinates
+#[pyfunction]
+pub fn hex_round(q: f32, r: f32) -> Axial {
+    let _scope = profiling::scope("hex_round");
+    Hex::round(q, r).into()
+}
+
+/// Cube coordinates `(q, r, s)` of an axial hex
+#[pyfunction]
+pub fn hex_to_cube(hex: Axial) -> (i32, i32, i32) {
+    let hex = Hex::from(hex);
+    (hex.q, hex.r, hex.s())
+}
+
+/// Axial coordinates of a cube hex, which must satisfy `q + r + s == 0`
+#[pyfunction]
+pub fn cube_to_hex(cube: (i32, i32, i32)) -> PyResult<Axial> {
+    let (q, r, s) = cube;
+    if q + r + s != 0 {
+        return Err(PyValueError::new_err(format!(
+            "cube coordinates must sum to 0, got ({}, {}, {})",
+            q, r, s
+        )));
+    }
+    Ok((q, r))
+}
+
+/// `(col, row)` of a hex in a rectangular array stored with `layout`
+#[pyfunction]
+#[pyo3(signature = (hex, layout = "odd-r"))]
+pub fn hex_to_offset(hex: Axial, layout: &str) -> PyResult<(i32, i32)> {
+    Ok(parse_offset_layout(layout)?.to_offset(hex.into()))
+}
+
+/// The hex at `(col, row)` of a rectangular array stored with `layout`
+#[pyfunction]
+#[pyo3(signature = (cell, layout = "odd-r"))]
+pub fn offset_to_hex(cell: (i32, i32), layout: &str) -> PyResult<Axial> {
+    Ok(parse_offset_layout(layout)?.to_axial(cell).into())
+}
+
+/// Conversion between hexes and pixel positions.
+///
+/// `size` is the distance from a hex's centre to its corners, either one number or
+/// an `(x, y)` pair for squashed hexes, and `origin` is where hex `(0, 0)` is drawn.
+#[pyclass]
+pub struct HexLayout {
+    layout: Layout,
+}
+
+#[derive(FromPyObject)]
+enum HexSize {
+    Uniform(f32),
+    Vector(VecLike),
+}
+
+#[pymethods]
+impl HexLayout {
+    #[new]
+    #[pyo3(signature = (orientation = "pointy", size = None, origin = None))]
+    fn new(orientation: &str, size: Option<HexSize>, origin: Option<VecLike>) -> PyResult<Self> {
+        let size = match size {
+            None => Vec2::new(1.0, 1.0),
+            Some(HexSize::Uniform(size)) => Vec2::new(size, size),
+            Some(HexSize::Vector(size)) => size.into(),
+        };
+        if !(size.x > 0.0 && size.y > 0.0) {
+            return Err(PyValueError::new_err("hex size must be positive"));
+        }
+        Ok(HexLayout {
+            layout: Layout {
+                orientation: parse_orientation(orientation)?,
+                size,
+                origin: origin.map_or(Vec2::ZERO, Vec2::from),
+            },
+        })
+    }
+
+    #[getter]
+    fn orientation(&self) -> &'static str {
+        match self.layout.orientation {
+            Orientation::Pointy => "pointy",
+            Orientation::Flat => "flat",
+        }
+    }
+
+    #[getter]
+    fn size(&self) -> Vec2 {
+        self.layout.size
+    }
+
+    #[getter]
+    fn origin(&self) -> Vec2 {
+        self.layout.origin
+    }
+
+    /// Pixel position of a hex's centre
+    fn to_pixel(&self, hex: Axial) -> Vec2 {
+        self.layout.to_pixel(hex.into())
+    }
+
+    /// The hex containing a pixel
+    fn hex_at(&self, point: VecLike) -> Axial {
+        self.layout.hex_at(point.into()).into()
+    }
+
+    /// Centres of many hexes at once, as a list of `(x, y)` tuples
+    fn to_pixels(&self, hexes: Vec<Axial>) -> Vec<(f32, f32)> {
+        let _scope = profiling::scope("HexLayout.to_pixels");
+        hexes
+            .into_iter()
+            .map(|hex| self.layout.to_pixel(hex.into()).into())
+            .collect()
+    }
+
+    /// The hexes containing many pixels at once
+    fn hexes_at(&self, points: Vec<(f32, f32)>) -> Vec<Axial> {
+        let _scope = profiling::scope("HexLayout.hexes_at");
+        points
+            .into_iter()
+            .map(|point| self.layout.hex_at(point.into()).into())
+            .collect()
+    }
+
+    /// The six corners of a hex's outline, for drawing it
+    fn corners(&self, hex: Axial) -> Vec<Vec2> {
+        self.layout.corners(hex.into()).to_vec()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "HexLayout(orientation='{}', size=({}, {}), origin=({}, {}))",
+            self.orientation(),
+            self.layout.size.x,
+            self.layout.size.y,
+            self.layout.origin.x,
+            self.layout.origin.y
+        )
+    }
+}
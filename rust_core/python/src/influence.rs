@@ -0,0 +1,256 @@
+// pyo3 0.18 expands binary operators into nested impls that newer compilers flag
+#![allow(non_local_definitions)]
+
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::influence::{self, Falloff};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::cancel::{self, CancelToken};
+use crate::errors::{MapShapeError, OutOfBoundsError};
+use crate::grid;
+use crate::profiling;
+use crate::progress::{self, Sink};
+use crate::state::{self, Persist};
+
+fn parse_falloff(name: &str) -> PyResult<Falloff> {
+    Falloff::from_name(name).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "unknown falloff '{}', expected 'constant', 'linear' or 'quadratic'",
+            name
+        ))
+    })
+}
+
+/// Right-hand side of an arithmetic operator: another map or a plain number
+#[derive(FromPyObject)]
+enum Operand<'a> {
+    Map(PyRef<'a, InfluenceMap>),
+    Scalar(f32),
+}
+
+/// A grid of influence values that spreads over walkable cells only.
+///
+/// Blocked cells always hold 0, so walls stop influence instead of letting it leak
+/// through to the other side.
+#[pyclass]
+pub struct InfluenceMap {
+    map: influence::InfluenceMap,
+}
+
+impl InfluenceMap {
+    fn index(&self, x: usize, y: usize) -> PyResult<usize> {
+        self.map.index(x, y).ok_or_else(|| {
+            OutOfBoundsError::new_err(format!(
+                "cell ({}, {}) is outside the {}x{} influence map",
+                x, y, self.map.width, self.map.height
+            ))
+        })
+    }
+
+    fn combine(&self, other: Operand, op: impl Fn(f32, f32) -> f32) -> PyResult<Self> {
+        let map = match other {
+            Operand::Map(other) => self.map.zip_with(&other.map, op).ok_or_else(|| {
+                MapShapeError::new_err(format!(
+                    "cannot combine a {}x{} influence map with a {}x{} one",
+                    self.map.width, self.map.height, other.map.width, other.map.height
+                ))
+            })?,
+            Operand::Scalar(scalar) => self.map.map(|value| op(value, scalar)),
+        };
+        Ok(InfluenceMap { map })
+    }
+}
+
+impl Persist for InfluenceMap {
+    const KIND: &'static str = "influence_map";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let map = &self.map;
+        out.usize(map.width);
+        out.usize(map.height);
+        state::write_f32s(out, &map.values);
+        for &passable in &map.passable {
+            out.bool(passable);
+        }
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let (width, height, cells) = state::read_grid_size(input)?;
+        let map = influence::InfluenceMap {
+            width,
+            height,
+            values: state::read_f32s(input, cells)?,
+            passable: (0..cells).map(|_| input.bool()).collect::<Result<_, _>>()?,
+        };
+        Ok(InfluenceMap { map })
+    }
+}
+
+#[pymethods]
+impl InfluenceMap {
+    /// Create an empty map, sized from `walkable_map` when one is given
+    #[new]
+    fn new(width: usize, height: usize, walkable_map: Option<Vec<Vec<bool>>>) -> PyResult<Self> {
+        let map = match walkable_map {
+            Some(walkable) => {
+                if grid::rectangular_dimensions(&walkable) != Some((width, height)) {
+                    return Err(MapShapeError::new_err(format!(
+                        "walkable_map must be a {}x{} grid",
+                        width, height
+                    )));
+                }
+                influence::InfluenceMap::with_walkable(&walkable)
+            }
+            None => influence::InfluenceMap::new(width, height),
+        };
+        Ok(InfluenceMap { map })
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.map.width
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.map.height
+    }
+
+    /// Add a source at a cell, spreading up to `radius` steps around walls
+    #[pyo3(signature = (x, y, strength, radius, falloff = "linear"))]
+    fn stamp(
+        &mut self,
+        x: usize,
+        y: usize,
+        strength: f32,
+        radius: f32,
+        falloff: &str,
+    ) -> PyResult<()> {
+        let _scope = profiling::scope("InfluenceMap.stamp");
+        let falloff = parse_falloff(falloff)?;
+        self.index(x, y)?;
+        self.map.stamp((x, y), strength, radius, falloff);
+        Ok(())
+    }
+
+    /// Spread influence outwards; `momentum` near 1 reacts quickly, near 0 keeps history
+    #[pyo3(signature = (decay = 0.3, momentum = 0.8, iterations = 1, cancel = None, progress = None))]
+    fn propagate(
+        &mut self,
+        py: Python<'_>,
+        decay: f32,
+        momentum: f32,
+        iterations: usize,
+        cancel: Option<PyRef<CancelToken>>,
+        progress: Option<&PyAny>,
+    ) -> PyResult<()> {
+        let _scope = profiling::scope("InfluenceMap.propagate");
+        let sink = Sink::from_arg(progress)?;
+        cancel::run(py, cancel.as_deref(), "InfluenceMap.propagate", || {
+            progress::watch(sink, || self.map.propagate(decay, momentum, iterations))
+        })?
+    }
+
+    /// Smooth the map by averaging each cell with its walkable neighbours
+    #[pyo3(signature = (iterations = 1, cancel = None, progress = None))]
+    fn blur(
+        &mut self,
+        py: Python<'_>,
+        iterations: usize,
+        cancel: Option<PyRef<CancelToken>>,
+        progress: Option<&PyAny>,
+    ) -> PyResult<()> {
+        let _scope = profiling::scope("InfluenceMap.blur");
+        let sink = Sink::from_arg(progress)?;
+        cancel::run(py, cancel.as_deref(), "InfluenceMap.blur", || {
+            progress::watch(sink, || self.map.blur(iterations))
+        })?
+    }
+
+    /// Multiply every value in place, e.g. to fade old influence each turn
+    fn scale(&mut self, factor: f32) {
+        self.map.scale(factor);
+    }
+
+    fn clear(&mut self) {
+        self.map.values.fill(0.0);
+    }
+
+    fn get(&self, x: usize, y: usize) -> PyResult<f32> {
+        Ok(self.map.values[self.index(x, y)?])
+    }
+
+    fn set(&mut self, x: usize, y: usize, value: f32) -> PyResult<()> {
+        let index = self.index(x, y)?;
+        self.map.set(index, value);
+        Ok(())
+    }
+
+    /// Cell with the highest value as `(x, y, value)`, ignoring walls
+    fn max_cell(&self) -> Option<(usize, usize, f32)> {
+        self.map.max_cell()
+    }
+
+    /// Cell with the lowest value as `(x, y, value)`, ignoring walls
+    fn min_cell(&self) -> Option<(usize, usize, f32)> {
+        self.map.min_cell()
+    }
+
+    /// Element-wise absolute value
+    fn abs(&self) -> Self {
+        InfluenceMap {
+            map: self.map.map(f32::abs),
+        }
+    }
+
+    /// The values as a `[y][x]` grid
+    fn to_list(&self) -> Vec<Vec<f32>> {
+        let map = &self.map;
+        if map.width == 0 {
+            return vec![Vec::new(); map.height];
+        }
+        map.values
+            .chunks(map.width)
+            .map(|row| row.to_vec())
+            .collect()
+    }
+
+    fn __add__(&self, other: Operand) -> PyResult<Self> {
+        self.combine(other, |a, b| a + b)
+    }
+
+    fn __radd__(&self, other: Operand) -> PyResult<Self> {
+        self.combine(other, |a, b| a + b)
+    }
+
+    fn __sub__(&self, other: Operand) -> PyResult<Self> {
+        self.combine(other, |a, b| a - b)
+    }
+
+    fn __rsub__(&self, other: Operand) -> PyResult<Self> {
+        self.combine(other, |a, b| b - a)
+    }
+
+    fn __mul__(&self, other: Operand) -> PyResult<Self> {
+        self.combine(other, |a, b| a * b)
+    }
+
+    fn __rmul__(&self, other: Operand) -> PyResult<Self> {
+        self.combine(other, |a, b| a * b)
+    }
+
+    fn __neg__(&self) -> Self {
+        InfluenceMap {
+            map: self.map.map(|value| -value),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "InfluenceMap(width={}, height={})",
+            self.map.width, self.map.height
+        )
+    }
+}
@@ -0,0 +1,338 @@
+//! Background jobs run on a Rust worker thread.
+//!
+//! `submit_*` functions check their arguments straight away, queue the work and
+//! return a [`Job`] at once. The worker runs jobs one after another without the
+//! GIL; each job carries its own cancellation token and progress handle, and can
+//! be polled, waited on or awaited from asyncio.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use llamaquest::explore::{self, ExploreTarget};
+use pyo3::exceptions::{PyRuntimeError, PyTimeoutError};
+use pyo3::prelude::*;
+use pyo3::pyclass::IterNextOutput;
+
+use crate::cancel::{self, CancelToken};
+use crate::dijkstra::{self, DijkstraMap};
+use crate::distance;
+use crate::errors::Cancelled;
+use crate::grid;
+use crate::logging;
+use crate::progress::{self, Progress, Sink};
+
+type Task = Box<dyn FnOnce() + Send>;
+
+/// A finished result that still has to be turned into a Python object
+type Output = Box<dyn FnOnce(Python<'_>) -> PyObject + Send>;
+
+enum State {
+    Pending,
+    Running,
+    Finished(PyResult<Output>),
+    Collected(PyResult<PyObject>),
+}
+
+struct Shared {
+    state: Mutex<State>,
+    finished: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn is_done(&self) -> bool {
+        matches!(*self.lock(), State::Finished(_) | State::Collected(_))
+    }
+
+    fn finish(&self, outcome: PyResult<Output>) {
+        *self.lock() = State::Finished(outcome);
+        self.finished.notify_all();
+    }
+}
+
+fn worker() -> &'static Mutex<Sender<Task>> {
+    static QUEUE: OnceLock<Mutex<Sender<Task>>> = OnceLock::new();
+    QUEUE.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Task>();
+        thread::Builder::new()
+            .name("llamaquest-worker".to_string())
+            .spawn(move || {
+                for task in receiver {
+                    task();
+                }
+            })
+            .expect("could not start the llamaquest worker thread");
+        Mutex::new(sender)
+    })
+}
+
+/// Handle to a computation queued on the worker thread
+#[pyclass]
+pub struct Job {
+    what: &'static str,
+    shared: Arc<Shared>,
+    token: CancelToken,
+    progress: Progress,
+}
+
+/// Queue `work` on the worker thread and return its handle
+fn submit<T>(what: &'static str, work: impl FnOnce() -> PyResult<T> + Send + 'static) -> Job
+where
+    T: IntoPy<PyObject> + Send + 'static,
+{
+    let job = Job {
+        what,
+        shared: Arc::new(Shared {
+            state: Mutex::new(State::Pending),
+            finished: Condvar::new(),
+        }),
+        token: CancelToken::default(),
+        progress: Progress::default(),
+    };
+    let (shared, token, handle) = (job.shared.clone(), job.token.clone(), job.progress.clone());
+    let task: Task = Box::new(move || {
+        if token.is_cancelled() {
+            shared.finish(Err(Cancelled::new_err(format!(
+                "{} was cancelled before it started",
+                what
+            ))));
+            return;
+        }
+        *shared.lock() = State::Running;
+        // A panic must fail this job only, not take the worker down with it
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| -> PyResult<T> {
+            // Watched inside the guard so the watch can tell a cancelled run apart
+            cancel::guarded(Some(&token), what, || {
+                progress::watch(Some(Sink::Handle(handle)), work)
+            })??
+        }))
+        .unwrap_or_else(|_| {
+            logging::error(format_args!("{} panicked on the worker thread", what));
+            Err(PyRuntimeError::new_err(format!("{} panicked", what)))
+        });
+        shared.finish(outcome.map(|value| -> Output { Box::new(move |py| value.into_py(py)) }));
+    });
+    // The worker never stops, so the queue only closes if the process is exiting
+    let _ = worker()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .send(task);
+    job
+}
+
+#[pymethods]
+impl Job {
+    fn is_done(&self) -> bool {
+        self.shared.is_done()
+    }
+
+    /// `"pending"`, `"running"` or `"done"`
+    #[getter]
+    fn status(&self) -> &'static str {
+        match *self.shared.lock() {
+            State::Pending => "pending",
+            State::Running => "running",
+            State::Finished(_) | State::Collected(_) => "done",
+        }
+    }
+
+    /// Live progress of the job, updated from the worker thread
+    #[getter]
+    fn progress(&self) -> Progress {
+        self.progress.clone()
+    }
+
+    /// Ask the job to stop; its result then raises `Cancelled`
+    fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Wait for the job and return its value, re-raising any error it hit.
+    ///
+    /// Raises `TimeoutError` if `timeout` seconds pass first; the job keeps running.
+    /// An infinite timeout, or one too long to represent, waits like `None`.
+    #[pyo3(signature = (timeout = None))]
+    fn result(&self, py: Python<'_>, timeout: Option<f64>) -> PyResult<PyObject> {
+        let limit =
+            timeout.and_then(|seconds| Duration::try_from_secs_f64(seconds.max(0.0)).ok());
+        let finished = py.allow_threads(|| {
+            let state = self.shared.lock();
+            let waiting = |state: &mut State| matches!(state, State::Pending | State::Running);
+            match limit {
+                Some(limit) => {
+                    let (mut state, _) = self
+                        .shared
+                        .finished
+                        .wait_timeout_while(state, limit, waiting)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    !waiting(&mut state)
+                }
+                None => {
+                    drop(
+                        self.shared
+                            .finished
+                            .wait_while(state, waiting)
+                            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+                    );
+                    true
+                }
+            }
+        });
+        if !finished {
+            return Err(PyTimeoutError::new_err(format!(
+                "{} did not finish within {} seconds",
+                self.what,
+                timeout.unwrap_or_default()
+            )));
+        }
+
+        let mut state = self.shared.lock();
+        if let State::Finished(_) = &*state {
+            let State::Finished(outcome) = std::mem::replace(&mut *state, State::Pending) else {
+                unreachable!();
+            };
+            *state = State::Collected(outcome.map(|output| output(py)));
+        }
+        match &*state {
+            State::Collected(Ok(value)) => Ok(value.clone_ref(py)),
+            State::Collected(Err(error)) => Err(error.clone_ref(py)),
+            _ => unreachable!("job state checked above"),
+        }
+    }
+
+    /// Awaiting a job polls it once per event loop iteration until it is done
+    fn __await__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python<'_>) -> PyResult<IterNextOutput<PyObject, PyObject>> {
+        if self.is_done() {
+            Ok(IterNextOutput::Return(self.result(py, None)?))
+        } else {
+            Ok(IterNextOutput::Yield(py.None()))
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Job({}, status='{}')", self.what, self.status())
+    }
+}
+
+/// Find a path on the worker thread; see `calculate_pathfinding`
+#[pyfunction]
+#[pyo3(signature = (start_x, start_y, end_x, end_y, walkable_map, max_steps = None))]
+pub fn submit_pathfinding(
+    start_x: usize,
+    start_y: usize,
+    end_x: usize,
+    end_y: usize,
+    walkable_map: Vec<Vec<bool>>,
+    max_steps: Option<usize>,
+) -> PyResult<Job> {
+    let size = grid::require_non_empty(&walkable_map, "walkable_map")?;
+    grid::require_cell("start", (start_x, start_y), size, "walkable_map")?;
+    grid::require_cell("end", (end_x, end_y), size, "walkable_map")?;
+    Ok(submit("calculate_pathfinding", move || {
+        crate::find_path((start_x, start_y), (end_x, end_y), &walkable_map, max_steps)
+    }))
+}
+
+/// Compute a field of view on the worker thread; see `calculate_field_of_view`
+#[pyfunction]
+pub fn submit_field_of_view(
+    origin_x: usize,
+    origin_y: usize,
+    radius: usize,
+    obstacle_map: Vec<Vec<bool>>,
+) -> PyResult<Job> {
+    let size = grid::require_non_empty(&obstacle_map, "obstacle_map")?;
+    grid::require_cell("origin", (origin_x, origin_y), size, "obstacle_map")?;
+    Ok(submit("calculate_field_of_view", move || {
+        Ok(llamaquest::fov::field_of_view(
+            (origin_x, origin_y),
+            radius,
+            &obstacle_map,
+        ))
+    }))
+}
+
+/// Build a Dijkstra map on the worker thread; see `calculate_dijkstra_map`
+#[pyfunction]
+#[pyo3(signature = (goals, walkable_map, diagonal = false))]
+pub fn submit_dijkstra_map(
+    goals: Vec<(usize, usize)>,
+    walkable_map: Vec<Vec<bool>>,
+    diagonal: bool,
+) -> PyResult<Job> {
+    dijkstra::require_cells(&walkable_map, &goals, "goal")?;
+    Ok(submit("calculate_dijkstra_map", move || {
+        Ok(DijkstraMap::from_goals(&walkable_map, &goals, diagonal).to_rows())
+    }))
+}
+
+/// Build a flee map on the worker thread; see `calculate_flee_map`
+#[pyfunction]
+#[pyo3(signature = (threats, walkable_map, diagonal = false, coefficient = -1.2))]
+pub fn submit_flee_map(
+    threats: Vec<(usize, usize)>,
+    walkable_map: Vec<Vec<bool>>,
+    diagonal: bool,
+    coefficient: f32,
+) -> PyResult<Job> {
+    dijkstra::require_cells(&walkable_map, &threats, "threat")?;
+    Ok(submit("calculate_flee_map", move || {
+        let mut map = DijkstraMap::from_goals(&walkable_map, &threats, diagonal);
+        map.invert(coefficient);
+        Ok(map.to_rows())
+    }))
+}
+
+/// Compute wall distances on the worker thread; see `calculate_wall_distance`
+#[pyfunction]
+#[pyo3(signature = (walkable_map, metric = "chebyshev", edges_block = true))]
+pub fn submit_wall_distance(
+    walkable_map: Vec<Vec<bool>>,
+    metric: &str,
+    edges_block: bool,
+) -> PyResult<Job> {
+    let metric = distance::parse_metric(metric)?;
+    grid::require_rectangular(&walkable_map, "walkable_map")?;
+    Ok(submit("calculate_wall_distance", move || {
+        Ok(distance::wall_distance(&walkable_map, metric, edges_block))
+    }))
+}
+
+/// Pick the next exploration target on the worker thread; see `calculate_autoexplore`
+#[pyfunction]
+#[pyo3(signature = (start_x, start_y, explored_map, visible_map, walkable_map, diagonal = false))]
+pub fn submit_autoexplore(
+    start_x: usize,
+    start_y: usize,
+    explored_map: Vec<Vec<bool>>,
+    visible_map: Vec<Vec<bool>>,
+    walkable_map: Vec<Vec<bool>>,
+    diagonal: bool,
+) -> PyResult<Job> {
+    let size = grid::require_non_empty(&walkable_map, "walkable_map")?;
+    grid::require_size(&explored_map, "explored_map", size)?;
+    grid::require_size(&visible_map, "visible_map", size)?;
+    grid::require_cell("start", (start_x, start_y), size, "walkable_map")?;
+    Ok(submit("calculate_autoexplore", move || {
+        let target: Option<ExploreTarget> = explore::autoexplore(
+            (start_x, start_y),
+            &explored_map,
+            &visible_map,
+            &walkable_map,
+            diagonal,
+        );
+        Ok(target)
+    }))
+}
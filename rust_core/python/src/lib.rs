@@ -0,0 +1,439 @@
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::wrap_pyfunction;
+use llamaquest::fov::field_of_view;
+use llamaquest::hooks::{self, Hooks};
+use llamaquest::physics::Physics;
+use llamaquest::python::{RectLike, VecLike};
+use llamaquest::shapes::{Circle, Rect, Shape};
+use llamaquest::vec2::Vec2;
+
+mod aoe;
+mod behavior_tree;
+mod buffers;
+mod camera;
+mod cancel;
+mod clock;
+mod dijkstra;
+mod distance;
+mod errors;
+mod events;
+mod explore;
+mod explosion;
+mod fluid;
+mod formation;
+mod fsm;
+mod goap;
+mod grid;
+mod hex;
+mod influence;
+mod jobs;
+mod logging;
+mod minimap;
+mod orca;
+mod particles;
+mod profiling;
+mod projection;
+mod progress;
+mod pyjson;
+mod raster;
+mod raycast;
+mod regions;
+mod replay;
+mod scent;
+mod state;
+mod steering;
+mod temperature;
+mod threat;
+mod tiled;
+mod transform;
+mod turns;
+mod tween;
+mod undo;
+mod utility;
+mod water;
+mod world;
+
+/// A Rust module providing performance-critical functionality for LlamaQuest
+#[pymodule]
+fn llamaquest_core(_py: Python, m: &PyModule) -> PyResult<()> {
+    // Let the core algorithms see `CancelToken`s, `Progress` sinks, the profiler and logging
+    hooks::install(Hooks {
+        cancelled: cancel::requested,
+        report: progress::report,
+        count: profiling::count,
+        log: logging::forward,
+    });
+    m.add_function(wrap_pyfunction!(calculate_pathfinding, m)?)?;
+    m.add_function(wrap_pyfunction!(collision_detection, m)?)?;
+    m.add_function(wrap_pyfunction!(calculate_field_of_view, m)?)?;
+    m.add_function(wrap_pyfunction!(distance::calculate_wall_distance, m)?)?;
+    m.add_function(wrap_pyfunction!(dijkstra::calculate_dijkstra_map, m)?)?;
+    m.add_function(wrap_pyfunction!(dijkstra::calculate_flee_map, m)?)?;
+    m.add_function(wrap_pyfunction!(explore::calculate_autoexplore, m)?)?;
+    m.add_function(wrap_pyfunction!(tiled::parse_tiled_map, m)?)?;
+    m.add_function(wrap_pyfunction!(tiled::load_tiled_map, m)?)?;
+    m.add_function(wrap_pyfunction!(transform::rotate_grid, m)?)?;
+    m.add_function(wrap_pyfunction!(transform::flip_grid, m)?)?;
+    m.add_function(wrap_pyfunction!(transform::crop_grid, m)?)?;
+    m.add_function(wrap_pyfunction!(transform::paste_grid, m)?)?;
+    m.add_function(wrap_pyfunction!(regions::build_region_graph, m)?)?;
+    m.add_function(wrap_pyfunction!(orca::compute_orca_velocities, m)?)?;
+    m.add_function(wrap_pyfunction!(threat::calculate_threat_map, m)?)?;
+    m.add_function(wrap_pyfunction!(formation::calculate_formation_slots, m)?)?;
+    m.add_function(wrap_pyfunction!(formation::calculate_formation_move, m)?)?;
+    m.add_function(wrap_pyfunction!(profiling::set_profiling, m)?)?;
+    m.add_function(wrap_pyfunction!(profiling::profiling_enabled, m)?)?;
+    m.add_function(wrap_pyfunction!(profiling::profiling_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(profiling::reset_profiling, m)?)?;
+    m.add_function(wrap_pyfunction!(jobs::submit_pathfinding, m)?)?;
+    m.add_function(wrap_pyfunction!(jobs::submit_field_of_view, m)?)?;
+    m.add_function(wrap_pyfunction!(jobs::submit_dijkstra_map, m)?)?;
+    m.add_function(wrap_pyfunction!(jobs::submit_flee_map, m)?)?;
+    m.add_function(wrap_pyfunction!(jobs::submit_wall_distance, m)?)?;
+    m.add_function(wrap_pyfunction!(jobs::submit_autoexplore, m)?)?;
+    m.add_function(wrap_pyfunction!(logging::set_log_level, m)?)?;
+    m.add_function(wrap_pyfunction!(logging::log_level, m)?)?;
+    m.add_function(wrap_pyfunction!(state::save_state, m)?)?;
+    m.add_function(wrap_pyfunction!(state::load_state, m)?)?;
+    m.add_function(wrap_pyfunction!(state::state_checksum, m)?)?;
+    m.add_function(wrap_pyfunction!(hex::hex_neighbors, m)?)?;
+    m.add_function(wrap_pyfunction!(hex::hex_distance, m)?)?;
+    m.add_function(wrap_pyfunction!(hex::hex_ring, m)?)?;
+    m.add_function(wrap_pyfunction!(hex::hex_spiral, m)?)?;
+    m.add_function(wrap_pyfunction!(hex::hex_line, m)?)?;
+    m.add_function(wrap_pyfunction!(hex::hex_round, m)?)?;
+    m.add_function(wrap_pyfunction!(hex::hex_to_cube, m)?)?;
+    m.add_function(wrap_pyfunction!(hex::cube_to_hex, m)?)?;
+    m.add_function(wrap_pyfunction!(hex::hex_to_offset, m)?)?;
+    m.add_function(wrap_pyfunction!(hex::offset_to_hex, m)?)?;
+    m.add_function(wrap_pyfunction!(raster::line_cells, m)?)?;
+    m.add_function(wrap_pyfunction!(raster::thick_line_cells, m)?)?;
+    m.add_function(wrap_pyfunction!(raster::circle_cells, m)?)?;
+    m.add_function(wrap_pyfunction!(raster::ellipse_cells, m)?)?;
+    m.add_function(wrap_pyfunction!(aoe::blast_cells, m)?)?;
+    m.add_function(wrap_pyfunction!(aoe::cone_cells, m)?)?;
+    m.add_function(wrap_pyfunction!(aoe::beam_cells, m)?)?;
+    m.add_function(wrap_pyfunction!(aoe::ring_cells, m)?)?;
+    m.add_function(wrap_pyfunction!(explosion::calculate_explosion_damage, m)?)?;
+    m.add_function(wrap_pyfunction!(explosion::explosion_damage_at, m)?)?;
+    m.add_function(wrap_pyfunction!(tween::ease, m)?)?;
+    m.add_function(wrap_pyfunction!(tween::ease_many, m)?)?;
+    m.add("MapShapeError", m.py().get_type::<errors::MapShapeError>())?;
+    m.add("OutOfBoundsError", m.py().get_type::<errors::OutOfBoundsError>())?;
+    m.add("NoPathError", m.py().get_type::<errors::NoPathError>())?;
+    m.add("SerializationError", m.py().get_type::<errors::SerializationError>())?;
+    m.add("Cancelled", m.py().get_type::<errors::Cancelled>())?;
+    m.add_class::<PhysicsEngine>()?;
+    m.add_class::<tiled::TiledMap>()?;
+    m.add_class::<tiled::TiledObject>()?;
+    m.add_class::<minimap::MinimapRenderer>()?;
+    m.add_class::<regions::RegionGraph>()?;
+    m.add_class::<steering::SteeringAgents>()?;
+    m.add_class::<influence::InfluenceMap>()?;
+    m.add_class::<goap::GoapPlanner>()?;
+    m.add_class::<behavior_tree::BehaviorTree>()?;
+    m.add_class::<utility::UtilityEvaluator>()?;
+    m.add_class::<scent::ScentMap>()?;
+    m.add_class::<formation::FormationMove>()?;
+    m.add_class::<fsm::StateMachines>()?;
+    m.add_class::<fluid::FluidGrid>()?;
+    m.add_class::<temperature::TemperatureGrid>()?;
+    m.add_class::<particles::ParticleSystem>()?;
+    m.add_class::<water::WaterSurface>()?;
+    m.add_class::<turns::TurnScheduler>()?;
+    m.add_class::<events::EventScheduler>()?;
+    m.add_class::<clock::GameClock>()?;
+    m.add_class::<world::World>()?;
+    m.add_class::<world::ColumnView>()?;
+    m.add_class::<world::WorldSnapshot>()?;
+    m.add_class::<cancel::CancelToken>()?;
+    m.add_class::<progress::Progress>()?;
+    m.add_class::<jobs::Job>()?;
+    m.add_class::<Vec2>()?;
+    m.add_class::<Rect>()?;
+    m.add_class::<Circle>()?;
+    m.add_class::<replay::Replay>()?;
+    m.add_class::<hex::HexLayout>()?;
+    m.add_class::<projection::ScreenProjection>()?;
+    m.add_class::<raycast::Raycaster>()?;
+    m.add_class::<tween::Tweens>()?;
+    m.add_class::<camera::Camera2D>()?;
+    m.add_class::<undo::UndoStack>()?;
+    Ok(())
+}
+
+/// Path between two cells as a direct walk only: it steps diagonally until level
+/// with the end and does not route around obstacles. Raises `NoPathError` if a
+/// blocked cell or `max_steps` (default 1000) stops the walk short of the end.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+fn calculate_pathfinding(
+    py: Python<'_>,
+    start_x: usize, start_y: usize,
+    end_x: usize, end_y: usize,
+    walkable_map: Vec<Vec<bool>>,
+    max_steps: Option<usize>,
+    cancel: Option<PyRef<cancel::CancelToken>>
+) -> PyResult<Vec<(usize, usize)>> {
+    let _scope = profiling::scope("calculate_pathfinding");
+    let size = grid::require_non_empty(&walkable_map, "walkable_map")?;
+    grid::require_cell("start", (start_x, start_y), size, "walkable_map")?;
+    grid::require_cell("end", (end_x, end_y), size, "walkable_map")?;
+    cancel::run(py, cancel.as_deref(), "calculate_pathfinding", || {
+        find_path((start_x, start_y), (end_x, end_y), &walkable_map, max_steps)
+    })?
+}
+
+/// `llamaquest::pathfinding::find_path`, raising `NoPathError` when the walk stops short
+fn find_path(
+    start: (usize, usize),
+    end: (usize, usize),
+    walkable_map: &[Vec<bool>],
+    max_steps: Option<usize>
+) -> PyResult<Vec<(usize, usize)>> {
+    llamaquest::pathfinding::find_path(start, end, walkable_map, max_steps)
+        .map_err(|error| errors::NoPathError::new_err(error.to_string()))
+}
+
+/// Whether two shapes overlap; each may be a `Rect`, a `Circle` or an
+/// `(x, y, width, height)` tuple
+#[pyfunction]
+fn collision_detection(a: Shape, b: Shape) -> bool {
+    let _scope = profiling::scope("collision_detection");
+    a.intersects(b)
+}
+
+/// Calculate field of view for the player
+#[pyfunction]
+fn calculate_field_of_view(
+    py: Python<'_>,
+    origin_x: usize, origin_y: usize,
+    radius: usize,
+    obstacle_map: Vec<Vec<bool>>
+) -> PyResult<Vec<Vec<bool>>> {
+    let _scope = profiling::scope("calculate_field_of_view");
+    let size = grid::require_non_empty(&obstacle_map, "obstacle_map")?;
+    grid::require_cell("origin", (origin_x, origin_y), size, "obstacle_map")?;
+    Ok(py.allow_threads(|| field_of_view((origin_x, origin_y), radius, &obstacle_map)))
+}
+
+/// Physics engine for game entities
+#[pyclass]
+struct PhysicsEngine {
+    physics: Physics,
+}
+
+/// Keys of `PhysicsEngine.to_dict`, in order
+const PHYSICS_SETTINGS: [&str; 5] = ["gravity", "friction", "air_resistance", "max_velocity_x", "max_velocity_y"];
+
+#[pymethods]
+impl PhysicsEngine {
+    #[new]
+    #[pyo3(signature = (gravity = None, friction = None, air_resistance = 0.01, max_velocity_x = None, max_velocity_y = None))]
+    fn new(
+        gravity: Option<f32>,
+        friction: Option<f32>,
+        air_resistance: f32,
+        max_velocity_x: Option<f32>,
+        max_velocity_y: Option<f32>
+    ) -> Self {
+        let defaults = Physics::default();
+        PhysicsEngine {
+            physics: Physics {
+                gravity: gravity.unwrap_or(defaults.gravity),
+                friction: friction.unwrap_or(defaults.friction),
+                air_resistance,
+                max_velocity_x,
+                max_velocity_y,
+            },
+        }
+    }
+
+    /// An engine tuned for a kind of game: `"platformer"`, `"top_down"` or `"space"`
+    #[staticmethod]
+    fn preset(name: &str) -> PyResult<Self> {
+        let physics = Physics::preset(name).ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "unknown physics preset '{}', expected one of: {}",
+                name,
+                llamaquest::physics::PRESETS.join(", ")
+            ))
+        })?;
+        Ok(PhysicsEngine { physics })
+    }
+
+    #[getter]
+    fn gravity(&self) -> f32 {
+        self.physics.gravity
+    }
+
+    #[setter]
+    fn set_gravity(&mut self, gravity: f32) {
+        self.physics.gravity = gravity;
+    }
+
+    /// Horizontal deceleration applied while on the ground
+    #[getter]
+    fn friction(&self) -> f32 {
+        self.physics.friction
+    }
+
+    #[setter]
+    fn set_friction(&mut self, friction: f32) {
+        self.physics.friction = friction;
+    }
+
+    /// Fraction of horizontal speed projectiles lose per second
+    #[getter]
+    fn air_resistance(&self) -> f32 {
+        self.physics.air_resistance
+    }
+
+    #[setter]
+    fn set_air_resistance(&mut self, air_resistance: f32) {
+        self.physics.air_resistance = air_resistance;
+    }
+
+    /// Fastest horizontal speed in either direction, or `None` for no limit
+    #[getter]
+    fn max_velocity_x(&self) -> Option<f32> {
+        self.physics.max_velocity_x
+    }
+
+    #[setter]
+    fn set_max_velocity_x(&mut self, max_velocity_x: Option<f32>) {
+        self.physics.max_velocity_x = max_velocity_x;
+    }
+
+    /// Fastest vertical speed in either direction, or `None` for no limit
+    #[getter]
+    fn max_velocity_y(&self) -> Option<f32> {
+        self.physics.max_velocity_y
+    }
+
+    #[setter]
+    fn set_max_velocity_y(&mut self, max_velocity_y: Option<f32>) {
+        self.physics.max_velocity_y = max_velocity_y;
+    }
+
+    /// The engine's settings, suitable for `from_dict` or a JSON config file
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let config = pyo3::types::PyDict::new(py);
+        config.set_item("gravity", self.physics.gravity)?;
+        config.set_item("friction", self.physics.friction)?;
+        config.set_item("air_resistance", self.physics.air_resistance)?;
+        config.set_item("max_velocity_x", self.physics.max_velocity_x)?;
+        config.set_item("max_velocity_y", self.physics.max_velocity_y)?;
+        Ok(config.into())
+    }
+
+    /// Build an engine from `to_dict` output; missing settings keep their defaults
+    #[staticmethod]
+    fn from_dict(config: &pyo3::types::PyDict) -> PyResult<Self> {
+        let mut physics = Physics::default();
+        for (key, value) in config.iter() {
+            let key: &str = key.extract()?;
+            match key {
+                "gravity" => physics.gravity = value.extract()?,
+                "friction" => physics.friction = value.extract()?,
+                "air_resistance" => physics.air_resistance = value.extract()?,
+                "max_velocity_x" => physics.max_velocity_x = value.extract()?,
+                "max_velocity_y" => physics.max_velocity_y = value.extract()?,
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "unknown physics setting '{}', expected one of: {}",
+                        key,
+                        PHYSICS_SETTINGS.join(", ")
+                    )))
+                }
+            }
+        }
+        Ok(PhysicsEngine { physics })
+    }
+
+    fn __repr__(&self) -> String {
+        let limit = |limit: Option<f32>| limit.map_or("None".to_string(), |limit| limit.to_string());
+        format!(
+            "PhysicsEngine(gravity={}, friction={}, air_resistance={}, max_velocity_x={}, max_velocity_y={})",
+            self.physics.gravity, self.physics.friction, self.physics.air_resistance,
+            limit(self.physics.max_velocity_x), limit(self.physics.max_velocity_y)
+        )
+    }
+    
+    /// Apply physics to an entity's velocity and position
+    fn update_entity(
+        &self,
+        position: VecLike,
+        velocity: VecLike,
+        is_on_ground: bool,
+        delta_time: f32
+    ) -> (Vec2, Vec2) {
+        let _scope = profiling::scope("PhysicsEngine.update_entity");
+        self.physics.integrate(position.into(), velocity.into(), is_on_ground, delta_time)
+    }
+    
+    /// Apply `update_entity` to many entities at once, updating the arrays in place.
+    ///
+    /// `positions` and `velocities` are contiguous float32 arrays of shape (n, 2) and
+    /// `on_ground` a bool array of length n, e.g. numpy arrays.
+    fn update_entities(
+        &self,
+        py: Python<'_>,
+        positions: &PyAny,
+        velocities: &PyAny,
+        on_ground: &PyAny,
+        delta_time: f32
+    ) -> PyResult<()> {
+        let _scope = profiling::scope("PhysicsEngine.update_entities");
+        let (position_buffer, count) = buffers::rows::<f32>(positions, "positions", "float32", 2, true)?;
+        let (velocity_buffer, velocity_count) = buffers::rows::<f32>(velocities, "velocities", "float32", 2, true)?;
+        let (ground_buffer, ground_count) = buffers::rows::<buffers::Flag>(on_ground, "on_ground", "bool", 1, false)?;
+        if velocity_count != count || ground_count != count {
+            return Err(PyValueError::new_err(format!(
+                "positions, velocities and on_ground hold {}, {} and {} entities",
+                count, velocity_count, ground_count
+            )));
+        }
+        let mut position_data = position_buffer.to_vec(py)?;
+        let mut velocity_data = velocity_buffer.to_vec(py)?;
+        let ground = ground_buffer.to_vec(py)?;
+        py.allow_threads(|| {
+            self.physics.integrate_all(&mut position_data, &mut velocity_data, |i| ground[i].get(), delta_time)
+        });
+        position_buffer.copy_from_slice(py, &position_data)?;
+        velocity_buffer.copy_from_slice(py, &velocity_data)?;
+        Ok(())
+    }
+    
+    /// Run `update_entity` once for every fixed step the clock produced on its last tick
+    fn update_entity_with_clock(
+        &self,
+        position: VecLike,
+        velocity: VecLike,
+        is_on_ground: bool,
+        clock: PyRef<clock::GameClock>
+    ) -> (Vec2, Vec2) {
+        let _scope = profiling::scope("PhysicsEngine.update_entity_with_clock");
+        self.physics.integrate_steps(position.into(), velocity.into(), is_on_ground, clock.steps(), clock.fixed_step() as f32)
+    }
+    
+    /// Calculate projectile trajectory
+    fn calculate_projectile_path(
+        &self,
+        start: VecLike,
+        velocity: VecLike,
+        time_steps: usize,
+        delta_time: f32
+    ) -> Vec<Vec2> {
+        let _scope = profiling::scope("PhysicsEngine.calculate_projectile_path");
+        self.physics.projectile_path(start.into(), velocity.into(), time_steps, delta_time)
+    }
+    
+    /// Whether the `entity` rectangle could move its top-left corner to
+    /// `new_position` without overlapping any of `obstacles`, which may be
+    /// rectangles or circles
+    fn can_move_to(&self, entity: RectLike, new_position: VecLike, obstacles: Vec<Shape>) -> bool {
+        llamaquest::physics::can_move_to(entity.into(), new_position.into(), &obstacles)
+    }
+} 
@@ -0,0 +1,87 @@
+//! Engine diagnostics forwarded to Python's `logging` module.
+//!
+//! Records go to the `llamaquest_core` logger. Anything below the level set with
+//! `set_log_level` is dropped on the Rust side before the message is formatted or
+//! the GIL is touched, so disabled logging costs one atomic load per call site.
+
+use std::fmt::Arguments;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use llamaquest::hooks;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+pub const DEBUG: u32 = 10;
+pub const INFO: u32 = 20;
+pub const WARNING: u32 = 30;
+pub const ERROR: u32 = 40;
+
+static LEVEL: AtomicU32 = AtomicU32::new(WARNING);
+
+/// A level given either as a number or as a name such as `"DEBUG"`
+#[derive(FromPyObject)]
+pub enum Level {
+    Number(u32),
+    Name(String),
+}
+
+impl Level {
+    fn value(&self) -> PyResult<u32> {
+        match self {
+            Level::Number(level) => Ok(*level),
+            Level::Name(name) => match name.to_ascii_uppercase().as_str() {
+                "DEBUG" => Ok(DEBUG),
+                "INFO" => Ok(INFO),
+                "WARNING" | "WARN" => Ok(WARNING),
+                "ERROR" => Ok(ERROR),
+                _ => Err(PyValueError::new_err(format!(
+                    "unknown log level '{}', expected 'DEBUG', 'INFO', 'WARNING' or 'ERROR'",
+                    name
+                ))),
+            },
+        }
+    }
+}
+
+pub fn enabled(level: u32) -> bool {
+    level >= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Send a record to the `llamaquest_core` logger, taking the GIL if needed
+pub fn log(level: u32, message: Arguments) {
+    if !enabled(level) {
+        return;
+    }
+    let message = message.to_string();
+    Python::with_gil(|py| {
+        // A broken handler must not turn a diagnostic into an engine failure
+        let result = py
+            .import("logging")
+            .and_then(|logging| logging.call_method1("getLogger", ("llamaquest_core",)))
+            .and_then(|logger| logger.call_method1("log", (level, message)));
+        if let Err(error) = result {
+            error.print(py);
+        }
+    });
+}
+
+/// The `llamaquest::hooks` log hook, which the core crate's diagnostics go through
+pub fn forward(level: hooks::Level, message: Arguments) {
+    log(level as u32, message);
+}
+
+pub fn error(message: Arguments) {
+    log(ERROR, message);
+}
+
+/// Forward engine records at `level` and above; the default is `WARNING`
+#[pyfunction]
+pub fn set_log_level(level: Level) -> PyResult<()> {
+    LEVEL.store(level.value()?, Ordering::Relaxed);
+    Ok(())
+}
+
+#[pyfunction]
+pub fn log_level() -> u32 {
+    LEVEL.load(Ordering::Relaxed)
+}
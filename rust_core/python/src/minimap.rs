@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use llamaquest::minimap::{self, Reduce, Rgba};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::grid;
+use crate::profiling;
+
+/// Downsamples tile grids into RGBA minimap images with fog of war composited on top
+#[pyclass]
+pub struct MinimapRenderer {
+    renderer: minimap::MinimapRenderer,
+}
+
+#[pymethods]
+impl MinimapRenderer {
+    #[new]
+    fn new(
+        palette: HashMap<u32, Rgba>,
+        block_size: Option<usize>,
+        mode: Option<&str>,
+        priority: Option<Vec<u32>>,
+    ) -> PyResult<Self> {
+        let mode = mode.unwrap_or("majority");
+        let mode = Reduce::from_name(mode).ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "unknown minimap mode '{}', expected 'majority' or 'priority'",
+                mode
+            ))
+        })?;
+        Ok(MinimapRenderer {
+            renderer: minimap::MinimapRenderer::new(
+                palette,
+                block_size.unwrap_or(4),
+                mode,
+                &priority.unwrap_or_default(),
+            ),
+        })
+    }
+
+    #[getter]
+    fn block_size(&self) -> usize {
+        self.renderer.block_size
+    }
+
+    #[setter]
+    fn set_block_size(&mut self, block_size: usize) {
+        self.renderer.block_size = block_size;
+    }
+
+    /// Colour for tiles missing from the palette
+    #[getter]
+    fn default_color(&self) -> Rgba {
+        self.renderer.default_color
+    }
+
+    #[setter]
+    fn set_default_color(&mut self, color: Rgba) {
+        self.renderer.default_color = color;
+    }
+
+    /// Colour of blocks with nothing explored
+    #[getter]
+    fn fog_color(&self) -> Rgba {
+        self.renderer.fog_color
+    }
+
+    #[setter]
+    fn set_fog_color(&mut self, color: Rgba) {
+        self.renderer.fog_color = color;
+    }
+
+    /// Brightness multiplier for explored blocks that are not currently visible
+    #[getter]
+    fn remembered_dim(&self) -> f32 {
+        self.renderer.remembered_dim
+    }
+
+    #[setter]
+    fn set_remembered_dim(&mut self, remembered_dim: f32) {
+        self.renderer.remembered_dim = remembered_dim;
+    }
+
+    /// Set or replace the colour used for a tile id
+    fn set_color(&mut self, tile: u32, color: Rgba) {
+        self.renderer.palette.insert(tile, color);
+    }
+
+    /// Render the minimap, returning `(width, height, rgba_bytes)`
+    fn render(
+        &self,
+        py: Python<'_>,
+        tile_map: Vec<Vec<u32>>,
+        explored_map: Option<Vec<Vec<bool>>>,
+        visible_map: Option<Vec<Vec<bool>>>,
+    ) -> PyResult<(usize, usize, PyObject)> {
+        let _scope = profiling::scope("MinimapRenderer.render");
+        let size = grid::require_rectangular(&tile_map, "tile_map")?;
+        if let Some(explored_map) = &explored_map {
+            grid::require_size(explored_map, "explored_map", size)?;
+        }
+        if let Some(visible_map) = &visible_map {
+            grid::require_size(visible_map, "visible_map", size)?;
+        }
+        let (width, height, pixels) = py.allow_threads(|| {
+            self.renderer
+                .render_rgba(&tile_map, explored_map.as_deref(), visible_map.as_deref())
+        });
+        Ok((width, height, PyBytes::new(py, &pixels).into()))
+    }
+}
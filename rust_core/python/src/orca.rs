@@ -0,0 +1,80 @@
+use llamaquest::orca::{nearest_neighbors, orca_velocities, OrcaAgent, OrcaSettings};
+use llamaquest::python::VecLike;
+use llamaquest::vec2::Vec2;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::profiling;
+
+/// Compute collision-free velocities for a crowd from each agent's preferred velocity.
+///
+/// `neighbors` lists the agent indices each agent should avoid; when omitted the
+/// nearest `max_neighbors` agents within `neighbor_distance` are used.
+#[allow(clippy::too_many_arguments)]
+#[pyfunction]
+#[pyo3(signature = (
+    positions, velocities, preferred_velocities, radii, max_speeds, time_step,
+    time_horizon = 2.0, neighbor_distance = 10.0, max_neighbors = 10, neighbors = None
+))]
+pub fn compute_orca_velocities(
+    py: Python<'_>,
+    positions: Vec<VecLike>,
+    velocities: Vec<VecLike>,
+    preferred_velocities: Vec<VecLike>,
+    radii: Vec<f32>,
+    max_speeds: Vec<f32>,
+    time_step: f32,
+    time_horizon: f32,
+    neighbor_distance: f32,
+    max_neighbors: usize,
+    neighbors: Option<Vec<Vec<usize>>>,
+) -> PyResult<Vec<Vec2>> {
+    let _scope = profiling::scope("compute_orca_velocities");
+    let count = positions.len();
+    if velocities.len() != count
+        || preferred_velocities.len() != count
+        || radii.len() != count
+        || max_speeds.len() != count
+    {
+        return Err(PyValueError::new_err(
+            "positions, velocities, preferred_velocities, radii and max_speeds must have the same length",
+        ));
+    }
+    if time_step <= 0.0 || time_horizon <= 0.0 {
+        return Err(PyValueError::new_err(
+            "time_step and time_horizon must be positive",
+        ));
+    }
+    if neighbors.as_ref().is_some_and(|lists| lists.len() != count) {
+        return Err(PyValueError::new_err(
+            "neighbors must have one list per agent",
+        ));
+    }
+
+    let agents: Vec<OrcaAgent> = positions
+        .into_iter()
+        .zip(velocities)
+        .zip(preferred_velocities)
+        .zip(radii.into_iter().zip(max_speeds))
+        .map(
+            |(((position, velocity), preferred_velocity), (radius, max_speed))| OrcaAgent {
+                position: position.into(),
+                velocity: velocity.into(),
+                preferred_velocity: preferred_velocity.into(),
+                radius,
+                max_speed,
+            },
+        )
+        .collect();
+    let settings = OrcaSettings {
+        time_horizon,
+        time_step,
+        neighbor_distance,
+        max_neighbors,
+    };
+    let velocities = py.allow_threads(|| {
+        let neighbors = neighbors.unwrap_or_else(|| nearest_neighbors(&agents, &settings));
+        orca_velocities(&agents, &neighbors, &settings)
+    });
+    Ok(velocities)
+}
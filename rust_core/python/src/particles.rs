@@ -0,0 +1,241 @@
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::particles::{self, Emitter, Particle, Rgba};
+use llamaquest::python::VecLike;
+use llamaquest::rng::Rng;
+use llamaquest::vec2::Vec2;
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::profiling;
+use crate::state::Persist;
+
+/// CPU particles spawned by emitters and simulated in bulk.
+///
+/// Render from `positions()` and `colors()`, which pack every live particle into
+/// flat buffers in the same order: native-endian `f32` x/y pairs and RGBA bytes.
+#[pyclass]
+pub struct ParticleSystem {
+    system: particles::ParticleSystem,
+}
+
+fn no_emitter(id: usize) -> PyErr {
+    PyIndexError::new_err(format!("no particle emitter with id {}", id))
+}
+
+impl ParticleSystem {
+    fn emitter_mut(&mut self, id: usize) -> PyResult<&mut Emitter> {
+        self.system.emitter_mut(id).ok_or_else(|| no_emitter(id))
+    }
+}
+
+impl Persist for ParticleSystem {
+    const KIND: &'static str = "particle_system";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let system = &self.system;
+        out.u64(system.rng.state());
+        out.usize(system.capacity);
+        out.usize(system.emitters.len());
+        for emitter in &system.emitters {
+            out.vec2(emitter.position);
+            out.f32(emitter.rate);
+            for (low, high) in [emitter.lifetime, emitter.speed, emitter.angle] {
+                out.f32(low);
+                out.f32(high);
+            }
+            out.vec2(emitter.gravity);
+            out.f32(emitter.drag);
+            out.usize(emitter.colors.len());
+            for &(r, g, b, a) in &emitter.colors {
+                for channel in [r, g, b, a] {
+                    out.u8(channel);
+                }
+            }
+            out.f32(emitter.pending);
+            out.bool(emitter.active);
+        }
+        out.usize(system.particles.len());
+        for particle in &system.particles {
+            out.vec2(particle.position);
+            out.vec2(particle.velocity);
+            out.f32(particle.age);
+            out.f32(particle.lifetime);
+            out.usize(particle.emitter);
+        }
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let rng = Rng::new(input.u64()?);
+        let capacity = input.usize()?;
+        let range = |input: &mut Reader| Ok::<_, DecodeError>((input.f32()?, input.f32()?));
+        let mut emitters = Vec::new();
+        for _ in 0..input.usize()? {
+            emitters.push(Emitter {
+                position: input.vec2()?,
+                rate: input.f32()?,
+                lifetime: range(input)?,
+                speed: range(input)?,
+                angle: range(input)?,
+                gravity: input.vec2()?,
+                drag: input.f32()?,
+                colors: (0..input.usize()?)
+                    .map(|_| Ok((input.u8()?, input.u8()?, input.u8()?, input.u8()?)))
+                    .collect::<Result<_, DecodeError>>()?,
+                pending: input.f32()?,
+                active: input.bool()?,
+            });
+        }
+        let mut particles = Vec::new();
+        for _ in 0..input.usize()? {
+            let particle = Particle {
+                position: input.vec2()?,
+                velocity: input.vec2()?,
+                age: input.f32()?,
+                lifetime: input.f32()?,
+                emitter: input.usize()?,
+            };
+            if particle.emitter >= emitters.len() {
+                return Err(DecodeError::Invalid(format!(
+                    "particle refers to unknown emitter {}",
+                    particle.emitter
+                )));
+            }
+            particles.push(particle);
+        }
+        let system = particles::ParticleSystem {
+            emitters,
+            particles,
+            capacity,
+            rng,
+        };
+        Ok(ParticleSystem { system })
+    }
+}
+
+#[pymethods]
+impl ParticleSystem {
+    #[new]
+    #[pyo3(signature = (capacity = 10000, seed = None))]
+    fn new(capacity: usize, seed: Option<u64>) -> Self {
+        ParticleSystem {
+            system: particles::ParticleSystem::new(capacity, seed.unwrap_or(0)),
+        }
+    }
+
+    /// Live particles never exceed this; spawns beyond it are dropped
+    #[getter]
+    fn capacity(&self) -> usize {
+        self.system.capacity
+    }
+
+    #[setter]
+    fn set_capacity(&mut self, capacity: usize) {
+        self.system.capacity = capacity;
+    }
+
+    /// Add an emitter and return its id; ranges are `(min, max)` and angles in radians
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        x,
+        y,
+        rate,
+        lifetime = (1.0, 1.0),
+        speed = (1.0, 1.0),
+        angle = (0.0, std::f32::consts::TAU),
+        gravity = VecLike::Vector(Vec2::ZERO),
+        drag = 0.0,
+        colors = None
+    ))]
+    fn add_emitter(
+        &mut self,
+        x: f32,
+        y: f32,
+        rate: f32,
+        lifetime: (f32, f32),
+        speed: (f32, f32),
+        angle: (f32, f32),
+        gravity: VecLike,
+        drag: f32,
+        colors: Option<Vec<Rgba>>,
+    ) -> PyResult<usize> {
+        if lifetime.0 <= 0.0 || lifetime.1 < lifetime.0 {
+            return Err(PyValueError::new_err(
+                "lifetime must be a positive (min, max) range",
+            ));
+        }
+        Ok(self.system.add_emitter(Emitter {
+            position: Vec2::new(x, y),
+            rate,
+            lifetime,
+            speed,
+            angle,
+            gravity: gravity.into(),
+            drag,
+            colors: colors.unwrap_or_default(),
+            pending: 0.0,
+            active: true,
+        }))
+    }
+
+    /// Stop an emitter; particles it already spawned live out their lifetime
+    fn remove_emitter(&mut self, id: usize) -> PyResult<()> {
+        if !self.system.remove_emitter(id) {
+            return Err(no_emitter(id));
+        }
+        Ok(())
+    }
+
+    fn set_emitter_position(&mut self, id: usize, x: f32, y: f32) -> PyResult<()> {
+        self.emitter_mut(id)?.position = Vec2::new(x, y);
+        Ok(())
+    }
+
+    fn set_emitter_rate(&mut self, id: usize, rate: f32) -> PyResult<()> {
+        self.emitter_mut(id)?.rate = rate;
+        Ok(())
+    }
+
+    /// Spawn `count` particles from an emitter at once, e.g. for explosions
+    fn burst(&mut self, id: usize, count: usize) -> PyResult<()> {
+        if !self.system.burst(id, count) {
+            return Err(no_emitter(id));
+        }
+        Ok(())
+    }
+
+    /// Spawn due particles, then age, accelerate and move every particle
+    fn update(&mut self, delta_time: f32) {
+        let _scope = profiling::scope("ParticleSystem.update");
+        self.system.update(delta_time);
+    }
+
+    fn clear(&mut self) {
+        self.system.clear();
+    }
+
+    /// Packed native-endian `f32` x/y pairs, one per live particle
+    fn positions<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        let mut bytes = Vec::with_capacity(self.system.len() * 8);
+        for particle in &self.system.particles {
+            bytes.extend_from_slice(&particle.position.x.to_ne_bytes());
+            bytes.extend_from_slice(&particle.position.y.to_ne_bytes());
+        }
+        PyBytes::new(py, &bytes)
+    }
+
+    /// Packed RGBA bytes from each particle's colour over its life
+    fn colors<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        let mut bytes = Vec::with_capacity(self.system.len() * 4);
+        for particle in &self.system.particles {
+            let color = self.system.color(particle);
+            bytes.extend_from_slice(&[color.0, color.1, color.2, color.3]);
+        }
+        PyBytes::new(py, &bytes)
+    }
+
+    fn __len__(&self) -> usize {
+        self.system.len()
+    }
+}
@@ -0,0 +1,150 @@
+//! Opt-in timing and counters for the Rust entry points.
+//!
+//! Every instrumented call opens a [`Scope`] named after its Python API. While
+//! profiling is off that costs a single atomic load; while it is on, the scope
+//! records its wall time when dropped along with any [`count`] calls made inside
+//! it, so work done with the GIL released is measured the same way. Counters are
+//! tallied per thread and only merged into the shared table when the scope ends.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STATS: Mutex<BTreeMap<&'static str, Entry>> = Mutex::new(BTreeMap::new());
+
+thread_local! {
+    /// Counters of the scopes currently open on this thread, innermost last
+    static OPEN: RefCell<Vec<Vec<(&'static str, u64)>>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Default)]
+struct Entry {
+    calls: u64,
+    total: Duration,
+    max: Duration,
+    counters: BTreeMap<&'static str, u64>,
+}
+
+fn with_stats<R>(f: impl FnOnce(&mut BTreeMap<&'static str, Entry>) -> R) -> R {
+    // A panic while recording must not disable profiling for the rest of the run
+    let mut stats = STATS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&mut stats)
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A timed call in progress; its duration is recorded when it goes out of scope
+pub struct Scope {
+    name: &'static str,
+    start: Instant,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let counters = OPEN
+            .with(|open| open.borrow_mut().pop())
+            .unwrap_or_default();
+        with_stats(|stats| {
+            let entry = stats.entry(self.name).or_default();
+            entry.calls += 1;
+            entry.total += elapsed;
+            entry.max = entry.max.max(elapsed);
+            for (counter, amount) in counters {
+                *entry.counters.entry(counter).or_default() += amount;
+            }
+        });
+    }
+}
+
+/// Start timing `name`, or do nothing while profiling is off
+pub fn scope(name: &'static str) -> Option<Scope> {
+    if !enabled() {
+        return None;
+    }
+    OPEN.with(|open| open.borrow_mut().push(Vec::new()));
+    Some(Scope {
+        name,
+        start: Instant::now(),
+    })
+}
+
+/// Add to a counter of the innermost open scope, e.g. nodes expanded by a search
+pub fn count(counter: &'static str, amount: u64) {
+    if !enabled() {
+        return;
+    }
+    OPEN.with(|open| {
+        let mut open = open.borrow_mut();
+        let Some(counters) = open.last_mut() else {
+            return;
+        };
+        match counters.iter_mut().find(|(name, _)| *name == counter) {
+            Some((_, total)) => *total += amount,
+            None => counters.push((counter, amount)),
+        }
+    });
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Turn timing and counters on or off for every Rust API
+#[pyfunction]
+#[pyo3(signature = (enabled = true))]
+pub fn set_profiling(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+#[pyfunction]
+pub fn profiling_enabled() -> bool {
+    enabled()
+}
+
+/// Collected stats keyed by API name.
+///
+/// Each entry holds `calls`, `total_ms`, `mean_ms`, `max_ms` and a `counters` dict.
+/// Pass `reset=True` to start a fresh window, e.g. once per frame.
+#[pyfunction]
+#[pyo3(signature = (reset = false))]
+pub fn profiling_stats(py: Python<'_>, reset: bool) -> PyResult<PyObject> {
+    let result = PyDict::new(py);
+    with_stats(|stats| -> PyResult<()> {
+        for (name, entry) in stats.iter() {
+            let item = PyDict::new(py);
+            item.set_item("calls", entry.calls)?;
+            item.set_item("total_ms", millis(entry.total))?;
+            let mean = if entry.calls > 0 {
+                millis(entry.total) / entry.calls as f64
+            } else {
+                0.0
+            };
+            item.set_item("mean_ms", mean)?;
+            item.set_item("max_ms", millis(entry.max))?;
+            item.set_item("counters", entry.counters.clone().into_py(py))?;
+            result.set_item(*name, item)?;
+        }
+        if reset {
+            stats.clear();
+        }
+        Ok(())
+    })?;
+    Ok(result.into())
+}
+
+/// Forget everything recorded so far without changing whether profiling is on
+#[pyfunction]
+pub fn reset_profiling() {
+    with_stats(|stats| stats.clear());
+}
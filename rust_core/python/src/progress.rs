@@ -0,0 +1,189 @@
+//! Progress reporting from long computations back to Python.
+//!
+//! Entry points take an optional `progress` argument that is either a callable,
+//! invoked as `callback(stage, percent)`, or a [`Progress`] handle that another
+//! thread can poll. Algorithms call [`report`] as they go; reports are throttled to
+//! whole-percent changes so a busy loop does not spend its time in Python.
+
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+
+use crate::cancel;
+
+thread_local! {
+    /// Where reports from the computation running on this thread go
+    static ACTIVE: RefCell<Option<Watch>> = const { RefCell::new(None) };
+}
+
+#[derive(Clone, Default)]
+struct State {
+    stage: String,
+    percent: f32,
+    done: bool,
+}
+
+/// A pollable view of how far a computation has got
+#[pyclass]
+#[derive(Clone, Default)]
+pub struct Progress {
+    state: Arc<Mutex<State>>,
+}
+
+impl Progress {
+    fn state(&self) -> State {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    fn update(&self, update: impl FnOnce(&mut State)) {
+        update(
+            &mut self
+                .state
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        );
+    }
+}
+
+#[pymethods]
+impl Progress {
+    #[new]
+    fn new() -> Self {
+        Progress::default()
+    }
+
+    /// Name of the stage currently running, e.g. `"scan"` or `"paths"`
+    #[getter]
+    fn stage(&self) -> String {
+        self.state().stage
+    }
+
+    /// Completion of the current stage, from 0 to 100
+    #[getter]
+    fn percent(&self) -> f32 {
+        self.state().percent
+    }
+
+    /// Whether the computation has finished, successfully or not
+    #[getter]
+    fn done(&self) -> bool {
+        self.state().done
+    }
+
+    fn __repr__(&self) -> String {
+        let state = self.state();
+        format!(
+            "Progress(stage='{}', percent={:.0}, done={})",
+            state.stage,
+            state.percent,
+            if state.done { "True" } else { "False" }
+        )
+    }
+}
+
+/// Where progress goes: a handle to update or a Python callback to call
+pub enum Sink {
+    Handle(Progress),
+    Callback(PyObject),
+}
+
+impl Sink {
+    /// Interpret a `progress` argument, which may be omitted
+    pub fn from_arg(progress: Option<&PyAny>) -> PyResult<Option<Self>> {
+        let Some(progress) = progress.filter(|value| !value.is_none()) else {
+            return Ok(None);
+        };
+        if let Ok(handle) = progress.extract::<PyRef<Progress>>() {
+            return Ok(Some(Sink::Handle(handle.clone())));
+        }
+        if progress.is_callable() {
+            return Ok(Some(Sink::Callback(progress.into())));
+        }
+        Err(PyTypeError::new_err(format!(
+            "progress must be a Progress handle or a callable, got {}",
+            progress.get_type().name()?
+        )))
+    }
+}
+
+struct Watch {
+    sink: Sink,
+    stage: &'static str,
+    percent: f32,
+    error: Option<PyErr>,
+}
+
+impl Watch {
+    fn send(&mut self, stage: &'static str, percent: f32) {
+        self.stage = stage;
+        self.percent = percent;
+        match &self.sink {
+            Sink::Handle(handle) => handle.update(|state| {
+                state.stage = stage.to_string();
+                state.percent = percent;
+            }),
+            Sink::Callback(callback) => {
+                if self.error.is_some() {
+                    return;
+                }
+                // The computation runs with the GIL released, so take it back briefly
+                let result = Python::with_gil(|py| callback.call1(py, (stage, percent)).map(drop));
+                self.error = result.err();
+            }
+        }
+    }
+}
+
+/// Report that `stage` is `fraction` (0 to 1) complete
+pub fn report(stage: &'static str, fraction: f32) {
+    let percent = (fraction.clamp(0.0, 1.0) * 100.0).floor();
+    // Taken out while sending, so a callback that runs other computations sees none
+    let Some(mut watch) = ACTIVE.with(|active| active.borrow_mut().take()) else {
+        return;
+    };
+    if watch.stage != stage || percent > watch.percent {
+        watch.send(stage, percent);
+    }
+    ACTIVE.with(|active| *active.borrow_mut() = Some(watch));
+}
+
+/// Run `work` with its reports going to `sink`.
+///
+/// The last stage is reported as finished unless the run was cancelled. An exception raised by a
+/// callback stops further callbacks and is returned once `work` completes.
+pub fn watch<T>(sink: Option<Sink>, work: impl FnOnce() -> T) -> PyResult<T> {
+    let Some(sink) = sink else {
+        return Ok(work());
+    };
+    if let Sink::Handle(handle) = &sink {
+        handle.update(|state| *state = State::default());
+    }
+    let watch = Watch {
+        sink,
+        stage: "",
+        percent: -1.0,
+        error: None,
+    };
+    let previous = ACTIVE.with(|active| active.replace(Some(watch)));
+    let value = work();
+    let cancelled = cancel::requested();
+    let mut watch = ACTIVE
+        .with(|active| active.replace(previous))
+        .expect("progress watch replaced while running");
+    if watch.percent < 100.0 && watch.error.is_none() && !cancelled {
+        let stage = watch.stage;
+        watch.send(stage, 100.0);
+    }
+    if let Sink::Handle(handle) = &watch.sink {
+        handle.update(|state| state.done = true);
+    }
+    match watch.error {
+        Some(error) => Err(error),
+        None => Ok(value),
+    }
+}
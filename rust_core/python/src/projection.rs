@@ -0,0 +1,236 @@
+//! `ScreenProjection`: tile ↔ screen conversion for single points and whole arrays
+
+use llamaquest::projection::{Projection, ProjectionKind};
+use llamaquest::python::VecLike;
+use llamaquest::vec2::Vec2;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::buffers;
+use crate::profiling;
+
+fn parse_kind(name: &str) -> PyResult<ProjectionKind> {
+    ProjectionKind::from_name(name).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "unknown projection '{}', expected 'isometric' or 'orthographic'",
+            name
+        ))
+    })
+}
+
+fn check_positive(name: &str, value: f32) -> PyResult<f32> {
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err(PyValueError::new_err(format!(
+            "{} must be positive, got {}",
+            name, value
+        )))
+    }
+}
+
+/// Converts positions between tile coordinates and screen pixels.
+///
+/// `tile_size` is a tile's `(width, height)` in pixels at zoom 1; for isometric views
+/// it is the size of the tile's diamond, e.g. `(64, 32)`. `camera` is the unzoomed
+/// pixel position drawn at the top-left of the screen. Tile coordinates are
+/// fractional: tile `(x, y)` covers `[x, x + 1) x [y, y + 1)`.
+#[pyclass]
+pub struct ScreenProjection {
+    projection: Projection,
+}
+
+impl ScreenProjection {
+    /// Apply `convert` to every `(x, y)` row of `positions`, writing into `out`, or
+    /// back into `positions` when `out` is `None`
+    fn convert_many(
+        &self,
+        py: Python<'_>,
+        positions: &PyAny,
+        out: Option<&PyAny>,
+        convert: fn(&Projection, Vec2) -> Vec2,
+    ) -> PyResult<()> {
+        let (input, count) =
+            buffers::rows::<f32>(positions, "positions", "float32", 2, out.is_none())?;
+        let output = match out {
+            Some(out) => {
+                let (output, out_count) = buffers::rows::<f32>(out, "out", "float32", 2, true)?;
+                if out_count != count {
+                    return Err(PyValueError::new_err(format!(
+                        "positions holds {} points but out holds {}",
+                        count, out_count
+                    )));
+                }
+                Some(output)
+            }
+            None => None,
+        };
+        let mut data = input.to_vec(py)?;
+        py.allow_threads(|| {
+            for point in data.chunks_exact_mut(2) {
+                let converted = convert(&self.projection, Vec2::new(point[0], point[1]));
+                point.copy_from_slice(&[converted.x, converted.y]);
+            }
+        });
+        output.as_ref().unwrap_or(&input).copy_from_slice(py, &data)
+    }
+
+    fn depth_keys_of(&self, data: &[f32]) -> Vec<f32> {
+        data.chunks_exact(2)
+            .map(|point| self.projection.depth_key(Vec2::new(point[0], point[1])))
+            .collect()
+    }
+}
+
+#[pymethods]
+impl ScreenProjection {
+    #[new]
+    #[pyo3(signature = (kind = "isometric", tile_size = None, zoom = 1.0, camera = None))]
+    fn new(
+        kind: &str,
+        tile_size: Option<VecLike>,
+        zoom: f32,
+        camera: Option<VecLike>,
+    ) -> PyResult<Self> {
+        let tile_size = tile_size.map_or(Vec2::new(64.0, 32.0), Vec2::from);
+        Ok(ScreenProjection {
+            projection: Projection {
+                kind: parse_kind(kind)?,
+                tile_size: Vec2::new(
+                    check_positive("tile width", tile_size.x)?,
+                    check_positive("tile height", tile_size.y)?,
+                ),
+                zoom: check_positive("zoom", zoom)?,
+                camera: camera.map_or(Vec2::ZERO, Vec2::from),
+            },
+        })
+    }
+
+    #[getter]
+    fn kind(&self) -> &'static str {
+        match self.projection.kind {
+            ProjectionKind::Orthographic => "orthographic",
+            ProjectionKind::Isometric => "isometric",
+        }
+    }
+
+    #[getter]
+    fn tile_size(&self) -> Vec2 {
+        self.projection.tile_size
+    }
+
+    #[getter]
+    fn zoom(&self) -> f32 {
+        self.projection.zoom
+    }
+
+    #[setter]
+    fn set_zoom(&mut self, zoom: f32) -> PyResult<()> {
+        self.projection.zoom = check_positive("zoom", zoom)?;
+        Ok(())
+    }
+
+    #[getter]
+    fn camera(&self) -> Vec2 {
+        self.projection.camera
+    }
+
+    #[setter]
+    fn set_camera(&mut self, camera: VecLike) {
+        self.projection.camera = camera.into();
+    }
+
+    /// Screen pixel of a point in tile coordinates
+    fn to_screen(&self, tile: VecLike) -> Vec2 {
+        self.projection.to_screen(tile.into())
+    }
+
+    /// Tile coordinates of a screen pixel
+    fn to_tile(&self, screen: VecLike) -> Vec2 {
+        self.projection.to_tile(screen.into())
+    }
+
+    /// The `(x, y)` tile a screen pixel falls in, e.g. the one under the mouse
+    fn tile_at(&self, screen: VecLike) -> (i32, i32) {
+        let tile = self.projection.to_tile(screen.into());
+        (tile.x.floor() as i32, tile.y.floor() as i32)
+    }
+
+    /// Apply `to_screen` to many points at once.
+    ///
+    /// `positions` is a contiguous float32 array of shape (n, 2), e.g. a numpy array.
+    /// The results are written into `out`, an array of the same shape, or back into
+    /// `positions` when `out` is omitted.
+    #[pyo3(signature = (positions, out = None))]
+    fn to_screen_many(
+        &self,
+        py: Python<'_>,
+        positions: &PyAny,
+        out: Option<&PyAny>,
+    ) -> PyResult<()> {
+        let _scope = profiling::scope("ScreenProjection.to_screen_many");
+        self.convert_many(py, positions, out, Projection::to_screen)
+    }
+
+    /// Apply `to_tile` to many points at once, with the same arrays as `to_screen_many`
+    #[pyo3(signature = (positions, out = None))]
+    fn to_tile_many(&self, py: Python<'_>, positions: &PyAny, out: Option<&PyAny>) -> PyResult<()> {
+        let _scope = profiling::scope("ScreenProjection.to_tile_many");
+        self.convert_many(py, positions, out, Projection::to_tile)
+    }
+
+    /// Draw-order keys for many points in tile coordinates.
+    ///
+    /// `positions` is a float32 array of shape (n, 2) and `out` a writable float32
+    /// array of length n. Drawing in ascending key order puts nearer sprites on top:
+    /// the key is `y` for orthographic views and `x + y` for isometric ones.
+    fn depth_keys(&self, py: Python<'_>, positions: &PyAny, out: &PyAny) -> PyResult<()> {
+        let _scope = profiling::scope("ScreenProjection.depth_keys");
+        let (input, count) = buffers::rows::<f32>(positions, "positions", "float32", 2, false)?;
+        let (output, out_count) = buffers::rows::<f32>(out, "out", "float32", 1, true)?;
+        if out_count != count {
+            return Err(PyValueError::new_err(format!(
+                "positions holds {} points but out holds {} keys",
+                count, out_count
+            )));
+        }
+        let data = input.to_vec(py)?;
+        let keys = py.allow_threads(|| self.depth_keys_of(&data));
+        output.copy_from_slice(py, &keys)
+    }
+
+    /// Indices of the points in `positions` in back-to-front draw order.
+    ///
+    /// Points with equal keys keep their original order, so a stable layer order
+    /// can be set up by the order entities are listed in.
+    fn depth_order(&self, py: Python<'_>, positions: &PyAny) -> PyResult<Vec<usize>> {
+        let _scope = profiling::scope("ScreenProjection.depth_order");
+        let (input, _) = buffers::rows::<f32>(positions, "positions", "float32", 2, false)?;
+        let data = input.to_vec(py)?;
+        Ok(py.allow_threads(|| {
+            let points: Vec<Vec2> = data
+                .chunks_exact(2)
+                .map(|point| Vec2::new(point[0], point[1]))
+                .collect();
+            self.projection.depth_order(&points)
+        }))
+    }
+
+    fn __repr__(&self) -> String {
+        let Projection {
+            tile_size,
+            zoom,
+            camera,
+            ..
+        } = self.projection;
+        format!(
+            "ScreenProjection(kind='{}', tile_size=({}, {}), zoom={}, camera=({}, {}))",
+            self.kind(),
+            tile_size.x,
+            tile_size.y,
+            zoom,
+            camera.x,
+            camera.y
+        )
+    }
+}
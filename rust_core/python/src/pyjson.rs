@@ -0,0 +1,37 @@
+//! Conversion of Python-side descriptions into JSON values for the data-driven subsystems
+
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+use serde_json::Value;
+
+use crate::errors::SerializationError;
+
+/// Accept either a JSON string or plain Python data (dicts, lists, numbers, strings)
+pub fn to_json_value(py: Python<'_>, description: &PyAny) -> PyResult<Value> {
+    let text: String = if description.is_instance_of::<PyString>()? {
+        description.extract()?
+    } else {
+        py.import("json")?
+            .call_method1("dumps", (description,))?
+            .extract()?
+    };
+    serde_json::from_str(&text)
+        .map_err(|e| SerializationError::new_err(format!("invalid description: {}", e)))
+}
+
+/// Compact JSON text for plain Python data, for storing it as bytes
+pub fn dumps(py: Python<'_>, value: &PyAny) -> PyResult<String> {
+    let json = py.import("json")?;
+    let options = pyo3::types::PyDict::new(py);
+    options.set_item("separators", (",", ":"))?;
+    json.call_method("dumps", (value,), Some(options))?
+        .extract()
+}
+
+/// Plain Python data from JSON text written by `dumps`
+pub fn loads(py: Python<'_>, text: &str) -> PyResult<PyObject> {
+    py.import("json")?
+        .call_method1("loads", (text,))
+        .map(Into::into)
+        .map_err(|e| SerializationError::new_err(format!("invalid JSON: {}", e)))
+}
@@ -0,0 +1,47 @@
+//! Grid rasterization for drawing tools and area effects. Cells are `(x, y)` tuples
+//! and may fall outside the map; callers clip them.
+
+use llamaquest::raster::{self, Cell};
+use pyo3::prelude::*;
+
+use crate::profiling;
+
+/// Cells of the Bresenham line from `start` to `end`, both included, in order
+#[pyfunction]
+pub fn line_cells(start: Cell, end: Cell) -> Vec<Cell> {
+    let _scope = profiling::scope("line_cells");
+    raster::line(start, end)
+}
+
+/// Cells whose centres lie within `width / 2` of the segment from `start` to `end`;
+/// a width of 1 or less gives `line_cells`
+#[pyfunction]
+pub fn thick_line_cells(start: Cell, end: Cell, width: f32) -> Vec<Cell> {
+    let _scope = profiling::scope("thick_line_cells");
+    raster::thick_line(start, end, width)
+}
+
+/// Cells of a circle's outline, or of the whole disc when `filled`, row by row
+#[pyfunction]
+#[pyo3(signature = (center, radius, filled = false))]
+pub fn circle_cells(center: Cell, radius: u32, filled: bool) -> Vec<Cell> {
+    let _scope = profiling::scope("circle_cells");
+    if filled {
+        raster::filled_circle(center, radius)
+    } else {
+        raster::circle(center, radius)
+    }
+}
+
+/// Cells of an axis-aligned ellipse's outline, or of its whole area when `filled`,
+/// row by row
+#[pyfunction]
+#[pyo3(signature = (center, radius_x, radius_y, filled = false))]
+pub fn ellipse_cells(center: Cell, radius_x: u32, radius_y: u32, filled: bool) -> Vec<Cell> {
+    let _scope = profiling::scope("ellipse_cells");
+    if filled {
+        raster::filled_ellipse(center, radius_x, radius_y)
+    } else {
+        raster::ellipse(center, radius_x, radius_y)
+    }
+}
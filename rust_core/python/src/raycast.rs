@@ -0,0 +1,219 @@
+//! `Raycaster`: DDA column raycasting for first-person views, written into flat
+//! arrays so a frame's rays cost one call
+
+use llamaquest::python::VecLike;
+use llamaquest::raycast::{cast_columns, cast_ray, Face};
+use llamaquest::vec2::Vec2;
+use pyo3::buffer::{Element, PyBuffer};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::buffers;
+use crate::errors::OutOfBoundsError;
+use crate::grid;
+use crate::profiling;
+
+/// `(distance, face, offset, (x, y), tile)` of a single ray's hit
+type RayHit = (f32, &'static str, f32, (usize, usize), u32);
+
+/// Value written to `faces` for columns whose ray hit nothing
+const NO_HIT: u8 = 255;
+
+/// An optional per-column output array, which must hold `columns` items
+fn column_buffer<T: Element>(
+    obj: Option<&PyAny>,
+    name: &str,
+    kind: &str,
+    columns: usize,
+) -> PyResult<Option<PyBuffer<T>>> {
+    let Some(obj) = obj else {
+        return Ok(None);
+    };
+    let (buffer, count) = buffers::rows::<T>(obj, name, kind, 1, true)?;
+    if count != columns {
+        return Err(PyValueError::new_err(format!(
+            "{} holds {} columns but distances holds {}",
+            name, count, columns
+        )));
+    }
+    Ok(Some(buffer))
+}
+
+fn face_code(face: Face) -> u8 {
+    match face {
+        Face::North => 0,
+        Face::East => 1,
+        Face::South => 2,
+        Face::West => 3,
+    }
+}
+
+/// A tile map to cast rays through.
+///
+/// `tile_map` is a `[y][x]` grid of tile ids, 0 for empty space and anything else a
+/// wall, e.g. a texture index; a bool wall map works too. Positions are in cell
+/// units with `y` pointing down, and angles in radians, 0 facing `+x`.
+#[pyclass]
+pub struct Raycaster {
+    tiles: Vec<Vec<u32>>,
+    width: usize,
+    height: usize,
+}
+
+impl Raycaster {
+    fn origin(&self, position: VecLike) -> PyResult<Vec2> {
+        let origin = Vec2::from(position);
+        if !(origin.x >= 0.0
+            && origin.y >= 0.0
+            && origin.x < self.width as f32
+            && origin.y < self.height as f32)
+        {
+            return Err(OutOfBoundsError::new_err(format!(
+                "position ({}, {}) is outside the {}x{} tile map",
+                origin.x, origin.y, self.width, self.height
+            )));
+        }
+        Ok(origin)
+    }
+
+    fn max_distance(&self, max_distance: Option<f32>) -> f32 {
+        // Far enough to cross the whole map
+        max_distance.unwrap_or((self.width + self.height) as f32)
+    }
+}
+
+#[pymethods]
+impl Raycaster {
+    #[new]
+    fn new(tile_map: Vec<Vec<u32>>) -> PyResult<Self> {
+        let (width, height) = grid::require_non_empty(&tile_map, "tile_map")?;
+        Ok(Raycaster {
+            tiles: tile_map,
+            width,
+            height,
+        })
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn get(&self, x: usize, y: usize) -> PyResult<u32> {
+        grid::require_cell("cell", (x, y), (self.width, self.height), "tile map")?;
+        Ok(self.tiles[y][x])
+    }
+
+    /// Change one tile, e.g. to open a door
+    fn set(&mut self, x: usize, y: usize, tile: u32) -> PyResult<()> {
+        grid::require_cell("cell", (x, y), (self.width, self.height), "tile map")?;
+        self.tiles[y][x] = tile;
+        Ok(())
+    }
+
+    /// Cast one ray per screen column for a viewer at `position` facing `angle`.
+    ///
+    /// The column count is the length of `distances`, a writable float32 array that
+    /// receives each column's distance to the wall it hit, perpendicular to the view
+    /// so walls `height / distance` pixels tall show no fisheye. The optional arrays
+    /// of the same length receive, per column: `faces` (uint8) the side of the wall
+    /// hit, 0 north, 1 east, 2 south or 3 west; `offsets` (float32) where along that
+    /// side, from 0 to 1, for the texture column; and `tiles` (uint32) the tile id.
+    /// Columns that hit nothing within `max_distance` get an infinite distance, face
+    /// 255 and tile 0.
+    #[pyo3(signature = (position, angle, fov, distances, faces = None, offsets = None, tiles = None, max_distance = None))]
+    #[allow(clippy::too_many_arguments)]
+    fn cast(
+        &self,
+        py: Python<'_>,
+        position: VecLike,
+        angle: f32,
+        fov: f32,
+        distances: &PyAny,
+        faces: Option<&PyAny>,
+        offsets: Option<&PyAny>,
+        tiles: Option<&PyAny>,
+        max_distance: Option<f32>,
+    ) -> PyResult<()> {
+        let _scope = profiling::scope("Raycaster.cast");
+        let origin = self.origin(position)?;
+        if !(fov > 0.0 && fov < std::f32::consts::PI) {
+            return Err(PyValueError::new_err(format!(
+                "fov must be between 0 and pi radians, got {}",
+                fov
+            )));
+        }
+        let (distance_buffer, columns) =
+            buffers::rows::<f32>(distances, "distances", "float32", 1, true)?;
+        let face_buffer = column_buffer::<u8>(faces, "faces", "uint8", columns)?;
+        let offset_buffer = column_buffer::<f32>(offsets, "offsets", "float32", columns)?;
+        let tile_buffer = column_buffer::<u32>(tiles, "tiles", "uint32", columns)?;
+
+        let max_distance = self.max_distance(max_distance);
+        let hits = py
+            .allow_threads(|| cast_columns(&self.tiles, origin, angle, fov, max_distance, columns));
+        profiling::count("rays_cast", columns as u64);
+        let distance_data: Vec<f32> = hits
+            .iter()
+            .map(|hit| hit.map_or(f32::INFINITY, |hit| hit.distance))
+            .collect();
+        distance_buffer.copy_from_slice(py, &distance_data)?;
+        if let Some(buffer) = face_buffer {
+            let data: Vec<u8> = hits
+                .iter()
+                .map(|hit| hit.map_or(NO_HIT, |hit| face_code(hit.face)))
+                .collect();
+            buffer.copy_from_slice(py, &data)?;
+        }
+        if let Some(buffer) = offset_buffer {
+            let data: Vec<f32> = hits
+                .iter()
+                .map(|hit| hit.map_or(0.0, |hit| hit.offset))
+                .collect();
+            buffer.copy_from_slice(py, &data)?;
+        }
+        if let Some(buffer) = tile_buffer {
+            let data: Vec<u32> = hits
+                .iter()
+                .map(|hit| hit.map_or(0, |hit| hit.tile))
+                .collect();
+            buffer.copy_from_slice(py, &data)?;
+        }
+        Ok(())
+    }
+
+    /// Cast a single ray, e.g. for hitscan weapons or picking the wall in front.
+    ///
+    /// Returns `(distance, face, offset, (x, y), tile)` for the first wall within
+    /// `max_distance`, with a Euclidean distance and the face as 'north', 'east',
+    /// 'south' or 'west', or None if the ray leaves the map first.
+    #[pyo3(signature = (position, angle, max_distance = None))]
+    fn cast_ray(
+        &self,
+        position: VecLike,
+        angle: f32,
+        max_distance: Option<f32>,
+    ) -> PyResult<Option<RayHit>> {
+        let origin = self.origin(position)?;
+        let hit = cast_ray(
+            &self.tiles,
+            origin,
+            Vec2::from_angle(angle),
+            self.max_distance(max_distance),
+        );
+        Ok(hit.map(|hit| {
+            (
+                hit.distance,
+                hit.face.name(),
+                hit.offset,
+                hit.cell,
+                hit.tile,
+            )
+        }))
+    }
+}
@@ -0,0 +1,93 @@
+use llamaquest::regions::{self, Region};
+use pyo3::exceptions::PyKeyError;
+use pyo3::prelude::*;
+
+use crate::grid;
+use crate::profiling;
+
+/// Rooms, corridors and the connections between them, built from a labelled map.
+///
+/// Every non-negative label is a region; negative labels (walls, void) belong to
+/// none. Two regions are adjacent when any of their cells touch orthogonally, and
+/// the touching cells on both sides are recorded as the door cells of that edge.
+#[pyclass]
+pub struct RegionGraph {
+    graph: regions::RegionGraph,
+}
+
+impl RegionGraph {
+    fn region(&self, label: i64) -> PyResult<&Region> {
+        self.graph
+            .region(label)
+            .ok_or_else(|| PyKeyError::new_err(format!("no region labelled {}", label)))
+    }
+}
+
+#[pymethods]
+impl RegionGraph {
+    /// All region labels in ascending order
+    fn labels(&self) -> Vec<i64> {
+        self.graph.labels().collect()
+    }
+
+    /// Number of cells in a region
+    fn area(&self, label: i64) -> PyResult<usize> {
+        Ok(self.region(label)?.area)
+    }
+
+    /// Inclusive bounding box as `(min_x, min_y, max_x, max_y)`
+    fn bounds(&self, label: i64) -> PyResult<(usize, usize, usize, usize)> {
+        let region = self.region(label)?;
+        Ok((region.min.0, region.min.1, region.max.0, region.max.1))
+    }
+
+    /// Mean cell position of a region
+    fn centroid(&self, label: i64) -> PyResult<(f32, f32)> {
+        Ok(self.region(label)?.centroid())
+    }
+
+    /// Labels of the regions adjacent to `label`
+    fn neighbors(&self, label: i64) -> PyResult<Vec<i64>> {
+        self.region(label)?;
+        Ok(self.graph.neighbours(label).collect())
+    }
+
+    /// Every adjacency as a `(lower_label, higher_label)` pair
+    fn edges(&self) -> Vec<(i64, i64)> {
+        self.graph.edges().collect()
+    }
+
+    /// Cells where two regions touch, or an empty list if they are not adjacent
+    fn doors(&self, a: i64, b: i64) -> Vec<(usize, usize)> {
+        self.graph.doors(a, b).to_vec()
+    }
+
+    /// Sequence of regions to pass through to get from one region to another
+    fn route_between(&self, from: i64, to: i64) -> PyResult<Option<Vec<i64>>> {
+        let _scope = profiling::scope("RegionGraph.route_between");
+        self.region(from)?;
+        self.region(to)?;
+        Ok(self.graph.route(from, to))
+    }
+
+    fn __len__(&self) -> usize {
+        self.graph.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "RegionGraph(regions={}, edges={})",
+            self.graph.len(),
+            self.graph.edges().len()
+        )
+    }
+}
+
+/// Build a region adjacency graph from a map of region labels (negative = no region)
+#[pyfunction]
+pub fn build_region_graph(py: Python<'_>, label_map: Vec<Vec<i64>>) -> PyResult<RegionGraph> {
+    let _scope = profiling::scope("build_region_graph");
+    grid::require_rectangular(&label_map, "label_map")?;
+    let graph = py.allow_threads(|| regions::RegionGraph::build(&label_map));
+    Ok(RegionGraph { graph })
+}
@@ -0,0 +1,151 @@
+//! Python wrapper for `llamaquest::replay`: record the commands fed to the game each
+//! tick, save them, and play them back.
+
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::replay;
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::errors::{OutOfBoundsError, SerializationError};
+use crate::profiling;
+use crate::pyjson;
+use crate::state::Persist;
+
+/// Every input fed to the game on each tick, with the seed the run started from,
+/// for bug reports and kill-cams.
+///
+/// While playing, call `record(command, payload)` for each input as it is applied
+/// and `end_tick()` once per simulation step. To play a run back, seed every
+/// RNG-driven system (`SteeringAgents`, `ParticleSystem`, `UtilityEvaluator`) from
+/// `seed`, step at `fixed_step` (e.g. with a `GameClock`) and apply
+/// `commands(tick)` on each tick in turn. Payloads are plain data (dicts, lists,
+/// numbers, strings, `None`) and are stored as JSON.
+#[pyclass]
+pub struct Replay {
+    replay: replay::Replay,
+}
+
+impl Persist for Replay {
+    const KIND: &'static str = "replay";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        out.bytes(&self.replay.encode());
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let replay = replay::Replay::decode(input.bytes()?)?;
+        Ok(Replay { replay })
+    }
+}
+
+#[pymethods]
+impl Replay {
+    #[new]
+    #[pyo3(signature = (seed = 0, fixed_step = 1.0 / 60.0))]
+    fn new(seed: u64, fixed_step: f64) -> Self {
+        Replay {
+            replay: replay::Replay::new(seed, fixed_step),
+        }
+    }
+
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.replay.seed
+    }
+
+    #[getter]
+    fn fixed_step(&self) -> f64 {
+        self.replay.fixed_step
+    }
+
+    /// Record a command for the tick in progress
+    #[pyo3(signature = (command, payload = None))]
+    fn record(&mut self, py: Python<'_>, command: &str, payload: Option<&PyAny>) -> PyResult<()> {
+        let payload = match payload {
+            Some(payload) if !payload.is_none() => pyjson::dumps(py, payload)?.into_bytes(),
+            _ => Vec::new(),
+        };
+        self.replay.record(command, payload);
+        Ok(())
+    }
+
+    /// Close the tick in progress; call it once per step, even when nothing happened
+    fn end_tick(&mut self) {
+        self.replay.end_tick();
+    }
+
+    /// The `(command, payload)` pairs recorded on a completed tick, in order
+    fn commands(&self, py: Python<'_>, tick: usize) -> PyResult<Vec<(String, PyObject)>> {
+        let commands = self.replay.tick(tick).ok_or_else(|| {
+            OutOfBoundsError::new_err(format!(
+                "tick {} is outside a replay of {} ticks",
+                tick,
+                self.replay.len()
+            ))
+        })?;
+        commands
+            .iter()
+            .map(|command| {
+                let payload = if command.payload.is_empty() {
+                    py.None()
+                } else {
+                    let text = std::str::from_utf8(&command.payload).map_err(|_| {
+                        SerializationError::new_err("replay payload is not valid UTF-8")
+                    })?;
+                    pyjson::loads(py, text)?
+                };
+                Ok((command.name.clone(), payload))
+            })
+            .collect()
+    }
+
+    /// Forget every tick from `tick` on, e.g. to branch a new recording from a kill-cam
+    fn truncate(&mut self, tick: usize) {
+        self.replay.truncate(tick);
+    }
+
+    /// The completed ticks in the compact replay format; the tick in progress is left out
+    fn to_bytes<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        let _scope = profiling::scope("Replay.to_bytes");
+        PyBytes::new(py, &self.replay.encode())
+    }
+
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<Self> {
+        let _scope = profiling::scope("Replay.from_bytes");
+        replay::Replay::decode(data)
+            .map(|replay| Replay { replay })
+            .map_err(|e| SerializationError::new_err(format!("invalid replay: {}", e)))
+    }
+
+    /// Write `to_bytes()` to a file
+    fn save(&self, py: Python<'_>, path: &str) -> PyResult<()> {
+        let data = self.replay.encode();
+        py.allow_threads(|| std::fs::write(path, data))
+            .map_err(|e| PyIOError::new_err(format!("could not write replay '{}': {}", path, e)))
+    }
+
+    #[staticmethod]
+    fn load(py: Python<'_>, path: &str) -> PyResult<Self> {
+        let data = py
+            .allow_threads(|| std::fs::read(path))
+            .map_err(|e| PyIOError::new_err(format!("could not read replay '{}': {}", path, e)))?;
+        Replay::from_bytes(&data)
+    }
+
+    /// Number of completed ticks
+    fn __len__(&self) -> usize {
+        self.replay.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Replay(seed={}, fixed_step={}, ticks={})",
+            self.replay.seed,
+            self.replay.fixed_step,
+            self.replay.len()
+        )
+    }
+}
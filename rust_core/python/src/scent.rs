@@ -0,0 +1,159 @@
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::scent;
+use pyo3::prelude::*;
+
+use crate::cancel::{self, CancelToken};
+use crate::errors::OutOfBoundsError;
+use crate::grid;
+use crate::profiling;
+use crate::progress::{self, Sink};
+use crate::state::{self, Persist};
+
+/// A trail of scent that spreads through open cells and fades over time.
+///
+/// Deposit scent where the player stands, `step()` once per turn, and let monsters
+/// follow `gradient()` uphill to track them around corners.
+#[pyclass]
+pub struct ScentMap {
+    map: scent::ScentMap,
+}
+
+impl ScentMap {
+    fn index(&self, x: usize, y: usize) -> PyResult<usize> {
+        self.map.index(x, y).ok_or_else(|| {
+            OutOfBoundsError::new_err(format!(
+                "cell ({}, {}) is outside the {}x{} scent map",
+                x, y, self.map.width, self.map.height
+            ))
+        })
+    }
+}
+
+impl Persist for ScentMap {
+    const KIND: &'static str = "scent_map";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let map = &self.map;
+        out.usize(map.width);
+        out.usize(map.height);
+        out.f32(map.diffusion);
+        out.f32(map.decay);
+        state::write_f32s(out, &map.values);
+        state::write_f32s(out, &map.conductance);
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let (width, height, cells) = state::read_grid_size(input)?;
+        let map = scent::ScentMap {
+            width,
+            height,
+            diffusion: input.f32()?,
+            decay: input.f32()?,
+            values: state::read_f32s(input, cells)?,
+            conductance: state::read_f32s(input, cells)?,
+        };
+        Ok(ScentMap { map })
+    }
+}
+
+#[pymethods]
+impl ScentMap {
+    #[new]
+    #[pyo3(signature = (walkable_map, diffusion = 0.5, decay = 0.05))]
+    fn new(walkable_map: Vec<Vec<bool>>, diffusion: f32, decay: f32) -> PyResult<Self> {
+        grid::require_rectangular(&walkable_map, "walkable_map")?;
+        Ok(ScentMap {
+            map: scent::ScentMap::new(&walkable_map, diffusion, decay),
+        })
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.map.width
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.map.height
+    }
+
+    /// Fraction of the difference to neighbours exchanged per step, 0 to 1
+    #[getter]
+    fn diffusion(&self) -> f32 {
+        self.map.diffusion
+    }
+
+    #[setter]
+    fn set_diffusion(&mut self, diffusion: f32) {
+        self.map.diffusion = diffusion;
+    }
+
+    /// Fraction of scent lost per step
+    #[getter]
+    fn decay(&self) -> f32 {
+        self.map.decay
+    }
+
+    #[setter]
+    fn set_decay(&mut self, decay: f32) {
+        self.map.decay = decay;
+    }
+
+    /// Add scent to an open cell; walls never hold scent
+    fn deposit(&mut self, x: usize, y: usize, amount: f32) -> PyResult<()> {
+        let index = self.index(x, y)?;
+        self.map.deposit(index, amount);
+        Ok(())
+    }
+
+    /// Diffuse and decay the scent, once per turn
+    #[pyo3(signature = (turns = 1, cancel = None, progress = None))]
+    fn step(
+        &mut self,
+        py: Python<'_>,
+        turns: usize,
+        cancel: Option<PyRef<CancelToken>>,
+        progress: Option<&PyAny>,
+    ) -> PyResult<()> {
+        let _scope = profiling::scope("ScentMap.step");
+        let sink = Sink::from_arg(progress)?;
+        cancel::run(py, cancel.as_deref(), "ScentMap.step", || {
+            progress::watch(sink, || self.map.step(turns))
+        })?
+    }
+
+    fn get(&self, x: usize, y: usize) -> PyResult<f32> {
+        Ok(self.map.values[self.index(x, y)?])
+    }
+
+    /// The open neighbour with the strongest scent, if it is stronger than this cell
+    #[pyo3(signature = (x, y, diagonal = false))]
+    fn gradient(&self, x: usize, y: usize, diagonal: bool) -> PyResult<Option<(usize, usize)>> {
+        self.index(x, y)?;
+        Ok(self.map.gradient(x, y, diagonal))
+    }
+
+    fn clear(&mut self) {
+        self.map.values.fill(0.0);
+    }
+
+    /// The scent values as a `[y][x]` grid
+    fn to_list(&self) -> Vec<Vec<f32>> {
+        let map = &self.map;
+        if map.width == 0 {
+            return vec![Vec::new(); map.height];
+        }
+        map.values
+            .chunks(map.width)
+            .map(|row| row.to_vec())
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ScentMap(width={}, height={})",
+            self.map.width, self.map.height
+        )
+    }
+}
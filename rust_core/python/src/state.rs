@@ -0,0 +1,219 @@
+//! `save_state`/`load_state`: every engine object of a save game in one versioned
+//! binary blob, so Python does not have to marshal world state itself.
+//! `state_checksum` hashes the same encoding to compare lockstep peers.
+//!
+//! Each saveable class implements [`Persist`] next to its definition. A blob holds
+//! named entries, each tagged with its class's `KIND` and carrying that class's own
+//! encoding, so a loader can name the entry it failed on.
+
+use llamaquest::checksum::Checksum;
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use pyo3::{PyClass, PyTypeInfo};
+
+use crate::behavior_tree::BehaviorTree;
+use crate::camera::Camera2D;
+use crate::clock::GameClock;
+use crate::errors::SerializationError;
+use crate::events::EventScheduler;
+use crate::fluid::FluidGrid;
+use crate::fsm::StateMachines;
+use crate::influence::InfluenceMap;
+use crate::particles::ParticleSystem;
+use crate::profiling;
+use crate::replay::Replay;
+use crate::scent::ScentMap;
+use crate::steering::SteeringAgents;
+use crate::temperature::TemperatureGrid;
+use crate::turns::TurnScheduler;
+use crate::tween::Tweens;
+use crate::undo::UndoStack;
+use crate::utility::UtilityEvaluator;
+use crate::water::WaterSurface;
+use crate::world::World;
+
+const MAGIC: &[u8; 4] = b"LQST";
+/// Bumped whenever any class's encoding changes incompatibly
+const VERSION: u8 = 1;
+
+/// An engine class whose whole state can be written to and restored from bytes
+pub trait Persist: PyClass + Sized {
+    /// Tags the class's entries in a blob; never change it for a released class
+    const KIND: &'static str;
+
+    fn save(&self, py: Python<'_>, out: &mut Writer) -> PyResult<()>;
+
+    fn load(py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError>;
+}
+
+/// Value columns of a grid, one per cell
+pub fn write_f32s(out: &mut Writer, values: &[f32]) {
+    for &value in values {
+        out.f32(value);
+    }
+}
+
+pub fn read_f32s(input: &mut Reader, count: usize) -> Result<Vec<f32>, DecodeError> {
+    (0..count).map(|_| input.f32()).collect()
+}
+
+/// Width and height of a saved grid, and its cell count
+pub fn read_grid_size(input: &mut Reader) -> Result<(usize, usize, usize), DecodeError> {
+    let (width, height) = (input.usize()?, input.usize()?);
+    let cells = width
+        .checked_mul(height)
+        .ok_or_else(|| DecodeError::Invalid(format!("{}x{} grid is too large", width, height)))?;
+    Ok((width, height, cells))
+}
+
+/// Encode `object` as an entry if it is a `T`
+fn save_entry<T: Persist>(
+    py: Python<'_>,
+    object: &PyAny,
+) -> PyResult<Option<(&'static str, Vec<u8>)>> {
+    let Ok(cell) = object.downcast::<PyCell<T>>() else {
+        return Ok(None);
+    };
+    let mut out = Writer::new();
+    cell.try_borrow()?.save(py, &mut out)?;
+    Ok(Some((T::KIND, out.into_bytes())))
+}
+
+fn load_entry<T: Persist>(py: Python<'_>, data: &[u8]) -> Result<T, DecodeError> {
+    let mut input = Reader::new(data);
+    let value = T::load(py, &mut input)?;
+    input.finish()?;
+    Ok(value)
+}
+
+macro_rules! saveable {
+    ($($class:ty),* $(,)?) => {
+        /// Python names of every class `save_state` accepts
+        const SAVEABLE: &[&str] = &[$(<$class as PyTypeInfo>::NAME),*];
+
+        fn save_any(py: Python<'_>, object: &PyAny) -> PyResult<Option<(&'static str, Vec<u8>)>> {
+            $(
+                if let Some(entry) = save_entry::<$class>(py, object)? {
+                    return Ok(Some(entry));
+                }
+            )*
+            Ok(None)
+        }
+
+        fn load_any(py: Python<'_>, kind: &str, data: &[u8]) -> Option<Result<PyResult<PyObject>, DecodeError>> {
+            $(
+                if kind == <$class>::KIND {
+                    return Some(load_entry::<$class>(py, data)
+                        .map(|value| Py::new(py, value).map(|value| value.into_py(py))));
+                }
+            )*
+            None
+        }
+    };
+}
+
+saveable!(
+    World,
+    GameClock,
+    TurnScheduler,
+    EventScheduler,
+    SteeringAgents,
+    ParticleSystem,
+    InfluenceMap,
+    ScentMap,
+    FluidGrid,
+    TemperatureGrid,
+    WaterSurface,
+    StateMachines,
+    BehaviorTree,
+    UtilityEvaluator,
+    Camera2D,
+    Tweens,
+    UndoStack,
+    Replay,
+);
+
+/// Save engine objects, given as a `{name: object}` dict, into one binary blob.
+///
+/// Supported objects are `World`, `GameClock`, `TurnScheduler`, `EventScheduler`,
+/// `SteeringAgents`, `ParticleSystem`, `InfluenceMap`, `ScentMap`, `FluidGrid`,
+/// `TemperatureGrid`, `WaterSurface`, `StateMachines`, `BehaviorTree`,
+/// `UtilityEvaluator`, `Camera2D`, `Tweens`, `UndoStack` and `Replay`; their RNG
+/// state is saved with them. `EventScheduler` payloads and `UndoStack` cell values
+/// must be plain data that `json.dumps` accepts, and an `UndoStack` cannot be saved
+/// while a group is open.
+#[pyfunction]
+pub fn save_state<'py>(py: Python<'py>, objects: &PyDict) -> PyResult<&'py PyBytes> {
+    let _scope = profiling::scope("save_state");
+    let mut out = Writer::with_header(MAGIC, VERSION);
+    out.usize(objects.len());
+    for (name, object) in objects.iter() {
+        let name: &str = name.extract()?;
+        let (kind, data) = save_any(py, object)?.ok_or_else(|| {
+            SerializationError::new_err(format!(
+                "cannot save '{}' of type {}, expected one of: {}",
+                name,
+                object.get_type().name().unwrap_or("?"),
+                SAVEABLE.join(", ")
+            ))
+        })?;
+        out.str(name);
+        out.str(kind);
+        out.bytes(&data);
+    }
+    Ok(PyBytes::new(py, &out.into_bytes()))
+}
+
+/// Rebuild the objects saved by `save_state`, as a new `{name: object}` dict
+#[pyfunction]
+pub fn load_state<'py>(py: Python<'py>, data: &[u8]) -> PyResult<&'py PyDict> {
+    let _scope = profiling::scope("load_state");
+    let invalid =
+        |e: DecodeError| SerializationError::new_err(format!("invalid saved state: {}", e));
+    let mut input =
+        Reader::with_header(data, MAGIC, VERSION, "a saved engine state").map_err(invalid)?;
+    let objects = PyDict::new(py);
+    for _ in 0..input.usize().map_err(invalid)? {
+        let name = input.str().map_err(invalid)?;
+        let kind = input.str().map_err(invalid)?;
+        let entry = input.bytes().map_err(invalid)?;
+        let object = load_any(py, kind, entry)
+            .ok_or_else(|| {
+                SerializationError::new_err(format!(
+                    "invalid saved state: '{}' has unknown kind '{}'",
+                    name, kind
+                ))
+            })?
+            .map_err(|e| {
+                SerializationError::new_err(format!("invalid saved state for '{}': {}", name, e))
+            })??;
+        objects.set_item(name, object)?;
+    }
+    input.finish().map_err(invalid)?;
+    Ok(objects)
+}
+
+/// A stable 64-bit hash of the state of the given objects, in order.
+///
+/// Covers everything `save_state` would save: entity positions and velocities, RNG
+/// state, grid contents and so on. Peers running the same lockstep simulation get
+/// equal checksums on every platform, so comparing them every few ticks catches
+/// a desync on the tick it happens. Any object `save_state` accepts can be passed.
+#[pyfunction]
+pub fn state_checksum(py: Python<'_>, objects: Vec<&PyAny>) -> PyResult<u64> {
+    let _scope = profiling::scope("state_checksum");
+    let mut hash = Checksum::new();
+    for object in objects {
+        let (kind, data) = save_any(py, object)?.ok_or_else(|| {
+            SerializationError::new_err(format!(
+                "cannot checksum an object of type {}, expected one of: {}",
+                object.get_type().name().unwrap_or("?"),
+                SAVEABLE.join(", ")
+            ))
+        })?;
+        hash.update_framed(kind.as_bytes());
+        hash.update_framed(&data);
+    }
+    Ok(hash.value())
+}
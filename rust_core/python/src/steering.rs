@@ -0,0 +1,406 @@
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::python::{CircleLike, VecLike};
+use llamaquest::rng::Rng;
+use llamaquest::shapes::Circle;
+use llamaquest::steering::{self, Agent, Behavior};
+use llamaquest::vec2::Vec2;
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::profiling;
+use crate::state::Persist;
+
+/// A batch of steering agents whose forces are computed together each tick.
+///
+/// Agent ids are stable for the lifetime of the batch; removed ids are not reused.
+/// Each agent combines its behaviours as a weighted sum truncated to `max_force`.
+#[pyclass]
+pub struct SteeringAgents {
+    batch: steering::SteeringAgents,
+}
+
+fn no_agent(id: usize) -> PyErr {
+    PyIndexError::new_err(format!("no steering agent with id {}", id))
+}
+
+impl SteeringAgents {
+    fn agent(&self, id: usize) -> PyResult<&Agent> {
+        self.batch.agent(id).ok_or_else(|| no_agent(id))
+    }
+
+    fn agent_mut(&mut self, id: usize) -> PyResult<&mut Agent> {
+        self.batch.agent_mut(id).ok_or_else(|| no_agent(id))
+    }
+
+    fn add_behavior(&mut self, id: usize, behavior: Behavior, weight: f32) -> PyResult<()> {
+        self.agent_mut(id)?.behaviors.push((behavior, weight));
+        Ok(())
+    }
+}
+
+impl Persist for SteeringAgents {
+    const KIND: &'static str = "steering_agents";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let batch = &self.batch;
+        out.u64(batch.rng.state());
+        out.usize(batch.obstacles.len());
+        for &(center, radius) in &batch.obstacles {
+            out.vec2(center);
+            out.f32(radius);
+        }
+        out.usize(batch.agents.len());
+        for agent in &batch.agents {
+            out.option(agent.as_ref(), |out, agent| {
+                out.vec2(agent.position);
+                out.vec2(agent.velocity);
+                out.f32(agent.max_speed);
+                out.f32(agent.max_force);
+                out.f32(agent.radius);
+                out.f32(agent.wander_angle);
+                out.usize(agent.waypoint);
+                out.usize(agent.behaviors.len());
+                for (behavior, weight) in &agent.behaviors {
+                    out.f32(*weight);
+                    save_behavior(out, behavior);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let rng = Rng::new(input.u64()?);
+        let mut obstacles = Vec::new();
+        for _ in 0..input.usize()? {
+            obstacles.push((input.vec2()?, input.f32()?));
+        }
+        let mut agents = Vec::new();
+        for _ in 0..input.usize()? {
+            agents.push(input.option(|input| {
+                let mut agent = Agent {
+                    position: input.vec2()?,
+                    velocity: input.vec2()?,
+                    max_speed: input.f32()?,
+                    max_force: input.f32()?,
+                    radius: input.f32()?,
+                    wander_angle: input.f32()?,
+                    waypoint: input.usize()?,
+                    behaviors: Vec::new(),
+                };
+                for _ in 0..input.usize()? {
+                    let weight = input.f32()?;
+                    agent.behaviors.push((load_behavior(input)?, weight));
+                }
+                Ok(agent)
+            })?);
+        }
+        let batch = steering::SteeringAgents {
+            agents,
+            obstacles,
+            rng,
+        };
+        Ok(SteeringAgents { batch })
+    }
+}
+
+fn save_behavior(out: &mut Writer, behavior: &Behavior) {
+    match behavior {
+        Behavior::Seek(target) => {
+            out.u8(0);
+            out.vec2(*target);
+        }
+        Behavior::Flee {
+            target,
+            panic_distance,
+        } => {
+            out.u8(1);
+            out.vec2(*target);
+            out.option(*panic_distance, Writer::f32);
+        }
+        Behavior::Arrive {
+            target,
+            slowing_radius,
+        } => {
+            out.u8(2);
+            out.vec2(*target);
+            out.f32(*slowing_radius);
+        }
+        Behavior::Pursue(other) => {
+            out.u8(3);
+            out.usize(*other);
+        }
+        Behavior::Evade(other) => {
+            out.u8(4);
+            out.usize(*other);
+        }
+        Behavior::Wander {
+            radius,
+            distance,
+            jitter,
+        } => {
+            out.u8(5);
+            out.f32(*radius);
+            out.f32(*distance);
+            out.f32(*jitter);
+        }
+        Behavior::AvoidObstacles { look_ahead } => {
+            out.u8(6);
+            out.f32(*look_ahead);
+        }
+        Behavior::FollowPath {
+            points,
+            waypoint_radius,
+            looped,
+        } => {
+            out.u8(7);
+            out.usize(points.len());
+            for &point in points {
+                out.vec2(point);
+            }
+            out.f32(*waypoint_radius);
+            out.bool(*looped);
+        }
+    }
+}
+
+fn load_behavior(input: &mut Reader) -> Result<Behavior, DecodeError> {
+    Ok(match input.u8()? {
+        0 => Behavior::Seek(input.vec2()?),
+        1 => Behavior::Flee {
+            target: input.vec2()?,
+            panic_distance: input.option(Reader::f32)?,
+        },
+        2 => Behavior::Arrive {
+            target: input.vec2()?,
+            slowing_radius: input.f32()?,
+        },
+        3 => Behavior::Pursue(input.usize()?),
+        4 => Behavior::Evade(input.usize()?),
+        5 => Behavior::Wander {
+            radius: input.f32()?,
+            distance: input.f32()?,
+            jitter: input.f32()?,
+        },
+        6 => Behavior::AvoidObstacles {
+            look_ahead: input.f32()?,
+        },
+        7 => Behavior::FollowPath {
+            points: (0..input.usize()?)
+                .map(|_| input.vec2())
+                .collect::<Result<_, _>>()?,
+            waypoint_radius: input.f32()?,
+            looped: input.bool()?,
+        },
+        other => {
+            return Err(DecodeError::Invalid(format!(
+                "unknown steering behaviour {}",
+                other
+            )))
+        }
+    })
+}
+
+#[pymethods]
+impl SteeringAgents {
+    #[new]
+    fn new(seed: Option<u64>) -> Self {
+        SteeringAgents {
+            batch: steering::SteeringAgents::new(seed.unwrap_or(0)),
+        }
+    }
+
+    /// Add an agent and return its id
+    #[pyo3(signature = (x, y, max_speed = 1.0, max_force = 0.5, radius = 0.5))]
+    fn add_agent(&mut self, x: f32, y: f32, max_speed: f32, max_force: f32, radius: f32) -> usize {
+        let agent = Agent::new(Vec2::new(x, y), max_speed, max_force, radius);
+        self.batch.add(agent)
+    }
+
+    fn remove_agent(&mut self, id: usize) -> PyResult<()> {
+        if self.batch.remove(id) {
+            Ok(())
+        } else {
+            Err(no_agent(id))
+        }
+    }
+
+    fn __len__(&self) -> usize {
+        self.batch.len()
+    }
+
+    fn position(&self, id: usize) -> PyResult<Vec2> {
+        Ok(self.agent(id)?.position)
+    }
+
+    fn set_position(&mut self, id: usize, x: f32, y: f32) -> PyResult<()> {
+        self.agent_mut(id)?.position = Vec2::new(x, y);
+        Ok(())
+    }
+
+    fn velocity(&self, id: usize) -> PyResult<Vec2> {
+        Ok(self.agent(id)?.velocity)
+    }
+
+    fn set_velocity(&mut self, id: usize, vx: f32, vy: f32) -> PyResult<()> {
+        self.agent_mut(id)?.velocity = Vec2::new(vx, vy);
+        Ok(())
+    }
+
+    /// Replace the circular obstacles used by obstacle avoidance
+    fn set_obstacles(&mut self, obstacles: Vec<CircleLike>) {
+        self.batch.obstacles = obstacles
+            .into_iter()
+            .map(|obstacle| {
+                let circle = Circle::from(obstacle);
+                (circle.center(), circle.radius)
+            })
+            .collect();
+    }
+
+    /// Remove every behaviour from an agent
+    fn clear_behaviors(&mut self, id: usize) -> PyResult<()> {
+        let agent = self.agent_mut(id)?;
+        agent.behaviors.clear();
+        agent.waypoint = 0;
+        Ok(())
+    }
+
+    #[pyo3(signature = (id, x, y, weight = 1.0))]
+    fn seek(&mut self, id: usize, x: f32, y: f32, weight: f32) -> PyResult<()> {
+        self.add_behavior(id, Behavior::Seek(Vec2::new(x, y)), weight)
+    }
+
+    /// Flee from a point, optionally only while it is closer than `panic_distance`
+    #[pyo3(signature = (id, x, y, panic_distance = None, weight = 1.0))]
+    fn flee(
+        &mut self,
+        id: usize,
+        x: f32,
+        y: f32,
+        panic_distance: Option<f32>,
+        weight: f32,
+    ) -> PyResult<()> {
+        let target = Vec2::new(x, y);
+        self.add_behavior(
+            id,
+            Behavior::Flee {
+                target,
+                panic_distance,
+            },
+            weight,
+        )
+    }
+
+    /// Seek a point, slowing down inside `slowing_radius` to stop on it
+    #[pyo3(signature = (id, x, y, slowing_radius = 2.0, weight = 1.0))]
+    fn arrive(
+        &mut self,
+        id: usize,
+        x: f32,
+        y: f32,
+        slowing_radius: f32,
+        weight: f32,
+    ) -> PyResult<()> {
+        let target = Vec2::new(x, y);
+        self.add_behavior(
+            id,
+            Behavior::Arrive {
+                target,
+                slowing_radius,
+            },
+            weight,
+        )
+    }
+
+    /// Seek the predicted future position of another agent
+    #[pyo3(signature = (id, target_id, weight = 1.0))]
+    fn pursue(&mut self, id: usize, target_id: usize, weight: f32) -> PyResult<()> {
+        self.agent(target_id)?;
+        self.add_behavior(id, Behavior::Pursue(target_id), weight)
+    }
+
+    /// Flee the predicted future position of another agent
+    #[pyo3(signature = (id, target_id, weight = 1.0))]
+    fn evade(&mut self, id: usize, target_id: usize, weight: f32) -> PyResult<()> {
+        self.agent(target_id)?;
+        self.add_behavior(id, Behavior::Evade(target_id), weight)
+    }
+
+    /// Drift randomly by seeking a point that jitters around a circle ahead of the agent
+    #[pyo3(signature = (id, radius = 1.0, distance = 2.0, jitter = 0.3, weight = 1.0))]
+    fn wander(
+        &mut self,
+        id: usize,
+        radius: f32,
+        distance: f32,
+        jitter: f32,
+        weight: f32,
+    ) -> PyResult<()> {
+        self.add_behavior(
+            id,
+            Behavior::Wander {
+                radius,
+                distance,
+                jitter,
+            },
+            weight,
+        )
+    }
+
+    /// Steer around obstacles within `look_ahead` of the agent at full speed
+    #[pyo3(signature = (id, look_ahead = 3.0, weight = 1.0))]
+    fn avoid_obstacles(&mut self, id: usize, look_ahead: f32, weight: f32) -> PyResult<()> {
+        self.add_behavior(id, Behavior::AvoidObstacles { look_ahead }, weight)
+    }
+
+    /// Visit waypoints in order, arriving at the last one unless the path loops
+    #[pyo3(signature = (id, points, waypoint_radius = 0.5, looped = false, weight = 1.0))]
+    fn follow_path(
+        &mut self,
+        id: usize,
+        points: Vec<VecLike>,
+        waypoint_radius: f32,
+        looped: bool,
+        weight: f32,
+    ) -> PyResult<()> {
+        if points.is_empty() {
+            return Err(PyValueError::new_err(
+                "path must contain at least one point",
+            ));
+        }
+        let points = points.into_iter().map(Vec2::from).collect();
+        let agent = self.agent_mut(id)?;
+        agent.waypoint = 0;
+        agent.behaviors.push((
+            Behavior::FollowPath {
+                points,
+                waypoint_radius,
+                looped,
+            },
+            weight,
+        ));
+        Ok(())
+    }
+
+    /// Compute this tick's steering force for every agent id (zero for removed ids)
+    fn steer(&mut self) -> Vec<Vec2> {
+        let _scope = profiling::scope("SteeringAgents.steer");
+        self.batch.forces()
+    }
+
+    /// Apply the steering forces and integrate velocities and positions, returning the forces
+    fn update(&mut self, delta_time: f32) -> Vec<Vec2> {
+        let _scope = profiling::scope("SteeringAgents.update");
+        self.batch.update(delta_time)
+    }
+
+    /// Positions of every agent id, `None` for removed ids
+    fn positions(&self) -> Vec<Option<Vec2>> {
+        self.batch
+            .agents
+            .iter()
+            .map(|slot| slot.as_ref().map(|agent| agent.position))
+            .collect()
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::temperature::{self, HeatSource};
+use pyo3::prelude::*;
+
+use crate::cancel::{self, CancelToken};
+use crate::errors::OutOfBoundsError;
+use crate::grid;
+use crate::profiling;
+use crate::progress::{self, Sink};
+use crate::state::{self, Persist};
+
+/// Temperatures spreading between cells, driven by the tiles they sit on.
+///
+/// Each tile id may have an insulation (0 conducts freely, 1 blocks all heat) and
+/// a base temperature that its cells slowly drift back to, so a mountain stays cold
+/// and a lava room stays hot while heat sources warm or chill their surroundings.
+#[pyclass]
+pub struct TemperatureGrid {
+    grid: temperature::TemperatureGrid,
+}
+
+impl TemperatureGrid {
+    fn index(&self, x: usize, y: usize) -> PyResult<usize> {
+        self.grid.index(x, y).ok_or_else(|| {
+            OutOfBoundsError::new_err(format!(
+                "cell ({}, {}) is outside the {}x{} temperature grid",
+                x, y, self.grid.width, self.grid.height
+            ))
+        })
+    }
+}
+
+impl Persist for TemperatureGrid {
+    const KIND: &'static str = "temperature_grid";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let grid = &self.grid;
+        out.usize(grid.width);
+        out.usize(grid.height);
+        out.f32(grid.conductivity);
+        out.f32(grid.relaxation);
+        state::write_f32s(out, &grid.values);
+        state::write_f32s(out, &grid.base);
+        state::write_f32s(out, &grid.conductance);
+        out.usize(grid.sources.len());
+        for source in &grid.sources {
+            out.usize(source.index);
+            out.f32(source.temperature);
+            out.f32(source.strength);
+        }
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let (width, height, cells) = state::read_grid_size(input)?;
+        let mut grid = temperature::TemperatureGrid {
+            width,
+            height,
+            conductivity: input.f32()?,
+            relaxation: input.f32()?,
+            values: state::read_f32s(input, cells)?,
+            base: state::read_f32s(input, cells)?,
+            conductance: state::read_f32s(input, cells)?,
+            sources: Vec::new(),
+        };
+        for _ in 0..input.usize()? {
+            let index = input.usize()?;
+            if index >= cells {
+                return Err(DecodeError::Invalid(format!(
+                    "heat source cell {} is outside the {}x{} grid",
+                    index, width, height
+                )));
+            }
+            grid.sources.push(HeatSource {
+                index,
+                temperature: input.f32()?,
+                strength: input.f32()?,
+            });
+        }
+        Ok(TemperatureGrid { grid })
+    }
+}
+
+#[pymethods]
+impl TemperatureGrid {
+    /// Build from a tile map; tiles missing from the tables use `ambient` and no insulation
+    #[new]
+    #[pyo3(signature = (
+        tile_map,
+        insulation = None,
+        base_temperature = None,
+        ambient = 20.0,
+        conductivity = 0.5,
+        relaxation = 0.02
+    ))]
+    fn new(
+        tile_map: Vec<Vec<u32>>,
+        insulation: Option<HashMap<u32, f32>>,
+        base_temperature: Option<HashMap<u32, f32>>,
+        ambient: f32,
+        conductivity: f32,
+        relaxation: f32,
+    ) -> PyResult<Self> {
+        grid::require_rectangular(&tile_map, "tile_map")?;
+        Ok(TemperatureGrid {
+            grid: temperature::TemperatureGrid::new(
+                &tile_map,
+                &insulation.unwrap_or_default(),
+                &base_temperature.unwrap_or_default(),
+                ambient,
+                conductivity,
+                relaxation,
+            ),
+        })
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.grid.width
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.grid.height
+    }
+
+    /// Share of a temperature difference exchanged with neighbours per step
+    #[getter]
+    fn conductivity(&self) -> f32 {
+        self.grid.conductivity
+    }
+
+    #[setter]
+    fn set_conductivity(&mut self, conductivity: f32) {
+        self.grid.conductivity = conductivity;
+    }
+
+    /// Share of the way back to the base temperature each cell moves per step
+    #[getter]
+    fn relaxation(&self) -> f32 {
+        self.grid.relaxation
+    }
+
+    #[setter]
+    fn set_relaxation(&mut self, relaxation: f32) {
+        self.grid.relaxation = relaxation;
+    }
+
+    /// Hold a cell near `temperature`; replaces any source already on that cell
+    #[pyo3(signature = (x, y, temperature, strength = 1.0))]
+    fn set_source(&mut self, x: usize, y: usize, temperature: f32, strength: f32) -> PyResult<()> {
+        let index = self.index(x, y)?;
+        self.grid.set_source(index, temperature, strength);
+        Ok(())
+    }
+
+    /// Remove a heat source, returning whether there was one
+    fn remove_source(&mut self, x: usize, y: usize) -> PyResult<bool> {
+        let index = self.index(x, y)?;
+        Ok(self.grid.remove_source(index))
+    }
+
+    /// Change a cell's insulation, e.g. when a wall is built or a door opens
+    fn set_insulation(&mut self, x: usize, y: usize, insulation: f32) -> PyResult<()> {
+        let index = self.index(x, y)?;
+        self.grid.set_insulation(index, insulation);
+        Ok(())
+    }
+
+    /// Advance the simulation, once per game turn
+    #[pyo3(signature = (steps = 1, cancel = None, progress = None))]
+    fn step(
+        &mut self,
+        py: Python<'_>,
+        steps: usize,
+        cancel: Option<PyRef<CancelToken>>,
+        progress: Option<&PyAny>,
+    ) -> PyResult<()> {
+        let _scope = profiling::scope("TemperatureGrid.step");
+        let sink = Sink::from_arg(progress)?;
+        cancel::run(py, cancel.as_deref(), "TemperatureGrid.step", || {
+            progress::watch(sink, || self.grid.step(steps))
+        })?
+    }
+
+    fn get(&self, x: usize, y: usize) -> PyResult<f32> {
+        Ok(self.grid.values[self.index(x, y)?])
+    }
+
+    fn set(&mut self, x: usize, y: usize, temperature: f32) -> PyResult<()> {
+        let index = self.index(x, y)?;
+        self.grid.values[index] = temperature;
+        Ok(())
+    }
+
+    /// The temperatures as a `[y][x]` grid
+    fn to_list(&self) -> Vec<Vec<f32>> {
+        let grid = &self.grid;
+        if grid.width == 0 {
+            return vec![Vec::new(); grid.height];
+        }
+        grid.values
+            .chunks(grid.width)
+            .map(|row| row.to_vec())
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "TemperatureGrid(width={}, height={}, sources={})",
+            self.grid.width,
+            self.grid.height,
+            self.grid.sources.len()
+        )
+    }
+}
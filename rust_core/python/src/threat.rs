@@ -0,0 +1,56 @@
+use llamaquest::threat::{threat_counts, Threat};
+use pyo3::prelude::*;
+
+use crate::cancel::{self, CancelToken};
+use crate::distance;
+use crate::grid;
+use crate::profiling;
+use crate::progress::{self, Sink};
+
+/// `(this_turn, next_turn)` threat counts as `[y][x]` grids
+pub type ThreatGrids = (Vec<Vec<u32>>, Vec<Vec<u32>>);
+
+fn to_rows(values: Vec<u32>, width: usize, height: usize) -> Vec<Vec<u32>> {
+    if width == 0 {
+        return vec![Vec::new(); height];
+    }
+    values.chunks(width).map(|row| row.to_vec()).collect()
+}
+
+/// Count, per tile, how many enemies can attack it this turn and by next turn.
+///
+/// Each enemy is `(x, y, move_range, attack_range)`. Returns `(this_turn, next_turn)`.
+#[pyfunction]
+#[pyo3(signature = (enemies, walkable_map, diagonal = false, attack_metric = "manhattan", cancel = None, progress = None))]
+pub fn calculate_threat_map(
+    py: Python<'_>,
+    enemies: Vec<(usize, usize, usize, usize)>,
+    walkable_map: Vec<Vec<bool>>,
+    diagonal: bool,
+    attack_metric: &str,
+    cancel: Option<PyRef<CancelToken>>,
+    progress: Option<&PyAny>,
+) -> PyResult<ThreatGrids> {
+    let _scope = profiling::scope("calculate_threat_map");
+    let metric = distance::parse_metric(attack_metric)?;
+    let threats: Vec<Threat> = enemies
+        .into_iter()
+        .map(|(x, y, move_range, attack_range)| Threat {
+            position: (x, y),
+            move_range,
+            attack_range,
+        })
+        .collect();
+    let (width, height) = grid::require_rectangular(&walkable_map, "walkable_map")?;
+    let sink = Sink::from_arg(progress)?;
+    let (this_turn, next_turn) =
+        cancel::run(py, cancel.as_deref(), "calculate_threat_map", || {
+            progress::watch(sink, || {
+                threat_counts(&threats, &walkable_map, diagonal, metric)
+            })
+        })??;
+    Ok((
+        to_rows(this_turn, width, height),
+        to_rows(next_turn, width, height),
+    ))
+}
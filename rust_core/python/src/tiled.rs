@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use llamaquest::shapes::Rect;
+use llamaquest::tiled::{self, PropertyValue, TileLayer};
+use pyo3::exceptions::{PyIOError, PyKeyError};
+use pyo3::prelude::*;
+
+use crate::errors::SerializationError;
+use crate::profiling;
+
+/// An object from an object layer, in map pixel coordinates
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct TiledObject {
+    object: tiled::TiledObject,
+}
+
+#[pymethods]
+impl TiledObject {
+    #[getter]
+    fn id(&self) -> u32 {
+        self.object.id
+    }
+
+    #[getter]
+    fn name(&self) -> String {
+        self.object.name.clone()
+    }
+
+    /// The object's class (or `type` in files from before Tiled 1.9)
+    #[getter]
+    fn kind(&self) -> String {
+        self.object.kind.clone()
+    }
+
+    /// One of `rect`, `ellipse`, `point`, `polygon` or `polyline`
+    #[getter]
+    fn shape(&self) -> String {
+        self.object.shape.clone()
+    }
+
+    #[getter]
+    fn x(&self) -> f32 {
+        self.object.x
+    }
+
+    #[getter]
+    fn y(&self) -> f32 {
+        self.object.y
+    }
+
+    #[getter]
+    fn width(&self) -> f32 {
+        self.object.width
+    }
+
+    #[getter]
+    fn height(&self) -> f32 {
+        self.object.height
+    }
+
+    #[getter]
+    fn rotation(&self) -> f32 {
+        self.object.rotation
+    }
+
+    /// Polygon and polyline vertices, already offset by the object position
+    #[getter]
+    fn points(&self) -> Vec<(f32, f32)> {
+        self.object.points.clone()
+    }
+
+    #[getter]
+    fn layer(&self) -> String {
+        self.object.layer.clone()
+    }
+
+    #[getter]
+    fn properties(&self) -> HashMap<String, PropertyValue> {
+        self.object.properties.clone()
+    }
+
+    /// The object's unrotated bounding box
+    #[getter]
+    fn bounds(&self) -> Rect {
+        self.object.bounds()
+    }
+
+    fn __repr__(&self) -> String {
+        let object = &self.object;
+        format!(
+            "TiledObject(id={}, name='{}', shape='{}', x={}, y={}, width={}, height={})",
+            object.id, object.name, object.shape, object.x, object.y, object.width, object.height
+        )
+    }
+}
+
+fn wrap_objects(objects: &[tiled::TiledObject]) -> Vec<TiledObject> {
+    objects
+        .iter()
+        .map(|object| TiledObject {
+            object: object.clone(),
+        })
+        .collect()
+}
+
+/// A map exported from the Tiled editor in its JSON format
+#[pyclass]
+pub struct TiledMap {
+    map: tiled::TiledMap,
+}
+
+impl TiledMap {
+    fn find_layer(&self, name: &str) -> PyResult<&TileLayer> {
+        self.map
+            .layer(name)
+            .ok_or_else(|| PyKeyError::new_err(format!("no tile layer named '{}'", name)))
+    }
+}
+
+#[pymethods]
+impl TiledMap {
+    #[getter]
+    fn width(&self) -> usize {
+        self.map.width
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.map.height
+    }
+
+    #[getter]
+    fn tile_width(&self) -> u32 {
+        self.map.tile_width
+    }
+
+    #[getter]
+    fn tile_height(&self) -> u32 {
+        self.map.tile_height
+    }
+
+    #[getter]
+    fn orientation(&self) -> String {
+        self.map.orientation.clone()
+    }
+
+    #[getter]
+    fn properties(&self) -> HashMap<String, PropertyValue> {
+        self.map.properties.clone()
+    }
+
+    /// Solid objects that should block movement
+    #[getter]
+    fn colliders(&self) -> Vec<TiledObject> {
+        wrap_objects(&self.map.colliders)
+    }
+
+    /// Objects that fire events when entered rather than blocking
+    #[getter]
+    fn triggers(&self) -> Vec<TiledObject> {
+        wrap_objects(&self.map.triggers)
+    }
+
+    /// Point objects such as spawn locations, which have no area
+    #[getter]
+    fn markers(&self) -> Vec<TiledObject> {
+        wrap_objects(&self.map.markers)
+    }
+
+    /// Names of all tile layers, in drawing order
+    fn layer_names(&self) -> Vec<String> {
+        self.map.layer_names()
+    }
+
+    /// The gids of a tile layer as a `[y][x]` grid
+    fn layer(&self, name: &str) -> PyResult<Vec<Vec<u32>>> {
+        Ok(self.find_layer(name)?.rows())
+    }
+
+    /// Custom properties of a tile layer
+    fn layer_properties(&self, name: &str) -> PyResult<HashMap<String, PropertyValue>> {
+        Ok(self.find_layer(name)?.properties.clone())
+    }
+
+    /// Walkability grid where any tile in one of the given layers blocks movement
+    fn walkable_map(&self, blocking_layers: Vec<String>) -> PyResult<Vec<Vec<bool>>> {
+        let layers = blocking_layers
+            .iter()
+            .map(|name| self.find_layer(name))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(self.map.walkable_map(&layers))
+    }
+
+    fn __repr__(&self) -> String {
+        let map = &self.map;
+        format!(
+            "TiledMap(width={}, height={}, layers={}, colliders={}, triggers={})",
+            map.width,
+            map.height,
+            map.layers.len(),
+            map.colliders.len(),
+            map.triggers.len()
+        )
+    }
+}
+
+/// Parse a Tiled JSON map from a string
+#[pyfunction]
+pub fn parse_tiled_map(py: Python<'_>, json: &str) -> PyResult<TiledMap> {
+    let _scope = profiling::scope("parse_tiled_map");
+    let map = py
+        .allow_threads(|| tiled::TiledMap::parse(json))
+        .map_err(SerializationError::new_err)?;
+    Ok(TiledMap { map })
+}
+/// Load a Tiled JSON map (`.tmj`/`.json`) from disk
+#[pyfunction]
+pub fn load_tiled_map(py: Python<'_>, path: &str) -> PyResult<TiledMap> {
+    let _scope = profiling::scope("load_tiled_map");
+    let json = py
+        .allow_threads(|| std::fs::read_to_string(path))
+        .map_err(|e| PyIOError::new_err(format!("could not read Tiled map '{}': {}", path, e)))?;
+    parse_tiled_map(py, &json)
+}
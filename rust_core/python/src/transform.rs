@@ -0,0 +1,65 @@
+use llamaquest::transform::{crop, flip, paste, rotate};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::grid;
+use crate::profiling;
+
+/// Rotate a grid clockwise by 90, 180 or 270 degrees
+#[pyfunction]
+pub fn rotate_grid(grid: Vec<Vec<PyObject>>, degrees: i32) -> PyResult<Vec<Vec<PyObject>>> {
+    let _scope = profiling::scope("rotate_grid");
+    grid::require_rectangular(&grid, "grid")?;
+    if degrees % 90 != 0 {
+        return Err(PyValueError::new_err(format!(
+            "grids can only be rotated in steps of 90 degrees, got {}",
+            degrees
+        )));
+    }
+    Ok(rotate(&grid, (degrees / 90).rem_euclid(4) as u32))
+}
+
+/// Mirror a grid horizontally (left-right) and/or vertically (top-bottom)
+#[pyfunction]
+pub fn flip_grid(
+    grid: Vec<Vec<PyObject>>,
+    horizontal: Option<bool>,
+    vertical: Option<bool>,
+) -> PyResult<Vec<Vec<PyObject>>> {
+    let _scope = profiling::scope("flip_grid");
+    Ok(flip(
+        &grid,
+        horizontal.unwrap_or(true),
+        vertical.unwrap_or(false),
+    ))
+}
+
+/// Cut a rectangular region out of a grid, clipped to its bounds
+#[pyfunction]
+pub fn crop_grid(
+    grid: Vec<Vec<PyObject>>,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> PyResult<Vec<Vec<PyObject>>> {
+    let _scope = profiling::scope("crop_grid");
+    grid::require_rectangular(&grid, "grid")?;
+    Ok(crop(&grid, x, y, width, height))
+}
+
+/// Return a copy of `target` with `source` pasted at `(x, y)`, optionally through a mask
+#[pyfunction]
+pub fn paste_grid(
+    target: Vec<Vec<PyObject>>,
+    source: Vec<Vec<PyObject>>,
+    x: isize,
+    y: isize,
+    mask: Option<Vec<Vec<bool>>>,
+) -> PyResult<Vec<Vec<PyObject>>> {
+    let _scope = profiling::scope("paste_grid");
+    grid::require_rectangular(&target, "target")?;
+    let mut target = target;
+    paste(&mut target, &source, x, y, mask.as_deref());
+    Ok(target)
+}
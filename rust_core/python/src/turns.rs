@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::turns::{Actor, Scheduler};
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::profiling;
+use crate::state::Persist;
+
+/// The classic energy system: every game tick each actor gains its speed in
+/// energy, and whoever has reached the action threshold gets to act.
+///
+/// Call `next()` to find the actor whose turn it is and `spend()` once it has
+/// acted. An actor holding enough energy keeps getting returned until it spends,
+/// so fast actors take several actions in a row. Ties go to the actor with more
+/// energy and then to the one added first.
+#[pyclass]
+pub struct TurnScheduler {
+    scheduler: Scheduler,
+}
+
+fn no_actor(id: u64) -> PyErr {
+    PyKeyError::new_err(format!("no actor with id {}", id))
+}
+
+impl TurnScheduler {
+    fn actor(&self, id: u64) -> PyResult<&Actor> {
+        self.scheduler.actor(id).ok_or_else(|| no_actor(id))
+    }
+
+    fn actor_mut(&mut self, id: u64) -> PyResult<&mut Actor> {
+        self.scheduler.actor_mut(id).ok_or_else(|| no_actor(id))
+    }
+}
+
+impl Persist for TurnScheduler {
+    const KIND: &'static str = "turn_scheduler";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        out.i64(self.scheduler.threshold());
+        out.varint(self.scheduler.time());
+        out.varint(self.scheduler.next_id());
+        out.usize(self.scheduler.len());
+        for (id, actor) in self.scheduler.actors() {
+            out.varint(id);
+            out.varint(actor.speed.into());
+            out.i64(actor.energy);
+        }
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let threshold = input.i64()?;
+        if threshold <= 0 {
+            return Err(DecodeError::Invalid(
+                "turn threshold must be positive".to_string(),
+            ));
+        }
+        let time = input.varint()?;
+        let next_id = input.varint()?;
+        let mut actors = BTreeMap::new();
+        for _ in 0..input.usize()? {
+            let id = input.varint()?;
+            let speed = u32::try_from(input.varint()?)
+                .map_err(|_| DecodeError::Invalid("actor speed is out of range".to_string()))?;
+            let energy = input.i64()?;
+            actors.insert(id, Actor { speed, energy });
+        }
+        Ok(TurnScheduler {
+            scheduler: Scheduler::from_parts(threshold, time, next_id, actors),
+        })
+    }
+}
+
+#[pymethods]
+impl TurnScheduler {
+    #[new]
+    #[pyo3(signature = (threshold = 100))]
+    fn new(threshold: i64) -> PyResult<Self> {
+        if threshold <= 0 {
+            return Err(PyValueError::new_err("turn threshold must be positive"));
+        }
+        Ok(TurnScheduler {
+            scheduler: Scheduler::new(threshold),
+        })
+    }
+
+    /// Energy needed to act, and the default cost of an action
+    #[getter]
+    fn threshold(&self) -> i64 {
+        self.scheduler.threshold()
+    }
+
+    /// Game ticks elapsed
+    #[getter]
+    fn time(&self) -> u64 {
+        self.scheduler.time()
+    }
+
+    /// Add an actor and return its id; it can be added at any point, even mid-turn
+    #[pyo3(signature = (speed, energy = 0))]
+    fn add_actor(&mut self, speed: u32, energy: i64) -> u64 {
+        self.scheduler.add(speed, energy)
+    }
+
+    /// Remove an actor, returning whether it was scheduled
+    fn remove_actor(&mut self, id: u64) -> bool {
+        self.scheduler.remove(id)
+    }
+
+    fn set_speed(&mut self, id: u64, speed: u32) -> PyResult<()> {
+        self.actor_mut(id)?.speed = speed;
+        Ok(())
+    }
+
+    fn speed(&self, id: u64) -> PyResult<u32> {
+        Ok(self.actor(id)?.speed)
+    }
+
+    fn energy(&self, id: u64) -> PyResult<i64> {
+        Ok(self.actor(id)?.energy)
+    }
+
+    /// Set an actor's energy directly, e.g. for stuns or haste effects
+    fn set_energy(&mut self, id: u64, energy: i64) -> PyResult<()> {
+        self.actor_mut(id)?.energy = energy;
+        Ok(())
+    }
+
+    /// Whose turn it is, or `None` when no actor can ever act
+    fn next(&mut self) -> Option<u64> {
+        let _scope = profiling::scope("TurnScheduler.next");
+        self.scheduler.next_actor()
+    }
+
+    /// Pay for an action; the cost defaults to the threshold and may leave energy negative
+    fn spend(&mut self, id: u64, cost: Option<i64>) -> PyResult<()> {
+        if !self.scheduler.spend(id, cost) {
+            return Err(no_actor(id));
+        }
+        Ok(())
+    }
+
+    /// Scheduled actor ids in the order they were added
+    fn actors(&self) -> Vec<u64> {
+        self.scheduler.actors().map(|(id, _)| id).collect()
+    }
+
+    fn __contains__(&self, id: u64) -> bool {
+        self.scheduler.actor(id).is_some()
+    }
+
+    fn __len__(&self) -> usize {
+        self.scheduler.len()
+    }
+}
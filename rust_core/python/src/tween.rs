@@ -0,0 +1,273 @@
+//! Easing curves over arrays, and `Tweens`, which animates many values per frame
+
+use std::collections::BTreeMap;
+
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::easing::{self, Easing, Tween, EASINGS};
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+
+use crate::buffers;
+use crate::profiling;
+use crate::state::{self, Persist};
+
+fn parse_easing(name: &str) -> PyResult<Easing> {
+    Easing::from_name(name).ok_or_else(|| {
+        let names: Vec<&str> = EASINGS.iter().map(|(name, _)| *name).collect();
+        PyValueError::new_err(format!(
+            "unknown easing '{}', expected one of: {}",
+            name,
+            names.join(", ")
+        ))
+    })
+}
+
+/// Eased progress at `t`, which is clamped to `[0, 1]`.
+///
+/// `easing` is 'linear' or a curve and direction such as 'quad_in', 'cubic_out' or
+/// 'bounce_in_out'; the curves are quad, cubic, back, elastic and bounce.
+#[pyfunction]
+pub fn ease(easing: &str, t: f32) -> PyResult<f32> {
+    Ok(parse_easing(easing)?.apply(t))
+}
+
+/// Apply `ease` to every value of a contiguous float32 array, writing the results
+/// into `out`, an array of the same length, or back into `values` when it is omitted
+#[pyfunction]
+#[pyo3(signature = (easing, values, out = None))]
+pub fn ease_many(
+    py: Python<'_>,
+    easing: &str,
+    values: &PyAny,
+    out: Option<&PyAny>,
+) -> PyResult<()> {
+    let _scope = profiling::scope("ease_many");
+    let easing = parse_easing(easing)?;
+    let (input, count) = buffers::rows::<f32>(values, "values", "float32", 1, out.is_none())?;
+    let output = match out {
+        Some(out) => {
+            let (output, out_count) = buffers::rows::<f32>(out, "out", "float32", 1, true)?;
+            if out_count != count {
+                return Err(PyValueError::new_err(format!(
+                    "values holds {} items but out holds {}",
+                    count, out_count
+                )));
+            }
+            Some(output)
+        }
+        None => None,
+    };
+    let mut data = input.to_vec(py)?;
+    py.allow_threads(|| {
+        for value in data.iter_mut() {
+            *value = easing.apply(*value);
+        }
+    });
+    output.as_ref().unwrap_or(&input).copy_from_slice(py, &data)
+}
+
+/// A tween's start or end value from Python: a number, or a sequence of numbers
+/// such as a position or a colour
+#[derive(FromPyObject)]
+enum TweenValue {
+    Scalar(f32),
+    Components(Vec<f32>),
+}
+
+impl TweenValue {
+    fn components(self) -> Vec<f32> {
+        match self {
+            TweenValue::Scalar(value) => vec![value],
+            TweenValue::Components(values) => values,
+        }
+    }
+}
+
+/// A tween's current value, read back as a plain number when `scalar` is set
+fn value_object(py: Python<'_>, (tween, scalar): &(Tween, bool)) -> PyObject {
+    let value = tween.value();
+    if *scalar {
+        value[0].into_py(py)
+    } else {
+        PyTuple::new(py, value).into_py(py)
+    }
+}
+
+/// Many tweens, advanced together once per frame.
+///
+/// Each tween moves a value, a number or a tuple of numbers like a position, from
+/// `start` to `end` along an easing curve. `update` advances every tween and
+/// returns the ids of those that finished; finished tweens can still be read until
+/// the next `update`, which removes them.
+#[pyclass]
+pub struct Tweens {
+    /// Each tween is tagged with whether its value was given as a plain number
+    tweens: easing::Tweens<bool>,
+}
+
+impl Tweens {
+    fn entry(&self, id: u64) -> PyResult<&(Tween, bool)> {
+        self.tweens
+            .get(id)
+            .ok_or_else(|| PyKeyError::new_err(format!("no tween with id {}", id)))
+    }
+}
+
+impl Persist for Tweens {
+    const KIND: &'static str = "tweens";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let tweens = &self.tweens;
+        out.varint(tweens.next_id);
+        out.usize(tweens.len());
+        for (&id, (tween, scalar)) in &tweens.tweens {
+            out.varint(id);
+            out.bool(*scalar);
+            out.usize(tween.start.len());
+            state::write_f32s(out, &tween.start);
+            state::write_f32s(out, &tween.end);
+            out.f32(tween.duration);
+            out.f32(tween.delay);
+            let tag = EASINGS
+                .iter()
+                .position(|&(_, easing)| easing == tween.easing);
+            out.u8(tag.expect("every easing is named") as u8);
+            out.f32(tween.elapsed);
+        }
+        out.usize(tweens.finished.len());
+        for &id in &tweens.finished {
+            out.varint(id);
+        }
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let mut tweens = easing::Tweens {
+            next_id: input.varint()?,
+            ..easing::Tweens::default()
+        };
+        for _ in 0..input.usize()? {
+            let id = input.varint()?;
+            // An id at or past next_id would be handed out again by the next add
+            if id >= tweens.next_id || tweens.tweens.contains_key(&id) {
+                return Err(DecodeError::Invalid(format!(
+                    "tween id {} is repeated or was never handed out",
+                    id
+                )));
+            }
+            let scalar = input.bool()?;
+            let components = input.usize()?;
+            // A scalar is read back as its only component
+            if scalar && components != 1 {
+                return Err(DecodeError::Invalid(format!(
+                    "scalar tween {} has {} components",
+                    id, components
+                )));
+            }
+            let start = state::read_f32s(input, components)?;
+            let end = state::read_f32s(input, components)?;
+            let (duration, delay) = (input.f32()?, input.f32()?);
+            let tag = input.u8()?;
+            let (_, easing) = *EASINGS
+                .get(tag as usize)
+                .ok_or_else(|| DecodeError::Invalid(format!("unknown easing {}", tag)))?;
+            let tween = Tween {
+                start,
+                end,
+                duration,
+                delay,
+                easing,
+                elapsed: input.f32()?,
+            };
+            tweens.tweens.insert(id, (tween, scalar));
+        }
+        for _ in 0..input.usize()? {
+            tweens.finished.push(input.varint()?);
+        }
+        Ok(Tweens { tweens })
+    }
+}
+
+#[pymethods]
+impl Tweens {
+    #[new]
+    fn new() -> Self {
+        Tweens {
+            tweens: easing::Tweens::new(),
+        }
+    }
+
+    /// Start a tween and return its id. `duration` and `delay` are in seconds.
+    #[pyo3(signature = (start, end, duration, easing = "linear", delay = 0.0))]
+    fn add(
+        &mut self,
+        start: TweenValue,
+        end: TweenValue,
+        duration: f32,
+        easing: &str,
+        delay: f32,
+    ) -> PyResult<u64> {
+        let scalar = matches!(start, TweenValue::Scalar(_));
+        let (start, end) = (start.components(), end.components());
+        if start.len() != end.len() {
+            return Err(PyValueError::new_err(format!(
+                "start has {} components but end has {}",
+                start.len(),
+                end.len()
+            )));
+        }
+        let tween = Tween {
+            start,
+            end,
+            duration,
+            delay,
+            easing: parse_easing(easing)?,
+            elapsed: 0.0,
+        };
+        Ok(self.tweens.add(tween, scalar))
+    }
+
+    /// Advance every tween by `delta` seconds and return the ids of those that
+    /// finished, in the order they were added
+    fn update(&mut self, delta: f32) -> Vec<u64> {
+        let _scope = profiling::scope("Tweens.update");
+        self.tweens.update(delta).to_vec()
+    }
+
+    /// Current value of a tween, a number or a tuple like its start value
+    fn value(&self, py: Python<'_>, id: u64) -> PyResult<PyObject> {
+        Ok(value_object(py, self.entry(id)?))
+    }
+
+    /// Current values of every tween, as an `{id: value}` dict
+    fn values(&self, py: Python<'_>) -> BTreeMap<u64, PyObject> {
+        self.tweens
+            .tweens
+            .iter()
+            .map(|(&id, entry)| (id, value_object(py, entry)))
+            .collect()
+    }
+
+    /// Progress of a tween from 0 to 1, before easing
+    fn progress(&self, id: u64) -> PyResult<f32> {
+        Ok(self.entry(id)?.0.progress())
+    }
+
+    /// Stop a tween where it is; returns whether it existed
+    fn cancel(&mut self, id: u64) -> bool {
+        self.tweens.cancel(id)
+    }
+
+    fn clear(&mut self) {
+        self.tweens.clear();
+    }
+
+    fn __contains__(&self, id: u64) -> bool {
+        self.tweens.contains(id)
+    }
+
+    fn __len__(&self) -> usize {
+        self.tweens.len()
+    }
+}
@@ -0,0 +1,259 @@
+//! `UndoStack`: undo and redo for level editor edits to row-major grids
+
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::undo::{Change, Group, History};
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+
+use crate::profiling;
+use crate::pyjson;
+use crate::state::Persist;
+
+fn get(grid: &PyAny, (x, y): (usize, usize)) -> PyResult<PyObject> {
+    Ok(grid.get_item(y)?.get_item(x)?.into())
+}
+
+fn put(py: Python<'_>, grid: &PyAny, (x, y): (usize, usize), value: &PyObject) -> PyResult<()> {
+    grid.get_item(y)?.set_item(x, value.as_ref(py))
+}
+
+/// Write `values` into `grid`, in order
+fn apply<'a>(
+    py: Python<'_>,
+    grid: &PyAny,
+    values: impl Iterator<Item = ((usize, usize), &'a PyObject)>,
+) -> PyResult<()> {
+    for (cell, value) in values {
+        put(py, grid, cell, value)?;
+    }
+    Ok(())
+}
+
+/// Undo history for edits to a row-major `[y][x]` grid such as a tile layer.
+///
+/// Edits are stored as the changed cells with their old and new values, so a brush
+/// stroke over a large map only costs the cells it touched. Edits made between
+/// `begin_group` and `end_group`, or by one `paint`, undo as a single step, and a
+/// cell edited twice in a group keeps only its first and last values.
+///
+/// Once more than `max_changes` cell changes are held, the oldest steps are
+/// forgotten. Any new edit clears the redo steps.
+#[pyclass]
+pub struct UndoStack {
+    history: History<PyObject>,
+}
+
+impl UndoStack {
+    fn check_closed(&self, action: &str) -> PyResult<()> {
+        if self.history.is_grouping() {
+            return Err(PyRuntimeError::new_err(format!(
+                "cannot {} while a group is open; call end_group first",
+                action
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn save_groups<'a>(
+    py: Python<'_>,
+    out: &mut Writer,
+    groups: impl ExactSizeIterator<Item = &'a Group<PyObject>>,
+) -> PyResult<()> {
+    out.usize(groups.len());
+    for group in groups {
+        out.str(&group.label);
+        out.usize(group.changes.len());
+        for change in &group.changes {
+            out.usize(change.cell.0);
+            out.usize(change.cell.1);
+            out.str(&pyjson::dumps(py, change.before.as_ref(py))?);
+            out.str(&pyjson::dumps(py, change.after.as_ref(py))?);
+        }
+    }
+    Ok(())
+}
+
+fn load_groups(py: Python<'_>, input: &mut Reader) -> Result<Vec<Group<PyObject>>, DecodeError> {
+    let value = |input: &mut Reader| {
+        pyjson::loads(py, input.str()?)
+            .map_err(|e| DecodeError::Invalid(format!("undo value: {}", e)))
+    };
+    let mut groups = Vec::new();
+    for _ in 0..input.usize()? {
+        let label = input.str()?.to_string();
+        let mut changes = Vec::new();
+        for _ in 0..input.usize()? {
+            changes.push(Change {
+                cell: (input.usize()?, input.usize()?),
+                before: value(input)?,
+                after: value(input)?,
+            });
+        }
+        groups.push(Group { label, changes });
+    }
+    Ok(groups)
+}
+
+impl Persist for UndoStack {
+    const KIND: &'static str = "undo_stack";
+
+    fn save(&self, py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        self.check_closed("save")?;
+        out.usize(self.history.max_changes);
+        save_groups(py, out, self.history.undo_groups())?;
+        save_groups(py, out, self.history.redo_groups())
+    }
+
+    fn load(py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let max_changes = input.usize()?;
+        let undo = load_groups(py, input)?;
+        let redo = load_groups(py, input)?;
+        Ok(UndoStack {
+            history: History::from_groups(max_changes, undo, redo),
+        })
+    }
+}
+
+#[pymethods]
+impl UndoStack {
+    #[new]
+    #[pyo3(signature = (max_changes = 100_000))]
+    fn new(max_changes: usize) -> Self {
+        UndoStack {
+            history: History::new(max_changes),
+        }
+    }
+
+    /// Set `grid[y][x]` to `value` and record the edit; returns `False`, recording
+    /// nothing, if the cell already held an equal value
+    fn set(
+        &mut self,
+        py: Python<'_>,
+        grid: &PyAny,
+        x: usize,
+        y: usize,
+        value: PyObject,
+    ) -> PyResult<bool> {
+        let before = get(grid, (x, y))?;
+        if before.as_ref(py).eq(value.as_ref(py))? {
+            return Ok(false);
+        }
+        put(py, grid, (x, y), &value)?;
+        self.history.record((x, y), before, value);
+        Ok(true)
+    }
+
+    /// Set every `(x, y)` of `cells` to `value` as one undo step, returning the
+    /// number of cells that changed
+    #[pyo3(signature = (grid, cells, value, label = "paint"))]
+    fn paint(
+        &mut self,
+        py: Python<'_>,
+        grid: &PyAny,
+        cells: Vec<(usize, usize)>,
+        value: PyObject,
+        label: &str,
+    ) -> PyResult<usize> {
+        let _scope = profiling::scope("UndoStack.paint");
+        self.history.begin_group(label);
+        let mut changed = 0;
+        let result = cells.into_iter().try_for_each(|(x, y)| {
+            if self.set(py, grid, x, y, value.clone_ref(py))? {
+                changed += 1;
+            }
+            Ok::<_, PyErr>(())
+        });
+        // Keep whatever was painted before a failure undoable
+        self.history.end_group();
+        result.map(|()| changed)
+    }
+
+    /// Record an edit the caller already made to cell `(x, y)`
+    fn record(&mut self, x: usize, y: usize, before: PyObject, after: PyObject) {
+        self.history.record((x, y), before, after);
+    }
+
+    /// Start grouping edits into one undo step; groups nest, and the step is
+    /// committed under the outermost label when the outermost group ends
+    #[pyo3(signature = (label = "edit"))]
+    fn begin_group(&mut self, label: &str) {
+        self.history.begin_group(label);
+    }
+
+    fn end_group(&mut self) -> PyResult<()> {
+        if !self.history.end_group() {
+            return Err(PyRuntimeError::new_err(
+                "end_group called without begin_group",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Revert the newest step in `grid`; returns whether there was one
+    fn undo(&mut self, py: Python<'_>, grid: &PyAny) -> PyResult<bool> {
+        let _scope = profiling::scope("UndoStack.undo");
+        self.check_closed("undo")?;
+        match self.history.undo() {
+            Some(group) => apply(py, grid, group.undo_values()).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Reapply the most recently undone step to `grid`; returns whether there was one
+    fn redo(&mut self, py: Python<'_>, grid: &PyAny) -> PyResult<bool> {
+        let _scope = profiling::scope("UndoStack.redo");
+        self.check_closed("redo")?;
+        match self.history.redo() {
+            Some(group) => apply(py, grid, group.redo_values()).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    #[getter]
+    fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    #[getter]
+    fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Label of the step `undo` would revert, e.g. for an "Undo paint" menu item
+    #[getter]
+    fn undo_label(&self) -> Option<&str> {
+        self.history.undo_label()
+    }
+
+    #[getter]
+    fn redo_label(&self) -> Option<&str> {
+        self.history.redo_label()
+    }
+
+    /// Cell changes held for undo and redo together
+    #[getter]
+    fn stored_changes(&self) -> usize {
+        self.history.stored_changes()
+    }
+
+    #[getter]
+    fn max_changes(&self) -> usize {
+        self.history.max_changes
+    }
+
+    #[setter]
+    fn set_max_changes(&mut self, max_changes: usize) {
+        self.history.set_max_changes(max_changes);
+    }
+
+    /// Forget every step, including an open group
+    fn clear(&mut self) {
+        self.history.clear();
+    }
+
+    /// Number of steps that can be undone
+    fn __len__(&self) -> usize {
+        self.history.len()
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::rng::Rng;
+use llamaquest::utility::{self, Action, Consideration, CurveKind};
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::profiling;
+use crate::state::Persist;
+
+/// Scores actions for many agents at once from per-agent input vectors.
+///
+/// Inputs are named when the evaluator is created and each agent supplies one
+/// vector of values in that order, normalised to `[0, 1]`.
+#[pyclass]
+pub struct UtilityEvaluator {
+    evaluator: utility::UtilityEvaluator,
+}
+
+fn parse_curve(name: &str) -> PyResult<CurveKind> {
+    CurveKind::from_name(name).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "unknown curve '{}', expected 'linear', 'polynomial', 'logistic' or 'step'",
+            name
+        ))
+    })
+}
+
+impl UtilityEvaluator {
+    fn check_inputs(&self, inputs: &[Vec<f32>]) -> PyResult<()> {
+        if let Some((agent, count)) = self.evaluator.mismatched_inputs(inputs) {
+            return Err(PyValueError::new_err(format!(
+                "agent {} has {} inputs, expected {}",
+                agent, count, self.evaluator.input_count
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Every curve kind, in the order their tags are saved
+const CURVE_KINDS: [CurveKind; 4] = [
+    CurveKind::Linear,
+    CurveKind::Polynomial,
+    CurveKind::Logistic,
+    CurveKind::Step,
+];
+
+impl Persist for UtilityEvaluator {
+    const KIND: &'static str = "utility_evaluator";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let evaluator = &self.evaluator;
+        out.u64(evaluator.rng.state());
+        // Input names in input order, so their indices survive the round trip
+        let mut inputs: Vec<_> = evaluator.inputs.iter().collect();
+        inputs.sort_by_key(|(_, &index)| index);
+        out.usize(inputs.len());
+        for (name, _) in inputs {
+            out.str(name);
+        }
+        out.usize(evaluator.actions.len());
+        for action in &evaluator.actions {
+            out.str(&action.name);
+            out.f32(action.weight);
+            out.usize(action.considerations.len());
+            for consideration in &action.considerations {
+                out.usize(consideration.input);
+                let tag = CURVE_KINDS
+                    .iter()
+                    .position(|&kind| kind == consideration.kind);
+                out.u8(tag.expect("every curve kind has a tag") as u8);
+                out.f32(consideration.slope);
+                out.f32(consideration.exponent);
+                out.f32(consideration.x_shift);
+                out.f32(consideration.y_shift);
+                out.bool(consideration.invert);
+            }
+        }
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let rng = Rng::new(input.u64()?);
+        let input_count = input.usize()?;
+        let mut inputs = HashMap::new();
+        for index in 0..input_count {
+            inputs.insert(input.str()?.to_string(), index);
+        }
+        if inputs.len() != input_count {
+            return Err(DecodeError::Invalid(
+                "utility input names are not unique".to_string(),
+            ));
+        }
+        let mut actions = Vec::new();
+        for _ in 0..input.usize()? {
+            let name = input.str()?.to_string();
+            let weight = input.f32()?;
+            let mut considerations = Vec::new();
+            for _ in 0..input.usize()? {
+                let index = input.usize()?;
+                if index >= input_count {
+                    return Err(DecodeError::Invalid(format!(
+                        "consideration input {} is out of range for {} inputs",
+                        index, input_count
+                    )));
+                }
+                let tag = input.u8()?;
+                let kind = *CURVE_KINDS
+                    .get(tag as usize)
+                    .ok_or_else(|| DecodeError::Invalid(format!("unknown curve kind {}", tag)))?;
+                considerations.push(Consideration {
+                    input: index,
+                    kind,
+                    slope: input.f32()?,
+                    exponent: input.f32()?,
+                    x_shift: input.f32()?,
+                    y_shift: input.f32()?,
+                    invert: input.bool()?,
+                });
+            }
+            actions.push(Action {
+                name,
+                weight,
+                considerations,
+            });
+        }
+        let evaluator = utility::UtilityEvaluator {
+            inputs,
+            input_count,
+            actions,
+            rng,
+        };
+        Ok(UtilityEvaluator { evaluator })
+    }
+}
+
+#[pymethods]
+impl UtilityEvaluator {
+    #[new]
+    fn new(inputs: Vec<String>, seed: Option<u64>) -> PyResult<Self> {
+        let evaluator = utility::UtilityEvaluator::new(inputs, seed.unwrap_or(0))
+            .ok_or_else(|| PyValueError::new_err("utility input names must be unique"))?;
+        Ok(UtilityEvaluator { evaluator })
+    }
+
+    /// Register an action; its score is multiplied by `weight`
+    #[pyo3(signature = (name, weight = 1.0))]
+    fn add_action(&mut self, name: String, weight: f32) -> PyResult<()> {
+        if self
+            .evaluator
+            .actions
+            .iter()
+            .any(|action| action.name == name)
+        {
+            return Err(PyValueError::new_err(format!(
+                "utility action '{}' already exists",
+                name
+            )));
+        }
+        self.evaluator.add_action(name, weight);
+        Ok(())
+    }
+
+    /// Add a response curve over one named input to an action
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (
+        action,
+        input,
+        curve = "linear",
+        slope = 1.0,
+        exponent = 1.0,
+        x_shift = 0.0,
+        y_shift = 0.0,
+        invert = false
+    ))]
+    fn add_consideration(
+        &mut self,
+        action: &str,
+        input: &str,
+        curve: &str,
+        slope: f32,
+        exponent: f32,
+        x_shift: f32,
+        y_shift: f32,
+        invert: bool,
+    ) -> PyResult<()> {
+        let input =
+            *self.evaluator.inputs.get(input).ok_or_else(|| {
+                PyKeyError::new_err(format!("no utility input named '{}'", input))
+            })?;
+        let consideration = Consideration {
+            input,
+            kind: parse_curve(curve)?,
+            slope,
+            exponent,
+            x_shift,
+            y_shift,
+            invert,
+        };
+        self.evaluator
+            .action_mut(action)
+            .ok_or_else(|| PyKeyError::new_err(format!("no utility action named '{}'", action)))?
+            .considerations
+            .push(consideration);
+        Ok(())
+    }
+
+    fn action_names(&self) -> Vec<String> {
+        self.evaluator.action_names()
+    }
+
+    /// Score every action for every agent, one row per agent in action order
+    fn scores(&self, inputs: Vec<Vec<f32>>) -> PyResult<Vec<Vec<f32>>> {
+        let _scope = profiling::scope("UtilityEvaluator.scores");
+        self.check_inputs(&inputs)?;
+        Ok(self.evaluator.score_rows(&inputs))
+    }
+
+    /// The chosen action name for every agent.
+    ///
+    /// The highest score wins unless `temperature` is given, in which case actions
+    /// are sampled from a softmax over the scores at that temperature.
+    #[pyo3(signature = (inputs, temperature = None))]
+    fn best_actions(
+        &mut self,
+        inputs: Vec<Vec<f32>>,
+        temperature: Option<f32>,
+    ) -> PyResult<Vec<Option<String>>> {
+        let _scope = profiling::scope("UtilityEvaluator.best_actions");
+        self.check_inputs(&inputs)?;
+        Ok(self.evaluator.best_actions(&inputs, temperature))
+    }
+
+    fn __len__(&self) -> usize {
+        self.evaluator.actions.len()
+    }
+}
@@ -0,0 +1,193 @@
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::water;
+use pyo3::prelude::*;
+
+use crate::cancel::{self, CancelToken};
+use crate::errors::OutOfBoundsError;
+use crate::profiling;
+use crate::progress::{self, Sink};
+use crate::state::{self, Persist};
+
+/// A water surface made of springs: each column bobs around its rest height and
+/// tugs on its neighbours, so splashes ripple outwards and die down.
+///
+/// A height of 1 gives the usual side-on strip of columns; larger heights make a
+/// 2D surface seen from above.
+#[pyclass]
+pub struct WaterSurface {
+    surface: water::WaterSurface,
+}
+
+impl WaterSurface {
+    fn index(&self, x: usize, y: usize) -> PyResult<usize> {
+        self.surface.index(x, y).ok_or_else(|| {
+            OutOfBoundsError::new_err(format!(
+                "column ({}, {}) is outside the {}x{} water surface",
+                x, y, self.surface.width, self.surface.height
+            ))
+        })
+    }
+}
+
+impl Persist for WaterSurface {
+    const KIND: &'static str = "water_surface";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let surface = &self.surface;
+        out.usize(surface.width);
+        out.usize(surface.height);
+        out.f32(surface.tension);
+        out.f32(surface.damping);
+        out.f32(surface.spread);
+        out.usize(surface.passes);
+        state::write_f32s(out, &surface.heights);
+        state::write_f32s(out, &surface.velocities);
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let (width, height, cells) = state::read_grid_size(input)?;
+        let surface = water::WaterSurface {
+            width,
+            height,
+            tension: input.f32()?,
+            damping: input.f32()?,
+            spread: input.f32()?,
+            passes: input.usize()?,
+            heights: state::read_f32s(input, cells)?,
+            velocities: state::read_f32s(input, cells)?,
+        };
+        Ok(WaterSurface { surface })
+    }
+}
+
+#[pymethods]
+impl WaterSurface {
+    #[new]
+    #[pyo3(signature = (width, height = 1, tension = 0.025, damping = 0.025, spread = 0.25, passes = 8))]
+    fn new(
+        width: usize,
+        height: usize,
+        tension: f32,
+        damping: f32,
+        spread: f32,
+        passes: usize,
+    ) -> Self {
+        WaterSurface {
+            surface: water::WaterSurface::new(width, height, tension, damping, spread, passes),
+        }
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.surface.width
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.surface.height
+    }
+
+    /// Spring stiffness pulling each column back to rest
+    #[getter]
+    fn tension(&self) -> f32 {
+        self.surface.tension
+    }
+
+    #[setter]
+    fn set_tension(&mut self, tension: f32) {
+        self.surface.tension = tension;
+    }
+
+    /// Share of velocity lost per step
+    #[getter]
+    fn damping(&self) -> f32 {
+        self.surface.damping
+    }
+
+    #[setter]
+    fn set_damping(&mut self, damping: f32) {
+        self.surface.damping = damping;
+    }
+
+    /// How strongly columns pull on their neighbours
+    #[getter]
+    fn spread(&self) -> f32 {
+        self.surface.spread
+    }
+
+    #[setter]
+    fn set_spread(&mut self, spread: f32) {
+        self.surface.spread = spread;
+    }
+
+    /// Neighbour propagation passes per step; more makes waves travel faster
+    #[getter]
+    fn passes(&self) -> usize {
+        self.surface.passes
+    }
+
+    #[setter]
+    fn set_passes(&mut self, passes: usize) {
+        self.surface.passes = passes;
+    }
+
+    /// Kick a column with a vertical speed; positive pushes the surface up
+    #[pyo3(signature = (x, speed, y = 0))]
+    fn splash(&mut self, x: usize, speed: f32, y: usize) -> PyResult<()> {
+        let index = self.index(x, y)?;
+        self.surface.splash(index, speed);
+        Ok(())
+    }
+
+    /// Advance the springs, once per frame
+    #[pyo3(signature = (steps = 1, cancel = None, progress = None))]
+    fn step(
+        &mut self,
+        py: Python<'_>,
+        steps: usize,
+        cancel: Option<PyRef<CancelToken>>,
+        progress: Option<&PyAny>,
+    ) -> PyResult<()> {
+        let _scope = profiling::scope("WaterSurface.step");
+        let sink = Sink::from_arg(progress)?;
+        cancel::run(py, cancel.as_deref(), "WaterSurface.step", || {
+            progress::watch(sink, || self.surface.step(steps))
+        })?
+    }
+
+    #[pyo3(signature = (x, y = 0))]
+    fn get(&self, x: usize, y: usize) -> PyResult<f32> {
+        Ok(self.surface.heights[self.index(x, y)?])
+    }
+
+    /// Every column's offset from rest height, row by row
+    fn heights(&self) -> Vec<f32> {
+        self.surface.heights.clone()
+    }
+
+    /// The offsets as a `[y][x]` grid
+    fn to_list(&self) -> Vec<Vec<f32>> {
+        let surface = &self.surface;
+        if surface.width == 0 {
+            return vec![Vec::new(); surface.height];
+        }
+        surface
+            .heights
+            .chunks(surface.width)
+            .map(|row| row.to_vec())
+            .collect()
+    }
+
+    /// Flatten the surface back to rest
+    fn calm(&mut self) {
+        self.surface.calm();
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "WaterSurface(width={}, height={})",
+            self.surface.width, self.surface.height
+        )
+    }
+}
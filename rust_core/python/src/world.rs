@@ -0,0 +1,657 @@
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+
+use llamaquest::codec::{DecodeError, Reader, Writer};
+use llamaquest::msgpack;
+use llamaquest::python::VecLike;
+use llamaquest::shapes::Shape;
+use llamaquest::vec2::Vec2;
+use llamaquest::world::{
+    self, Component, Renderable, Rgba, Steering, ALIVE, COLLIDER, POSITION, RENDERABLE, STATIC,
+    STEERING, VELOCITY,
+};
+use pyo3::exceptions::{PyBufferError, PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyTuple};
+use pyo3::{ffi, AsPyPointer};
+
+use crate::errors::SerializationError;
+use crate::profiling;
+use crate::state::Persist;
+
+fn parse_component(name: &str) -> PyResult<Component> {
+    Component::from_name(name).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "unknown component '{}', expected 'position', 'velocity', 'collider', \
+             'renderable' or 'steering'",
+            name
+        ))
+    })
+}
+
+fn cannot_grow() -> PyErr {
+    PyBufferError::new_err(
+        "cannot grow the world while column views are held; \
+         release them or create the world with more capacity",
+    )
+}
+
+/// Entities with builtin components, and the Rust systems that run over them.
+///
+/// Hot component data lives in flat per-slot columns (structure of arrays) so the
+/// systems stream through memory; which components a slot has is recorded in its
+/// `flags` bits. Python spawns entities, attaches components and reads query
+/// results or whole columns, while physics, steering and collisions run in Rust.
+#[pyclass]
+pub struct World {
+    world: world::World,
+    /// Column views currently held by Python; the columns must not move meanwhile
+    exports: usize,
+}
+
+impl World {
+    /// Slot of a live entity
+    fn slot(&self, entity: u64) -> PyResult<usize> {
+        self.world
+            .slot(entity)
+            .ok_or_else(|| PyKeyError::new_err(format!("no live entity {}", entity)))
+    }
+
+    fn component_object(&self, py: Python<'_>, index: usize, component: Component) -> PyObject {
+        let world = &self.world;
+        match component {
+            Component::Position => world.position_of(index).map(|p| (p.x, p.y)).into_py(py),
+            Component::Velocity => world.velocity_of(index).map(|v| (v.x, v.y)).into_py(py),
+            Component::Collider => world.collider_of(index).into_py(py),
+            Component::Renderable => world.renderables[index]
+                .map(|r| (r.sprite, r.layer, r.color))
+                .into_py(py),
+            Component::Steering => world.steering[index]
+                .map(|s| {
+                    (
+                        s.max_speed,
+                        s.max_force,
+                        s.slowing_radius,
+                        s.target.map(|t| (t.x, t.y)),
+                    )
+                })
+                .into_py(py),
+        }
+    }
+
+    /// Run `system` with the GIL released, unless Python holds column views that
+    /// could be read while the columns are written
+    fn without_gil<T: Send>(
+        &mut self,
+        py: Python<'_>,
+        system: impl FnOnce(&mut world::World) -> T + Send,
+    ) -> T {
+        if self.exports > 0 {
+            system(&mut self.world)
+        } else {
+            py.allow_threads(|| system(&mut self.world))
+        }
+    }
+}
+
+impl Persist for World {
+    const KIND: &'static str = "world";
+
+    fn save(&self, _py: Python<'_>, out: &mut Writer) -> PyResult<()> {
+        let world = &self.world;
+        out.vec2(world.gravity);
+        out.f32(world.drag);
+        out.usize(world.flags.len());
+        for index in 0..world.flags.len() {
+            out.varint(world.generations[index].into());
+            out.u8(world.flags[index]);
+            for column in [
+                &world.xs,
+                &world.ys,
+                &world.vxs,
+                &world.vys,
+                &world.widths,
+                &world.heights,
+            ] {
+                out.f32(column[index]);
+            }
+            out.option(world.renderables[index], |out, renderable| {
+                out.varint(renderable.sprite.into());
+                out.i64(renderable.layer.into());
+                let (r, g, b, a) = renderable.color;
+                for channel in [r, g, b, a] {
+                    out.u8(channel);
+                }
+            });
+            out.option(world.steering[index], |out, steering| {
+                out.f32(steering.max_speed);
+                out.f32(steering.max_force);
+                out.f32(steering.slowing_radius);
+                out.option(steering.target, Writer::vec2);
+            });
+        }
+        // Free slots in order, so spawning after a load reuses the same slots
+        out.usize(world.free.len());
+        for &index in &world.free {
+            out.usize(index);
+        }
+        Ok(())
+    }
+
+    fn load(_py: Python<'_>, input: &mut Reader) -> Result<Self, DecodeError> {
+        let gravity = input.vec2()?;
+        let drag = input.f32()?;
+        let mut world = world::World::new(gravity, drag, 0);
+        let invalid = |what: &str| DecodeError::Invalid(format!("world {} is out of range", what));
+        for _ in 0..input.usize()? {
+            let generation = u32::try_from(input.varint()?).map_err(|_| invalid("generation"))?;
+            world.generations.push(generation);
+            world.flags.push(input.u8()?);
+            for column in [
+                &mut world.xs,
+                &mut world.ys,
+                &mut world.vxs,
+                &mut world.vys,
+                &mut world.widths,
+                &mut world.heights,
+            ] {
+                column.push(input.f32()?);
+            }
+            let renderable = input.option(|input| {
+                let sprite = u32::try_from(input.varint()?).map_err(|_| invalid("sprite"))?;
+                let layer = i32::try_from(input.i64()?).map_err(|_| invalid("layer"))?;
+                let color = (input.u8()?, input.u8()?, input.u8()?, input.u8()?);
+                Ok(Renderable {
+                    sprite,
+                    layer,
+                    color,
+                })
+            })?;
+            world.renderables.push(renderable);
+            let steering = input.option(|input| {
+                Ok(Steering {
+                    max_speed: input.f32()?,
+                    max_force: input.f32()?,
+                    slowing_radius: input.f32()?,
+                    target: input.option(Reader::vec2)?,
+                })
+            })?;
+            world.steering.push(steering);
+        }
+        for _ in 0..input.usize()? {
+            let index = input.usize()?;
+            if index >= world.flags.len() || world.flags[index] & ALIVE != 0 {
+                return Err(invalid("free slot"));
+            }
+            world.free.push(index);
+        }
+        Ok(World { world, exports: 0 })
+    }
+}
+
+#[pymethods]
+impl World {
+    #[classattr]
+    const ALIVE: u8 = ALIVE;
+    #[classattr]
+    const POSITION: u8 = POSITION;
+    #[classattr]
+    const VELOCITY: u8 = VELOCITY;
+    #[classattr]
+    const COLLIDER: u8 = COLLIDER;
+    #[classattr]
+    const STATIC: u8 = STATIC;
+    #[classattr]
+    const RENDERABLE: u8 = RENDERABLE;
+    #[classattr]
+    const STEERING: u8 = STEERING;
+
+    /// Create an empty world with room for `capacity` entity slots before it grows
+    #[new]
+    #[pyo3(signature = (gravity = VecLike::Vector(Vec2::ZERO), drag = 0.0, capacity = 1024))]
+    fn new(gravity: VecLike, drag: f32, capacity: usize) -> Self {
+        World {
+            world: world::World::new(gravity.into(), drag, capacity),
+            exports: 0,
+        }
+    }
+
+    /// Create an entity with any of the builtin components and return its handle.
+    ///
+    /// Growing the columns is refused while Python holds views into them, unless
+    /// every viewable column has spare capacity.
+    #[pyo3(signature = (position = None, velocity = None, collider = None, is_static = false, renderable = None))]
+    fn spawn(
+        &mut self,
+        position: Option<VecLike>,
+        velocity: Option<VecLike>,
+        collider: Option<(f32, f32)>,
+        is_static: bool,
+        renderable: Option<(u32, i32)>,
+    ) -> PyResult<u64> {
+        if self.exports > 0 && self.world.spawn_reallocates() {
+            return Err(cannot_grow());
+        }
+        Ok(self.world.spawn(
+            position.map(Vec2::from),
+            velocity.map(Vec2::from),
+            collider,
+            is_static,
+            renderable,
+        ))
+    }
+
+    /// Destroy an entity, returning whether it was alive
+    fn despawn(&mut self, entity: u64) -> bool {
+        self.world.despawn(entity)
+    }
+
+    fn is_alive(&self, entity: u64) -> bool {
+        self.world.slot(entity).is_some()
+    }
+
+    /// Handles of every live entity
+    fn entities(&self) -> Vec<u64> {
+        self.world.entities()
+    }
+
+    /// Column index of an entity, for reading the column views
+    fn slot_of(&self, entity: u64) -> PyResult<usize> {
+        self.slot(entity)
+    }
+
+    /// Handle of every slot's current entity, or `None` for free slots
+    fn handles(&self) -> Vec<Option<u64>> {
+        self.world.handles()
+    }
+
+    fn set_position(&mut self, entity: u64, x: f32, y: f32) -> PyResult<()> {
+        let index = self.slot(entity)?;
+        self.world.set_position(index, Vec2::new(x, y));
+        Ok(())
+    }
+
+    fn position(&self, entity: u64) -> PyResult<Option<Vec2>> {
+        Ok(self.world.position_of(self.slot(entity)?))
+    }
+
+    fn set_velocity(&mut self, entity: u64, vx: f32, vy: f32) -> PyResult<()> {
+        let index = self.slot(entity)?;
+        self.world.set_velocity(index, Vec2::new(vx, vy));
+        Ok(())
+    }
+
+    fn velocity(&self, entity: u64) -> PyResult<Option<Vec2>> {
+        Ok(self.world.velocity_of(self.slot(entity)?))
+    }
+
+    #[pyo3(signature = (entity, width, height, is_static = false))]
+    fn set_collider(
+        &mut self,
+        entity: u64,
+        width: f32,
+        height: f32,
+        is_static: bool,
+    ) -> PyResult<()> {
+        let index = self.slot(entity)?;
+        self.world.set_collider(index, width, height, is_static);
+        Ok(())
+    }
+
+    #[pyo3(signature = (entity, sprite, layer = 0, color = (255, 255, 255, 255)))]
+    fn set_renderable(
+        &mut self,
+        entity: u64,
+        sprite: u32,
+        layer: i32,
+        color: Rgba,
+    ) -> PyResult<()> {
+        let index = self.slot(entity)?;
+        self.world.set_renderable(
+            index,
+            Renderable {
+                sprite,
+                layer,
+                color,
+            },
+        );
+        Ok(())
+    }
+
+    /// Let the steering system drive this entity's velocity towards its target
+    #[pyo3(signature = (entity, max_speed, max_force, slowing_radius = 1.0))]
+    fn set_steering(
+        &mut self,
+        entity: u64,
+        max_speed: f32,
+        max_force: f32,
+        slowing_radius: f32,
+    ) -> PyResult<()> {
+        let index = self.slot(entity)?;
+        self.world
+            .set_steering(index, max_speed, max_force, slowing_radius);
+        Ok(())
+    }
+
+    /// Point a steering entity at a target, or `None` to stop steering
+    fn set_steering_target(&mut self, entity: u64, target: Option<VecLike>) -> PyResult<()> {
+        let index = self.slot(entity)?;
+        if self
+            .world
+            .set_steering_target(index, target.map(Vec2::from))
+        {
+            Ok(())
+        } else {
+            Err(PyValueError::new_err(format!(
+                "entity {} has no steering component",
+                entity
+            )))
+        }
+    }
+
+    /// A component as a tuple, or `None` if the entity does not have it
+    fn get(&self, py: Python<'_>, entity: u64, component: &str) -> PyResult<PyObject> {
+        let index = self.slot(entity)?;
+        Ok(self.component_object(py, index, parse_component(component)?))
+    }
+
+    fn has_component(&self, entity: u64, component: &str) -> PyResult<bool> {
+        Ok(self
+            .world
+            .has(self.slot(entity)?, parse_component(component)?))
+    }
+
+    /// Detach a component, returning whether the entity had it
+    fn remove_component(&mut self, entity: u64, component: &str) -> PyResult<bool> {
+        let index = self.slot(entity)?;
+        Ok(self
+            .world
+            .remove_component(index, parse_component(component)?))
+    }
+
+    /// Every entity with all of the named components, as `(handle, component, ...)`
+    /// tuples with the components in the order they were asked for
+    fn query(&self, py: Python<'_>, components: Vec<&str>) -> PyResult<Vec<PyObject>> {
+        let components = components
+            .into_iter()
+            .map(parse_component)
+            .collect::<PyResult<Vec<_>>>()?;
+        let mut rows = Vec::new();
+        for index in self.world.query(&components) {
+            let mut row = Vec::with_capacity(components.len() + 1);
+            row.push(self.world.handle_of(index).into_py(py));
+            for &component in &components {
+                row.push(self.component_object(py, index, component));
+            }
+            rows.push(PyTuple::new(py, row).into_py(py));
+        }
+        Ok(rows)
+    }
+
+    /// Handles of entities overlapping `area`, a `Rect` or `Circle`: by their collider
+    /// box if they have one, otherwise by their position
+    fn entities_in(&self, area: Shape) -> Vec<u64> {
+        let _scope = profiling::scope("World.entities_in");
+        self.world.entities_in(area)
+    }
+
+    /// Zero-copy, read-only view of the x positions of every slot
+    fn xs(slf: &PyCell<Self>) -> ColumnView {
+        ColumnView::new(slf.into(), Column::Xs)
+    }
+
+    fn ys(slf: &PyCell<Self>) -> ColumnView {
+        ColumnView::new(slf.into(), Column::Ys)
+    }
+
+    fn vxs(slf: &PyCell<Self>) -> ColumnView {
+        ColumnView::new(slf.into(), Column::Vxs)
+    }
+
+    fn vys(slf: &PyCell<Self>) -> ColumnView {
+        ColumnView::new(slf.into(), Column::Vys)
+    }
+
+    /// Zero-copy view of every slot's component bits (see the `World.ALIVE`… constants)
+    fn flags(slf: &PyCell<Self>) -> ColumnView {
+        ColumnView::new(slf.into(), Column::Flags)
+    }
+
+    /// Move every entity with a position and velocity
+    fn run_physics(&mut self, py: Python<'_>, delta_time: f32) {
+        let _scope = profiling::scope("World.run_physics");
+        self.without_gil(py, |world| world.physics_system(delta_time));
+    }
+
+    /// Update the velocities of every steering entity with a target
+    fn run_steering(&mut self, py: Python<'_>, delta_time: f32) {
+        let _scope = profiling::scope("World.run_steering");
+        self.without_gil(py, |world| world.steering_system(delta_time));
+    }
+
+    /// Overlapping collider pairs, pushed apart first when `resolve` is set
+    #[pyo3(signature = (resolve = false))]
+    fn run_collisions(&mut self, py: Python<'_>, resolve: bool) -> Vec<(u64, u64)> {
+        let _scope = profiling::scope("World.run_collisions");
+        self.without_gil(py, |world| world.collision_system(resolve))
+    }
+
+    /// Run steering, physics and resolved collisions, returning the colliding pairs
+    fn step(&mut self, py: Python<'_>, delta_time: f32) -> Vec<(u64, u64)> {
+        let _scope = profiling::scope("World.step");
+        self.without_gil(py, |world| world.step(delta_time))
+    }
+
+    /// Acceleration applied to every moving entity
+    #[getter]
+    fn gravity(&self) -> Vec2 {
+        self.world.gravity
+    }
+
+    #[setter]
+    fn set_gravity(&mut self, gravity: VecLike) {
+        self.world.gravity = gravity.into();
+    }
+
+    /// Fraction of velocity lost per second
+    #[getter]
+    fn drag(&self) -> f32 {
+        self.world.drag
+    }
+
+    #[setter]
+    fn set_drag(&mut self, drag: f32) {
+        self.world.drag = drag;
+    }
+
+    /// Every live entity and its components as MessagePack, for tools and spectators.
+    ///
+    /// The envelope is a map with `schema` (`"llamaquest.world"`), `version`,
+    /// `gravity`, `drag` and `entities`. Each entity is a map with its `id` handle
+    /// and whichever of `position`, `velocity`, `collider`, `renderable` and
+    /// `steering` it has, named and shaped as in `get()`, but with maps instead of
+    /// tuples for the multi-field components.
+    fn to_msgpack<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        let _scope = profiling::scope("World.to_msgpack");
+        PyBytes::new(py, &msgpack::encode(&self.world.export()))
+    }
+
+    /// Rebuild a world from `to_msgpack` output. Live entities keep their handles,
+    /// but handles of entities despawned before the export may be handed out again
+    #[staticmethod]
+    fn from_msgpack(data: &[u8]) -> PyResult<World> {
+        let _scope = profiling::scope("World.from_msgpack");
+        let invalid =
+            |e: String| SerializationError::new_err(format!("invalid world export: {}", e));
+        let export = msgpack::decode(data).map_err(|e| invalid(e.to_string()))?;
+        let world = world::World::import(&export).map_err(invalid)?;
+        Ok(World { world, exports: 0 })
+    }
+
+    /// Copy the whole world state for a later `rollback`; this is a flat copy of
+    /// each column, so it is cheap enough to take every tick
+    fn snapshot(&self) -> WorldSnapshot {
+        let _scope = profiling::scope("World.snapshot");
+        WorldSnapshot {
+            world: self.world.clone(),
+        }
+    }
+
+    /// Restore the state captured by `snapshot`, including which handles are alive.
+    ///
+    /// Columns are copied into the existing storage, so column views stay valid;
+    /// rolling back to a snapshot with more slots than the world has room for is
+    /// refused while views are held.
+    fn rollback(&mut self, snapshot: PyRef<WorldSnapshot>) -> PyResult<()> {
+        let _scope = profiling::scope("World.rollback");
+        if self.exports > 0 && snapshot.world.flags.len() > self.world.column_capacity() {
+            return Err(cannot_grow());
+        }
+        self.world.restore(&snapshot.world);
+        Ok(())
+    }
+
+    fn __len__(&self) -> usize {
+        self.world.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("World(entities={})", self.__len__())
+    }
+}
+
+/// A frozen copy of a `World`, taken by `World.snapshot()` for `World.rollback()`.
+///
+/// Keep a ring of recent snapshots for rollback netcode or a rewind mechanic; a
+/// snapshot can be rolled back to any number of times.
+#[pyclass]
+pub struct WorldSnapshot {
+    world: world::World,
+}
+
+#[pymethods]
+impl WorldSnapshot {
+    /// Live entities when the snapshot was taken
+    fn __len__(&self) -> usize {
+        self.world.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("WorldSnapshot(entities={})", self.__len__())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Column {
+    Xs,
+    Ys,
+    Vxs,
+    Vys,
+    Flags,
+}
+
+/// A buffer-protocol view of one world column, one item per slot.
+///
+/// `numpy.asarray(view)` or `memoryview(view)` reads the world's own storage
+/// without copying, and sees every later update. The view's length is fixed when
+/// the buffer is taken, so take a fresh one after spawning new slots.
+#[pyclass]
+pub struct ColumnView {
+    world: Py<World>,
+    column: Column,
+}
+
+impl ColumnView {
+    fn new(world: Py<World>, column: Column) -> Self {
+        ColumnView { world, column }
+    }
+}
+
+#[pymethods]
+impl ColumnView {
+    /// # Safety
+    ///
+    /// Called by Python with a valid `Py_buffer`. The exported pointer stays valid
+    /// because the world refuses to reallocate its columns while `exports > 0`, and
+    /// the view object keeps the world alive.
+    unsafe fn __getbuffer__(
+        slf: &PyCell<Self>,
+        view: *mut ffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("view is null"));
+        }
+        if flags & ffi::PyBUF_WRITABLE == ffi::PyBUF_WRITABLE {
+            return Err(PyBufferError::new_err("world columns are read-only"));
+        }
+        let py = slf.py();
+        let this = slf.borrow();
+        let mut world = this.world.as_ref(py).try_borrow_mut()?;
+        world.exports += 1;
+        let columns = &world.world;
+
+        // Formats are static C strings, so there is nothing to free on release
+        let (buf, len, itemsize, format): (*const c_void, usize, usize, &'static [u8]) =
+            match this.column {
+                Column::Xs => (columns.xs.as_ptr().cast(), columns.xs.len(), 4, b"f\0"),
+                Column::Ys => (columns.ys.as_ptr().cast(), columns.ys.len(), 4, b"f\0"),
+                Column::Vxs => (columns.vxs.as_ptr().cast(), columns.vxs.len(), 4, b"f\0"),
+                Column::Vys => (columns.vys.as_ptr().cast(), columns.vys.len(), 4, b"f\0"),
+                Column::Flags => (
+                    columns.flags.as_ptr().cast(),
+                    columns.flags.len(),
+                    1,
+                    b"B\0",
+                ),
+            };
+
+        (*view).obj = ffi::_Py_NewRef(slf.as_ptr());
+        (*view).buf = buf as *mut c_void;
+        (*view).len = (len * itemsize) as isize;
+        (*view).readonly = 1;
+        (*view).itemsize = itemsize as isize;
+        (*view).format = if flags & ffi::PyBUF_FORMAT == ffi::PyBUF_FORMAT {
+            format.as_ptr() as *mut c_char
+        } else {
+            ptr::null_mut()
+        };
+        (*view).ndim = 1;
+        // Py_buffer has no room for a shape of its own, so the item count is stashed in
+        // `internal` and `shape` points at it
+        let shape = Box::into_raw(Box::new(len as isize));
+        (*view).internal = shape.cast();
+        (*view).shape = if flags & ffi::PyBUF_ND == ffi::PyBUF_ND {
+            shape
+        } else {
+            ptr::null_mut()
+        };
+        (*view).strides = if flags & ffi::PyBUF_STRIDES == ffi::PyBUF_STRIDES {
+            &mut (*view).itemsize
+        } else {
+            ptr::null_mut()
+        };
+        (*view).suboffsets = ptr::null_mut();
+        Ok(())
+    }
+
+    /// # Safety
+    ///
+    /// Called by Python once for every successful `__getbuffer__`.
+    unsafe fn __releasebuffer__(&self, view: *mut ffi::Py_buffer) {
+        drop(Box::from_raw((*view).internal as *mut isize));
+        Python::with_gil(|py| {
+            if let Ok(mut world) = self.world.as_ref(py).try_borrow_mut() {
+                world.exports = world.exports.saturating_sub(1);
+            }
+        });
+    }
+
+    fn __len__(&self, py: Python<'_>) -> usize {
+        self.world.borrow(py).world.flags.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ColumnView({:?})", self.column)
+    }
+}
@@ -0,0 +1,56 @@
+"""
+Tests for behavior tree depth limits in llamaquest_core.
+"""
+import pytest
+
+import llamaquest_core as core
+
+MAX_DEPTH = 64
+
+
+def chain(depth):
+    """A tree `depth` nodes deep: inverters down to a single condition"""
+    node = {"type": "condition", "name": "ready"}
+    for _ in range(depth - 1):
+        node = {"type": "inverter", "child": node}
+    return node
+
+
+def varint(value):
+    out = bytearray()
+    while value >= 0x80:
+        out.append(value & 0x7F | 0x80)
+        value >>= 7
+    out.append(value)
+    return bytes(out)
+
+
+def test_trees_up_to_the_limit_tick():
+    """Test that the deepest accepted tree still ticks down to its leaf."""
+    tree = core.BehaviorTree(chain(MAX_DEPTH))
+    agent = tree.add_agent()
+    # 63 inverters, an odd number, flip the condition's answer
+    assert tree.tick_agent(agent, 0.1, lambda agent, name: True) == "failure"
+
+
+def test_builder_rejects_deeper_trees():
+    """Test that a description one level too deep is refused."""
+    with pytest.raises(ValueError, match="deeper than 64"):
+        core.BehaviorTree(chain(MAX_DEPTH + 1))
+
+
+def test_load_rejects_deeper_trees():
+    """Test that a saved tree relinked into a chain too deep to tick is refused."""
+    # A selector over inverters, each saved as kind 3, one child, then that child.
+    # Inverter k sits at 2k - 1 with its leaf at 2k; pointing it at inverter k + 1
+    # instead chains every inverter below the one before.
+    inverters = MAX_DEPTH + 1
+    children = [chain(2) for _ in range(inverters)]
+    tree = core.BehaviorTree({"type": "selector", "children": children})
+    blob = core.save_state({"tree": tree})
+    for k in range(1, inverters):
+        old = b"\x03\x01" + varint(2 * k)
+        assert blob.count(old) == 1
+        blob = blob.replace(old, b"\x03\x01" + varint(2 * k + 1))
+    with pytest.raises(core.SerializationError, match="deeper than 64"):
+        core.load_state(blob)
@@ -0,0 +1,81 @@
+"""
+Tests for cancelling long computations in llamaquest_core.
+"""
+import pytest
+
+import llamaquest_core as core
+
+
+def cancel_after(token, reports, seen=None):
+    """A progress callback that trips `token` once it has seen `reports` reports"""
+    seen = [] if seen is None else seen
+
+    def callback(stage, percent):
+        seen.append(percent)
+        if len(seen) == reports:
+            token.cancel()
+
+    return callback
+
+
+def splashed_water():
+    surface = core.WaterSurface(32)
+    surface.splash(5, 1.0)
+    return surface
+
+
+def scented_trail():
+    trail = core.ScentMap([[True] * 6] * 4)
+    trail.deposit(2, 2, 1.0)
+    return trail
+
+
+@pytest.mark.parametrize("make", [splashed_water, scented_trail])
+def test_cancelled_steps_keep_the_steps_already_done(make):
+    """Test that steps finished before a cancel are kept."""
+    grid, expected = make(), make()
+    expected.step(3)
+    token = core.CancelToken()
+    # The token is only checked between steps, so cancelling from the third
+    # step's report still lets that step finish
+    with pytest.raises(core.Cancelled):
+        grid.step(10, cancel=token, progress=cancel_after(token, 3))
+    assert core.state_checksum([grid]) == core.state_checksum([expected])
+
+
+def test_a_cancelled_run_is_not_reported_as_finished():
+    """Test that only a run left to finish gets a final 100% report."""
+    token = core.CancelToken()
+    seen = []
+    with pytest.raises(core.Cancelled):
+        core.WaterSurface(32).step(10, cancel=token, progress=cancel_after(token, 3, seen))
+    assert len(seen) == 3
+    assert seen[-1] < 100.0
+
+    seen.clear()
+    core.WaterSurface(32).step(10, progress=cancel_after(core.CancelToken(), 0, seen))
+    assert seen[-1] == 100.0
+
+
+def test_a_cancelled_run_leaves_its_handle_short_of_finished():
+    """Test that a progress handle is marked done but not complete after a cancel."""
+    fluid = core.FluidGrid([[True] * 4] * 4)
+    token = core.CancelToken()
+    token.cancel()
+    progress = core.Progress()
+    with pytest.raises(core.Cancelled):
+        fluid.step(5, cancel=token, progress=progress)
+    assert progress.percent < 100.0
+    assert progress.done
+
+
+def test_a_cancelled_token_runs_no_steps():
+    """Test that a token cancelled up front leaves the grid untouched."""
+    fluid = core.FluidGrid([[True] * 4] * 4)
+    fluid.add(1, 1, 2.0)
+    before = fluid.to_list()
+    token = core.CancelToken()
+    token.cancel()
+    with pytest.raises(core.Cancelled):
+        fluid.step(5, cancel=token)
+    assert fluid.to_list() == before
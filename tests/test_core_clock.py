@@ -0,0 +1,90 @@
+"""
+Tests for the GameClock in llamaquest_core.
+"""
+import struct
+
+import pytest
+
+import llamaquest_core as core
+
+
+def clock_with_timer(duration):
+    clock = core.GameClock(fixed_step=0.1)
+    clock.start_timer("spawn", duration, repeat=True)
+    return clock
+
+
+def patched(blob, old, new):
+    """`blob` with the first f64 equal to `old` replaced by `new`"""
+    old, new = struct.pack("<d", old), struct.pack("<d", new)
+    assert old in blob
+    return blob.replace(old, new, 1)
+
+
+def test_repeating_timer_fires_once_per_elapsed_duration():
+    """Test that a repeating timer fires as many times as its duration fits."""
+    clock = clock_with_timer(0.1)
+    for _ in range(3):
+        clock.tick(0.1)
+        assert clock.fired() == [("spawn", 1)]
+    clock.tick(0.35)
+    assert clock.fired() == [("spawn", 3)]
+    assert clock.remaining("spawn") == pytest.approx(0.05)
+
+
+def test_huge_delta_does_not_hang():
+    """Test that a huge tick is capped instead of looping for ever."""
+    clock = clock_with_timer(1e-6)
+    assert clock.tick(1e12) == clock.max_steps
+    assert clock.fired() == [("spawn", 2**32 - 1)]
+    assert 0.0 < clock.remaining("spawn") <= 1e-6 + 1e-9
+
+
+@pytest.mark.parametrize("delta", [float("inf"), float("nan")])
+def test_non_finite_delta_is_refused(delta):
+    """Test that a non-finite tick is refused without moving the clock."""
+    clock = clock_with_timer(1.0)
+    with pytest.raises(ValueError):
+        clock.tick(delta)
+    assert (clock.frame, clock.time) == (0, 0.0)
+
+
+def test_non_finite_time_scale_is_refused():
+    """Test that time scales that would make time non-finite are refused."""
+    clock = clock_with_timer(1.0)
+    with pytest.raises(ValueError):
+        clock.time_scale = float("inf")
+    with pytest.raises(ValueError):
+        core.GameClock(time_scale=float("nan"))
+    clock.time_scale = 1e308
+    with pytest.raises(ValueError):
+        clock.tick(10.0)
+    assert clock.time == 0.0
+    clock.time_scale = -2.0
+    assert clock.time_scale == 0.0
+
+
+def test_saved_timer_round_trips():
+    """Test that a loaded clock carries on its timers where they left off."""
+    clock = clock_with_timer(0.75)
+    clock.tick(0.5)
+    restored = core.load_state(core.save_state({"clock": clock}))["clock"]
+    restored.tick(0.5)
+    assert restored.fired() == [("spawn", 1)]
+    assert restored.remaining("spawn") == pytest.approx(0.5)
+
+
+@pytest.mark.parametrize("duration", [0.0, -1.0, float("inf"), float("nan")])
+def test_load_rejects_timers_that_never_advance(duration):
+    """Test that a saved timer must have a positive, finite duration."""
+    # A timer's duration is saved just before the time it has left
+    blob = core.save_state({"clock": clock_with_timer(0.75)})
+    with pytest.raises(core.SerializationError, match="timer 'spawn'"):
+        core.load_state(patched(blob, 0.75, duration))
+
+
+def test_load_rejects_an_infinite_time_scale():
+    """Test that a saved clock must have a finite time scale."""
+    blob = core.save_state({"clock": core.GameClock(time_scale=1.25)})
+    with pytest.raises(core.SerializationError, match="time_scale"):
+        core.load_state(patched(blob, 1.25, float("inf")))
@@ -0,0 +1,46 @@
+"""
+Tests for loading saved event schedulers in llamaquest_core.
+"""
+import struct
+
+import pytest
+
+import llamaquest_core as core
+
+
+def saved_scheduler():
+    """A saved scheduler at time 1.25 with events 0 and 1 due at 2.5 and 3.75"""
+    scheduler = core.EventScheduler(now=1.25)
+    scheduler.schedule_at(2.5, "first")
+    scheduler.schedule_at(3.75, "second")
+    return core.save_state({"events": scheduler})
+
+
+def load_events(blob):
+    return core.load_state(blob)["events"]
+
+
+def test_saved_events_round_trip():
+    """Test that a loaded scheduler hands its events back in order."""
+    scheduler = load_events(saved_scheduler())
+    assert scheduler.now == 1.25
+    assert [payload for _, _, payload in scheduler.advance(5.0)] == ["first", "second"]
+
+
+@pytest.mark.parametrize("time", [float("inf"), float("-inf"), float("nan")])
+def test_load_rejects_non_finite_times(time):
+    """Test that neither the clock nor an event may load at a non-finite time."""
+    blob = saved_scheduler()
+    for old in (1.25, 3.75):
+        patched = blob.replace(struct.pack("<d", old), struct.pack("<d", time), 1)
+        with pytest.raises(core.SerializationError, match="finite"):
+            load_events(patched)
+
+
+@pytest.mark.parametrize("handle", [0, 2, 100])
+def test_load_rejects_bad_handles(handle):
+    """Test that repeated handles and handles not handed out yet are refused."""
+    second = b"\x01" + struct.pack("<d", 3.75)
+    blob = saved_scheduler().replace(second, bytes([handle]) + struct.pack("<d", 3.75))
+    with pytest.raises(core.SerializationError, match=f"event handle {handle}"):
+        load_events(blob)
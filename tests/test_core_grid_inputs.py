@@ -0,0 +1,87 @@
+"""
+Tests for how llamaquest_core handles malformed and out-of-range grid inputs.
+"""
+import pytest
+
+import llamaquest_core as core
+
+OPEN = [[True] * 4 for _ in range(3)]
+RAGGED = [[True] * 4, [True] * 2, [True] * 4]
+UNEXPLORED = [[False] * 4 for _ in range(3)]
+SMALL = [[False] * 3 for _ in range(3)]
+PALETTE = {0: (0, 0, 0, 255), 1: (255, 255, 255, 255)}
+TILES = [[0, 1, 0, 1] for _ in range(3)]
+
+
+@pytest.mark.parametrize(
+    "call, message",
+    [
+        (lambda: core.calculate_pathfinding(0, 0, 1, 0, RAGGED), "row 1 has length 2"),
+        (lambda: core.calculate_pathfinding(0, 0, 0, 0, []), "0x0"),
+        (lambda: core.calculate_pathfinding(0, 0, 0, 0, [[], []]), "0x2"),
+        (lambda: core.calculate_field_of_view(0, 0, 2, RAGGED), "obstacle_map"),
+        (lambda: core.calculate_field_of_view(0, 0, 2, []), "0x0"),
+        (lambda: core.calculate_dijkstra_map([(0, 0)], RAGGED), "row 1"),
+        (lambda: core.calculate_autoexplore(0, 0, UNEXPLORED, UNEXPLORED, RAGGED), "walkable_map"),
+        (lambda: core.calculate_autoexplore(0, 0, [], [], []), "0x0"),
+        (
+            lambda: core.calculate_autoexplore(0, 0, SMALL, UNEXPLORED, OPEN),
+            "explored_map must be a 4x3 grid.*got 3x3",
+        ),
+        (lambda: core.calculate_autoexplore(0, 0, UNEXPLORED, SMALL, OPEN), "visible_map"),
+        (lambda: core.calculate_formation_move([(0, 0)], (1, 1), RAGGED), "row 1"),
+        (lambda: core.calculate_formation_move([(0, 0)], (0, 0), []), "0x0"),
+        (lambda: core.MinimapRenderer(PALETTE).render([[0, 1], [0]]), "tile_map"),
+        (
+            lambda: core.MinimapRenderer(PALETTE).render(TILES, explored_map=[[True] * 4]),
+            "explored_map.*got 4x1",
+        ),
+        (
+            lambda: core.MinimapRenderer(PALETTE).render(TILES, visible_map=[[True] * 3] * 3),
+            "visible_map",
+        ),
+    ],
+)
+def test_malformed_grids_raise_map_shape_error(call, message):
+    """Test that ragged, empty and mismatched grids raise MapShapeError."""
+    with pytest.raises(core.MapShapeError, match=message):
+        call()
+
+
+@pytest.mark.parametrize(
+    "call, message",
+    [
+        (lambda: core.calculate_pathfinding(4, 0, 0, 0, OPEN), r"start \(4, 0\).*4x3"),
+        (lambda: core.calculate_pathfinding(0, 0, 0, 3, OPEN), r"end \(0, 3\)"),
+        (lambda: core.calculate_field_of_view(0, 9, 2, [[False] * 4] * 3), r"origin \(0, 9\)"),
+        (lambda: core.calculate_dijkstra_map([(0, 0)], []), r"goal \(0, 0\)"),
+        (lambda: core.calculate_dijkstra_map([(1, 1), (7, 1)], OPEN), r"goal \(7, 1\)"),
+        (lambda: core.calculate_autoexplore(5, 5, UNEXPLORED, UNEXPLORED, OPEN), r"\(5, 5\)"),
+        (lambda: core.calculate_formation_move([(0, 0)], (4, 0), OPEN), r"destination \(4, 0\)"),
+        (lambda: core.calculate_formation_move([(0, 0), (0, 3)], (1, 1), OPEN), r"unit \(0, 3\)"),
+    ],
+)
+def test_cells_off_the_map_raise_out_of_bounds_error(call, message):
+    """Test that starts, goals and units outside the map raise OutOfBoundsError."""
+    with pytest.raises(core.OutOfBoundsError, match=message):
+        call()
+
+
+def test_errors_subclass_the_old_builtin_exceptions():
+    """Test that callers catching ValueError and IndexError keep working."""
+    with pytest.raises(ValueError):
+        core.calculate_pathfinding(0, 0, 1, 0, RAGGED)
+    with pytest.raises(IndexError):
+        core.calculate_pathfinding(4, 0, 0, 0, OPEN)
+
+
+def test_degenerate_but_valid_inputs_are_accepted():
+    """Test that a one-cell path, a zero radius, no goals and an empty minimap work."""
+    assert core.calculate_pathfinding(0, 0, 0, 0, OPEN) == [(0, 0)]
+    assert core.calculate_field_of_view(0, 0, 0, [[False] * 4] * 3)[0][0]
+    # No goals at all is allowed: every cell is simply unreachable
+    distances = core.calculate_dijkstra_map([], OPEN)
+    assert all(value == float("inf") for row in distances for value in row)
+    # An empty tile map is rectangular, so it renders as an empty image
+    width, height, _ = core.MinimapRenderer(PALETTE).render([])
+    assert (width, height) == (0, 0)
@@ -0,0 +1,16 @@
+"""
+Tests for background jobs in llamaquest_core.
+"""
+import pytest
+
+import llamaquest_core as core
+
+OPEN = [[True] * 8 for _ in range(8)]
+
+
+@pytest.mark.parametrize("timeout", [float("inf"), 1e300, None])
+def test_unbounded_timeouts_wait_for_the_result(timeout):
+    """Test that timeouts too long to represent wait like no timeout."""
+    job = core.submit_pathfinding(0, 0, 7, 7, OPEN)
+    path = job.result(timeout=timeout)
+    assert (path[0], path[-1]) == ((0, 0), (7, 7))
@@ -0,0 +1,59 @@
+"""
+Tests for the collision and physics bindings of llamaquest_core.
+"""
+import llamaquest_core as core
+
+
+def test_collision_detection_takes_shapes():
+    """Test that rects, circles and (x, y, width, height) tuples can be mixed."""
+    assert core.collision_detection(core.Rect(0, 0, 2, 2), (1, 1, 2, 2))
+    assert core.collision_detection((0, 0, 2, 2), core.Circle(3, 1, 1.5))
+    assert not core.collision_detection(core.Circle(0, 0, 1), core.Circle(3, 0, 1))
+
+
+def test_touching_rectangles_do_not_collide():
+    """Test that edges are half-open, as in the old four-float AABB test."""
+    assert not core.collision_detection((0, 0, 2, 2), (2, 0, 2, 2))
+
+
+def test_can_move_to_checks_the_moved_rectangle():
+    """Test that only the new position matters and obstacles may be rects or circles."""
+    engine = core.PhysicsEngine()
+    entity = core.Rect(0, 0, 1, 1)
+    wall = (5, 0, 1, 10)
+    boulder = core.Circle(0, 5, 1)
+    assert engine.can_move_to(entity, (3, 0), [wall, boulder])
+    assert not engine.can_move_to(entity, core.Vec2(4.5, 0), [wall, boulder])
+    assert not engine.can_move_to((0, 0, 1, 1), (0, 4.5), [wall, boulder])
+
+
+def test_update_entity_takes_and_returns_vectors():
+    """Test that positions and velocities may be Vec2s or (x, y) pairs."""
+    engine = core.PhysicsEngine(gravity=10.0)
+    from_pairs = engine.update_entity((0, 0), (2, 0), False, 0.5)
+    from_vectors = engine.update_entity(core.Vec2(0, 0), core.Vec2(2, 0), False, 0.5)
+    assert from_pairs == from_vectors
+    position, velocity = from_pairs
+    assert isinstance(position, core.Vec2) and isinstance(velocity, core.Vec2)
+    assert velocity == (2, 5)
+
+
+def test_update_entity_with_clock_runs_every_fixed_step():
+    """Test that the result matches calling update_entity once per step."""
+    engine = core.PhysicsEngine()
+    clock = core.GameClock(fixed_step=0.25)
+    clock.tick(0.5)
+    state = (core.Vec2(1, 1), core.Vec2(0, -3))
+    expected = state
+    for _ in range(2):
+        expected = engine.update_entity(*expected, False, 0.25)
+    assert engine.update_entity_with_clock(*state, False, clock) == expected
+
+
+def test_projectile_path_takes_vectors():
+    """Test that the path holds the start and then one point per step."""
+    engine = core.PhysicsEngine()
+    path = engine.calculate_projectile_path((0, 0), core.Vec2(5, -5), 4, 0.1)
+    assert len(path) == 5 and path[0] == (0, 0)
+    assert all(isinstance(point, core.Vec2) for point in path)
+    assert path == engine.calculate_projectile_path(core.Vec2(0, 0), (5, -5), 4, 0.1)
@@ -0,0 +1,234 @@
+"""
+Tests for saving and loading simulation state in llamaquest_core.
+"""
+import pytest
+
+import llamaquest_core as core
+
+OPEN = [[True] * 5 for _ in range(4)]
+
+
+def round_trip(obj):
+    return core.load_state(core.save_state({"obj": obj}))["obj"]
+
+
+def test_fluid_grid():
+    """Test that a restored fluid grid flows on exactly like the original."""
+    fluid = core.FluidGrid(OPEN, medium="liquid", flow_rate=0.6)
+    fluid.set_open(2, 1, False)
+    fluid.add(0, 0, 3.0)
+    fluid.step(2)
+    restored = round_trip(fluid)
+    assert type(restored) is core.FluidGrid
+    assert restored.to_list() == fluid.to_list()
+    assert restored.flow_rate == pytest.approx(0.6)
+    fluid.step(3)
+    restored.step(3)
+    assert restored.to_list() == fluid.to_list()
+
+
+def test_temperature_grid():
+    """Test that a restored temperature grid keeps its tiles and heat sources."""
+    tiles = [[0, 1, 0], [0, 0, 0]]
+    grid = core.TemperatureGrid(tiles, insulation={1: 0.9}, base_temperature={1: -5.0})
+    grid.set_source(0, 0, 300.0, 0.5)
+    grid.step(2)
+    restored = round_trip(grid)
+    assert restored.to_list() == grid.to_list()
+    grid.step(4)
+    restored.step(4)
+    assert restored.to_list() == grid.to_list()
+
+
+def test_water_surface():
+    """Test that a restored water surface keeps its waves moving."""
+    water = core.WaterSurface(16, passes=4)
+    water.splash(8, 2.0)
+    water.step(3)
+    restored = round_trip(water)
+    assert restored.heights() == water.heights()
+    water.step(5)
+    restored.step(5)
+    assert restored.heights() == water.heights()
+
+
+def test_state_machines():
+    """Test that restored state machines keep states, timers, blackboards and gaps."""
+    machines = core.StateMachines(
+        {
+            "initial": "idle",
+            "states": {
+                "idle": {"transitions": [{"to": "alert", "event": "noise"}]},
+                "alert": {
+                    "transitions": [
+                        {"to": "idle", "after": 2.0},
+                        {"to": "flee", "when": {"key": "hp", "op": "<", "value": 10}},
+                    ]
+                },
+                "flee": {},
+            },
+        }
+    )
+    for hp in (50, 5, 20):
+        machines.add_entity({"hp": hp, "name": "goblin", "boss": False})
+    machines.remove_entity(2)
+    machines.send_event(0, "noise")
+    machines.send_event(1, "noise")
+    machines.tick(0.5)
+
+    def read(m):
+        return [(m.state(i), m.time_in_state(i), m.get_value(i, "hp")) for i in (0, 1)]
+
+    restored = round_trip(machines)
+    assert read(restored) == read(machines)
+    machines.tick(0.5)
+    restored.tick(0.5)
+    assert read(restored) == read(machines)
+    assert len(restored) == 2
+    assert restored.get_value(0, "name") == "goblin"
+    assert restored.get_value(0, "boss") is False
+    with pytest.raises(IndexError):
+        restored.state(2)
+
+
+def test_behavior_tree():
+    """Test that a restored behavior tree resumes its cooldowns and running waits."""
+    tree = core.BehaviorTree(
+        {
+            "type": "sequence",
+            "children": [
+                {"type": "cooldown", "seconds": 5.0, "child": {"type": "action", "name": "shout"}},
+                {"type": "wait", "seconds": 1.0},
+                {"type": "action", "name": "attack"},
+            ],
+        }
+    )
+    calls = []
+
+    def callback(agent, name):
+        calls.append((agent, name))
+        return True
+
+    tree.add_agent()
+    tree.add_agent()
+    tree.tick(0.5, callback)
+    restored = round_trip(tree)
+    calls.clear()
+    expected = [tree.tick(0.6, callback) for _ in range(4)]
+    original_calls, calls[:] = list(calls), []
+    assert [restored.tick(0.6, callback) for _ in range(4)] == expected
+    # The cooldown and the running wait carried over, so the leaves run identically
+    assert calls == original_calls
+
+
+def test_utility_evaluator_keeps_its_rng():
+    """Test that a restored utility evaluator draws the same random choices."""
+    evaluator = core.UtilityEvaluator(["hunger", "danger"], seed=7)
+    evaluator.add_action("eat")
+    evaluator.add_consideration("eat", "hunger", curve="polynomial", exponent=2.0)
+    evaluator.add_action("run", weight=0.9)
+    evaluator.add_consideration("run", "danger", curve="logistic", slope=10.0, x_shift=0.5)
+    inputs = [[0.6, 0.55]] * 16
+    evaluator.best_actions(inputs, temperature=0.2)
+    restored = round_trip(evaluator)
+    assert restored.best_actions(inputs, temperature=0.2) == evaluator.best_actions(
+        inputs, temperature=0.2
+    )
+    assert restored.action_names() == ["eat", "run"]
+    assert restored.scores(inputs[:1]) == evaluator.scores(inputs[:1])
+
+
+def test_camera():
+    """Test that a restored camera follows and shakes like the original."""
+    camera = core.Camera2D(
+        (320, 180), position=(10, 10), deadzone=(8, 4), bounds=(0, 0, 1000, 500), seed=3
+    )
+    camera.add_trauma(0.8)
+    camera.update((60, 40), 1 / 60, velocity=(120, 0))
+
+    def read(c):
+        return (c.position, c.view_center, c.shake_angle, c.trauma, c.bounds, c.deadzone)
+
+    restored = round_trip(camera)
+    assert read(restored) == read(camera)
+    camera.update((80, 40), 1 / 60)
+    restored.update((80, 40), 1 / 60)
+    assert read(restored) == read(camera)
+
+
+def test_tweens():
+    """Test that restored tweens keep their progress and hand out the same next id."""
+    tweens = core.Tweens()
+    tweens.add(0.0, 1.0, 0.5, easing="bounce_out")
+    fade = tweens.add((0, 0, 0), (255, 128, 64), 1.0, easing="cubic_in_out", delay=0.25)
+    tweens.add(5.0, 6.0, 0.1)
+    tweens.update(0.3)
+    restored = round_trip(tweens)
+    assert restored.values() == tweens.values()
+    tweens.update(0.3)
+    restored.update(0.3)
+    assert restored.values() == tweens.values()
+    assert restored.progress(fade) == tweens.progress(fade)
+    assert restored.add(0.0, 1.0, 1.0) == tweens.add(0.0, 1.0, 1.0)
+
+
+def test_undo_stack():
+    """Test that a restored undo stack can redo and undo the saved edits."""
+    grid = [[0] * 4 for _ in range(3)]
+    stack = core.UndoStack(max_changes=50)
+    stack.paint(grid, [(0, 0), (1, 0), (2, 0)], 1, label="row")
+    stack.set(grid, 3, 2, 7)
+    stack.undo(grid)
+    restored = round_trip(stack)
+    assert (restored.undo_label, restored.redo_label) == ("row", "edit")
+    assert (restored.stored_changes, len(restored)) == (4, 1)
+    copy = [row[:] for row in grid]
+    assert restored.redo(copy)
+    assert copy[2][3] == 7
+    assert restored.undo(copy) and restored.undo(copy)
+    assert copy == [[0] * 4 for _ in range(3)]
+
+
+def test_undo_stack_with_an_open_group_is_refused():
+    """Test that an undo stack cannot be saved halfway through a group."""
+    stack = core.UndoStack()
+    stack.begin_group("stroke")
+    with pytest.raises(RuntimeError):
+        core.save_state({"undo": stack})
+
+
+@pytest.mark.parametrize(
+    "make, change",
+    [
+        (lambda: core.FluidGrid(OPEN), lambda g: g.add(1, 1, 0.5)),
+        (lambda: core.TemperatureGrid([[0, 0], [0, 0]]), lambda g: g.set(1, 0, 21.0)),
+        (lambda: core.WaterSurface(8), lambda g: g.splash(3, 0.1)),
+    ],
+)
+def test_grid_contents_change_the_checksum(make, change):
+    """Test that equal grids share a checksum and an edited one does not."""
+    a, b = make(), make()
+    assert core.state_checksum([a]) == core.state_checksum([b])
+    change(b)
+    assert core.state_checksum([a]) != core.state_checksum([b])
+
+
+def test_utility_rng_changes_the_checksum():
+    """Test that drawing from a utility evaluator's RNG changes its checksum."""
+    a = core.UtilityEvaluator(["x"], seed=1)
+    b = core.UtilityEvaluator(["x"], seed=1)
+    for evaluator in (a, b):
+        evaluator.add_action("idle")
+        evaluator.add_action("walk")
+    assert core.state_checksum([a]) == core.state_checksum([b])
+    b.best_actions([[0.5]], temperature=1.0)
+    assert core.state_checksum([a]) != core.state_checksum([b])
+
+
+def test_blackboard_order_does_not_matter():
+    """Test that blackboards with the same entries share a checksum in any order."""
+    definition = {"initial": "a", "states": {"a": {}}}
+    a, b = core.StateMachines(definition), core.StateMachines(definition)
+    a.add_entity({"k%d" % i: i for i in range(20)})
+    b.add_entity({"k%d" % i: i for i in reversed(range(20))})
+    assert core.state_checksum([a]) == core.state_checksum([b])
@@ -0,0 +1,26 @@
+"""
+Tests for threat maps in llamaquest_core.
+"""
+import pytest
+
+import llamaquest_core as core
+
+OPEN = [[True] * 7 for _ in range(5)]
+
+
+@pytest.mark.parametrize("metric", ["manhattan", "chebyshev", "euclidean"])
+def test_huge_attack_range_covers_the_map(metric):
+    """Test that a reach far beyond the map is clamped to the map."""
+    huge = core.calculate_threat_map([(0, 0, 0, 100_000)], OPEN, attack_metric=metric)
+    exact = core.calculate_threat_map([(0, 0, 0, 7 + 5)], OPEN, attack_metric=metric)
+    assert huge == exact
+    this_turn, _ = huge
+    assert all(count == 1 for row in this_turn for count in row)
+
+
+def test_enemies_sharing_a_range_each_count():
+    """Test that enemies sharing a range each add their own threat."""
+    this_turn, next_turn = core.calculate_threat_map([(0, 0, 1, 1), (6, 4, 1, 1)], OPEN)
+    assert this_turn[0][0] == 1 and this_turn[4][6] == 1
+    assert this_turn[2][3] == 0
+    assert next_turn[0][2] == 1
@@ -0,0 +1,69 @@
+"""
+Tests for loading Tiled maps through llamaquest_core.
+"""
+import base64
+import gzip
+import json
+import struct
+import zlib
+
+import pytest
+
+import llamaquest_core as core
+
+
+def tiled_json(width, height, data, compression=None, **layer):
+    """A one-layer Tiled map with base64 tile data."""
+    tile_layer = {
+        "type": "tilelayer",
+        "name": "ground",
+        "width": width,
+        "height": height,
+        "encoding": "base64",
+        "data": base64.b64encode(data).decode(),
+        **layer,
+    }
+    if compression:
+        tile_layer["compression"] = compression
+    return json.dumps(
+        {
+            "width": width,
+            "height": height,
+            "tilewidth": 16,
+            "tileheight": 16,
+            "layers": [tile_layer],
+        }
+    )
+
+
+def gids(*values):
+    return struct.pack("<%dI" % len(values), *values)
+
+
+@pytest.mark.parametrize("compression, compress", [("zlib", zlib.compress), ("gzip", gzip.compress)])
+def test_compressed_layers_decode(compression, compress):
+    """Test that both compressions Tiled writes are read back tile for tile."""
+    data = compress(gids(1, 0, 2, 3, 0, 4))
+    tiled = core.parse_tiled_map(tiled_json(3, 2, data, compression))
+    assert tiled.layer("ground") == [[1, 0, 2], [3, 0, 4]]
+
+
+def test_layer_that_inflates_too_far_is_refused():
+    """Test that inflating stops one byte past the layer size."""
+    bomb = zlib.compress(bytes(64 << 20), 9)
+    assert len(bomb) < 100_000
+    with pytest.raises(core.SerializationError, match="more than 16 bytes, expected 16"):
+        core.parse_tiled_map(tiled_json(2, 2, bomb, "zlib"))
+
+
+def test_layer_that_inflates_too_little_is_refused():
+    """Test that short data is reported with the size it came to."""
+    with pytest.raises(core.SerializationError, match="inflates to 8 bytes, expected 16"):
+        core.parse_tiled_map(tiled_json(2, 2, zlib.compress(gids(1, 2)), "zlib"))
+
+
+@pytest.mark.parametrize("width, height", [(100_000, 100_000), (2**63, 4)])
+def test_oversized_maps_are_refused_before_allocating(width, height):
+    """Test that huge or overflowing map sizes are refused before allocating."""
+    with pytest.raises(core.SerializationError, match="more than"):
+        core.parse_tiled_map(tiled_json(width, height, b""))
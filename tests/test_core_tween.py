@@ -0,0 +1,47 @@
+"""
+Tests for loading saved tweens in llamaquest_core.
+"""
+import struct
+
+import pytest
+
+import llamaquest_core as core
+
+
+def saved_tweens():
+    """Saved tweens 0 and 1, a scalar and a position, with next id 2"""
+    tweens = core.Tweens()
+    tweens.add(0.0, 10.0, 1.0)
+    tweens.add((0.0, 0.0), (4.0, 8.0), 2.0)
+    return core.save_state({"tweens": tweens})
+
+
+def load_tweens(blob):
+    return core.load_state(blob)["tweens"]
+
+
+def test_saved_tweens_round_trip():
+    """Test that scalars come back as numbers and positions as tuples."""
+    tweens = load_tweens(saved_tweens())
+    tweens.update(0.5)
+    assert tweens.value(0) == pytest.approx(5.0)
+    assert tweens.value(1) == pytest.approx((1.0, 2.0))
+
+
+def test_load_rejects_a_scalar_without_exactly_one_component():
+    """Test that a scalar tween must have exactly one component."""
+    # Tween 0 is saved as id, scalar flag, component count, then its start
+    scalar = b"\x00\x01\x01" + struct.pack("<f", 0.0)
+    blob = saved_tweens().replace(scalar, b"\x00\x01\x00" + scalar[3:], 1)
+    with pytest.raises(core.SerializationError, match="scalar tween 0 has 0 components"):
+        load_tweens(blob)
+
+
+@pytest.mark.parametrize("id", [0, 2, 100])
+def test_load_rejects_bad_ids(id):
+    """Test that repeated ids and ids not handed out yet are refused."""
+    # Tween 1 is the only tween saved as a two-component position
+    position = b"\x01\x00\x02"
+    blob = saved_tweens().replace(position, bytes([id]) + position[1:], 1)
+    with pytest.raises(core.SerializationError, match=f"tween id {id}"):
+        load_tweens(blob)
@@ -0,0 +1,110 @@
+"""
+Tests for the entity World in llamaquest_core.
+"""
+import pytest
+
+import llamaquest_core as core
+
+
+def loaded_world(entities):
+    """A world rebuilt from an export, so its columns start with no spare room"""
+    world = core.World(capacity=0)
+    for i in range(entities):
+        world.spawn(position=(float(i), 0.0))
+    return core.World.from_msgpack(world.to_msgpack())
+
+
+def stepped_world(hold_view):
+    """Colliding pairs and the export of a small world after every kind of step"""
+    world = core.World(drag=0.1)
+    world.spawn(position=(0.0, 0.0), velocity=(1.0, 0.0), collider=(1.0, 1.0))
+    world.spawn(position=(0.5, 0.0), collider=(1.0, 1.0))
+    view = memoryview(world.xs()) if hold_view else None
+    pairs = world.step(0.5)
+    world.run_physics(0.5)
+    world.run_steering(0.5)
+    pairs += world.run_collisions(resolve=True)
+    if view is not None:
+        view.release()
+    return pairs, world.to_msgpack()
+
+
+def test_spawning_is_refused_before_any_viewed_column_moves():
+    """Test that a held column view stops the columns from reallocating."""
+    world = loaded_world(1)
+    view = memoryview(world.xs())
+    with pytest.raises(BufferError):
+        for _ in range(64):
+            world.spawn(position=(9.0, 9.0))
+    assert view.tolist() == [0.0]
+    view.release()
+    world.spawn(position=(9.0, 9.0))
+
+
+def test_rollback_to_a_larger_world_is_refused_while_viewed():
+    """Test that rolling back cannot grow columns that Python is viewing."""
+    # Growing from one slot leaves the byte-wide flags column with more spare
+    # room than the float columns, which only fit four slots at this point
+    world = loaded_world(1)
+    for _ in range(3):
+        world.spawn(position=(5.0, 5.0))
+    grown = core.World.from_msgpack(world.to_msgpack())
+    grown.spawn(position=(5.0, 5.0))
+    snapshot = grown.snapshot()
+    view = memoryview(world.ys())
+    with pytest.raises(BufferError):
+        world.rollback(snapshot)
+    view.release()
+    world.rollback(snapshot)
+    assert len(world) == 5
+
+
+def test_held_views_do_not_change_what_a_step_does():
+    """Test that steps give the same result whether or not the GIL is released."""
+    # Holding a view keeps the GIL during the step; the systems run the same
+    pairs, export = stepped_world(hold_view=False)
+    assert (pairs, export) == stepped_world(hold_view=True)
+    assert len(pairs) >= 1
+
+
+def test_round_trip_keeps_handles_and_components():
+    """Test that a msgpack export loads back with the same handles and components."""
+    world = core.World(gravity=(0.0, 9.8), drag=0.1)
+    kept = [world.spawn(position=(float(i), 2.0), velocity=(1.0, 0.0)) for i in range(4)]
+    world.despawn(kept.pop(1))
+    loaded = core.World.from_msgpack(world.to_msgpack())
+    assert loaded.entities() == kept
+    assert [loaded.position(e) for e in kept] == [world.position(e) for e in kept]
+    assert loaded.to_msgpack() == world.to_msgpack()
+
+
+def test_sparse_slots_within_the_bound_load():
+    """Test that a world with many freed slots still loads."""
+    world = core.World()
+    entities = [world.spawn() for _ in range(3000)]
+    for entity in entities[:-1]:
+        world.despawn(entity)
+    loaded = core.World.from_msgpack(world.to_msgpack())
+    assert loaded.entities() == entities[-1:]
+
+
+def test_slot_index_beyond_the_bound_is_refused():
+    """Test that an export naming an absurd slot is refused."""
+    world = core.World()
+    world.spawn(position=(1.0, 1.0))
+    data = world.to_msgpack()
+    # Swap the first entity's id 0 for slot 0xFFFFFFFF, a msgpack uint32
+    assert data.count(b"\xa2id\x00") == 1
+    corrupt = data.replace(b"\xa2id\x00", b"\xa2id\xce\xff\xff\xff\xff")
+    with pytest.raises(core.SerializationError, match="slot 4294967295"):
+        core.World.from_msgpack(corrupt)
+
+
+def test_malformed_exports_are_refused():
+    """Test that empty, truncated and foreign exports are refused."""
+    world = core.World()
+    world.spawn()
+    data = world.to_msgpack()
+    for corrupt in (b"", data[:-3], b"\x90", data.replace(b"llamaquest.world", b"llamaquest.other")):
+        with pytest.raises(core.SerializationError):
+            core.World.from_msgpack(corrupt)